@@ -1,11 +1,22 @@
 #![allow(dead_code)]
 
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
 
 use crate::ast::*;
-use crate::lexer::Lexer;
+use crate::dotconfig::DotConfig;
+use crate::include_graph::{IncludeGraph, TreeOrder};
+use crate::index_stats::SkippedFile;
+use crate::lexer::{Dialect, Lexer};
+use crate::menu_tree::{self, MenuNode, MenuNodeKind};
 use crate::parser;
+use crate::preprocess::{Expander, MacroAssignment};
+use crate::profile::Profile;
+use crate::settings::PreprocessorSettings;
+use crate::text_index::TextIndex;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DefKind {
@@ -21,11 +32,104 @@ pub struct SymbolDef {
     pub name_span: Span,
     pub type_kind: Option<TypeKind>,
     pub prompt: Option<String>,
+    /// The `if` condition of the prompt, if any.
+    pub prompt_condition: Option<Expr>,
     pub help: Option<String>,
+    /// `#` comment block directly above the entry.
+    pub doc_comment: Option<String>,
+    /// Expressions from the entry's own `depends on` lines.
+    pub depends_on: Vec<Expr>,
+    /// Dependencies inherited from enclosing `if` blocks and the `depends on`
+    /// of enclosing menus and choices.
+    pub inherited_depends: Vec<Expr>,
+    /// `visible if` conditions of enclosing menus. They hide the prompt but
+    /// do not limit the value.
+    pub visible_if: Vec<Expr>,
+    /// `default` lines, with `def_bool`/`def_tristate` values folded in.
+    pub defaults: Vec<DefaultAttr>,
+    pub ranges: Vec<RangeAttr>,
+    pub selects: Vec<SelectImplyAttr>,
+    pub implies: Vec<SelectImplyAttr>,
+    /// Index into the defining file's [`FileAnalysis::choices`] when the
+    /// symbol is a member of a `choice` block.
+    pub choice: Option<usize>,
+    /// Marked `transitional`: kept only so old `.config` files migrate.
+    pub transitional: bool,
+    /// `option` names (`defconfig_list`, `allnoconfig_y`, ...). The
+    /// `modules` attribute is recorded as `modules`.
+    pub options: Vec<String>,
     pub file: PathBuf,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl SymbolDef {
+    /// Whether the definition has `option <name>`.
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options.iter().any(|o| o == name)
+    }
+}
+
+/// Every definition of a symbol, in parse order. Kconfig concatenates their
+/// prompts, defaults, ranges, and selects in this order, each guarded by the
+/// dependencies of the definition it came from; `depends on` itself stays per
+/// definition, and the symbol is usable when any definition's holds.
+#[derive(Debug, Clone)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub definitions: Vec<&'a SymbolDef>,
+}
+
+impl<'a> Symbol<'a> {
+    /// Definitions that have a prompt.
+    pub fn prompts(&self) -> impl Iterator<Item = &'a SymbolDef> + '_ {
+        self.definitions
+            .iter()
+            .copied()
+            .filter(|d| d.prompt.is_some())
+    }
+
+    pub fn defaults(&self) -> impl Iterator<Item = (&'a SymbolDef, &'a DefaultAttr)> + '_ {
+        self.definitions
+            .iter()
+            .flat_map(|d| d.defaults.iter().map(move |a| (*d, a)))
+    }
+
+    pub fn ranges(&self) -> impl Iterator<Item = (&'a SymbolDef, &'a RangeAttr)> + '_ {
+        self.definitions
+            .iter()
+            .flat_map(|d| d.ranges.iter().map(move |a| (*d, a)))
+    }
+
+    pub fn selects(&self) -> impl Iterator<Item = (&'a SymbolDef, &'a SelectImplyAttr)> + '_ {
+        self.definitions
+            .iter()
+            .flat_map(|d| d.selects.iter().map(move |a| (*d, a)))
+    }
+
+    pub fn implies(&self) -> impl Iterator<Item = (&'a SymbolDef, &'a SelectImplyAttr)> + '_ {
+        self.definitions
+            .iter()
+            .flat_map(|d| d.implies.iter().map(move |a| (*d, a)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChoiceDef {
+    /// Name of a named choice (`choice NAME`).
+    pub name: Option<String>,
+    pub prompt: Option<String>,
+    /// Span of the `choice` keyword.
+    pub span: Span,
+    /// Member symbol names, in definition order.
+    pub members: Vec<String>,
+    pub optional: bool,
+    pub depends_on: Vec<Expr>,
+    /// `default` lines naming the member selected by default.
+    pub defaults: Vec<DefaultAttr>,
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub enum RefKind {
     DependsOn,
     Select,
@@ -50,14 +154,89 @@ pub struct FileAnalysis {
     pub line_index: LineIndex,
     pub source: String,
     pub diagnostics: Vec<ParseDiagnostic>,
+    pub choices: Vec<ChoiceDef>,
+    pub sources: Vec<SourceStmt>,
+    /// Preprocessor variable assignments, in file order.
+    pub assignments: Vec<MacroAssignment>,
+    /// The symbols defined in the file, in file order.
+    pub symbols: Vec<SymbolDef>,
+    /// The references the file makes, in file order.
+    pub references: Vec<SymbolRef>,
+    pub outline: Vec<OutlineEntry>,
+}
+
+/// An entry of a file's outline: a config, choice, menu or comment, with
+/// the entries nested in it. The entries of `if` blocks belong to the
+/// enclosing level.
+#[derive(Debug, Clone)]
+pub struct OutlineEntry {
+    pub kind: MenuNodeKind,
+    pub name: Option<String>,
+    pub prompt: Option<String>,
+    pub span: Span,
+    /// The symbol name, or the prompt of a menu or comment.
+    pub selection_span: Span,
+    pub children: Vec<OutlineEntry>,
+}
+
+/// A `source` statement together with the conditions it is nested under.
+#[derive(Debug, Clone)]
+pub struct SourceStmt {
+    pub path: String,
+    pub path_span: Span,
+    /// `osource`/`orsource`.
+    pub optional: bool,
+    /// `rsource`/`orsource`.
+    pub relative: bool,
+    /// Conditions of enclosing `if` blocks, and `depends on` of enclosing
+    /// menus and choices. Everything in the sourced file inherits these.
+    pub conditions: Vec<Expr>,
 }
 
 #[derive(Debug, Default)]
 pub struct WorldIndex {
     pub definitions: HashMap<String, Vec<SymbolDef>>,
     pub references: HashMap<String, Vec<SymbolRef>>,
+    /// Every defined name, sorted.
     pub all_symbols: Vec<String>,
     pub files: HashMap<PathBuf, FileAnalysis>,
+    /// The loaded `.config`, if any, used for evaluation-dependent checks.
+    pub config: Option<DotConfig>,
+    /// The configuration the last build generated from the `.config`
+    /// (`auto.conf` or `autoconf.h`), if the `buildConfig` setting names
+    /// one that exists.
+    pub build_config: Option<DotConfig>,
+    /// The fragments the `.config` was merged from, in merge order, as the
+    /// `configFragments` setting lists them.
+    pub fragments: Vec<DotConfig>,
+    /// Source tree root (`srctree`), against which `source` paths resolve.
+    pub root: Option<PathBuf>,
+    /// The top-level Kconfig file; parse order is traversed from here.
+    pub root_kconfig: Option<PathBuf>,
+    pub preprocessor: PreprocessorSettings,
+    /// What each `$(shell,...)` command run so far printed, or why it
+    /// failed, by command. The server runs them outside the index lock; see
+    /// [`crate::preprocess::shell_commands`].
+    pub shell_outputs: HashMap<String, Result<String, String>>,
+    /// The profile applied to the workspace; `Auto` when unknown.
+    pub profile: Profile,
+    /// The grammar files are lexed with.
+    pub dialect: Dialect,
+    /// Files the workspace scan found but did not index.
+    pub skipped: Vec<SkippedFile>,
+    /// Words of every prompt and help text, for text search.
+    pub text: TextIndex,
+    /// Counts the changes to the indexed files, so that results computed
+    /// from one state of the index can be told from those of a later one.
+    /// Changing `root`, `root_kconfig`, `preprocessor` or `config` of a
+    /// populated index must bump it too.
+    pub epoch: u64,
+    /// The parse order as of an epoch, built on first use.
+    tree_order: Mutex<Option<(u64, Arc<TreeOrder>)>>,
+    /// The include graph as of an epoch, built on first use.
+    include_graph: Mutex<Option<(u64, Arc<IncludeGraph>)>>,
+    /// The menu trees built as of an epoch, by root file.
+    menu_trees: Mutex<(u64, HashMap<PathBuf, Arc<MenuNode>>)>,
 }
 
 impl WorldIndex {
@@ -65,63 +244,188 @@ impl WorldIndex {
         Self::default()
     }
 
+    /// The parse order of the tree, built once per epoch.
+    pub fn tree_order(&self) -> Arc<TreeOrder> {
+        cached(&self.tree_order, self.epoch, || TreeOrder::new(self))
+    }
+
+    /// Who sources each file, and the cycles and repeated inclusions of the
+    /// tree, built once per epoch.
+    pub fn include_graph(&self) -> Arc<IncludeGraph> {
+        cached(&self.include_graph, self.epoch, || IncludeGraph::new(self))
+    }
+
+    /// The menu tree rooted at `root`, built once per epoch; see
+    /// [`menu_tree::menu_tree`].
+    pub fn menu_tree(&self, root: &Path) -> Option<Arc<MenuNode>> {
+        {
+            let trees = self.menu_trees.lock().unwrap();
+            if trees.0 == self.epoch
+                && let Some(tree) = trees.1.get(root)
+            {
+                return Some(tree.clone());
+            }
+        }
+        let tree = Arc::new(menu_tree::menu_tree(self, root)?);
+        let mut trees = self.menu_trees.lock().unwrap();
+        if trees.0 != self.epoch {
+            *trees = (self.epoch, HashMap::new());
+        }
+        trees.1.insert(root.to_path_buf(), tree.clone());
+        Some(tree)
+    }
+
     pub fn analyze_file(&mut self, path: &Path, source: &str) {
-        let tokens = Lexer::new(source).tokenize();
+        self.epoch += 1;
+        let tokens = Lexer::new(source).with_dialect(self.dialect).tokenize();
         let result = parser::parse(source, tokens);
         let line_index = LineIndex::new(source);
 
         let file_path = path.to_path_buf();
-        let mut defs = Vec::new();
-        let mut refs = Vec::new();
-
-        collect_entries(&result.file.entries, &file_path, &mut defs, &mut refs);
+        let mut out = FileSymbols::default();
+        collect_entries(
+            &result.file.entries,
+            &file_path,
+            &Scope::default(),
+            &mut out,
+        );
+        let FileSymbols {
+            defs,
+            refs,
+            choices,
+            sources,
+        } = out;
 
+        // Kept sorted by path and offset, so results do not depend on the
+        // order in which files were discovered or opened.
         for d in &defs {
-            self.definitions
-                .entry(d.name.clone())
-                .or_default()
-                .push(d.clone());
-            if !self.all_symbols.contains(&d.name) {
-                self.all_symbols.push(d.name.clone());
+            let list = self.definitions.entry(d.name.clone()).or_default();
+            let at = list
+                .partition_point(|e| (&e.file, e.name_span.start) <= (&d.file, d.name_span.start));
+            list.insert(at, d.clone());
+            if let Err(at) = self.all_symbols.binary_search(&d.name) {
+                self.all_symbols.insert(at, d.name.clone());
             }
         }
+        self.text.add_file(&file_path, &defs);
         for r in &refs {
-            self.references
-                .entry(r.name.clone())
-                .or_default()
-                .push(r.clone());
+            let list = self.references.entry(r.name.clone()).or_default();
+            let at = list.partition_point(|e| (&e.file, e.span.start) <= (&r.file, r.span.start));
+            list.insert(at, r.clone());
         }
 
+        let mut refs = refs;
+        refs.sort_by_key(|r| r.span.start);
+        let outline = outline(&result.file.entries);
         self.files.insert(
             file_path,
             FileAnalysis {
+                outline,
+                symbols: defs,
+                references: refs,
                 file: result.file,
                 line_index,
                 source: source.to_string(),
                 diagnostics: result.diagnostics,
+                choices,
+                sources,
+                assignments: result.assignments,
             },
         );
     }
 
     pub fn remove_file(&mut self, path: &Path) {
-        self.files.remove(path);
+        let Some(fa) = self.files.remove(path) else {
+            return;
+        };
+        self.epoch += 1;
+        self.text.remove_file(path);
 
-        self.definitions.retain(|_, defs| {
-            defs.retain(|d| d.file != path);
-            !defs.is_empty()
-        });
-        self.references.retain(|_, refs| {
-            refs.retain(|r| r.file != path);
-            !refs.is_empty()
-        });
-        self.all_symbols = self.definitions.keys().cloned().collect();
+        // Only the names the file defines or references can change.
+        let mut removed_symbol = false;
+        for d in &fa.symbols {
+            if let Some(defs) = self.definitions.get_mut(&d.name) {
+                defs.retain(|d| d.file != path);
+                if defs.is_empty() {
+                    self.definitions.remove(&d.name);
+                    removed_symbol = true;
+                }
+            }
+        }
+        for r in &fa.references {
+            if let Some(refs) = self.references.get_mut(&r.name) {
+                refs.retain(|r| r.file != path);
+                if refs.is_empty() {
+                    self.references.remove(&r.name);
+                }
+            }
+        }
+        if removed_symbol {
+            self.all_symbols
+                .retain(|name| self.definitions.contains_key(name));
+        }
     }
 
-    pub fn reanalyze_file(&mut self, path: &Path, source: &str) {
+    /// Index `path` anew from `source`. Returns the names that gained or
+    /// lost a definition in the file: references to them elsewhere may now
+    /// resolve differently.
+    pub fn reanalyze_file(&mut self, path: &Path, source: &str) -> BTreeSet<String> {
+        let defined = |index: &Self| -> BTreeSet<String> {
+            index
+                .symbols_in_file(path)
+                .iter()
+                .map(|d| d.name.clone())
+                .collect()
+        };
+        let before = defined(self);
         self.remove_file(path);
         self.analyze_file(path, source);
+        let after = defined(self);
+        before.symmetric_difference(&after).cloned().collect()
+    }
+
+    /// The files that reference any of `names`.
+    pub fn files_referencing(&self, names: &BTreeSet<String>) -> BTreeSet<PathBuf> {
+        names
+            .iter()
+            .flat_map(|name| self.get_references(name))
+            .map(|r| r.file.clone())
+            .collect()
+    }
+
+    /// The symbols defined in `path`, in file order.
+    pub fn symbols_in_file(&self, path: &Path) -> &[SymbolDef] {
+        self.files
+            .get(path)
+            .map(|fa| fa.symbols.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The outline of `path`: its entries, nested as in the file.
+    pub fn outline(&self, path: &Path) -> &[OutlineEntry] {
+        self.files
+            .get(path)
+            .map(|fa| fa.outline.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The outline entries of `path` enclosing `offset`, outermost first:
+    /// the breadcrumbs of a position.
+    pub fn outline_at(&self, path: &Path, offset: usize) -> Vec<&OutlineEntry> {
+        let mut chain = Vec::new();
+        let mut entries = self.outline(path);
+        while let Some(e) = entries
+            .iter()
+            .find(|e| e.span.start <= offset && offset <= e.span.end)
+        {
+            chain.push(e);
+            entries = &e.children;
+        }
+        chain
     }
 
+    /// The definitions of `name`, by path and then offset. For parse order,
+    /// use [`WorldIndex::symbol`] or [`TreeOrder::sort_definitions`].
     pub fn get_definitions(&self, name: &str) -> &[SymbolDef] {
         self.definitions
             .get(name)
@@ -129,20 +433,241 @@ impl WorldIndex {
             .unwrap_or(&[])
     }
 
+    /// The references to `name`, by path and then offset.
     pub fn get_references(&self, name: &str) -> &[SymbolRef] {
         self.references
             .get(name)
             .map(|v| v.as_slice())
             .unwrap_or(&[])
     }
+
+    /// Resolve the path of a `source` statement in `from` to an indexed file.
+    /// Macros and `$NAME` variable references in the path are expanded
+    /// first; a path whose expansion fails does not resolve. Relative paths
+    /// are tried against the source tree root (kernel semantics), then
+    /// `$(srctree)` when it is set, then the sourcing file's directory.
+    /// `rsource` paths (`relative`) are only tried against the directory.
+    pub fn resolve_source(&self, from: &Path, path: &str, relative: bool) -> Option<PathBuf> {
+        self.source_candidates(from, path, relative)
+            .into_iter()
+            .find(|c| self.files.contains_key(c))
+    }
+
+    /// The paths [`WorldIndex::resolve_source`] tries, in order, whether or
+    /// not they are indexed.
+    pub fn source_candidates(&self, from: &Path, path: &str, relative: bool) -> Vec<PathBuf> {
+        let expanded;
+        let path = if path.contains('$') {
+            let Some(text) = Expander::unordered(self, from).expand(path).ok() else {
+                return Vec::new();
+            };
+            let Some(words) = expand_variable_words(&text, &self.preprocessor.variables) else {
+                return Vec::new();
+            };
+            expanded = words;
+            Path::new(&expanded)
+        } else {
+            Path::new(path)
+        };
+        let mut candidates = Vec::new();
+        if path.is_absolute() {
+            candidates.push(path.to_path_buf());
+        } else {
+            if !relative {
+                if let Some(root) = &self.root {
+                    candidates.push(root.join(path));
+                }
+                if let Some(srctree) = self.preprocessor.variables.get("srctree") {
+                    let srctree = match &self.root {
+                        Some(root) => root.join(srctree),
+                        None => PathBuf::from(srctree),
+                    };
+                    candidates.push(srctree.join(path));
+                }
+            }
+            if let Some(dir) = from.parent() {
+                candidates.push(dir.join(path));
+            }
+        }
+        candidates.iter().map(|c| normalize(c)).collect()
+    }
+
+    /// The declared type of a symbol, taken from the first definition that
+    /// specifies one.
+    pub fn symbol_type(&self, name: &str) -> Option<TypeKind> {
+        self.get_definitions(name).iter().find_map(|d| d.type_kind)
+    }
+
+    /// All definitions of `name` in parse order, or `None` if it has none.
+    pub fn symbol<'a>(&'a self, name: &'a str, order: &TreeOrder) -> Option<Symbol<'a>> {
+        let definitions = order.sort_definitions(self.get_definitions(name));
+        if definitions.is_empty() {
+            return None;
+        }
+        Some(Symbol { name, definitions })
+    }
+
+    /// The choice named `name`, in any indexed file.
+    pub fn named_choice(&self, name: &str) -> Option<&ChoiceDef> {
+        self.files
+            .values()
+            .flat_map(|fa| &fa.choices)
+            .find(|c| c.name.as_deref() == Some(name))
+    }
+
+    /// The choice block a definition belongs to, if any.
+    pub fn choice_of(&self, def: &SymbolDef) -> Option<&ChoiceDef> {
+        let idx = def.choice?;
+        self.files.get(&def.file)?.choices.get(idx)
+    }
 }
 
-fn collect_entries(
-    entries: &[Entry],
-    file: &Path,
-    defs: &mut Vec<SymbolDef>,
-    refs: &mut Vec<SymbolRef>,
-) {
+/// The value in `slot` if it was built at `epoch`, or else a new one from
+/// `build`, kept for the next caller. `build` runs without the lock held.
+fn cached<T>(slot: &Mutex<Option<(u64, Arc<T>)>>, epoch: u64, build: impl FnOnce() -> T) -> Arc<T> {
+    if let Some((built, value)) = &*slot.lock().unwrap()
+        && *built == epoch
+    {
+        return value.clone();
+    }
+    let value = Arc::new(build());
+    *slot.lock().unwrap() = Some((epoch, value.clone()));
+    value
+}
+
+/// `$NAME` references as older kconfig and kconfig-frontends (used by
+/// NuttX) expand them in `source` paths, taken from `variables`. `None` if
+/// one is not set.
+fn expand_variable_words(text: &str, variables: &HashMap<String, String>) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+        if len == 0 {
+            out.push('$');
+        } else {
+            out.push_str(variables.get(&after[..len])?);
+        }
+        rest = &after[len..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+/// `path` with `.` and `..` components folded away, without touching the
+/// file system: `source "$(ZEPHYR_BASE)/..."` with a relative base, or a
+/// module root given as `../modules`, should name the same file as the
+/// workspace scan found.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Per-file accumulator for [`collect_entries`].
+#[derive(Default)]
+struct FileSymbols {
+    defs: Vec<SymbolDef>,
+    refs: Vec<SymbolRef>,
+    choices: Vec<ChoiceDef>,
+    sources: Vec<SourceStmt>,
+}
+
+/// Context inherited from the blocks enclosing an entry.
+#[derive(Clone, Default)]
+struct Scope {
+    choice: Option<usize>,
+    conditions: Vec<Expr>,
+    visible_if: Vec<Expr>,
+}
+
+fn outline(entries: &[Entry]) -> Vec<OutlineEntry> {
+    let mut out = Vec::new();
+    for entry in entries {
+        let (kind, name, prompt, selection_span) = match entry {
+            Entry::Config(c) | Entry::MenuConfig(c) => {
+                let kind = if matches!(entry, Entry::MenuConfig(_)) {
+                    MenuNodeKind::MenuConfig
+                } else {
+                    MenuNodeKind::Config
+                };
+                (
+                    kind,
+                    Some(c.name.clone()),
+                    prompt_of(&c.attributes),
+                    c.name_span,
+                )
+            }
+            Entry::Choice(ch) => {
+                let (name, selection) = match &ch.name {
+                    Some((name, span)) => (Some(name.clone()), *span),
+                    None => (
+                        None,
+                        Span::new(ch.span.start, ch.span.start + "choice".len()),
+                    ),
+                };
+                (
+                    MenuNodeKind::Choice,
+                    name,
+                    prompt_of(&ch.attributes),
+                    selection,
+                )
+            }
+            Entry::Menu(m) => (
+                MenuNodeKind::Menu,
+                None,
+                Some(m.prompt.clone()),
+                m.prompt_span,
+            ),
+            Entry::Comment(cm) => (
+                MenuNodeKind::Comment,
+                None,
+                Some(cm.prompt.clone()),
+                cm.prompt_span,
+            ),
+            Entry::If(i) => {
+                out.extend(outline(&i.entries));
+                continue;
+            }
+            Entry::Source(_) | Entry::MainMenu(_) => continue,
+        };
+        out.push(OutlineEntry {
+            kind,
+            name,
+            prompt,
+            span: entry.span(),
+            selection_span,
+            children: outline(entry.children()),
+        });
+    }
+    out
+}
+
+fn prompt_of(attributes: &[Attribute]) -> Option<String> {
+    attributes.iter().find_map(|a| match a {
+        Attribute::Type(TypeAttr {
+            prompt: Some(p), ..
+        })
+        | Attribute::Prompt(p) => Some(p.text.clone()),
+        _ => None,
+    })
+}
+
+fn collect_entries(entries: &[Entry], file: &Path, scope: &Scope, out: &mut FileSymbols) {
     for entry in entries {
         match entry {
             Entry::Config(c) | Entry::MenuConfig(c) => {
@@ -153,7 +678,14 @@ fn collect_entries(
                 };
                 let mut type_kind = None;
                 let mut prompt = None;
+                let mut prompt_condition = None;
                 let mut help = None;
+                let mut depends_on = Vec::new();
+                let mut defaults = Vec::new();
+                let mut ranges = Vec::new();
+                let mut selects = Vec::new();
+                let mut implies = Vec::new();
+                let mut options = Vec::new();
 
                 for attr in &c.attributes {
                     match attr {
@@ -161,54 +693,138 @@ fn collect_entries(
                             type_kind = Some(t.kind);
                             if let Some(p) = &t.prompt {
                                 prompt = Some(p.text.clone());
+                                prompt_condition = p.condition.clone();
                             }
                         }
                         Attribute::DefType(dt) => {
                             type_kind = Some(dt.kind);
+                            defaults.push(DefaultAttr {
+                                value: dt.value.clone(),
+                                condition: dt.condition.clone(),
+                                span: dt.span,
+                            });
+                        }
+                        Attribute::Default(d) => {
+                            defaults.push(d.clone());
+                        }
+                        Attribute::Range(r) => {
+                            ranges.push(r.clone());
                         }
                         Attribute::Prompt(p) => {
                             prompt = Some(p.text.clone());
+                            prompt_condition = p.condition.clone();
                         }
                         Attribute::Help(h) => {
                             help = Some(h.text.clone());
                         }
+                        Attribute::DependsOn(d) => {
+                            depends_on.push(d.expr.clone());
+                        }
+                        Attribute::Select(s) => selects.push(s.clone()),
+                        Attribute::Imply(i) => implies.push(i.clone()),
+                        Attribute::Option(o) => options.push(o.name.clone()),
+                        Attribute::Modules(_) => options.push("modules".into()),
                         _ => {}
                     }
-                    collect_attr_refs(attr, file, refs);
+                    collect_attr_refs(attr, file, &mut out.refs);
                 }
 
-                defs.push(SymbolDef {
+                out.defs.push(SymbolDef {
                     name: c.name.clone(),
                     kind,
                     name_span: c.name_span,
                     type_kind,
                     prompt,
+                    prompt_condition,
                     help,
+                    doc_comment: c.doc_comment.clone(),
+                    depends_on,
+                    inherited_depends: scope.conditions.clone(),
+                    visible_if: scope.visible_if.clone(),
+                    defaults,
+                    ranges,
+                    selects,
+                    implies,
+                    choice: scope.choice,
+                    transitional: c
+                        .attributes
+                        .iter()
+                        .any(|a| matches!(a, Attribute::Transitional(_))),
+                    options,
                     file: file.to_path_buf(),
                 });
+                if let Some(idx) = scope.choice {
+                    out.choices[idx].members.push(c.name.clone());
+                }
             }
             Entry::Choice(ch) => {
+                let mut prompt = None;
+                let mut optional = false;
+                let mut depends_on = Vec::new();
+                let mut defaults = Vec::new();
                 for attr in &ch.attributes {
-                    collect_attr_refs(attr, file, refs);
+                    match attr {
+                        Attribute::Prompt(p) => prompt = Some(p.text.clone()),
+                        Attribute::Type(t) => {
+                            if let Some(p) = &t.prompt {
+                                prompt = Some(p.text.clone());
+                            }
+                        }
+                        Attribute::Optional(_) => optional = true,
+                        Attribute::DependsOn(d) => depends_on.push(d.expr.clone()),
+                        Attribute::Default(d) => defaults.push(d.clone()),
+                        _ => {}
+                    }
+                    collect_attr_refs(attr, file, &mut out.refs);
                 }
-                collect_entries(&ch.entries, file, defs, refs);
+                let mut inner = scope.clone();
+                inner.choice = Some(out.choices.len());
+                inner.conditions.extend(depends_on.iter().cloned());
+                out.choices.push(ChoiceDef {
+                    name: ch.name.as_ref().map(|(n, _)| n.clone()),
+                    prompt,
+                    span: Span::new(ch.span.start, ch.span.start + "choice".len()),
+                    members: Vec::new(),
+                    optional,
+                    depends_on,
+                    defaults,
+                    file: file.to_path_buf(),
+                });
+                collect_entries(&ch.entries, file, &inner, out);
             }
             Entry::Comment(cm) => {
                 for attr in &cm.attributes {
-                    collect_attr_refs(attr, file, refs);
+                    collect_attr_refs(attr, file, &mut out.refs);
                 }
             }
             Entry::Menu(m) => {
+                let mut inner = scope.clone();
                 for attr in &m.attributes {
-                    collect_attr_refs(attr, file, refs);
+                    match attr {
+                        Attribute::DependsOn(d) => inner.conditions.push(d.expr.clone()),
+                        Attribute::VisibleIf(v) => inner.visible_if.push(v.expr.clone()),
+                        _ => {}
+                    }
+                    collect_attr_refs(attr, file, &mut out.refs);
                 }
-                collect_entries(&m.entries, file, defs, refs);
+                collect_entries(&m.entries, file, &inner, out);
             }
             Entry::If(i) => {
-                collect_expr_refs(&i.condition, RefKind::IfCondition, file, refs);
-                collect_entries(&i.entries, file, defs, refs);
+                collect_expr_refs(&i.condition, RefKind::IfCondition, file, &mut out.refs);
+                let mut inner = scope.clone();
+                inner.conditions.push(i.condition.clone());
+                collect_entries(&i.entries, file, &inner, out);
             }
-            Entry::Source(_) | Entry::MainMenu(_) => {}
+            Entry::Source(src) => {
+                out.sources.push(SourceStmt {
+                    path: src.path.clone(),
+                    path_span: src.path_span,
+                    optional: src.optional,
+                    relative: src.relative,
+                    conditions: scope.conditions.clone(),
+                });
+            }
+            Entry::MainMenu(_) => {}
         }
     }
 }
@@ -263,10 +879,10 @@ fn collect_attr_refs(attr: &Attribute, file: &Path, refs: &mut Vec<SymbolRef>) {
             }
         }
         Attribute::Type(t) => {
-            if let Some(p) = &t.prompt {
-                if let Some(cond) = &p.condition {
-                    collect_expr_refs(cond, RefKind::DependsOn, file, refs);
-                }
+            if let Some(p) = &t.prompt
+                && let Some(cond) = &p.condition
+            {
+                collect_expr_refs(cond, RefKind::DependsOn, file, refs);
             }
         }
         Attribute::Prompt(p) => {
@@ -277,7 +893,8 @@ fn collect_attr_refs(attr: &Attribute, file: &Path, refs: &mut Vec<SymbolRef>) {
         Attribute::Help(_)
         | Attribute::Modules(_)
         | Attribute::Transitional(_)
-        | Attribute::Optional(_) => {}
+        | Attribute::Optional(_)
+        | Attribute::Option(_) => {}
     }
 }
 
@@ -302,6 +919,7 @@ fn is_tristate_literal(s: &str) -> bool {
 }
 
 fn is_numeric_literal(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
     if s.starts_with("0x") || s.starts_with("0X") {
         s.len() > 2 && s[2..].chars().all(|c| c.is_ascii_hexdigit())
     } else {
@@ -1,5 +1,9 @@
 #![allow(dead_code)]
 
+use std::fmt;
+
+use crate::codes::Code;
+
 /// Byte-offset span in source text.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Span {
@@ -73,6 +77,23 @@ pub struct KconfigFile {
     pub entries: Vec<Entry>,
 }
 
+impl KconfigFile {
+    /// The `config`/`menuconfig` entry whose span covers `offset`.
+    pub fn config_at(&self, offset: usize) -> Option<&ConfigEntry> {
+        fn find(entries: &[Entry], offset: usize) -> Option<&ConfigEntry> {
+            let entry = entries.iter().find(|e| {
+                let span = e.span();
+                span.start <= offset && offset <= span.end
+            })?;
+            match entry {
+                Entry::Config(c) | Entry::MenuConfig(c) => Some(c),
+                _ => find(entry.children(), offset),
+            }
+        }
+        find(&self.entries, offset)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Entry {
     Config(ConfigEntry),
@@ -85,12 +106,49 @@ pub enum Entry {
     MainMenu(MainMenuEntry),
 }
 
+impl Entry {
+    pub fn span(&self) -> Span {
+        match self {
+            Entry::Config(c) | Entry::MenuConfig(c) => c.span,
+            Entry::Choice(c) => c.span,
+            Entry::Comment(c) => c.span,
+            Entry::Menu(m) => m.span,
+            Entry::If(i) => i.span,
+            Entry::Source(s) => s.span,
+            Entry::MainMenu(m) => m.span,
+        }
+    }
+
+    /// Attributes attached directly to this entry.
+    pub fn attributes(&self) -> &[Attribute] {
+        match self {
+            Entry::Config(c) | Entry::MenuConfig(c) => &c.attributes,
+            Entry::Choice(c) => &c.attributes,
+            Entry::Comment(c) => &c.attributes,
+            Entry::Menu(m) => &m.attributes,
+            Entry::If(_) | Entry::Source(_) | Entry::MainMenu(_) => &[],
+        }
+    }
+
+    /// Entries nested inside this one (`choice`, `menu`, `if` blocks).
+    pub fn children(&self) -> &[Entry] {
+        match self {
+            Entry::Choice(c) => &c.entries,
+            Entry::Menu(m) => &m.entries,
+            Entry::If(i) => &i.entries,
+            _ => &[],
+        }
+    }
+}
+
 /// Shared between `config` and `menuconfig`.
 #[derive(Debug, Clone)]
 pub struct ConfigEntry {
     pub name: String,
     pub name_span: Span,
     pub attributes: Vec<Attribute>,
+    /// Text of the `#` comment block directly above the entry, if any.
+    pub doc_comment: Option<String>,
     pub span: Span,
 }
 
@@ -109,6 +167,61 @@ pub enum Attribute {
     Modules(Span),
     Transitional(Span),
     Optional(Span),
+    Option(OptionAttr),
+}
+
+impl Attribute {
+    pub fn span(&self) -> Span {
+        match self {
+            Attribute::Type(t) => t.span,
+            Attribute::Prompt(p) => p.span,
+            Attribute::Default(d) => d.span,
+            Attribute::DefType(d) => d.span,
+            Attribute::DependsOn(d) => d.span,
+            Attribute::Select(s) => s.span,
+            Attribute::Imply(i) => i.span,
+            Attribute::VisibleIf(v) => v.span,
+            Attribute::Range(r) => r.span,
+            Attribute::Help(h) => h.span,
+            Attribute::Modules(s) | Attribute::Transitional(s) | Attribute::Optional(s) => *s,
+            Attribute::Option(o) => o.span,
+        }
+    }
+
+    /// Every expression in the attribute: values, range bounds, and `if`
+    /// conditions.
+    pub fn expressions(&self) -> Vec<&Expr> {
+        match self {
+            Attribute::Type(t) => t
+                .prompt
+                .as_ref()
+                .and_then(|p| p.condition.as_ref())
+                .into_iter()
+                .collect(),
+            Attribute::Prompt(p) => p.condition.iter().collect(),
+            Attribute::Default(d) => std::iter::once(&d.value).chain(&d.condition).collect(),
+            Attribute::DefType(d) => std::iter::once(&d.value).chain(&d.condition).collect(),
+            Attribute::DependsOn(d) => vec![&d.expr],
+            Attribute::Select(s) | Attribute::Imply(s) => s.condition.iter().collect(),
+            Attribute::VisibleIf(v) => vec![&v.expr],
+            Attribute::Range(r) => [&r.low, &r.high].into_iter().chain(&r.condition).collect(),
+            Attribute::Help(_)
+            | Attribute::Modules(_)
+            | Attribute::Transitional(_)
+            | Attribute::Optional(_)
+            | Attribute::Option(_) => Vec::new(),
+        }
+    }
+}
+
+/// `option defconfig_list`, `option allnoconfig_y`, `option modules`, or
+/// `option env="VAR"`.
+#[derive(Debug, Clone)]
+pub struct OptionAttr {
+    pub name: String,
+    pub name_span: Span,
+    pub value: Option<String>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -200,6 +313,10 @@ pub struct HelpAttr {
 
 #[derive(Debug, Clone)]
 pub struct ChoiceEntry {
+    /// `choice NAME`; older trees name choices so they can be referenced.
+    pub name: Option<(String, Span)>,
+    /// In source order, which may interleave with `entries`: attributes can
+    /// follow members.
     pub attributes: Vec<Attribute>,
     pub entries: Vec<Entry>,
     pub span: Span,
@@ -233,6 +350,11 @@ pub struct IfEntry {
 pub struct SourceEntry {
     pub path: String,
     pub path_span: Span,
+    /// `osource`/`orsource`: a path matching no file is not an error.
+    pub optional: bool,
+    /// `rsource`/`orsource`: the path is relative to the sourcing file's
+    /// directory instead of the source tree root.
+    pub relative: bool,
     pub span: Span,
 }
 
@@ -262,10 +384,25 @@ pub enum Expr {
 }
 
 impl Expr {
+    /// From the first operand to the last. Walks down the tree rather than
+    /// recursing, as long `&&` chains nest deeply.
     pub fn span(&self) -> Span {
+        let leaf = |mut e: &Expr, rightmost: bool| loop {
+            match e {
+                Expr::Symbol(_, s) | Expr::StringLit(_, s) => return *s,
+                Expr::Not(inner) | Expr::Paren(inner) => e = inner,
+                _ => {
+                    let (a, b) = e.operands().unwrap();
+                    e = if rightmost { b } else { a };
+                }
+            }
+        };
+        leaf(self, false).merge(leaf(self, true))
+    }
+
+    /// The two sides of a binary operator.
+    pub fn operands(&self) -> Option<(&Expr, &Expr)> {
         match self {
-            Expr::Symbol(_, s) | Expr::StringLit(_, s) => *s,
-            Expr::Not(e) | Expr::Paren(e) => e.span(),
             Expr::And(a, b)
             | Expr::Or(a, b)
             | Expr::Eq(a, b)
@@ -273,31 +410,49 @@ impl Expr {
             | Expr::Less(a, b)
             | Expr::LessEq(a, b)
             | Expr::Greater(a, b)
-            | Expr::GreaterEq(a, b) => a.span().merge(b.span()),
+            | Expr::GreaterEq(a, b) => Some((a, b)),
+            Expr::Symbol(..) | Expr::StringLit(..) | Expr::Not(_) | Expr::Paren(_) => None,
         }
     }
 
-    /// Collect all symbol references inside this expression.
+    /// Collect all symbol references inside this expression, in source
+    /// order.
     pub fn collect_symbols(&self, out: &mut Vec<(String, Span)>) {
-        match self {
-            Expr::Symbol(name, span) => out.push((name.clone(), *span)),
-            Expr::StringLit(..) => {}
-            Expr::Not(e) | Expr::Paren(e) => e.collect_symbols(out),
-            Expr::And(a, b)
-            | Expr::Or(a, b)
-            | Expr::Eq(a, b)
-            | Expr::NotEq(a, b)
-            | Expr::Less(a, b)
-            | Expr::LessEq(a, b)
-            | Expr::Greater(a, b)
-            | Expr::GreaterEq(a, b) => {
-                a.collect_symbols(out);
-                b.collect_symbols(out);
+        let mut pending = vec![self];
+        while let Some(e) = pending.pop() {
+            match e {
+                Expr::Symbol(name, span) => out.push((name.clone(), *span)),
+                Expr::StringLit(..) => {}
+                Expr::Not(inner) | Expr::Paren(inner) => pending.push(inner),
+                _ => {
+                    let (a, b) = e.operands().unwrap();
+                    pending.push(b);
+                    pending.push(a);
+                }
             }
         }
     }
 }
 
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Symbol(name, _) => write!(f, "{}", name),
+            Expr::StringLit(s, _) => write!(f, "\"{}\"", s.replace('"', "\\\"")),
+            Expr::Not(e) => write!(f, "!{}", e),
+            Expr::Paren(e) => write!(f, "({})", e),
+            Expr::And(a, b) => write!(f, "{} && {}", a, b),
+            Expr::Or(a, b) => write!(f, "{} || {}", a, b),
+            Expr::Eq(a, b) => write!(f, "{} = {}", a, b),
+            Expr::NotEq(a, b) => write!(f, "{} != {}", a, b),
+            Expr::Less(a, b) => write!(f, "{} < {}", a, b),
+            Expr::LessEq(a, b) => write!(f, "{} <= {}", a, b),
+            Expr::Greater(a, b) => write!(f, "{} > {}", a, b),
+            Expr::GreaterEq(a, b) => write!(f, "{} >= {}", a, b),
+        }
+    }
+}
+
 // -- Parse diagnostics (errors / warnings) ----------------------------------
 
 #[derive(Debug, Clone)]
@@ -305,6 +460,7 @@ pub struct ParseDiagnostic {
     pub message: String,
     pub span: Span,
     pub severity: DiagSeverity,
+    pub code: Code,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
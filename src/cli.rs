@@ -0,0 +1,949 @@
+//! Command-line subcommands. Without a subcommand the binary runs the
+//! language server on stdio.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::analysis::WorldIndex;
+use crate::code_action;
+use crate::codes;
+use crate::debug_dump;
+use crate::dependency_path::{self, DependencyPathParams};
+use crate::diagnostics;
+use crate::doc_site::{self, DocFormat};
+use crate::format::{self, FormatOptions};
+use crate::index_stats;
+use crate::lexer::Dialect;
+use crate::lint;
+use crate::lint_config::{Baseline, LintConfig};
+use crate::patch::Patch;
+use crate::profile;
+use crate::project_stats::{self, ProjectStatsParams};
+use crate::sarif::SarifLog;
+use crate::server;
+use crate::settings::Settings;
+use crate::sourced_files;
+use crate::symbol_metrics::{self, SymbolMetricsParams};
+use crate::tree_diff;
+
+/// Run the subcommand named by `args[0]`, or return `None` to start the
+/// language server.
+pub fn run(args: &[String]) -> Option<ExitCode> {
+    let (cmd, rest) = args.split_first()?;
+    Some(match cmd.as_str() {
+        "fmt" => fmt(rest),
+        "lint" => lint(rest),
+        "check-diff" => check_diff(rest),
+        "doc" => doc(rest),
+        "diff" => diff(rest),
+        "stats" => stats(rest),
+        "dependency-path" => dependency_path(rest),
+        "metrics" => metrics(rest),
+        "dump-tokens" => dump(rest, debug_dump::dump_tokens),
+        "dump-ast" => dump(rest, debug_dump::dump_ast),
+        "help" | "--help" | "-h" => {
+            print!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        "--version" | "-V" => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprint!("unknown command `{}`\n\n{}", cmd, USAGE);
+            ExitCode::from(2)
+        }
+    })
+}
+
+const USAGE: &str = "\
+Usage: kconfig-lsp [COMMAND]
+
+Without a command, runs the language server on stdin/stdout.
+
+Commands:
+  fmt [--check] [--tab-size N] [--insert-spaces] <PATH>...
+      Format Kconfig files in place. Directories are searched for Kconfig
+      files; `-` formats stdin to stdout. With --check, files are left
+      untouched and the exit status is 1 if any would change.
+
+  lint [--root DIR] [--config FILE] [--baseline FILE] [--write-baseline FILE]
+       [--format text|sarif] [PATH...]
+      Report diagnostics for the Kconfig files under PATH (default: the
+      root, itself defaulting to the current directory). The whole root is
+      indexed so cross-file references resolve. --config reads per-path lint
+      settings from a TOML file; problems listed in the --baseline file are
+      not reported. --write-baseline records the current problems instead.
+      --format sarif prints a SARIF log, with the quick fixes for the
+      problems that have one. The exit status is 1 if any problem is
+      reported.
+
+  check-diff [--root DIR] [--config FILE] (PATCH | - | --git REV)
+      Analyze the tree under the root (which must already contain the
+      change) and report only problems on lines the patch adds, plus
+      symbols whose last definition it removes while they are still
+      referenced. The patch is a unified diff with paths relative to the
+      root, read from a file, stdin, or `git diff --relative REV`.
+
+  doc [--root PATH] [--format markdown|html] --out DIR
+      Write one page per menu to DIR, starting with index.md (or
+      index.html): every option with the documentation hover shows for it,
+      an anchor per symbol, and links to the symbols it depends on, selects
+      and implies. PATH is the top-level Kconfig file, or a directory
+      containing one (default: the current directory).
+
+  diff [--root DIR] [--json] OLD NEW
+      List symbols added, removed or retyped between two trees, and those
+      whose prompt, defaults or dependencies changed. OLD and NEW are two
+      directories (or top-level Kconfig files), or else two revisions of
+      the git repository at the root.
+
+  stats [--root DIR] [--json] [--top N]
+      Count the symbols defined per directory of the tree at the root, and
+      list the files with the most parse errors, the most referenced
+      symbols, the longest dependency chains, and the longest help texts,
+      N of each (default: 10).
+
+  metrics [--root DIR] [SYMBOL...]
+      Print per-symbol metrics of the tree at the root as JSON: definition
+      count, references by kind, dependency fan-in and fan-out, and help
+      length, for every defined symbol or only those named.
+
+  dependency-path [--root DIR] [--json] [--limit N] [--max-length N] FROM TO
+      List the chains of `depends on`, `select` and `imply` leading from
+      symbol FROM to symbol TO, shortest first, with where each link is
+      written: at most N chains (default: 10) of at most --max-length links
+      (default: 8). The exit status is 1 if there is none.
+
+  dump-tokens [--dialect kconfig|kconfiglib] <FILE>
+  dump-ast [--dialect kconfig|kconfiglib] <FILE>
+      Print the tokens, or the parse tree with its diagnostics, that the
+      server makes of a Kconfig file (`-` for stdin), to attach to parser
+      bug reports. --dialect is the grammar, as the `dialect` setting.
+";
+
+fn fmt(args: &[String]) -> ExitCode {
+    let mut options = FormatOptions::default();
+    let mut check = false;
+    let mut paths: Vec<PathBuf> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--insert-spaces" => options.insert_spaces = true,
+            "--tab-size" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => options.tab_size = n,
+                None => return usage_error("--tab-size expects a number"),
+            },
+            s if s.starts_with("--") => return usage_error(&format!("unknown option `{}`", s)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        return usage_error("no paths given");
+    }
+
+    let mut unformatted = false;
+    let mut failed = false;
+    for path in paths {
+        if path == Path::new("-") {
+            let mut text = String::new();
+            if let Err(e) = std::io::stdin().read_to_string(&mut text) {
+                eprintln!("<stdin>: {}", e);
+                failed = true;
+                continue;
+            }
+            let formatted = format::format(&text, &options);
+            if check {
+                if formatted != text {
+                    println!("<stdin>");
+                    unformatted = true;
+                }
+            } else if std::io::stdout().write_all(formatted.as_bytes()).is_err() {
+                failed = true;
+            }
+            continue;
+        }
+
+        let files = if path.is_dir() {
+            let mut files = server::discover_kconfig_files(&path);
+            files.sort();
+            files
+        } else {
+            vec![path]
+        };
+        for file in files {
+            match fmt_file(&file, &options, check) {
+                Ok(changed) => unformatted |= changed,
+                Err(e) => {
+                    eprintln!("{}: {}", file.display(), e);
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if check && unformatted {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Format one file, returning whether its contents differ from the
+/// formatted output. With `check`, the file is reported but not written.
+fn fmt_file(path: &Path, options: &FormatOptions, check: bool) -> std::io::Result<bool> {
+    let text = std::fs::read_to_string(path)?;
+    let formatted = format::format(&text, options);
+    if formatted == text {
+        return Ok(false);
+    }
+    if check {
+        println!("{}", path.display());
+    } else {
+        std::fs::write(path, formatted)?;
+    }
+    Ok(true)
+}
+
+fn lint(args: &[String]) -> ExitCode {
+    let mut root = PathBuf::from(".");
+    let mut sarif = false;
+    let mut config = LintConfig::default();
+    let mut baseline: Option<Baseline> = None;
+    let mut write_baseline: Option<PathBuf> = None;
+    let mut paths: Vec<PathBuf> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .map(PathBuf::from)
+                .ok_or_else(|| format!("{} expects a path", name))
+        };
+        let parsed: Result<(), String> = match arg.as_str() {
+            "--root" => value("--root").map(|p| root = p),
+            "--config" => value("--config").and_then(|p| {
+                config = LintConfig::load(&p).map_err(|e| format!("{}: {}", p.display(), e))?;
+                Ok(())
+            }),
+            "--baseline" => value("--baseline").and_then(|p| {
+                baseline = Some(Baseline::load(&p).map_err(|e| format!("{}: {}", p.display(), e))?);
+                Ok(())
+            }),
+            "--write-baseline" => value("--write-baseline").map(|p| write_baseline = Some(p)),
+            "--format" => match args.next().map(String::as_str) {
+                Some("text") => Ok(false),
+                Some("sarif") => Ok(true),
+                _ => Err("--format expects `text` or `sarif`".to_string()),
+            }
+            .map(|s| sarif = s),
+            s if s.starts_with("--") => Err(format!("unknown option `{}`", s)),
+            _ => {
+                paths.push(PathBuf::from(arg));
+                Ok(())
+            }
+        };
+        if let Err(e) = parsed {
+            return usage_error(&e);
+        }
+    }
+
+    let root = match root.canonicalize() {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}: {}", root.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    let index = index_tree(&root);
+
+    let mut targets: Vec<PathBuf> = Vec::new();
+    if paths.is_empty() {
+        paths.push(root.clone());
+    }
+    for path in paths {
+        let Ok(path) = path.canonicalize() else {
+            eprintln!("{}: no such file or directory", path.display());
+            return ExitCode::from(2);
+        };
+        if path.is_dir() {
+            targets.extend(server::discover_kconfig_files(&path));
+        } else {
+            targets.push(path);
+        }
+    }
+    targets.sort();
+    targets.dedup();
+
+    let mut settings = Settings {
+        max_diagnostics: 0,
+        ..Settings::default()
+    };
+    let mut keys: Vec<String> = Vec::new();
+    let mut log = sarif.then(|| SarifLog::new(&root));
+    let (mut reported, mut baselined) = (0usize, 0usize);
+    for path in &targets {
+        let Some(fa) = index.files.get(path) else {
+            eprintln!("{}: not a Kconfig file under the root", path.display());
+            continue;
+        };
+        let rel = path.strip_prefix(&root).unwrap_or(path);
+        settings.lint = config.settings_for(rel);
+        let fixes = match log {
+            Some(_) => {
+                code_action::quick_fixes(fa, &lint::check_file(&index, path, &settings.lint))
+            }
+            None => Vec::new(),
+        };
+        for diag in diagnostics::collect(&index, path, &settings) {
+            let key = Baseline::key(rel, &diag_code(&diag), &diag.message);
+            if write_baseline.is_some() {
+                keys.push(key);
+                continue;
+            }
+            if baseline.as_mut().is_some_and(|b| b.take(&key)) {
+                baselined += 1;
+                continue;
+            }
+            reported += 1;
+            match &mut log {
+                Some(log) => log.add(rel, fa, &diag, &fixes),
+                None => print_diag(rel, &diag),
+            }
+        }
+    }
+
+    if let Some(out) = write_baseline {
+        if let Err(e) = std::fs::write(&out, Baseline::render(&keys)) {
+            eprintln!("{}: {}", out.display(), e);
+            return ExitCode::from(2);
+        }
+        eprintln!("wrote {} problems to {}", keys.len(), out.display());
+        return ExitCode::SUCCESS;
+    }
+    if let Some(log) = log {
+        println!("{}", log.render());
+    }
+
+    eprintln!(
+        "{} file{} checked, {} problem{}{}",
+        targets.len(),
+        if targets.len() == 1 { "" } else { "s" },
+        reported,
+        if reported == 1 { "" } else { "s" },
+        if baselined > 0 {
+            format!(" ({} baselined)", baselined)
+        } else {
+            String::new()
+        }
+    );
+    if reported > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn check_diff(args: &[String]) -> ExitCode {
+    let mut root = PathBuf::from(".");
+    let mut config = LintConfig::default();
+    let mut git_rev: Option<String> = None;
+    let mut patch_file: Option<PathBuf> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => match args.next() {
+                Some(p) => root = PathBuf::from(p),
+                None => return usage_error("--root expects a path"),
+            },
+            "--config" => match args.next().map(|p| (p, LintConfig::load(Path::new(p)))) {
+                Some((_, Ok(c))) => config = c,
+                Some((p, Err(e))) => return usage_error(&format!("{}: {}", p, e)),
+                None => return usage_error("--config expects a path"),
+            },
+            "--git" => match args.next() {
+                Some(rev) => git_rev = Some(rev.clone()),
+                None => return usage_error("--git expects a revision"),
+            },
+            s if s.starts_with("--") => return usage_error(&format!("unknown option `{}`", s)),
+            _ => patch_file = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let text = match (&git_rev, &patch_file) {
+        (Some(rev), None) => {
+            let output = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(["diff", "--relative", "--no-color", rev])
+                .output();
+            match output {
+                Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout).into_owned(),
+                Ok(o) => {
+                    eprint!("{}", String::from_utf8_lossy(&o.stderr));
+                    return ExitCode::from(2);
+                }
+                Err(e) => {
+                    eprintln!("git: {}", e);
+                    return ExitCode::from(2);
+                }
+            }
+        }
+        (None, Some(path)) if path == Path::new("-") => {
+            let mut text = String::new();
+            if let Err(e) = std::io::stdin().read_to_string(&mut text) {
+                eprintln!("<stdin>: {}", e);
+                return ExitCode::from(2);
+            }
+            text
+        }
+        (None, Some(path)) => match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                return ExitCode::from(2);
+            }
+        },
+        _ => return usage_error("give either a patch file or --git REV"),
+    };
+    let patch = Patch::parse(&text);
+
+    let root = match root.canonicalize() {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}: {}", root.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    let index = index_tree(&root);
+    let mut settings = Settings {
+        max_diagnostics: 0,
+        ..Settings::default()
+    };
+
+    let mut reported = 0usize;
+    for file in &patch.files {
+        let Some(rel) = &file.new_path else {
+            continue;
+        };
+        let path = root.join(rel);
+        if !index.files.contains_key(&path) {
+            continue;
+        }
+        settings.lint = config.settings_for(rel);
+        for diag in diagnostics::collect(&index, &path, &settings) {
+            if file.adds_line(diag.range.start.line) {
+                reported += 1;
+                print_diag(rel, &diag);
+            }
+        }
+    }
+
+    // Cross-file consequences: definitions removed by the patch whose symbol
+    // is now undefined but still referenced elsewhere.
+    for file in &patch.files {
+        let Some(rel) = file.old_path.as_ref().or(file.new_path.as_ref()) else {
+            continue;
+        };
+        for removed in &file.removed_symbols {
+            if !index.get_definitions(&removed.name).is_empty() {
+                continue;
+            }
+            let refs = index.get_references(&removed.name);
+            let mut ref_files: Vec<&Path> = refs.iter().map(|r| r.file.as_path()).collect();
+            ref_files.sort();
+            ref_files.dedup();
+            if ref_files.is_empty() {
+                continue;
+            }
+            reported += 1;
+            println!(
+                "{}:{}:1: warning: this patch removes the last definition of {}, still referenced in {} file{}",
+                rel.display(),
+                removed.line,
+                removed.name,
+                ref_files.len(),
+                if ref_files.len() == 1 { "" } else { "s" }
+            );
+            for r in refs {
+                let Some(fa) = index.files.get(&r.file) else {
+                    continue;
+                };
+                let (line, col) = fa.line_index.line_col(r.span.start);
+                println!(
+                    "{}:{}:{}: note: {} referenced here",
+                    r.file.strip_prefix(&root).unwrap_or(&r.file).display(),
+                    line + 1,
+                    col + 1,
+                    removed.name
+                );
+            }
+        }
+    }
+
+    eprintln!(
+        "{} problem{} introduced",
+        reported,
+        if reported == 1 { "" } else { "s" }
+    );
+    if reported > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn diag_code(diag: &Diagnostic) -> String {
+    match &diag.code {
+        Some(NumberOrString::String(s)) => s.clone(),
+        Some(NumberOrString::Number(n)) => n.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Print a diagnostic as `path:line:col: severity: message [code name]`.
+fn print_diag(rel: &Path, diag: &Diagnostic) {
+    let severity = match diag.severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        _ => "note",
+    };
+    let code = diag_code(diag);
+    let code = match codes::by_id(&code) {
+        Some(c) => format!(" [{} {}]", c.id, c.name),
+        None if code.is_empty() => code,
+        None => format!(" [{}]", code),
+    };
+    println!(
+        "{}:{}:{}: {}: {}{}",
+        rel.display(),
+        diag.range.start.line + 1,
+        diag.range.start.character + 1,
+        severity,
+        diag.message,
+        code
+    );
+}
+
+fn doc(args: &[String]) -> ExitCode {
+    let mut root = PathBuf::from(".");
+    let mut format = DocFormat::Markdown;
+    let mut out: Option<PathBuf> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let parsed: Result<(), String> = match arg.as_str() {
+            "--root" => args
+                .next()
+                .map(|p| root = PathBuf::from(p))
+                .ok_or_else(|| "--root expects a path".to_string()),
+            "--out" => args
+                .next()
+                .map(|p| out = Some(PathBuf::from(p)))
+                .ok_or_else(|| "--out expects a path".to_string()),
+            "--format" => match args.next().map(String::as_str) {
+                Some("markdown" | "md") => Ok(DocFormat::Markdown),
+                Some("html") => Ok(DocFormat::Html),
+                _ => Err("--format expects `markdown` or `html`".to_string()),
+            }
+            .map(|f| format = f),
+            s => Err(format!("unexpected argument `{}`", s)),
+        };
+        if let Err(e) = parsed {
+            return usage_error(&e);
+        }
+    }
+    let Some(out) = out else {
+        return usage_error("doc needs --out");
+    };
+
+    let root = match root.canonicalize() {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}: {}", root.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    let (dir, kconfig) = if root.is_dir() {
+        (root.clone(), root.join("Kconfig"))
+    } else {
+        (root.parent().unwrap_or(&root).to_path_buf(), root.clone())
+    };
+    let mut index = index_tree(&dir);
+    if !index.files.contains_key(&kconfig)
+        && let Ok(source) = index_stats::read_kconfig(&kconfig, Settings::default().max_file_size)
+    {
+        index.analyze_file(&kconfig, &source);
+    }
+    index.root_kconfig = Some(kconfig.clone());
+
+    let Some(pages) = doc_site::render(&index, &kconfig, format) else {
+        eprintln!("{}: not a readable Kconfig file", kconfig.display());
+        return ExitCode::from(2);
+    };
+    if let Err(e) = std::fs::create_dir_all(&out) {
+        eprintln!("{}: {}", out.display(), e);
+        return ExitCode::from(2);
+    }
+    for page in &pages {
+        let path = out.join(&page.path);
+        if let Err(e) = std::fs::write(&path, &page.content) {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::from(2);
+        }
+    }
+    eprintln!(
+        "wrote {} page{} to {}",
+        pages.len(),
+        if pages.len() == 1 { "" } else { "s" },
+        out.display()
+    );
+    ExitCode::SUCCESS
+}
+
+fn diff(args: &[String]) -> ExitCode {
+    let mut root = PathBuf::from(".");
+    let mut json = false;
+    let mut sides: Vec<&String> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => match args.next() {
+                Some(p) => root = PathBuf::from(p),
+                None => return usage_error("--root expects a path"),
+            },
+            "--json" => json = true,
+            s if s.starts_with("--") => return usage_error(&format!("unknown option `{}`", s)),
+            _ => sides.push(arg),
+        }
+    }
+    let [old, new] = sides[..] else {
+        return usage_error("diff takes two trees or two revisions");
+    };
+
+    let indexes = if Path::new(old).exists() && Path::new(new).exists() {
+        index_path(Path::new(old)).and_then(|o| Ok((o, index_path(Path::new(new))?)))
+    } else {
+        index_revision(&root, old).and_then(|o| Ok((o, index_revision(&root, new)?)))
+    };
+    let (old, new) = match indexes {
+        Ok(pair) => pair,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let changes = tree_diff::diff(&old, &new);
+    if json {
+        match serde_json::to_string_pretty(&changes) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::from(2);
+            }
+        }
+    } else {
+        print!("{}", tree_diff::render_text(&changes));
+    }
+    ExitCode::SUCCESS
+}
+
+fn stats(args: &[String]) -> ExitCode {
+    let mut root = PathBuf::from(".");
+    let mut json = false;
+    let mut params = ProjectStatsParams::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => match args.next() {
+                Some(p) => root = PathBuf::from(p),
+                None => return usage_error("--root expects a path"),
+            },
+            "--json" => json = true,
+            "--top" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => params.limit = Some(n),
+                None => return usage_error("--top expects a number"),
+            },
+            other => return usage_error(&format!("unexpected argument `{}`", other)),
+        }
+    }
+
+    let index = match index_path(&root) {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let stats = project_stats::project_stats(&index, &params);
+    if json {
+        match serde_json::to_string_pretty(&stats) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::from(2);
+            }
+        }
+    } else {
+        print!("{}", project_stats::render_text(&stats));
+    }
+    ExitCode::SUCCESS
+}
+
+fn metrics(args: &[String]) -> ExitCode {
+    let mut root = PathBuf::from(".");
+    let mut params = SymbolMetricsParams::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => match args.next() {
+                Some(p) => root = PathBuf::from(p),
+                None => return usage_error("--root expects a path"),
+            },
+            s if s.starts_with("--") => return usage_error(&format!("unknown option `{}`", s)),
+            _ => params.symbols.push(arg.clone()),
+        }
+    }
+
+    let index = match index_path(&root) {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+    match serde_json::to_string_pretty(&symbol_metrics::symbol_metrics(&index, &params)) {
+        Ok(text) => println!("{}", text),
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn dependency_path(args: &[String]) -> ExitCode {
+    let mut root = PathBuf::from(".");
+    let mut json = false;
+    let mut limit = None;
+    let mut max_length = None;
+    let mut symbols: Vec<&String> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => match args.next() {
+                Some(p) => root = PathBuf::from(p),
+                None => return usage_error("--root expects a path"),
+            },
+            "--json" => json = true,
+            "--limit" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => limit = Some(n),
+                None => return usage_error("--limit expects a number"),
+            },
+            "--max-length" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => max_length = Some(n),
+                None => return usage_error("--max-length expects a number"),
+            },
+            s if s.starts_with("--") => return usage_error(&format!("unknown option `{}`", s)),
+            _ => symbols.push(arg),
+        }
+    }
+    let [from, to] = symbols[..] else {
+        return usage_error("dependency-path takes two symbols");
+    };
+
+    let index = match index_path(&root) {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let params = DependencyPathParams {
+        from: from.clone(),
+        to: to.clone(),
+        limit,
+        max_length,
+    };
+    let paths = dependency_path::dependency_paths(&index, &params);
+    if json {
+        match serde_json::to_string_pretty(&paths) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::from(2);
+            }
+        }
+    } else if paths.is_empty() {
+        eprintln!("no dependency path from {} to {}", from, to);
+    } else {
+        print!("{}", dependency_path::render_text(&index, &paths));
+    }
+    if paths.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// `dump-tokens` and `dump-ast`: print what `dump` makes of one file.
+fn dump(args: &[String], dump: fn(&str, Dialect) -> String) -> ExitCode {
+    let (dialect, args) = match args {
+        [flag, name, rest @ ..] if flag == "--dialect" => match name.as_str() {
+            "kconfig" => (Dialect::Kconfig, rest),
+            "kconfiglib" => (Dialect::Kconfiglib, rest),
+            _ => return usage_error(&format!("unknown dialect `{}`", name)),
+        },
+        _ => (Dialect::default(), args),
+    };
+    let [path] = args else {
+        return usage_error("expected one file");
+    };
+    let mut text = String::new();
+    let read = if path == "-" {
+        std::io::stdin().read_to_string(&mut text).map(|_| ())
+    } else {
+        std::fs::read_to_string(path).map(|t| text = t)
+    };
+    if let Err(e) = read {
+        eprintln!("{}: {}", path, e);
+        return ExitCode::from(2);
+    }
+    print!("{}", dump(&text, dialect));
+    ExitCode::SUCCESS
+}
+
+/// Index the tree of a directory, or of the directory of a top-level
+/// Kconfig file.
+fn index_path(path: &Path) -> Result<WorldIndex, String> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    if path.is_dir() {
+        return Ok(index_tree(&path));
+    }
+    let dir = path.parent().unwrap_or(&path);
+    let mut index = index_tree(dir);
+    if !index.files.contains_key(&path) {
+        let source = index_stats::read_kconfig(&path, Settings::default().max_file_size)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        index.analyze_file(&path, &source);
+    }
+    index.root_kconfig = Some(path);
+    Ok(index)
+}
+
+/// Index the Kconfig files under `root` as of git revision `rev`, without
+/// checking it out.
+fn index_revision(root: &Path, rev: &str) -> Result<WorldIndex, String> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", root.display(), e))?;
+    let git = |args: &[&str], input: Option<String>| -> Result<Vec<u8>, String> {
+        let mut child = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&root)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("git: {}", e))?;
+        let mut stdin = child.stdin.take().expect("piped stdin");
+        // Write from another thread so a full stdout pipe cannot deadlock us.
+        let writer = std::thread::spawn(move || {
+            if let Some(input) = input {
+                let _ = stdin.write_all(input.as_bytes());
+            }
+        });
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("git: {}", e))?;
+        let _ = writer.join();
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string());
+        }
+        Ok(output.stdout)
+    };
+
+    let listing = git(&["ls-tree", "-r", "--name-only", "-z", rev], None)?;
+    let files: Vec<String> = listing
+        .split(|&b| b == 0)
+        .map(|p| String::from_utf8_lossy(p).into_owned())
+        .filter(|p| server::is_kconfig_file(Path::new(p)))
+        .collect();
+    let request: String = files.iter().map(|f| format!("{}:./{}\n", rev, f)).collect();
+    let blobs = git(&["cat-file", "--batch"], Some(request))?;
+
+    let mut index = WorldIndex::new();
+    index.root = Some(root.clone());
+    index.root_kconfig = Some(root.join("Kconfig"));
+    let mut rest = &blobs[..];
+    for file in &files {
+        // `<oid> blob <size>\n<content>\n`, or `<object> missing\n`.
+        let Some(eol) = rest.iter().position(|&b| b == b'\n') else {
+            break;
+        };
+        let header = String::from_utf8_lossy(&rest[..eol]).into_owned();
+        rest = &rest[eol + 1..];
+        let Some(size) = header
+            .split(' ')
+            .nth(2)
+            .and_then(|n| n.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let content = &rest[..size.min(rest.len())];
+        rest = &rest[(size + 1).min(rest.len())..];
+        match String::from_utf8(content.to_vec()) {
+            Ok(source) => index.analyze_file(&root.join(file), &source),
+            Err(_) => eprintln!("{}:{}: skipped: not valid UTF-8", rev, file),
+        }
+    }
+    Ok(index)
+}
+
+/// Index every Kconfig file under `root`, as the server does for a workspace
+/// with default settings and the profile detected for it.
+fn index_tree(root: &Path) -> WorldIndex {
+    let mut settings = Settings::default();
+    let profile = profile::apply(&mut settings, root);
+    let mut index = WorldIndex::new();
+    index.profile = profile;
+    index.root = Some(root.to_path_buf());
+    index.root_kconfig = Some(root.join(&settings.root_kconfig));
+    index.preprocessor = settings.effective_preprocessor(Some(root));
+    for path in server::discover_workspace_files(
+        &settings.scan_roots(root),
+        &settings.file_names,
+        settings.scan_depth,
+    ) {
+        match index_stats::read_kconfig(&path, settings.max_file_size) {
+            Ok(source) => index.analyze_file(&path, &source),
+            Err(reason) => eprintln!("{}: skipped: {}", path.display(), reason),
+        }
+    }
+    let indexed = index.files.keys().cloned().collect();
+    sourced_files::index_sourced_files(&mut index, indexed, settings.max_file_size);
+    index
+}
+
+fn usage_error(msg: &str) -> ExitCode {
+    eprint!("error: {}\n\n{}", msg, USAGE);
+    ExitCode::from(2)
+}
@@ -0,0 +1,147 @@
+//! What the client declared it supports in `initialize`. Optional protocol
+//! features are only advertised and used when the client asked for them, and
+//! content is rendered in what it can show, e.g. plain-text hovers.
+
+use tower_lsp::lsp_types::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientCaps {
+    /// Hovers may be Markdown; otherwise they are sent as plain text.
+    pub hover_markdown: bool,
+    pub completion: bool,
+    /// Completion documentation may be Markdown.
+    pub completion_markdown: bool,
+    /// Completion items may insert snippets with placeholders.
+    pub completion_snippets: bool,
+    /// Code actions may be returned as `CodeAction` literals.
+    pub code_actions: bool,
+    pub code_lens: bool,
+    /// `window/workDoneProgress/create` may be sent.
+    pub work_done_progress: bool,
+    /// File watchers may be registered at runtime.
+    pub watched_files: bool,
+    pub diagnostic_related_information: bool,
+    /// Diagnostics may link their code to documentation.
+    pub diagnostic_code_description: bool,
+    /// Workspace edits may carry change annotations that ask the user to
+    /// confirm them.
+    pub change_annotations: bool,
+    /// `kconfig/fileAssociations` may be sent, from
+    /// `experimental.kconfigFileAssociations`.
+    pub file_associations: bool,
+    /// The type hierarchy requests may be registered at runtime; the
+    /// protocol types in use have no static capability for them.
+    pub type_hierarchy: bool,
+}
+
+impl Default for ClientCaps {
+    /// Everything, as assumed before `initialize`.
+    fn default() -> Self {
+        ClientCaps {
+            hover_markdown: true,
+            completion: true,
+            completion_markdown: true,
+            completion_snippets: true,
+            code_actions: true,
+            code_lens: true,
+            work_done_progress: true,
+            watched_files: true,
+            diagnostic_related_information: true,
+            diagnostic_code_description: true,
+            change_annotations: true,
+            file_associations: true,
+            type_hierarchy: true,
+        }
+    }
+}
+
+impl ClientCaps {
+    pub fn new(caps: &ClientCapabilities) -> Self {
+        let text = caps.text_document.as_ref();
+        let diagnostics = text.and_then(|t| t.publish_diagnostics.as_ref());
+        ClientCaps {
+            hover_markdown: text
+                .and_then(|t| t.hover.as_ref())
+                .and_then(|h| h.content_format.as_ref())
+                .is_some_and(|formats| formats.contains(&MarkupKind::Markdown)),
+            completion: text.is_some_and(|t| t.completion.is_some()),
+            completion_markdown: text
+                .and_then(|t| t.completion.as_ref())
+                .and_then(|c| c.completion_item.as_ref())
+                .and_then(|i| i.documentation_format.as_ref())
+                .is_some_and(|formats| formats.contains(&MarkupKind::Markdown)),
+            completion_snippets: text
+                .and_then(|t| t.completion.as_ref())
+                .and_then(|c| c.completion_item.as_ref())
+                .and_then(|i| i.snippet_support)
+                .unwrap_or(false),
+            code_actions: text
+                .and_then(|t| t.code_action.as_ref())
+                .is_some_and(|c| c.code_action_literal_support.is_some()),
+            code_lens: text.is_some_and(|t| t.code_lens.is_some()),
+            work_done_progress: caps
+                .window
+                .as_ref()
+                .and_then(|w| w.work_done_progress)
+                .unwrap_or(false),
+            watched_files: caps
+                .workspace
+                .as_ref()
+                .and_then(|w| w.did_change_watched_files.as_ref())
+                .and_then(|d| d.dynamic_registration)
+                .unwrap_or(false),
+            diagnostic_related_information: diagnostics
+                .and_then(|d| d.related_information)
+                .unwrap_or(false),
+            diagnostic_code_description: diagnostics
+                .and_then(|d| d.code_description_support)
+                .unwrap_or(false),
+            change_annotations: caps
+                .workspace
+                .as_ref()
+                .and_then(|w| w.workspace_edit.as_ref())
+                .is_some_and(|e| e.change_annotation_support.is_some()),
+            file_associations: caps
+                .experimental
+                .as_ref()
+                .and_then(|e| e.get("kconfigFileAssociations"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            type_hierarchy: text
+                .and_then(|t| t.type_hierarchy.as_ref())
+                .and_then(|h| h.dynamic_registration)
+                .unwrap_or(false),
+        }
+    }
+
+    /// The format to render hovers in.
+    pub fn hover_format(&self) -> MarkupKind {
+        if self.hover_markdown {
+            MarkupKind::Markdown
+        } else {
+            MarkupKind::PlainText
+        }
+    }
+
+    /// The format to render completion documentation in.
+    pub fn completion_format(&self) -> MarkupKind {
+        if self.completion_markdown {
+            MarkupKind::Markdown
+        } else {
+            MarkupKind::PlainText
+        }
+    }
+
+    /// `diags` without the fields the client does not understand.
+    pub fn diagnostics(&self, mut diags: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        for diag in &mut diags {
+            if !self.diagnostic_related_information {
+                diag.related_information = None;
+            }
+            if !self.diagnostic_code_description {
+                diag.code_description = None;
+            }
+        }
+        diags
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::{FileAnalysis, WorldIndex};
+use crate::ast::Span;
+use crate::codes::{self, Code};
+use crate::impact;
+use crate::lint::{self, Edit, Finding, Fix};
+use crate::settings::Settings;
+use crate::util::range;
+use crate::{end_comments, extract, sort_entries, transitional};
+
+pub fn code_actions(
+    index: &WorldIndex,
+    uri: &Url,
+    path: &Path,
+    range: Range,
+    settings: &Settings,
+) -> Vec<CodeActionOrCommand> {
+    let Some(fa) = index.files.get(path) else {
+        return Vec::new();
+    };
+    let start = fa
+        .line_index
+        .offset(range.start.line, range.start.character);
+    let end = fa.line_index.offset(range.end.line, range.end.character);
+
+    let findings = lint::check_file(index, path, &settings.lint);
+    let mut actions = Vec::new();
+
+    for quick_fix in quick_fixes(fa, &findings) {
+        if quick_fix.span.end < start || quick_fix.span.start > end {
+            continue;
+        }
+        actions.push(action(
+            fa,
+            uri,
+            &quick_fix.fix.title,
+            CodeActionKind::QUICKFIX,
+            quick_fix.fix.edits.iter(),
+        ));
+    }
+
+    let whitespace: Vec<&Finding> = findings
+        .iter()
+        .filter(|f| f.code == codes::WHITESPACE)
+        .collect();
+    if !whitespace.is_empty() {
+        let starts = lint::entry_starts(&fa.file.entries);
+        let entry_start = starts.iter().rev().find(|&&s| s <= start).copied();
+        if let Some(entry_start) = entry_start {
+            let entry_end = starts
+                .iter()
+                .find(|&&s| s > entry_start)
+                .copied()
+                .unwrap_or(fa.source.len());
+            let in_entry: Vec<&Edit> = whitespace
+                .iter()
+                .filter(|f| f.span.start >= entry_start && f.span.start < entry_end)
+                .flat_map(|f| f.fixes.iter().flat_map(|fix| &fix.edits))
+                .collect();
+            if !in_entry.is_empty() {
+                actions.push(action(
+                    fa,
+                    uri,
+                    "Fix whitespace in entry",
+                    CodeActionKind::QUICKFIX,
+                    in_entry.into_iter(),
+                ));
+            }
+        }
+        actions.push(action(
+            fa,
+            uri,
+            "Fix whitespace in file",
+            CodeActionKind::SOURCE_FIX_ALL,
+            whitespace
+                .iter()
+                .flat_map(|f| f.fixes.iter().flat_map(|fix| &fix.edits)),
+        ));
+    }
+
+    if let Some(entry) = fa.file.config_at(start)
+        && !transitional::is_transitional(entry)
+    {
+        let edits = transitional::convert(&fa.source, entry);
+        actions.push(action(
+            fa,
+            uri,
+            &format!("Convert {} to transitional", entry.name),
+            CodeActionKind::REFACTOR_REWRITE,
+            edits.iter(),
+        ));
+    }
+
+    if let Some((menu, edits)) = sort_entries::sort_at(&fa.source, &fa.file, start) {
+        let title = match menu {
+            Some(prompt) => format!("Sort config entries in menu \"{}\"", prompt),
+            None => "Sort config entries in file".to_string(),
+        };
+        actions.push(action(
+            fa,
+            uri,
+            &title,
+            CodeActionKind::SOURCE,
+            edits.iter(),
+        ));
+    }
+
+    let annotations: Vec<Edit> = end_comments::end_comments(&fa.source, &fa.file.entries)
+        .iter()
+        .filter_map(|end| end.edit(&fa.source))
+        .collect();
+    if !annotations.is_empty() {
+        actions.push(action(
+            fa,
+            uri,
+            "Annotate end keywords",
+            CodeActionKind::SOURCE,
+            annotations.iter(),
+        ));
+    }
+
+    if let Some(extract) = extract::code_action(index, uri, path, start, end) {
+        actions.push(extract);
+    }
+    actions.extend(extract::inline_code_actions(index, uri, path, start));
+
+    if let Some(preview) = impact::code_action(index, uri, path, range.start) {
+        actions.push(preview);
+    }
+
+    actions
+}
+
+/// A fix for the problem of code `code` reported at `span`.
+pub struct QuickFix {
+    pub code: Code,
+    pub span: Span,
+    pub fix: Fix,
+}
+
+/// The quick fixes for the problems of a file: those of its lint
+/// `findings`, then those for statements the parser found misplaced.
+pub fn quick_fixes(fa: &FileAnalysis, findings: &[Finding]) -> Vec<QuickFix> {
+    let mut out: Vec<QuickFix> = findings
+        .iter()
+        .flat_map(|finding| {
+            finding.fixes.iter().map(|fix| QuickFix {
+                code: finding.code,
+                span: finding.span,
+                fix: fix.clone(),
+            })
+        })
+        .collect();
+    for pd in &fa.diagnostics {
+        if pd.code != codes::MISPLACED_STATEMENT {
+            continue;
+        }
+        if let Some((title, edits)) = misplaced_fix(fa, pd.span) {
+            out.push(QuickFix {
+                code: pd.code,
+                span: pd.span,
+                fix: Fix { title, edits },
+            });
+        }
+    }
+    out
+}
+
+/// The fix for a statement kconfig rejects where it is, starting at `span`:
+/// `mainmenu` moves to the top of the file, above the first statement and
+/// the comments directly on it, and an `endmenu`, `endif` or `endchoice`
+/// that closes nothing is removed.
+fn misplaced_fix(fa: &FileAnalysis, span: Span) -> Option<(String, Vec<Edit>)> {
+    let src = &fa.source;
+    let keyword = &src[span.start..span.end];
+    let line_start = src[..span.start].rfind('\n').map_or(0, |p| p + 1);
+    let line_end = src[span.start..]
+        .find('\n')
+        .map_or(src.len(), |p| span.start + p + 1);
+    let remove = Edit {
+        span: Span::new(line_start, line_end),
+        new_text: String::new(),
+    };
+    match keyword {
+        "mainmenu" => {
+            let first = fa.file.entries.first()?.span().start;
+            if first == span.start {
+                return None;
+            }
+            let mut top = src[..first].rfind('\n').map_or(0, |p| p + 1);
+            while top > 0 {
+                let above = src[..top - 1].rfind('\n').map_or(0, |p| p + 1);
+                if !src[above..top].trim_start().starts_with('#') {
+                    break;
+                }
+                top = above;
+            }
+            let mut line = src[line_start..line_end].to_string();
+            if !line.ends_with('\n') {
+                line.push('\n');
+            }
+            let insert = Edit {
+                span: Span::new(top, top),
+                new_text: line,
+            };
+            Some((
+                "Move `mainmenu` to the top of the file".into(),
+                vec![insert, remove],
+            ))
+        }
+        "endmenu" | "endif" | "endchoice" => {
+            Some((format!("Remove the stray `{}`", keyword), vec![remove]))
+        }
+        _ => None,
+    }
+}
+
+fn action<'a>(
+    fa: &FileAnalysis,
+    uri: &Url,
+    title: &str,
+    kind: CodeActionKind,
+    edits: impl Iterator<Item = &'a Edit>,
+) -> CodeActionOrCommand {
+    let edits: Vec<TextEdit> = edits.map(|e| text_edit(fa, e)).collect();
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title: title.to_string(),
+        kind: Some(kind),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+fn text_edit(fa: &FileAnalysis, edit: &Edit) -> TextEdit {
+    TextEdit {
+        range: range(&fa.line_index, edit.span),
+        new_text: edit.new_text.clone(),
+    }
+}
@@ -0,0 +1,279 @@
+//! Stable diagnostic codes. Each diagnostic carries a numbered code
+//! (`KC0005`) whose `codeDescription` links to its entry in
+//! `docs/rules.md`, which is generated from this table by
+//! [`rules_markdown`]. Lint rules can be disabled by number or by name.
+
+use tower_lsp::lsp_types::{self as lsp, NumberOrString};
+
+/// Where `docs/rules.md` is published.
+pub const DOCS_URL: &str = "https://github.com/cccheng/kconfig-lsp/blob/main/docs/rules.md";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Code {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// One line, leading the rule's documentation.
+    pub summary: &'static str,
+    pub explanation: &'static str,
+    /// Kconfig that triggers the diagnostic.
+    pub example: &'static str,
+}
+
+pub const SYNTAX: Code = Code {
+    id: "KC0001",
+    name: "syntax",
+    summary: "Kconfig syntax error",
+    explanation: "The file does not parse: a keyword is misspelled, a block is not closed, or an \
+                  attribute is missing its argument. Entries after the error may be misread. \
+                  A string missing its closing quote, or a character kconfig does not know, is \
+                  a warning: kconfig ends the string at the end of the line, or skips the \
+                  character. Expressions nested more than 128 levels deep or with more than \
+                  1024 operands are cut off where they pass the limit.",
+    example: "menu \"Drivers\"\n\nconfig FOO\n\tbool \"Foo\"\n",
+};
+
+pub const UNDEFINED_SYMBOL: Code = Code {
+    id: "KC0002",
+    name: "undefined-symbol",
+    summary: "reference to a symbol no indexed file defines",
+    explanation: "An undefined symbol is always `n`, so a `depends on` on it hides the entry and a \
+                  `select` of it does nothing. Usually a typo or a symbol removed elsewhere.",
+    example: "config FOO\n\tbool \"Foo\"\n\tdepends on PCII\n",
+};
+
+pub const TRANSITIONAL_REFERENCE: Code = Code {
+    id: "KC0003",
+    name: "transitional-reference",
+    summary: "reference to a `transitional` symbol",
+    explanation: "A transitional symbol only carries old `.config` values over to its \
+                  replacement. Anything other than the replacement's `default` should refer to \
+                  the replacement instead.",
+    example: "config OLD\n\tbool\n\ttransitional\n\nconfig USER\n\tbool \"User\"\n\tdepends on OLD\n",
+};
+
+pub const SELECT_CHOICE_MEMBER: Code = Code {
+    id: "KC0004",
+    name: "select-choice-member",
+    summary: "`select` or `imply` of a choice member",
+    explanation: "Selecting a choice member forces its value but does not make it the choice's \
+                  selection, so the choice can end up with a different member selected. Set the \
+                  choice's `default` instead.",
+    example: "choice\n\tprompt \"Compression\"\nconfig GZIP\n\tbool \"gzip\"\nendchoice\n\nconfig BOOT\n\tbool\n\tselect GZIP\n",
+};
+
+pub const SELECT_CHOICE: Code = Code {
+    id: "KC0005",
+    name: "select-choice",
+    summary: "`select` or `imply` of a named choice",
+    explanation: "A choice has no value of its own and cannot be selected. Pick a member through \
+                  the choice's `default`.",
+    example: "choice COMPRESSION\n\tprompt \"Compression\"\nconfig GZIP\n\tbool \"gzip\"\nendchoice\n\nconfig BOOT\n\tbool\n\tselect COMPRESSION\n",
+};
+
+pub const CHOICE_SELECTION: Code = Code {
+    id: "KC0006",
+    name: "choice-selection",
+    summary: "choice selection in `.config` is inconsistent",
+    explanation: "The loaded `.config` sets several members of a choice to `y`, none of a \
+                  required choice, or a member whose dependencies are not met. `make oldconfig` \
+                  would change the selection.",
+    example: "# .config: CONFIG_GZIP=y and CONFIG_XZ=y\nchoice\n\tprompt \"Compression\"\nconfig GZIP\n\tbool \"gzip\"\nconfig XZ\n\tbool \"xz\"\nendchoice\n",
+};
+
+pub const DEFAULT_OUT_OF_RANGE: Code = Code {
+    id: "KC0007",
+    name: "default-out-of-range",
+    summary: "`default` outside the symbol's `range`",
+    explanation: "kconfig clamps an out-of-range default to the nearest bound, so the written \
+                  default never takes effect.",
+    example: "config NR_CPUS\n\tint \"Maximum CPUs\"\n\trange 2 64\n\tdefault 128\n",
+};
+
+pub const CONFIG_OUT_OF_RANGE: Code = Code {
+    id: "KC0008",
+    name: "config-out-of-range",
+    summary: "`.config` value outside the symbol's `range`, or not a number",
+    explanation: "The loaded `.config` sets an `int` or `hex` symbol to a value its active \
+                  `range` does not allow; `make oldconfig` would reset it.",
+    example: "# .config: CONFIG_NR_CPUS=128\nconfig NR_CPUS\n\tint \"Maximum CPUs\"\n\trange 2 64\n",
+};
+
+pub const SOURCE_CYCLE: Code = Code {
+    id: "KC0009",
+    name: "source-cycle",
+    summary: "`source` cycle",
+    explanation: "A file sources itself, directly or through other files. kconfig would recurse \
+                  until it fails.",
+    example: "# net/Kconfig\nsource \"net/ipv4/Kconfig\"\n\n# net/ipv4/Kconfig\nsource \"net/Kconfig\"\n",
+};
+
+pub const SOURCE_DUPLICATE: Code = Code {
+    id: "KC0010",
+    name: "source-duplicate",
+    summary: "file sourced more than once",
+    explanation: "Every `source` parses the file again, so each of its symbols gets a second set \
+                  of definitions, with doubled defaults and selects.",
+    example: "source \"lib/Kconfig\"\nsource \"net/Kconfig\"\n\n# net/Kconfig\nsource \"lib/Kconfig\"\n",
+};
+
+pub const DEFAULT_QUOTING: Code = Code {
+    id: "KC0011",
+    name: "default-quoting",
+    summary: "`default` quoted inconsistently with the symbol type",
+    explanation: "`string` defaults should be quoted unless they name a symbol, and `int`/`hex` \
+                  defaults should not be.",
+    example: "config LOCALVERSION\n\tstring \"Local version\"\n\tdefault -custom\n",
+};
+
+pub const COMPARISON_TYPES: Code = Code {
+    id: "KC0012",
+    name: "comparison-types",
+    summary: "comparison operands don't match the symbol type",
+    explanation: "A quoted `\"y\"` compared with a bool, an unquoted word compared with a string, \
+                  or `<`/`>` on non-numbers usually does not compare what was meant.",
+    example: "config FOO\n\tbool \"Foo\"\n\tdepends on BAR = \"y\"\n",
+};
+
+pub const WHITESPACE: Code = Code {
+    id: "KC0013",
+    name: "whitespace",
+    summary: "indentation and trailing whitespace (opt-in)",
+    explanation: "Attributes indented with spaces, trailing whitespace, and help text not \
+                  indented past its `help` line. Enabled by `lint.whitespace`.",
+    example: "config FOO\n    bool \"Foo\"\n",
+};
+
+pub const SYMBOL_NAMING: Code = Code {
+    id: "KC0014",
+    name: "symbol-naming",
+    summary: "symbol name breaks the naming convention of its path (opt-in)",
+    explanation: "The symbol has lowercase letters, does not match the pattern, or lacks one of \
+                  the prefixes that `lint.naming` sets for the files under its path, such as a \
+                  subsystem's or vendor's prefix.",
+    example: "# lint.naming: [{ \"path\": \"soc/\", \"prefixes\": [\"SOC_\"] }]\nconfig GPIO_FAST\n\tbool \"Fast GPIO\"\n",
+};
+
+pub const MENU_TITLES: Code = Code {
+    id: "KC0015",
+    name: "menu-titles",
+    summary: "empty or repeated menu title",
+    explanation: "A `menu` with an empty prompt shows as a blank line, two menus of the same \
+                  block with one title cannot be told apart, and a `comment` repeating the title \
+                  of the menu next to it or of the menu it opens says the same thing twice. Menus \
+                  in different `if` blocks may share a title.",
+    example: "menu \"Networking\"\ncomment \"Networking\"\nendmenu\n",
+};
+
+pub const DEFAULT_VALUE: Code = Code {
+    id: "KC0016",
+    name: "default-value",
+    summary: "`default` value doesn't fit the symbol type",
+    explanation: "A bool or tristate default that is a number, a string or a symbol of another \
+                  type, or an int or hex default that is `y`, `m`, `n` or a bool, tristate or \
+                  string symbol. kconfig does not convert between types: a bool default of `1` \
+                  is `n`, and an int default of `y` is not a number.",
+    example: "config DEBUG_INFO\n\tbool \"Debug info\"\n\tdefault 1\n",
+};
+
+pub const MISPLACED_STATEMENT: Code = Code {
+    id: "KC0017",
+    name: "misplaced-statement",
+    summary: "statement where kconfig does not accept it",
+    explanation: "`mainmenu` after other statements, an `endmenu`, `endif` or `endchoice` that \
+                  closes nothing, or an attribute outside any entry, as left behind when part of \
+                  an entry is copied or deleted. kconfig stops with a syntax error.",
+    example: "config FOO\n\tbool \"Foo\"\nendif\n",
+};
+
+pub const END_COMMENT: Code = Code {
+    id: "KC0018",
+    name: "end-comment",
+    summary: "comment after an end keyword names another block",
+    explanation: "The comment after an `endmenu`, `endif` or `endchoice` is meant to say which \
+                  block it closes: the menu's title, the `if` condition, or the choice's prompt. \
+                  One that names something else was left behind when the block was renamed or \
+                  moved, and misleads whoever reads the end of a long block.",
+    example: "if NETDEVICES\nconfig DUMMY\n\ttristate \"Dummy net driver\"\nendif # NET_CORE\n",
+};
+
+pub const ALL: &[Code] = &[
+    SYNTAX,
+    UNDEFINED_SYMBOL,
+    TRANSITIONAL_REFERENCE,
+    SELECT_CHOICE_MEMBER,
+    SELECT_CHOICE,
+    CHOICE_SELECTION,
+    DEFAULT_OUT_OF_RANGE,
+    CONFIG_OUT_OF_RANGE,
+    SOURCE_CYCLE,
+    SOURCE_DUPLICATE,
+    DEFAULT_QUOTING,
+    COMPARISON_TYPES,
+    WHITESPACE,
+    SYMBOL_NAMING,
+    MENU_TITLES,
+    DEFAULT_VALUE,
+    MISPLACED_STATEMENT,
+    END_COMMENT,
+];
+
+impl Code {
+    /// Whether `rule` (from `lint.disable`) names this code.
+    pub fn matches(&self, rule: &str) -> bool {
+        rule == self.name || rule.eq_ignore_ascii_case(self.id)
+    }
+
+    /// Anchor of the rule's heading in `docs/rules.md`.
+    fn anchor(&self) -> String {
+        format!("{}-{}", self.id.to_ascii_lowercase(), self.name)
+    }
+
+    /// The rule's entry in the published `docs/rules.md`.
+    pub fn docs_url(&self) -> String {
+        format!("{}#{}", DOCS_URL, self.anchor())
+    }
+
+    /// Set the diagnostic's code and documentation link.
+    pub fn apply(&self, mut diag: lsp::Diagnostic) -> lsp::Diagnostic {
+        diag.code = Some(NumberOrString::String(self.id.into()));
+        diag.code_description = lsp::Url::parse(&self.docs_url())
+            .ok()
+            .map(|href| lsp::CodeDescription { href });
+        diag
+    }
+}
+
+pub fn by_id(id: &str) -> Option<&'static Code> {
+    ALL.iter().find(|c| c.id == id)
+}
+
+/// The contents of `docs/rules.md`.
+#[allow(dead_code)]
+pub fn rules_markdown() -> String {
+    let mut out = String::from(
+        "<!-- Generated from src/codes.rs; edit there and run the tests to check. -->\n\n\
+         # Diagnostics\n\n\
+         Every diagnostic kconfig-lsp reports has a stable code. Lint rules can be turned off \
+         with `lint.disable`, by code or by name.\n",
+    );
+    for code in ALL {
+        out.push_str(&format!(
+            "\n## {} {}\n\n{}.\n\n{}\n\n```kconfig\n{}```\n",
+            code.id,
+            code.name,
+            capitalize(code.summary),
+            code.explanation,
+            code.example
+        ));
+    }
+    out
+}
+
+#[allow(dead_code)]
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -1,17 +1,48 @@
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 use tower_lsp::lsp_types::*;
 
-use crate::analysis::WorldIndex;
+use crate::analysis::{FileAnalysis, WorldIndex};
+use crate::ast::{Attribute, ConfigEntry, Entry, Span, TypeKind};
+use crate::hover;
+use crate::include_graph::TreeOrder;
+use crate::lexer::logical_line_before;
+use crate::settings::HoverSettings;
 
-pub fn complete(index: &WorldIndex, path: &Path, pos: Position) -> Option<CompletionResponse> {
+/// Completions at `pos`. With `snippets`, values are inserted with a
+/// placeholder to type over.
+pub fn complete(
+    index: &WorldIndex,
+    path: &Path,
+    pos: Position,
+    snippets: bool,
+) -> Option<CompletionResponse> {
     let fa = index.files.get(path)?;
     let offset = fa.line_index.offset(pos.line, pos.character);
+    if let Some(items) = source_path_items(index, path, fa, offset) {
+        return (!items.is_empty()).then_some(CompletionResponse::Array(items));
+    }
     let prefix = prefix_at_offset(&fa.source, offset);
+    if let Some(items) = value_items(index, fa, offset - prefix.len(), &prefix, snippets) {
+        return (!items.is_empty()).then_some(CompletionResponse::Array(items));
+    }
 
     let mut items: Vec<CompletionItem> = Vec::new();
 
+    // In an `if` condition, symbols the current entry already depends on are
+    // the likely guards, so they are ranked ahead of the global list.
+    let in_condition = is_condition_position(&fa.source, offset - prefix.len());
+    let context = if in_condition {
+        context_symbols(index, path, &fa.file.entries, offset)
+    } else {
+        Vec::new()
+    };
+
     for kw in KEYWORDS {
+        if in_condition {
+            break;
+        }
         if kw.starts_with(&prefix) || prefix.is_empty() {
             items.push(CompletionItem {
                 label: kw.to_string(),
@@ -21,18 +52,17 @@ pub fn complete(index: &WorldIndex, path: &Path, pos: Position) -> Option<Comple
         }
     }
 
+    let order = index.tree_order();
     for sym in &index.all_symbols {
-        if sym.starts_with(&prefix) || (prefix.is_empty() && is_symbol_position(&fa.source, offset))
+        if sym.starts_with(&prefix)
+            || (prefix.is_empty() && (in_condition || is_symbol_position(&fa.source, offset)))
         {
-            items.push(CompletionItem {
-                label: sym.clone(),
-                kind: Some(CompletionItemKind::CONSTANT),
-                detail: index
-                    .get_definitions(sym)
-                    .first()
-                    .and_then(|d| d.prompt.clone()),
-                ..Default::default()
-            });
+            let sort_text = match context.iter().position(|c| c == sym) {
+                Some(rank) => format!("0{:04}", rank),
+                None if in_condition => format!("1{}", sym),
+                None => sym.clone(),
+            };
+            items.push(symbol_item(index, &order, sym, sort_text));
         }
     }
 
@@ -43,6 +73,241 @@ pub fn complete(index: &WorldIndex, path: &Path, pos: Position) -> Option<Comple
     }
 }
 
+fn symbol_item(
+    index: &WorldIndex,
+    order: &TreeOrder,
+    sym: &str,
+    sort_text: String,
+) -> CompletionItem {
+    CompletionItem {
+        label: sym.to_string(),
+        kind: Some(CompletionItemKind::CONSTANT),
+        detail: order
+            .sort_definitions(index.get_definitions(sym))
+            .first()
+            .and_then(|d| d.prompt.clone()),
+        sort_text: Some(sort_text),
+        ..Default::default()
+    }
+}
+
+/// Right after `default` or a `def_<type>` keyword, with `start` where the
+/// value begins: the values that fit the symbol's type. `y` and `n` (and
+/// `m` for tristate) then bool and tristate symbols for a bool or tristate,
+/// a quoted string for a string, and a number then symbols of the same type
+/// for an int or hex. `None` where the type is not known.
+fn value_items(
+    index: &WorldIndex,
+    fa: &FileAnalysis,
+    start: usize,
+    prefix: &str,
+    snippets: bool,
+) -> Option<Vec<CompletionItem>> {
+    let line = logical_line_before(&fa.source, start);
+    let (keyword, rest) = line.trim_start().split_once([' ', '\t'])?;
+    if !rest.trim().is_empty() {
+        return None;
+    }
+    let entry = entry_context(&fa.file.entries, start).map(|(entry, _)| entry);
+    let kind = match keyword {
+        "default" => {
+            let entry = entry?;
+            entry
+                .attributes
+                .iter()
+                .find_map(|a| match a {
+                    Attribute::Type(t) => Some(t.kind),
+                    Attribute::DefType(d) => Some(d.kind),
+                    _ => None,
+                })
+                .or_else(|| index.symbol_type(&entry.name))?
+        }
+        "def_bool" => TypeKind::Bool,
+        "def_tristate" => TypeKind::Tristate,
+        "def_int" => TypeKind::Int,
+        "def_hex" => TypeKind::Hex,
+        "def_string" => TypeKind::String,
+        _ => return None,
+    };
+
+    let value = |label: &str, snippet: &str| CompletionItem {
+        label: label.to_string(),
+        kind: Some(CompletionItemKind::VALUE),
+        sort_text: Some(format!("0{}", label)),
+        insert_text: snippets.then(|| snippet.to_string()),
+        insert_text_format: snippets.then_some(InsertTextFormat::SNIPPET),
+        ..Default::default()
+    };
+    let mut items: Vec<CompletionItem> = Vec::new();
+    let symbol_types: &[TypeKind] = match kind {
+        TypeKind::Bool | TypeKind::Tristate => {
+            let constants: &[&str] = match kind {
+                TypeKind::Tristate => &["y", "m", "n"],
+                _ => &["y", "n"],
+            };
+            for (rank, constant) in constants.iter().enumerate() {
+                if constant.starts_with(prefix) {
+                    items.push(CompletionItem {
+                        sort_text: Some(format!("0{}", rank)),
+                        ..value(constant, constant)
+                    });
+                }
+            }
+            &[TypeKind::Bool, TypeKind::Tristate]
+        }
+        TypeKind::String => {
+            if prefix.is_empty() {
+                items.push(value("\"\"", "\"$1\""));
+            }
+            &[]
+        }
+        TypeKind::Int => {
+            if prefix.is_empty() {
+                items.push(value("0", "${1:0}"));
+            }
+            &[TypeKind::Int]
+        }
+        TypeKind::Hex => {
+            if prefix.is_empty() {
+                items.push(value("0x0", "0x${1:0}"));
+            }
+            &[TypeKind::Hex]
+        }
+    };
+
+    let order = index.tree_order();
+    for sym in &index.all_symbols {
+        if sym.starts_with(prefix)
+            && entry.is_none_or(|e| &e.name != sym)
+            && index
+                .symbol_type(sym)
+                .is_some_and(|t| symbol_types.contains(&t))
+        {
+            items.push(symbol_item(index, &order, sym, format!("1{}", sym)));
+        }
+    }
+    Some(items)
+}
+
+/// Fill in the documentation of a symbol completion: what hover shows for
+/// the symbol, in `format`.
+pub fn resolve(
+    index: &WorldIndex,
+    mut item: CompletionItem,
+    format: MarkupKind,
+    settings: &HoverSettings,
+) -> CompletionItem {
+    if item.kind == Some(CompletionItemKind::CONSTANT)
+        && item.documentation.is_none()
+        && let Some(value) = hover::symbol_documentation_as(
+            index,
+            &item.label,
+            format.clone(),
+            settings.expand_definitions,
+        )
+    {
+        item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+            kind: format,
+            value,
+        }));
+    }
+    item
+}
+
+/// Inside the quotes of a `source` statement: the indexed files the path
+/// could name. Files nothing sources yet come first, since the usual reason
+/// to write a `source` line is wiring up a new fragment.
+fn source_path_items(
+    index: &WorldIndex,
+    path: &Path,
+    fa: &FileAnalysis,
+    offset: usize,
+) -> Option<Vec<CompletionItem>> {
+    let line = logical_line_before(&fa.source, offset);
+    let (keyword, rest) = line.trim_start().split_once([' ', '\t'])?;
+    if !matches!(keyword, "source" | "osource" | "rsource" | "orsource") {
+        return None;
+    }
+    let typed = rest.trim_start().strip_prefix('"')?;
+    if typed.contains('"') {
+        return None;
+    }
+    // Paths are relative to the source tree, or for `rsource` and
+    // `orsource` to the directory of the file.
+    let base = match &index.root {
+        Some(root) if !keyword.ends_with("rsource") => root.clone(),
+        _ => path.parent()?.to_path_buf(),
+    };
+
+    let sourced: HashSet<PathBuf> = index
+        .files
+        .iter()
+        .flat_map(|(from, fa)| {
+            fa.sources
+                .iter()
+                .filter_map(|s| index.resolve_source(from, &s.path, s.relative))
+        })
+        .collect();
+    let start = fa.line_index.line_col(offset - typed.len());
+    let end = fa.line_index.line_col(offset);
+    let range = Range {
+        start: Position::new(start.0, start.1),
+        end: Position::new(end.0, end.1),
+    };
+    let mut items: Vec<CompletionItem> = index
+        .files
+        .keys()
+        .filter(|f| f.as_path() != path && index.root_kconfig.as_ref() != Some(*f))
+        .filter_map(|f| {
+            let relative = f.strip_prefix(&base).ok()?.to_str()?;
+            if !relative.starts_with(typed) {
+                return None;
+            }
+            let unsourced = !sourced.contains(f);
+            Some(CompletionItem {
+                label: relative.to_string(),
+                kind: Some(CompletionItemKind::FILE),
+                detail: unsourced.then(|| "Not sourced anywhere yet".to_string()),
+                sort_text: Some(format!("{}{}", if unsourced { 0 } else { 1 }, relative)),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range,
+                    new_text: relative.to_string(),
+                })),
+                ..Default::default()
+            })
+        })
+        .collect();
+    items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+    Some(items)
+}
+
+/// Whether `pos` is in free text: help text, or a quoted string other than
+/// the path of a `source` statement.
+pub fn in_text(index: &WorldIndex, path: &Path, pos: Position) -> bool {
+    let Some(fa) = index.files.get(path) else {
+        return false;
+    };
+    let offset = fa.line_index.offset(pos.line, pos.character);
+    if in_help(&fa.file.entries, offset) {
+        return true;
+    }
+    let line = logical_line_before(&fa.source, offset);
+    let keyword = line.split_whitespace().next().unwrap_or("");
+    !matches!(keyword, "source" | "osource" | "rsource" | "orsource")
+        && line.matches('"').count() % 2 == 1
+}
+
+fn in_help(entries: &[Entry], offset: usize) -> bool {
+    entries.iter().any(|entry| {
+        let span = entry.span();
+        span.start <= offset
+            && offset <= span.end
+            && (entry.attributes().iter().any(|a| {
+                matches!(a, Attribute::Help(h) if h.span.start <= offset && offset <= h.span.end)
+            }) || in_help(entry.children(), offset))
+    })
+}
+
 fn prefix_at_offset(source: &str, offset: usize) -> String {
     let bytes = source.as_bytes();
     let mut start = offset;
@@ -53,8 +318,8 @@ fn prefix_at_offset(source: &str, offset: usize) -> String {
 }
 
 fn is_symbol_position(source: &str, offset: usize) -> bool {
-    let line_start = source[..offset].rfind('\n').map_or(0, |p| p + 1);
-    let before = source[line_start..offset].trim_start();
+    let line = logical_line_before(source, offset);
+    let before = line.trim_start();
     matches!(
         before,
         "depends on " | "select " | "imply " | "default " | "def_bool " | "def_tristate "
@@ -63,6 +328,94 @@ fn is_symbol_position(source: &str, offset: usize) -> bool {
         || before.starts_with("imply ")
 }
 
+/// Whether `offset` directly follows the `if` of an attribute's condition,
+/// e.g. `default y if ` or `select FOO if `.
+fn is_condition_position(source: &str, offset: usize) -> bool {
+    let line = logical_line_before(source, offset);
+    let first = line.split_whitespace().next().unwrap_or("");
+    if !CONDITIONAL_ATTRIBUTES.contains(&first) {
+        return false;
+    }
+    let before = line.trim_end();
+    before.len() < line.len()
+        && before.ends_with("if")
+        && before[..before.len() - 2].ends_with([' ', '\t', '"', '\''])
+}
+
+const CONDITIONAL_ATTRIBUTES: &[&str] = &[
+    "default",
+    "def_bool",
+    "def_tristate",
+    "select",
+    "imply",
+    "prompt",
+    "range",
+    "bool",
+    "tristate",
+    "string",
+    "hex",
+    "int",
+];
+
+/// Symbols the entry around `offset` depends on – its own `depends on`, the
+/// conditions it inherits, then the enclosing `menuconfig` – in that order.
+fn context_symbols(
+    index: &WorldIndex,
+    path: &Path,
+    entries: &[Entry],
+    offset: usize,
+) -> Vec<String> {
+    let Some((entry, menuconfig)) = entry_context(entries, offset) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(String, Span)> = Vec::new();
+    if let Some(def) = index
+        .get_definitions(&entry.name)
+        .iter()
+        .find(|d| d.file == path && d.name_span == entry.name_span)
+    {
+        for expr in def.depends_on.iter().chain(&def.inherited_depends) {
+            expr.collect_symbols(&mut found);
+        }
+    }
+    let mut out: Vec<String> = Vec::new();
+    let names = found
+        .into_iter()
+        .map(|(name, _)| name)
+        .chain(menuconfig.map(|m| m.name.clone()));
+    for name in names {
+        if name != entry.name && !matches!(name.as_str(), "y" | "m" | "n") && !out.contains(&name) {
+            out.push(name);
+        }
+    }
+    out
+}
+
+/// The `config`/`menuconfig` whose attributes `offset` falls in, along with
+/// the closest preceding `menuconfig` in the same block.
+fn entry_context(entries: &[Entry], offset: usize) -> Option<(&ConfigEntry, Option<&ConfigEntry>)> {
+    let mut current = None;
+    let mut menuconfig = None;
+    for entry in entries {
+        let span = entry.span();
+        if span.start > offset {
+            break;
+        }
+        if !entry.children().is_empty() && offset <= span.end {
+            return entry_context(entry.children(), offset);
+        }
+        match entry {
+            Entry::Config(c) => current = Some((c, menuconfig)),
+            Entry::MenuConfig(c) => {
+                current = Some((c, menuconfig));
+                menuconfig = Some(c);
+            }
+            _ => current = None,
+        }
+    }
+    current
+}
+
 const KEYWORDS: &[&str] = &[
     "config",
     "menuconfig",
@@ -74,6 +427,9 @@ const KEYWORDS: &[&str] = &[
     "if",
     "endif",
     "source",
+    "osource",
+    "rsource",
+    "orsource",
     "mainmenu",
     "bool",
     "tristate",
@@ -93,4 +449,5 @@ const KEYWORDS: &[&str] = &[
     "modules",
     "transitional",
     "optional",
+    "option",
 ];
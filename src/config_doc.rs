@@ -0,0 +1,258 @@
+//! Configuration files open in the editor: the `.config`, defconfigs, and
+//! fragments such as Zephyr's `prj.conf`. They are not Kconfig, so they are
+//! kept out of the index; semantic tokens color their lines instead, which
+//! makes them readable in editors without a grammar for them, and
+//! `# CONFIG_FOO is not set` lines offer to set the symbol.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::WorldIndex;
+use crate::ast::TypeKind;
+use crate::diagnostics;
+use crate::dotconfig::{self, DotConfig};
+use crate::eval::{self, Evaluator, Tristate};
+
+const COMMENT: u32 = 0;
+/// A `bool` or `tristate` symbol, or one the tree does not define.
+const SWITCH: u32 = 1;
+/// A `string`, `int`, or `hex` symbol.
+const VALUED: u32 = 2;
+/// `y`, `m`, `n`, and `is not set`.
+const KEYWORD: u32 = 3;
+const NUMBER: u32 = 4;
+const STRING: u32 = 5;
+const OPERATOR: u32 = 6;
+
+/// Modifier of undefined symbols and of values the symbol cannot take;
+/// most themes strike these through.
+const INVALID: u32 = 1;
+
+/// Whether `path` is a configuration file rather than Kconfig: the
+/// configured `.config`, or a file named like one.
+pub fn is_config_file(path: &Path, config: Option<&Path>) -> bool {
+    if config == Some(path) {
+        return true;
+    }
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    name == ".config"
+        || name.ends_with("defconfig")
+        || name.ends_with(".config")
+        || name.ends_with(".conf")
+        || name.starts_with("sdkconfig")
+}
+
+/// The token types and modifiers [`semantic_tokens`] uses, by index.
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![
+            SemanticTokenType::COMMENT,
+            SemanticTokenType::VARIABLE,
+            SemanticTokenType::PROPERTY,
+            SemanticTokenType::KEYWORD,
+            SemanticTokenType::NUMBER,
+            SemanticTokenType::STRING,
+            SemanticTokenType::OPERATOR,
+        ],
+        token_modifiers: vec![SemanticTokenModifier::DEPRECATED],
+    }
+}
+
+/// Tokens for every assignment, `# CONFIG_FOO is not set` line, and comment
+/// of `text`. Values are checked against the symbol's type and its active
+/// `range`, evaluated with the values `text` itself sets.
+pub fn semantic_tokens(index: &WorldIndex, text: &str, prefix: &str) -> Vec<SemanticToken> {
+    let values = DotConfig::parse(Path::new(""), text, prefix);
+    let eval = Evaluator::new(index, &values);
+    let order = index.tree_order();
+    let symbol = |name: &str| {
+        let kind = match index.symbol_type(name) {
+            Some(TypeKind::String | TypeKind::Int | TypeKind::Hex) => VALUED,
+            _ => SWITCH,
+        };
+        // Without a tree there is nothing to check names against.
+        let undefined = !index.files.is_empty() && index.get_definitions(name).is_empty();
+        (kind, if undefined { INVALID } else { 0 })
+    };
+    let valid = |name: &str, value: &str| match index.symbol_type(name) {
+        None => true,
+        Some(TypeKind::Bool) => matches!(value, "y" | "n"),
+        Some(TypeKind::Tristate) => matches!(value, "y" | "m" | "n"),
+        Some(TypeKind::String) => {
+            value.len() >= 2 && value.starts_with('"') && value.ends_with('"')
+        }
+        Some(kind) => {
+            let hex = kind == TypeKind::Hex;
+            let Some(n) = eval::parse_number(value, hex) else {
+                return false;
+            };
+            index
+                .symbol(name, &order)
+                .and_then(|sym| diagnostics::active_range(index, &sym, hex, Some(&eval)))
+                .is_none_or(|(_, _, low, high)| low <= n && n <= high)
+        }
+    };
+
+    let mut tokens: Vec<(u32, u32, u32, u32, u32)> = Vec::new();
+    for (line, raw) in text.lines().enumerate() {
+        let line = line as u32;
+        let indent = (raw.len() - raw.trim_start().len()) as u32;
+        let content = raw.trim();
+        if content.is_empty() {
+            continue;
+        }
+        if let Some(comment) = content.strip_prefix('#') {
+            let not_set = comment
+                .trim_start()
+                .strip_suffix("is not set")
+                .map(str::trim_end)
+                .and_then(|name| name.strip_prefix(prefix).map(|n| (name, n)));
+            let Some((spelled, name)) = not_set else {
+                tokens.push((line, indent, content.len() as u32, COMMENT, 0));
+                continue;
+            };
+            let start = indent + (content.len() - comment.trim_start().len()) as u32;
+            let (kind, modifiers) = symbol(name);
+            tokens.push((line, indent, 1, COMMENT, 0));
+            tokens.push((line, start, spelled.len() as u32, kind, modifiers));
+            let keyword = content.len() as u32 - "is not set".len() as u32;
+            tokens.push((
+                line,
+                indent + keyword,
+                "is not set".len() as u32,
+                KEYWORD,
+                0,
+            ));
+            continue;
+        }
+        let Some((spelled, value)) = content.split_once('=') else {
+            continue;
+        };
+        let Some(name) = spelled.trim_end().strip_prefix(prefix) else {
+            continue;
+        };
+        let (kind, modifiers) = symbol(name);
+        tokens.push((
+            line,
+            indent,
+            spelled.trim_end().len() as u32,
+            kind,
+            modifiers,
+        ));
+        let equals = indent + spelled.len() as u32;
+        tokens.push((line, equals, 1, OPERATOR, 0));
+        let value_start = equals + 1 + (value.len() - value.trim_start().len()) as u32;
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        let kind = if value.starts_with('"') {
+            STRING
+        } else if matches!(value, "y" | "m" | "n") {
+            KEYWORD
+        } else {
+            NUMBER
+        };
+        let modifiers = if valid(name, value) { 0 } else { INVALID };
+        tokens.push((line, value_start, value.len() as u32, kind, modifiers));
+    }
+
+    let mut previous = (0, 0);
+    tokens
+        .into_iter()
+        .map(|(line, start, length, token_type, modifiers)| {
+            let delta_line = line - previous.0;
+            let delta_start = if delta_line == 0 {
+                start - previous.1
+            } else {
+                start
+            };
+            previous = (line, start);
+            SemanticToken {
+                delta_line,
+                delta_start,
+                length,
+                token_type,
+                token_modifiers_bitset: modifiers,
+            }
+        })
+        .collect()
+}
+
+/// "Set to y" (and "Set to m" for tristates) on the `# CONFIG_FOO is not
+/// set` lines in `range`. When `olddefconfig` would undo the change, because
+/// the symbol is undefined, has no prompt, or its dependencies are not met
+/// with the document's values, the title says so.
+pub fn code_actions(
+    index: &WorldIndex,
+    uri: &Url,
+    text: &str,
+    range: Range,
+    prefix: &str,
+) -> Vec<CodeActionOrCommand> {
+    let values = DotConfig::parse(Path::new(""), text, prefix);
+    let eval = Evaluator::new(index, &values);
+    let order = index.tree_order();
+    let mut actions = Vec::new();
+    let lines = text.lines().enumerate();
+    for (line, raw) in lines.take(range.end.line as usize + 1) {
+        if (line as u32) < range.start.line {
+            continue;
+        }
+        let Some(name) = dotconfig::not_set(raw, prefix) else {
+            continue;
+        };
+        let choices: &[&str] = match index.symbol_type(name) {
+            Some(TypeKind::Tristate) => &["y", "m"],
+            Some(TypeKind::Bool) | None => &["y"],
+            Some(_) => &[],
+        };
+        for value in choices {
+            let reverted = match index.symbol(name, &order) {
+                None => Some("it is not defined".to_string()),
+                Some(sym) if sym.prompts().next().is_none() => Some("it has no prompt".to_string()),
+                Some(sym) => {
+                    let limit = eval.symbol_visibility(&sym);
+                    let unmet = sym
+                        .prompts()
+                        .flat_map(|d| d.depends_on.iter().chain(&d.inherited_depends))
+                        .find(|e| eval.eval(e) < Tristate::parse(value));
+                    match unmet {
+                        _ if limit >= Tristate::parse(value) => None,
+                        Some(e) => Some(format!("it depends on `{}`", e)),
+                        None => Some(format!("its prompt is limited to {}", limit.as_str())),
+                    }
+                }
+            };
+            let title = match &reverted {
+                None => format!("Set {}{} to {}", prefix, name, value),
+                Some(why) => format!(
+                    "Set {}{} to {} (olddefconfig reverts it: {})",
+                    prefix, name, value, why
+                ),
+            };
+            let edit = TextEdit {
+                range: Range {
+                    start: Position::new(line as u32, 0),
+                    end: Position::new(line as u32, raw.len() as u32),
+                },
+                new_text: format!("{}{}={}", prefix, name, value),
+            };
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                kind: Some(CodeActionKind::QUICKFIX),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    uri.clone(),
+                    vec![edit],
+                )]))),
+                is_preferred: Some(reverted.is_none() && *value == "y"),
+                ..Default::default()
+            }));
+        }
+    }
+    actions
+}
@@ -0,0 +1,70 @@
+//! `kconfig.debug.dumpTokens` and `kconfig.debug.dumpAst`, and the
+//! `dump-tokens` and `dump-ast` commands: the tokens and parse tree the
+//! server makes of a file, to attach to parser bug reports.
+
+use std::fmt::Write as _;
+
+use crate::ast::{LineIndex, Span};
+use crate::lexer::{Dialect, Lexer};
+use crate::parser;
+
+/// `workspace/executeCommand` returning [`dump_tokens`] of the file whose
+/// URI is the first argument.
+pub const DUMP_TOKENS_COMMAND: &str = "kconfig.debug.dumpTokens";
+
+/// `workspace/executeCommand` returning [`dump_ast`] of the file whose URI
+/// is the first argument.
+pub const DUMP_AST_COMMAND: &str = "kconfig.debug.dumpAst";
+
+/// One token per line: its byte span, where it starts as `line:column`
+/// (1-based, columns in bytes), and its kind.
+pub fn dump_tokens(source: &str, dialect: Dialect) -> String {
+    let lines = LineIndex::new(source);
+    let mut out = String::new();
+    for token in Lexer::new(source).with_dialect(dialect).tokenize() {
+        let _ = writeln!(out, "{}\t{:?}", position(&lines, token.span), token.kind);
+    }
+    out
+}
+
+/// The parse tree, with spans as byte offsets, then the parse diagnostics
+/// and the preprocessor assignments.
+pub fn dump_ast(source: &str, dialect: Dialect) -> String {
+    let lines = LineIndex::new(source);
+    let result = parser::parse(source, Lexer::new(source).with_dialect(dialect).tokenize());
+    let mut out = format!("{:#?}\n", result.file.entries);
+    out.push_str("\nDiagnostics:\n");
+    if result.diagnostics.is_empty() {
+        out.push_str("  (none)\n");
+    }
+    for d in &result.diagnostics {
+        let _ = writeln!(
+            out,
+            "  {}\t{:?}\t{}: {}",
+            position(&lines, d.span),
+            d.severity,
+            d.code.id,
+            d.message
+        );
+    }
+    out.push_str("\nAssignments:\n");
+    if result.assignments.is_empty() {
+        out.push_str("  (none)\n");
+    }
+    for a in &result.assignments {
+        let _ = writeln!(
+            out,
+            "  {}\t{} {:?} {:?}",
+            position(&lines, a.name_span),
+            a.name,
+            a.flavor,
+            a.value
+        );
+    }
+    out
+}
+
+fn position(lines: &LineIndex, span: Span) -> String {
+    let (line, col) = lines.line_col(span.start);
+    format!("{}..{}\t{}:{}", span.start, span.end, line + 1, col + 1)
+}
@@ -2,7 +2,9 @@ use std::path::Path;
 
 use tower_lsp::lsp_types::*;
 
-use crate::analysis::WorldIndex;
+use crate::analysis::{SymbolDef, WorldIndex};
+use crate::preprocess::{self, Expander};
+use crate::util::{location, word_at_offset};
 
 pub fn goto_definition(
     index: &WorldIndex,
@@ -11,30 +13,56 @@ pub fn goto_definition(
 ) -> Option<GotoDefinitionResponse> {
     let fa = index.files.get(path)?;
     let offset = fa.line_index.offset(pos.line, pos.character);
+    if let Some((_, body)) = preprocess::macro_at(&fa.source, offset) {
+        return macro_definition(index, path, body);
+    }
     let word = word_at_offset(&fa.source, offset)?;
 
-    let defs = index.get_definitions(&word);
+    // Parse order, so multiple definitions come back the same way each time.
+    let defs = index
+        .tree_order()
+        .sort_definitions(index.get_definitions(&word));
     if defs.is_empty() {
         return None;
     }
 
-    let locations: Vec<Location> = defs
+    // On a definition's own name, jump to the symbol's other definitions
+    // (kernel symbols are frequently redefined per-arch) rather than back to
+    // the definition under the cursor.
+    let current = defs
         .iter()
-        .filter_map(|d| {
-            let target_fa = index.files.get(&d.file)?;
-            let (line, col) = target_fa.line_index.line_col(d.name_span.start);
-            let (end_line, end_col) = target_fa.line_index.line_col(d.name_span.end);
-            let uri = Url::from_file_path(&d.file).ok()?;
-            Some(Location {
-                uri,
-                range: Range {
-                    start: Position::new(line, col),
-                    end: Position::new(end_line, end_col),
-                },
-            })
-        })
+        .position(|d| d.file == path && d.name_span.start <= offset && offset <= d.name_span.end);
+    let targets: Vec<&SymbolDef> = match current {
+        Some(i) if defs.len() > 1 => defs
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, d)| *d)
+            .collect(),
+        _ => defs,
+    };
+
+    let locations: Vec<Location> = targets
+        .into_iter()
+        .filter_map(|d| location(index, &d.file, d.name_span))
         .collect();
 
+    response(locations)
+}
+
+/// The assignments of the variable a `$(...)` reference names, in parse
+/// order, such as the `cc-option := ...` line of `scripts/Kconfig.include`.
+fn macro_definition(index: &WorldIndex, path: &Path, body: &str) -> Option<GotoDefinitionResponse> {
+    let name = body.split(',').next().unwrap_or(body).trim();
+    let locations = Expander::new(index, path, 0)
+        .assignments(name)
+        .into_iter()
+        .filter_map(|(file, a)| location(index, file, a.name_span))
+        .collect();
+    response(locations)
+}
+
+fn response(locations: Vec<Location>) -> Option<GotoDefinitionResponse> {
     if locations.is_empty() {
         None
     } else if locations.len() == 1 {
@@ -46,25 +74,33 @@ pub fn goto_definition(
     }
 }
 
-fn word_at_offset(source: &str, offset: usize) -> Option<String> {
-    let bytes = source.as_bytes();
-    if offset >= bytes.len() {
-        return None;
-    }
-    let mut start = offset;
-    while start > 0 && is_word_char(bytes[start - 1]) {
-        start -= 1;
-    }
-    let mut end = offset;
-    while end < bytes.len() && is_word_char(bytes[end]) {
-        end += 1;
-    }
-    if start == end {
-        return None;
+/// Jump from a choice member to the `choice` block that contains it. Served
+/// as `textDocument/typeDefinition`: a member's "type" is its choice.
+pub fn goto_containing_choice(
+    index: &WorldIndex,
+    path: &Path,
+    pos: Position,
+) -> Option<GotoDefinitionResponse> {
+    let fa = index.files.get(path)?;
+    let offset = fa.line_index.offset(pos.line, pos.character);
+    let word = word_at_offset(&fa.source, offset)?;
+
+    let mut locations: Vec<Location> = Vec::new();
+    for d in index.get_definitions(&word) {
+        let Some(choice) = index.choice_of(d) else {
+            continue;
+        };
+        let Some(location) = location(index, &choice.file, choice.span) else {
+            continue;
+        };
+        if !locations.contains(&location) {
+            locations.push(location);
+        }
     }
-    Some(source[start..end].to_string())
-}
 
-fn is_word_char(b: u8) -> bool {
-    b.is_ascii_alphanumeric() || b == b'_'
+    match locations.len() {
+        0 => None,
+        1 => Some(GotoDefinitionResponse::Scalar(locations.remove(0))),
+        _ => Some(GotoDefinitionResponse::Array(locations)),
+    }
 }
@@ -0,0 +1,262 @@
+//! `kconfig/dependencyPath` and `kconfig-lsp dependency-path`: the chains of
+//! `depends on`, `select` and `imply` that lead from one symbol to another,
+//! to answer why enabling one drags in, or needs, the other.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::WorldIndex;
+use crate::ast::Span;
+use crate::uri;
+use crate::util::location;
+
+/// Paths returned unless the request asks for another number.
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Edges a path may have unless the request asks for another number.
+pub const DEFAULT_MAX_LENGTH: usize = 8;
+
+/// Partial paths examined before the search gives up on finding more.
+const MAX_EXPANSIONS: usize = 100_000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyPathParams {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Most edges in a path.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EdgeKind {
+    /// The symbol's own `depends on`.
+    DependsOn,
+    /// The condition of an `if` block, menu or choice around the symbol.
+    InheritedDependency,
+    Select,
+    Imply,
+}
+
+impl EdgeKind {
+    fn label(self) -> &'static str {
+        match self {
+            EdgeKind::DependsOn => "depends on",
+            EdgeKind::InheritedDependency => "depends on (inherited)",
+            EdgeKind::Select => "select",
+            EdgeKind::Imply => "imply",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+    /// Where `to` is written in the definition of `from`.
+    pub location: Option<Location>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyPath {
+    pub edges: Vec<DependencyEdge>,
+}
+
+/// The shortest paths from `params.from` to `params.to`, shortest first,
+/// none visiting a symbol twice.
+pub fn dependency_paths(index: &WorldIndex, params: &DependencyPathParams) -> Vec<DependencyPath> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    let max_length = params.max_length.unwrap_or(DEFAULT_MAX_LENGTH);
+    let graph = Graph::new(index);
+    if params.from == params.to || limit == 0 {
+        return Vec::new();
+    }
+
+    // Edges left to `to` from each symbol that reaches it, to prune paths
+    // that could not get there within the limit.
+    let mut distance: HashMap<&str, usize> = HashMap::from([(params.to.as_str(), 0)]);
+    let mut queue = VecDeque::from([params.to.as_str()]);
+    while let Some(name) = queue.pop_front() {
+        let d = distance[name];
+        for &from in graph.reverse.get(name).into_iter().flatten() {
+            if !distance.contains_key(from) {
+                distance.insert(from, d + 1);
+                queue.push_back(from);
+            }
+        }
+    }
+    if !distance.contains_key(params.from.as_str()) {
+        return Vec::new();
+    }
+
+    let mut paths = Vec::new();
+    let mut pending: VecDeque<(Vec<&str>, Vec<usize>)> =
+        VecDeque::from([(vec![params.from.as_str()], Vec::new())]);
+    let mut expansions = 0;
+    while let Some((symbols, edges)) = pending.pop_front() {
+        let last = *symbols.last().unwrap();
+        if last == params.to {
+            paths.push(DependencyPath {
+                edges: edges
+                    .iter()
+                    .zip(symbols.windows(2))
+                    .map(|(&i, pair)| graph.edge(index, pair[0], i))
+                    .collect(),
+            });
+            if paths.len() == limit {
+                break;
+            }
+            continue;
+        }
+        expansions += 1;
+        if expansions > MAX_EXPANSIONS {
+            break;
+        }
+        for (i, edge) in graph.edges(last).iter().enumerate() {
+            let next = edge.to.as_str();
+            let fits = distance
+                .get(next)
+                .is_some_and(|d| edges.len() + 1 + d <= max_length);
+            if fits && !symbols.contains(&next) {
+                let mut symbols = symbols.clone();
+                symbols.push(next);
+                let mut edges = edges.clone();
+                edges.push(i);
+                pending.push_back((symbols, edges));
+            }
+        }
+    }
+    paths
+}
+
+/// An edge from a symbol, before its location is resolved.
+struct RawEdge<'a> {
+    to: String,
+    kind: EdgeKind,
+    file: &'a Path,
+    span: Span,
+}
+
+/// The `depends on`, `select` and `imply` edges between the symbols of the
+/// index, one per target and kind from each symbol.
+struct Graph<'a> {
+    forward: HashMap<&'a str, Vec<RawEdge<'a>>>,
+    reverse: HashMap<String, Vec<&'a str>>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(index: &'a WorldIndex) -> Self {
+        let mut forward: HashMap<&str, Vec<RawEdge>> = HashMap::new();
+        let mut reverse: HashMap<String, Vec<&str>> = HashMap::new();
+        for (name, defs) in &index.definitions {
+            // Ordered by target then kind, so paths come out the same way
+            // every time.
+            let mut edges: BTreeMap<(String, EdgeKind), (&Path, Span)> = BTreeMap::new();
+            for def in defs {
+                let mut add = |to: &str, kind: EdgeKind, span: Span| {
+                    if to != name && !matches!(to, "y" | "m" | "n") {
+                        edges
+                            .entry((to.to_string(), kind))
+                            .or_insert((def.file.as_path(), span));
+                    }
+                };
+                let conditions = [
+                    (&def.depends_on, EdgeKind::DependsOn),
+                    (&def.inherited_depends, EdgeKind::InheritedDependency),
+                ];
+                for (exprs, kind) in conditions {
+                    let mut symbols = Vec::new();
+                    for expr in exprs {
+                        expr.collect_symbols(&mut symbols);
+                    }
+                    for (to, span) in symbols {
+                        add(&to, kind, span);
+                    }
+                }
+                for s in &def.selects {
+                    add(&s.symbol, EdgeKind::Select, s.symbol_span);
+                }
+                for s in &def.implies {
+                    add(&s.symbol, EdgeKind::Imply, s.symbol_span);
+                }
+            }
+            let edges: Vec<RawEdge> = edges
+                .into_iter()
+                .map(|((to, kind), (file, span))| RawEdge {
+                    to,
+                    kind,
+                    file,
+                    span,
+                })
+                .collect();
+            for edge in &edges {
+                let from = reverse.entry(edge.to.clone()).or_default();
+                if from.last() != Some(&name.as_str()) {
+                    from.push(name);
+                }
+            }
+            forward.insert(name, edges);
+        }
+        Self { forward, reverse }
+    }
+
+    fn edges(&self, name: &str) -> &[RawEdge<'a>] {
+        self.forward.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    fn edge(&self, index: &WorldIndex, from: &str, i: usize) -> DependencyEdge {
+        let raw = &self.edges(from)[i];
+        DependencyEdge {
+            from: from.to_string(),
+            to: raw.to.clone(),
+            kind: raw.kind,
+            location: location(index, raw.file, raw.span),
+        }
+    }
+}
+
+/// The paths as text: each starts at its first symbol, then has a line per
+/// edge with where it is written, relative to the source tree root.
+pub fn render_text(index: &WorldIndex, paths: &[DependencyPath]) -> String {
+    let mut out = String::new();
+    for path in paths {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let Some(first) = path.edges.first() else {
+            continue;
+        };
+        let _ = writeln!(out, "{}", first.from);
+        for edge in &path.edges {
+            let _ = write!(out, "  {} {}", edge.kind.label(), edge.to);
+            if let Some(location) = &edge.location
+                && let Some(file) = uri::to_path(&location.uri)
+            {
+                let file = index
+                    .root
+                    .as_deref()
+                    .and_then(|root| file.strip_prefix(root).ok())
+                    .unwrap_or(&file);
+                let _ = write!(
+                    out,
+                    "  ({}:{})",
+                    file.display(),
+                    location.range.start.line + 1
+                );
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -2,63 +2,491 @@ use std::path::Path;
 
 use tower_lsp::lsp_types::{self as lsp, DiagnosticSeverity};
 
-use crate::analysis::WorldIndex;
-use crate::ast::DiagSeverity;
+use crate::analysis::{ChoiceDef, FileAnalysis, RefKind, Symbol, SymbolDef, WorldIndex};
+use crate::ast::{DiagSeverity, RangeAttr, Span, TypeKind};
+use crate::codes::{self, Code};
+use crate::eval::{self, Evaluator, Tristate};
+use crate::include_graph::{self, SourceProblemKind};
+use crate::lint;
+use crate::settings::{DiagnosticRanges, Settings};
+use crate::util::{location, range};
 
-pub fn collect(index: &WorldIndex, path: &Path) -> Vec<lsp::Diagnostic> {
+pub fn collect(index: &WorldIndex, path: &Path, settings: &Settings) -> Vec<lsp::Diagnostic> {
     let fa = match index.files.get(path) {
         Some(fa) => fa,
         None => return Vec::new(),
     };
 
     let mut diags: Vec<lsp::Diagnostic> = Vec::new();
+    // A file sourced under a condition that never holds, such as one on a
+    // subsystem this tree lacks, may name symbols only that subsystem
+    // defines; the condition itself is flagged where the file is sourced.
+    let dead = include_graph::inherited_condition(index, path)
+        .is_some_and(|c| eval::always_false(index, &c));
 
     for pd in &fa.diagnostics {
-        let (line, col) = fa.line_index.line_col(pd.span.start);
-        let (end_line, end_col) = fa.line_index.line_col(pd.span.end);
-        diags.push(lsp::Diagnostic {
-            range: lsp::Range {
-                start: lsp::Position::new(line, col),
-                end: lsp::Position::new(end_line, end_col),
-            },
-            severity: Some(match pd.severity {
-                DiagSeverity::Error => DiagnosticSeverity::ERROR,
-                DiagSeverity::Warning => DiagnosticSeverity::WARNING,
-            }),
+        diags.push(pd.code.apply(lsp::Diagnostic {
+            range: range(&fa.line_index, pd.span),
+            severity: Some(severity(pd.severity)),
             source: Some("kconfig-lsp".into()),
             message: pd.message.clone(),
             ..Default::default()
-        });
+        }));
     }
 
-    for ref_entry in index.references.values() {
-        for r in ref_entry {
-            if r.file != path {
-                continue;
-            }
-            if index.get_definitions(&r.name).is_empty()
-                && !is_well_known_symbol(&r.name)
-                && !r.name.starts_with("$(")
-            {
-                let (line, col) = fa.line_index.line_col(r.span.start);
-                let (end_line, end_col) = fa.line_index.line_col(r.span.end);
-                diags.push(lsp::Diagnostic {
-                    range: lsp::Range {
-                        start: lsp::Position::new(line, col),
-                        end: lsp::Position::new(end_line, end_col),
-                    },
-                    severity: Some(DiagnosticSeverity::WARNING),
+    for r in &fa.references {
+        let defs = index.get_definitions(&r.name);
+        if !defs.is_empty() {
+            // `default OLD` is how a replacement inherits the old value;
+            // anything else still depends on the symbol being retired.
+            if r.kind != RefKind::Default && defs.iter().all(|d| d.transitional) {
+                diags.push(codes::TRANSITIONAL_REFERENCE.apply(lsp::Diagnostic {
+                    range: range(&fa.line_index, r.span),
+                    severity: Some(DiagnosticSeverity::INFORMATION),
                     source: Some("kconfig-lsp".into()),
-                    message: format!("symbol `{}` is not defined in any open file", r.name),
+                    message: format!(
+                        "`{}` is transitional; migrate this reference to its replacement",
+                        r.name
+                    ),
                     ..Default::default()
-                });
+                }));
             }
+            if matches!(r.kind, RefKind::Select | RefKind::Imply)
+                && let Some(choice) = defs.iter().find_map(|d| index.choice_of(d))
+            {
+                let message = format!(
+                    "`{}` is a member of {}; {} sets its value but does not make it the \
+                     choice's selection, set the choice's `default` instead",
+                    r.name,
+                    choice_label(choice),
+                    if r.kind == RefKind::Select {
+                        "`select`"
+                    } else {
+                        "`imply`"
+                    }
+                );
+                diags.push(pointing_at_choice(
+                    index,
+                    warning(fa, r.span, codes::SELECT_CHOICE_MEMBER, message),
+                    choice,
+                ));
+            }
+        } else if let Some(choice) = index.named_choice(&r.name) {
+            if matches!(r.kind, RefKind::Select | RefKind::Imply) {
+                let mut diag = warning(
+                    fa,
+                    r.span,
+                    codes::SELECT_CHOICE,
+                    format!(
+                        "`{}` is a choice and cannot be {}; pick a member with the \
+                         choice's `default` instead",
+                        r.name,
+                        if r.kind == RefKind::Select {
+                            "selected"
+                        } else {
+                            "implied"
+                        }
+                    ),
+                );
+                diag.severity = Some(DiagnosticSeverity::ERROR);
+                diags.push(pointing_at_choice(index, diag, choice));
+            }
+        } else if !is_well_known_symbol(&r.name) && !r.name.contains('$') && !dead {
+            diags.push(warning(
+                fa,
+                r.span,
+                codes::UNDEFINED_SYMBOL,
+                format!("symbol `{}` is not defined in any open file", r.name),
+            ));
         }
     }
 
+    let eval = index.config.as_ref().map(|c| Evaluator::new(index, c));
+    if let Some(eval) = &eval {
+        collect_choice_diags(index, fa, eval, &mut diags);
+    }
+    collect_range_diags(index, fa, path, eval.as_ref(), &mut diags);
+    collect_source_diags(index, fa, path, &mut diags);
+
+    for finding in lint::check_file(index, path, &settings.lint) {
+        diags.push(finding.code.apply(lsp::Diagnostic {
+            range: range(&fa.line_index, finding.span),
+            severity: Some(severity(finding.severity)),
+            source: Some("kconfig-lsp".into()),
+            message: finding.message,
+            ..Default::default()
+        }));
+    }
+
+    if settings.diagnostic_ranges == DiagnosticRanges::FirstLine {
+        for diag in &mut diags {
+            diag.range = first_line(fa, diag.range);
+        }
+    }
+    cap(&mut diags, settings.max_diagnostics);
     diags
 }
 
+/// `range` cut at the end of the content of its first line, so a diagnostic
+/// about a whole block does not underline all of it.
+fn first_line(fa: &FileAnalysis, range: lsp::Range) -> lsp::Range {
+    if range.end.line == range.start.line {
+        return range;
+    }
+    let start = fa.line_index.offset(range.start.line, 0);
+    let line = fa.source[start..].lines().next().unwrap_or("");
+    // Not counting a line continuation.
+    let content = line.trim_end().trim_end_matches('\\').trim_end();
+    let end = (content.len() as u32).max(range.start.character);
+    lsp::Range {
+        start: range.start,
+        end: lsp::Position::new(range.start.line, end),
+    }
+}
+
+/// Diagnostics of every indexed file that has any, in path order, for
+/// publishing problems in files that are not open.
+pub fn collect_workspace<'a>(
+    index: &'a WorldIndex,
+    settings: &Settings,
+) -> Vec<(&'a Path, Vec<lsp::Diagnostic>)> {
+    let mut out: Vec<(&Path, Vec<lsp::Diagnostic>)> = index
+        .files
+        .keys()
+        .map(|path| (path.as_path(), collect(index, path, settings)))
+        .filter(|(_, diags)| !diags.is_empty())
+        .collect();
+    out.sort_by(|a, b| a.0.cmp(b.0));
+    out
+}
+
+/// Keep at most `max` diagnostics, errors before warnings, and replace the
+/// rest with a single summary so a badly broken file can't flood the client.
+fn cap(diags: &mut Vec<lsp::Diagnostic>, max: usize) {
+    if max == 0 || diags.len() <= max {
+        return;
+    }
+    diags.sort_by_key(|d| d.severity.map_or(u8::MAX, severity_rank));
+    let hidden = diags.len() - max;
+    diags.truncate(max);
+    diags.push(lsp::Diagnostic {
+        range: lsp::Range::default(),
+        severity: Some(DiagnosticSeverity::INFORMATION),
+        source: Some("kconfig-lsp".into()),
+        message: format!(
+            "{} more problem{} not shown",
+            hidden,
+            if hidden == 1 { "" } else { "s" }
+        ),
+        ..Default::default()
+    });
+}
+
+fn severity_rank(severity: DiagnosticSeverity) -> u8 {
+    match severity {
+        DiagnosticSeverity::ERROR => 0,
+        DiagnosticSeverity::WARNING => 1,
+        DiagnosticSeverity::INFORMATION => 2,
+        _ => 3,
+    }
+}
+
+/// Check each choice in the file against the loaded `.config`: at most one
+/// member may be `y`, the selected member's dependencies must hold, and a
+/// visible non-optional choice must have a selection.
+fn collect_choice_diags(
+    index: &WorldIndex,
+    fa: &FileAnalysis,
+    eval: &Evaluator,
+    diags: &mut Vec<lsp::Diagnostic>,
+) {
+    let order = index.tree_order();
+    for choice in &fa.choices {
+        let selected: Vec<&str> = choice
+            .members
+            .iter()
+            .filter(|m| eval.symbol_tristate(m) == Tristate::Y)
+            .map(|m| m.as_str())
+            .collect();
+
+        let message = match selected.as_slice() {
+            [] => {
+                if choice.optional || eval.eval_all(&choice.depends_on) == Tristate::N {
+                    continue;
+                }
+                "choice has no member selected in .config".to_string()
+            }
+            [member] => {
+                // Only a problem when no definition of the member is usable.
+                let inherited = include_graph::inherited_condition(index, &choice.file);
+                let unmet = index
+                    .symbol(member, &order)
+                    .filter(|sym| eval.symbol_dependencies(sym) == Tristate::N)
+                    .and_then(|sym| {
+                        sym.definitions
+                            .iter()
+                            .flat_map(|d| d.depends_on.iter().chain(&d.inherited_depends))
+                            .find(|e| eval.eval(e) == Tristate::N)
+                    })
+                    .or_else(|| inherited.as_ref().filter(|e| eval.eval(e) == Tristate::N));
+                match unmet {
+                    Some(expr) => format!(
+                        "`{}` is selected in .config but its dependency `{}` is not met",
+                        member, expr
+                    ),
+                    None => continue,
+                }
+            }
+            many => format!(
+                "choice has multiple members set to y in .config: {}",
+                many.join(", ")
+            ),
+        };
+
+        diags.push(warning(fa, choice.span, codes::CHOICE_SELECTION, message));
+    }
+}
+
+/// Check `default` literals and `.config` values of `int`/`hex` symbols
+/// against the active `range`, which may come from any definition.
+fn collect_range_diags(
+    index: &WorldIndex,
+    fa: &FileAnalysis,
+    path: &Path,
+    eval: Option<&Evaluator>,
+    diags: &mut Vec<lsp::Diagnostic>,
+) {
+    let order = index.tree_order();
+    let mut names: Vec<&str> = index
+        .symbols_in_file(path)
+        .iter()
+        .map(|d| d.name.as_str())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    for name in names {
+        let Some(sym) = index.symbol(name, &order) else {
+            continue;
+        };
+        let hex = match index.symbol_type(name) {
+            Some(TypeKind::Hex) => true,
+            Some(TypeKind::Int) => false,
+            _ => continue,
+        };
+        let Some((range_def, range, low, high)) = active_range(index, &sym, hex, eval) else {
+            continue;
+        };
+        let bounds = format!(
+            "{}..{}",
+            eval::format_number(low, hex),
+            eval::format_number(high, hex)
+        );
+
+        for (def, d) in sym.defaults() {
+            if def.file != path
+                || d.condition.is_some()
+                || eval.is_some_and(|e| e.dependencies(def) == Tristate::N)
+            {
+                continue;
+            }
+            if let Some(n) = eval::literal_number(index, &d.value, hex)
+                && (n < low || n > high)
+            {
+                diags.push(warning(
+                    fa,
+                    d.value.span(),
+                    codes::DEFAULT_OUT_OF_RANGE,
+                    format!(
+                        "default {} is outside the range {} of `{}`",
+                        d.value, bounds, name
+                    ),
+                ));
+            }
+        }
+
+        // Reported once, on the range that applies.
+        if range_def.file != path {
+            continue;
+        }
+        let Some(config) = &index.config else {
+            continue;
+        };
+        let Some(value) = config.get(name) else {
+            continue;
+        };
+        let message = match eval::parse_number(value, hex) {
+            Some(n) if n < low || n > high => format!(
+                ".config sets `{}` to {}, outside the range {}",
+                name, value, bounds
+            ),
+            Some(_) => continue,
+            None => format!(
+                ".config value `{}` of `{}` is not a valid {} number",
+                value,
+                name,
+                if hex { "hex" } else { "int" }
+            ),
+        };
+        diags.push(warning(fa, range.span, codes::CONFIG_OUT_OF_RANGE, message));
+    }
+}
+
+/// The first `range`, in parse order, whose condition and definition
+/// dependencies hold and whose bounds resolve. Without a loaded `.config`,
+/// only unconditional ranges are considered.
+pub fn active_range<'d>(
+    index: &WorldIndex,
+    sym: &Symbol<'d>,
+    hex: bool,
+    eval: Option<&Evaluator>,
+) -> Option<(&'d SymbolDef, &'d RangeAttr, i64, i64)> {
+    sym.ranges().find_map(|(def, r)| {
+        let (low, high) = match (eval, &r.condition) {
+            (Some(eval), cond) => {
+                if eval.dependencies(def) == Tristate::N
+                    || cond.as_ref().is_some_and(|c| eval.eval(c) == Tristate::N)
+                {
+                    return None;
+                }
+                (eval.number(&r.low, hex)?, eval.number(&r.high, hex)?)
+            }
+            (None, Some(_)) => return None,
+            (None, None) => (
+                eval::literal_number(index, &r.low, hex)?,
+                eval::literal_number(index, &r.high, hex)?,
+            ),
+        };
+        Some((def, r, low, high))
+    })
+}
+
+/// `source` cycles (errors) and files sourced a second time (warnings), with
+/// the include chain as related information.
+fn collect_source_diags(
+    index: &WorldIndex,
+    fa: &FileAnalysis,
+    path: &Path,
+    diags: &mut Vec<lsp::Diagnostic>,
+) {
+    let display = |p: &Path| {
+        index
+            .root
+            .as_deref()
+            .and_then(|root| p.strip_prefix(root).ok())
+            .unwrap_or(p)
+            .display()
+            .to_string()
+    };
+    let related = |chain: &[(std::path::PathBuf, Span)], message: &str| {
+        chain
+            .iter()
+            .filter_map(|(file, span)| {
+                Some(lsp::DiagnosticRelatedInformation {
+                    location: location(index, file, *span)?,
+                    message: message.to_string(),
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    for problem in &index.include_graph().problems {
+        if problem.file != path {
+            continue;
+        }
+        let target = fa.source[problem.span.start..problem.span.end].trim_matches('"');
+        let diag = match problem.kind {
+            SourceProblemKind::Cycle => {
+                let mut files: Vec<String> =
+                    problem.chain.iter().map(|(f, _)| display(f)).collect();
+                files.extend(files.first().cloned());
+                let mut diag = warning(
+                    fa,
+                    problem.span,
+                    codes::SOURCE_CYCLE,
+                    format!(
+                        "`source \"{}\"` forms a cycle: {}",
+                        target,
+                        files.join(" → ")
+                    ),
+                );
+                diag.severity = Some(DiagnosticSeverity::ERROR);
+                diag.related_information = Some(related(&problem.chain, "part of the cycle"));
+                diag
+            }
+            SourceProblemKind::Duplicate => {
+                let via = problem.first.last().map_or_else(
+                    || "the root Kconfig".to_string(),
+                    |(file, span)| {
+                        let line = index
+                            .files
+                            .get(file)
+                            .map_or(0, |f| f.line_index.line_col(span.start).0 + 1);
+                        format!("already sourced from {}:{}", display(file), line)
+                    },
+                );
+                let mut diag = warning(
+                    fa,
+                    problem.span,
+                    codes::SOURCE_DUPLICATE,
+                    format!(
+                        "\"{}\" is {}; its symbols get a second set of definitions",
+                        target, via
+                    ),
+                );
+                let mut info = related(&problem.first, "first sourced through here");
+                info.extend(related(
+                    &problem.chain[..problem.chain.len().saturating_sub(1)],
+                    "sourced again through here",
+                ));
+                diag.related_information = Some(info);
+                diag
+            }
+        };
+        diags.push(diag);
+    }
+}
+
+fn choice_label(choice: &ChoiceDef) -> String {
+    match (&choice.name, &choice.prompt) {
+        (Some(name), _) => format!("the choice `{}`", name),
+        (None, Some(prompt)) => format!("the choice \"{}\"", prompt),
+        (None, None) => "a choice".into(),
+    }
+}
+
+/// Attach the location of `choice` as related information.
+fn pointing_at_choice(
+    index: &WorldIndex,
+    mut diag: lsp::Diagnostic,
+    choice: &ChoiceDef,
+) -> lsp::Diagnostic {
+    let Some(location) = location(index, &choice.file, choice.span) else {
+        return diag;
+    };
+    diag.related_information = Some(vec![lsp::DiagnosticRelatedInformation {
+        location,
+        message: "choice defined here".into(),
+    }]);
+    diag
+}
+
+fn warning(fa: &FileAnalysis, span: Span, code: Code, message: String) -> lsp::Diagnostic {
+    code.apply(lsp::Diagnostic {
+        range: range(&fa.line_index, span),
+        severity: Some(DiagnosticSeverity::WARNING),
+        source: Some("kconfig-lsp".into()),
+        message,
+        ..Default::default()
+    })
+}
+
+fn severity(severity: DiagSeverity) -> DiagnosticSeverity {
+    match severity {
+        DiagSeverity::Error => DiagnosticSeverity::ERROR,
+        DiagSeverity::Warning => DiagnosticSeverity::WARNING,
+    }
+}
+
 fn is_well_known_symbol(name: &str) -> bool {
     matches!(
         name,
@@ -0,0 +1,428 @@
+//! `kconfig-lsp doc`: the menu tree rendered as cross-linked pages, one per
+//! menu, with an anchor per symbol. Each symbol shows what hover shows for
+//! it, after links to the symbols it depends on, selects and implies.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tower_lsp::lsp_types::MarkupKind;
+
+use crate::analysis::WorldIndex;
+use crate::hover::{self, ReverseDependencies};
+use crate::include_graph::{self, TreeOrder};
+use crate::menu_tree::{self, MenuNode, MenuNodeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DocFormat::Markdown => "md",
+            DocFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Page {
+    /// File name relative to the output directory.
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// One page for the tree rooted at `root` (`index.md` or `index.html`) and
+/// one for every menu below it. `None` if `root` is not indexed.
+pub fn render(index: &WorldIndex, root: &Path, format: DocFormat) -> Option<Vec<Page>> {
+    let tree = menu_tree::menu_tree(index, root)?;
+    let mut site = Site {
+        index,
+        format,
+        order: index.tree_order(),
+        reverse: hover::all_reverse_dependencies(index),
+        names: HashMap::new(),
+        anchors: HashMap::new(),
+    };
+    site.plan(&tree, "index".into(), &mut Vec::new());
+
+    let mut emitted = HashSet::new();
+    let mut crumbs = Vec::new();
+    let mut out = Vec::new();
+    site.pages(&tree, &mut crumbs, &mut emitted, &mut out);
+    Some(out)
+}
+
+struct Site<'a> {
+    index: &'a WorldIndex,
+    format: DocFormat,
+    /// Built once for the documentation of every symbol.
+    order: Arc<TreeOrder>,
+    reverse: HashMap<&'a str, ReverseDependencies<'a>>,
+    /// Page file name per menu node, keyed by its address.
+    names: HashMap<*const MenuNode, String>,
+    /// Page and anchor of the first place each symbol appears.
+    anchors: HashMap<String, String>,
+}
+
+impl Site<'_> {
+    /// Name the page of `node` and of every menu below it, and record where
+    /// each symbol is documented.
+    fn plan(&mut self, node: &MenuNode, stem: String, taken: &mut Vec<String>) {
+        let file = format!("{}.{}", stem, self.format.extension());
+        taken.push(stem);
+        self.names.insert(node as *const _, file.clone());
+        self.plan_entries(&node.children, &file, taken);
+    }
+
+    fn plan_entries(&mut self, nodes: &[MenuNode], file: &str, taken: &mut Vec<String>) {
+        for node in nodes {
+            if node.kind == MenuNodeKind::Menu {
+                let stem = unique_stem(&slug(&node.label), taken);
+                self.plan(node, stem, taken);
+                continue;
+            }
+            if let Some(sym) = &node.symbol {
+                self.anchors
+                    .entry(sym.clone())
+                    .or_insert_with(|| format!("{}#{}", file, sym));
+            }
+            self.plan_entries(&node.children, file, taken);
+        }
+    }
+
+    fn pages(
+        &self,
+        node: &MenuNode,
+        crumbs: &mut Vec<(String, String)>,
+        emitted: &mut HashSet<String>,
+        out: &mut Vec<Page>,
+    ) {
+        let file = self.names[&(node as *const _)].clone();
+        let mut md = String::new();
+        if !crumbs.is_empty() {
+            let trail: Vec<String> = crumbs
+                .iter()
+                .map(|(label, file)| format!("[{}]({})", label, file))
+                .collect();
+            md.push_str(&format!("{} › {}\n\n", trail.join(" › "), node.label));
+        }
+        md.push_str(&format!("# {}\n", node.label));
+        self.entries(&node.children, 2, emitted, &mut md);
+        out.push(Page {
+            path: PathBuf::from(&file),
+            content: match self.format {
+                DocFormat::Markdown => md,
+                DocFormat::Html => html_page(&node.label, &md),
+            },
+        });
+
+        crumbs.push((node.label.clone(), file));
+        self.subpages(&node.children, crumbs, emitted, out);
+        crumbs.pop();
+    }
+
+    fn subpages(
+        &self,
+        nodes: &[MenuNode],
+        crumbs: &mut Vec<(String, String)>,
+        emitted: &mut HashSet<String>,
+        out: &mut Vec<Page>,
+    ) {
+        for node in nodes {
+            if node.kind == MenuNodeKind::Menu {
+                self.pages(node, crumbs, emitted, out);
+            } else {
+                self.subpages(&node.children, crumbs, emitted, out);
+            }
+        }
+    }
+
+    fn entries(
+        &self,
+        nodes: &[MenuNode],
+        level: usize,
+        emitted: &mut HashSet<String>,
+        md: &mut String,
+    ) {
+        let heading = "#".repeat(level.min(6));
+        for node in nodes {
+            match node.kind {
+                MenuNodeKind::Menu => {
+                    let file = &self.names[&(node as *const _)];
+                    md.push_str(&format!("\n**Menu:** [{}]({})\n", node.label, file));
+                }
+                MenuNodeKind::Comment => md.push_str(&format!("\n*{}*\n", node.label)),
+                MenuNodeKind::Choice => {
+                    md.push_str(&format!("\n{} Choice: {}\n", heading, node.label));
+                    self.entries(&node.children, level + 1, emitted, md);
+                }
+                MenuNodeKind::Config | MenuNodeKind::MenuConfig | MenuNodeKind::Root => {
+                    if let Some(sym) = &node.symbol {
+                        self.symbol(sym, &node.label, &heading, emitted, md);
+                    }
+                    self.entries(&node.children, level + 1, emitted, md);
+                }
+            }
+        }
+    }
+
+    fn symbol(
+        &self,
+        name: &str,
+        label: &str,
+        heading: &str,
+        emitted: &mut HashSet<String>,
+        md: &mut String,
+    ) {
+        let title = if label == name {
+            format!("`{}`", name)
+        } else {
+            format!("`{}`: {}", name, label)
+        };
+        if !emitted.insert(name.to_string()) {
+            // Defined again elsewhere in the tree; document it once.
+            md.push_str(&format!(
+                "\n{} {}\n\nSee [{}]({}).\n",
+                heading, title, name, self.anchors[name]
+            ));
+            return;
+        }
+        md.push_str(&format!(
+            "\n<a id=\"{}\"></a>\n\n{} {}\n",
+            name, heading, title
+        ));
+
+        let defs = self.index.get_definitions(name);
+        let mut lines = Vec::new();
+        // What a definition depends on includes the conditions its file is
+        // sourced under.
+        let mut depends = Vec::new();
+        for d in defs {
+            let sourced = include_graph::inherited_condition(self.index, &d.file);
+            for expr in sourced
+                .iter()
+                .chain(&d.inherited_depends)
+                .chain(&d.depends_on)
+            {
+                expr.collect_symbols(&mut depends);
+            }
+        }
+        let selects = defs.iter().flat_map(|d| &d.selects);
+        let implies = defs.iter().flat_map(|d| &d.implies);
+        for (what, targets) in [
+            ("Depends on", self.links(depends.iter().map(|(n, _)| n))),
+            ("Selects", self.links(selects.map(|s| &s.symbol))),
+            ("Implies", self.links(implies.map(|s| &s.symbol))),
+        ] {
+            if !targets.is_empty() {
+                lines.push(format!("{}: {}", what, targets.join(", ")));
+            }
+        }
+        if !lines.is_empty() {
+            md.push_str(&format!("\n{}\n", lines.join("  \n")));
+        }
+        let none = ReverseDependencies::default();
+        let reverse = self.reverse.get(name).unwrap_or(&none);
+        if let Some(doc) = hover::documentation(
+            self.index,
+            &self.order,
+            reverse,
+            name,
+            MarkupKind::Markdown,
+            0,
+        ) {
+            md.push_str(&format!("\n{}\n", doc));
+        }
+    }
+
+    /// Links to the defined symbols among `names`, each once. Symbols that
+    /// are not in the menu tree are named without a link.
+    fn links<'n>(&self, names: impl Iterator<Item = &'n String>) -> Vec<String> {
+        let mut seen = HashSet::new();
+        names
+            .filter(|name| !self.index.get_definitions(name).is_empty())
+            .filter(|name| seen.insert(*name))
+            .map(|name| match self.anchors.get(name) {
+                Some(target) => format!("[{}]({})", name, target),
+                None => format!("`{}`", name),
+            })
+            .collect()
+    }
+}
+
+/// A file name stem from a menu prompt: lowercase words joined by `-`.
+fn slug(label: &str) -> String {
+    let mut out = String::new();
+    for word in label
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if !out.is_empty() {
+            out.push('-');
+        }
+        out.push_str(&word.to_ascii_lowercase());
+    }
+    if out.is_empty() {
+        out.push_str("menu");
+    }
+    out
+}
+
+fn unique_stem(stem: &str, taken: &[String]) -> String {
+    let mut candidate = stem.to_string();
+    let mut n = 1;
+    while taken.contains(&candidate) {
+        n += 1;
+        candidate = format!("{}-{}", stem, n);
+    }
+    candidate
+}
+
+fn html_page(title: &str, md: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        markdown_to_html(md)
+    )
+}
+
+/// The Markdown subset pages and hover use: headings, paragraphs, `---`
+/// rules, `-` lists, `>` quotes, fenced code, inline code, emphasis and
+/// links. Lines starting with `<` are passed through as HTML.
+pub fn markdown_to_html(md: &str) -> String {
+    let mut out = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut lines = md.lines().peekable();
+    let flush = |paragraph: &mut Vec<&str>, out: &mut String| {
+        if !paragraph.is_empty() {
+            let text = paragraph.join("\n").replace("  \n", "<br>\n");
+            out.push_str(&format!("<p>{}</p>\n", inline(&text)));
+            paragraph.clear();
+        }
+    };
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_end();
+        if let Some(lang) = trimmed.strip_prefix("```") {
+            flush(&mut paragraph, &mut out);
+            let mut code = String::new();
+            for line in lines.by_ref() {
+                if line.trim_end() == "```" {
+                    break;
+                }
+                code.push_str(line);
+                code.push('\n');
+            }
+            let class = if lang.is_empty() {
+                String::new()
+            } else {
+                format!(" class=\"language-{}\"", escape(lang))
+            };
+            out.push_str(&format!(
+                "<pre><code{}>{}</code></pre>\n",
+                class,
+                escape(&code)
+            ));
+        } else if trimmed.is_empty() {
+            flush(&mut paragraph, &mut out);
+        } else if trimmed.starts_with('<') {
+            flush(&mut paragraph, &mut out);
+            out.push_str(trimmed);
+            out.push('\n');
+        } else if trimmed == "---" {
+            flush(&mut paragraph, &mut out);
+            out.push_str("<hr>\n");
+        } else if let Some((level, text)) = heading(trimmed) {
+            flush(&mut paragraph, &mut out);
+            out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline(text)));
+        } else if let Some(item) = trimmed.strip_prefix("- ") {
+            flush(&mut paragraph, &mut out);
+            out.push_str(&format!("<ul>\n<li>{}</li>\n", inline(item)));
+            while let Some(item) = lines.peek().and_then(|l| l.strip_prefix("- ")) {
+                out.push_str(&format!("<li>{}</li>\n", inline(item.trim_end())));
+                lines.next();
+            }
+            out.push_str("</ul>\n");
+        } else if let Some(quoted) = trimmed.strip_prefix('>') {
+            flush(&mut paragraph, &mut out);
+            let mut text = vec![quoted.trim_start()];
+            while let Some(quoted) = lines.peek().and_then(|l| l.strip_prefix('>')) {
+                text.push(quoted.trim());
+                lines.next();
+            }
+            out.push_str(&format!(
+                "<blockquote><p>{}</p></blockquote>\n",
+                inline(&text.join("\n"))
+            ));
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush(&mut paragraph, &mut out);
+    out
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text))
+}
+
+fn inline(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            out.push_str(&format!("<code>{}</code>", escape(&rest[1..=end])));
+            rest = &rest[end + 2..];
+        } else if let Some(inner) = rest.strip_prefix("**")
+            && let Some(end) = inner.find("**").filter(|&e| e > 0)
+        {
+            out.push_str(&format!("<strong>{}</strong>", inline(&inner[..end])));
+            rest = &inner[end + 2..];
+        } else if let Some(inner) = rest.strip_prefix('*')
+            && !inner.starts_with(char::is_whitespace)
+            && let Some(end) = inner.find('*').filter(|&e| e > 0)
+        {
+            out.push_str(&format!("<em>{}</em>", inline(&inner[..end])));
+            rest = &inner[end + 1..];
+        } else if let Some((label, href, len)) = link(rest) {
+            out.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape(href),
+                inline(label)
+            ));
+            rest = &rest[len..];
+        } else {
+            out.push_str(&escape(&rest[..c.len_utf8()]));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// `[label](href)` at the start of `text`, and its length.
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let inner = text.strip_prefix('[')?;
+    let close = inner.find("](")?;
+    let label = &inner[..close];
+    let after = &inner[close + 2..];
+    let end = after.find(')')?;
+    let href = &after[..end];
+    if label.contains('[') || href.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((label, href, 1 + close + 2 + end + 1))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
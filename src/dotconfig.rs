@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::Url;
+use tower_lsp::lsp_types::notification::Notification;
+
+use crate::analysis::WorldIndex;
+use crate::ast::Expr;
+use crate::eval::{Evaluator, Tristate};
+
+/// `kconfig/configReloaded`: sent after the `.config` changed on disk (e.g.
+/// after running `make menuconfig` in a terminal) and was loaded again.
+pub enum ConfigReloaded {}
+
+impl Notification for ConfigReloaded {
+    type Params = ConfigReloadedParams;
+    const METHOD: &'static str = "kconfig/configReloaded";
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigReloadedParams {
+    pub uri: Url,
+    /// Symbols whose value was added, removed, or changed.
+    pub changed: usize,
+}
+
+/// A single `CONFIG_FOO=value` assignment from a `.config` file.
+#[derive(Debug, Clone)]
+pub struct ConfigValue {
+    /// Raw value with string quoting removed (`y`, `m`, `0x10`, `foo`).
+    pub value: String,
+    /// 0-based line of the assignment.
+    pub line: u32,
+}
+
+/// Parsed contents of a `.config` (or `sdkconfig`, `prj.conf`, ...) file.
+#[derive(Debug, Clone, Default)]
+pub struct DotConfig {
+    pub path: PathBuf,
+    /// Assignments keyed by symbol name, with the prefix stripped.
+    pub values: HashMap<String, ConfigValue>,
+}
+
+impl DotConfig {
+    pub fn load(path: &Path, prefix: &str) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(path, &text, prefix))
+    }
+
+    /// Parse `CONFIG_FOO=value` assignments, and `# CONFIG_FOO is not set`
+    /// comments as assignments of `n`.
+    pub fn parse(path: &Path, text: &str, prefix: &str) -> Self {
+        let mut values = HashMap::new();
+        for (line, raw) in text.lines().enumerate() {
+            let raw = raw.trim();
+            let (name, value) = if let Some(name) = not_set(raw, prefix) {
+                (name, "n".to_string())
+            } else {
+                let Some(rest) = raw.strip_prefix(prefix) else {
+                    continue;
+                };
+                let Some((name, value)) = rest.split_once('=') else {
+                    continue;
+                };
+                (name.trim(), unquote(value.trim()))
+            };
+            values.insert(
+                name.to_string(),
+                ConfigValue {
+                    value,
+                    line: line as u32,
+                },
+            );
+        }
+        Self {
+            path: path.to_path_buf(),
+            values,
+        }
+    }
+
+    /// Load the configuration a build generated from the `.config`:
+    /// `include/config/auto.conf`, written like a `.config`, or the C header
+    /// `include/generated/autoconf.h`.
+    pub fn load_generated(path: &Path, prefix: &str) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(if path.extension().is_some_and(|e| e == "h") {
+            Self::parse_header(path, &text, prefix)
+        } else {
+            Self::parse(path, &text, prefix)
+        })
+    }
+
+    /// Parse the `#define CONFIG_FOO value` lines of `autoconf.h`. A
+    /// tristate set to `m` is defined as `CONFIG_FOO_MODULE 1` and read back
+    /// as `m`; a symbol set to `y` stays `1`, since only its type tells it
+    /// from the number.
+    pub fn parse_header(path: &Path, text: &str, prefix: &str) -> Self {
+        let mut values = HashMap::new();
+        for (line, raw) in text.lines().enumerate() {
+            let Some(rest) = raw
+                .trim()
+                .strip_prefix("#define")
+                .and_then(|r| r.trim_start().strip_prefix(prefix))
+            else {
+                continue;
+            };
+            let Some((name, value)) = rest.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let value = value.trim();
+            let (name, value) = match name.strip_suffix("_MODULE") {
+                Some(base) if value == "1" => (base, "m".to_string()),
+                _ => (name, unquote(value)),
+            };
+            values.insert(
+                name.to_string(),
+                ConfigValue {
+                    value,
+                    line: line as u32,
+                },
+            );
+        }
+        Self {
+            path: path.to_path_buf(),
+            values,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|v| v.value.as_str())
+    }
+
+    /// Number of symbols whose value differs between `self` and `other`,
+    /// counting symbols set in only one of them.
+    pub fn changed_symbols(&self, other: &DotConfig) -> usize {
+        let changed = self
+            .values
+            .iter()
+            .filter(|(name, v)| other.get(name) != Some(v.value.as_str()))
+            .count();
+        let added = other
+            .values
+            .keys()
+            .filter(|name| !self.values.contains_key(*name))
+            .count();
+        changed + added
+    }
+}
+
+/// Initial configurations named by the `option defconfig_list` symbol, in
+/// the order kconfig tries them: its defaults whose condition holds with
+/// nothing configured. Paths that need macro or environment expansion are
+/// skipped.
+pub fn defconfig_list(index: &WorldIndex) -> Vec<String> {
+    let Some(def) = index
+        .definitions
+        .values()
+        .flatten()
+        .find(|d| d.has_option("defconfig_list"))
+    else {
+        return Vec::new();
+    };
+    let order = index.tree_order();
+    let Some(sym) = index.symbol(&def.name, &order) else {
+        return Vec::new();
+    };
+    let empty = DotConfig::default();
+    let eval = Evaluator::new(index, &empty);
+    sym.defaults()
+        .filter(|(_, d)| {
+            d.condition
+                .as_ref()
+                .is_none_or(|c| eval.eval(c) != Tristate::N)
+        })
+        .filter_map(|(_, d)| match &d.value {
+            Expr::StringLit(path, _) | Expr::Symbol(path, _) if !path.contains('$') => {
+                Some(path.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The symbol, without the prefix, that a `# CONFIG_FOO is not set` line
+/// sets to `n`.
+pub fn not_set<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    let name = line
+        .trim()
+        .strip_prefix('#')?
+        .trim_start()
+        .strip_suffix("is not set")?
+        .trim_end()
+        .strip_prefix(prefix)?;
+    (!name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_'))
+        .then_some(name)
+}
+
+fn unquote(value: &str) -> String {
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return value.to_string();
+    };
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(esc) = chars.next() {
+                out.push(esc);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
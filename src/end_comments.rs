@@ -0,0 +1,118 @@
+//! Comments after `endmenu`, `endif` and `endchoice` naming the block they
+//! close, as in `endmenu # "General setup"` and `endif # NETDEVICES`: a
+//! convention of long files. The "Annotate end keywords" source action
+//! writes them, and the `end-comment` lint flags those naming another block.
+
+use crate::ast::*;
+use crate::lint::Edit;
+
+/// The end keyword of a block, with the comment after it.
+#[derive(Debug, Clone)]
+pub struct EndComment {
+    pub keyword: Span,
+    /// The text of the comment, without the `#` and the blanks around it.
+    pub comment: Option<Span>,
+    /// What the comment should say: the menu's title as written, the
+    /// block's condition, or the choice's prompt or name.
+    pub expected: String,
+}
+
+impl EndComment {
+    /// Whether the comment names another block than the one it closes.
+    pub fn is_stale(&self, source: &str) -> bool {
+        self.comment.is_some_and(|c| {
+            c.start < c.end && normalize(&source[c.start..c.end]) != normalize(&self.expected)
+        })
+    }
+
+    /// The edit writing the expected comment, if it is missing or stale.
+    pub fn edit(&self, source: &str) -> Option<Edit> {
+        match self.comment {
+            None => Some(Edit {
+                span: Span::new(self.keyword.end, self.keyword.end),
+                new_text: format!(" # {}", self.expected),
+            }),
+            // A bare `#`.
+            Some(comment) if comment.start == comment.end => Some(Edit {
+                span: comment,
+                new_text: format!(" {}", self.expected),
+            }),
+            Some(comment) if self.is_stale(source) => Some(Edit {
+                span: comment,
+                new_text: self.expected.clone(),
+            }),
+            Some(_) => None,
+        }
+    }
+}
+
+/// The end keywords of the blocks in `entries`, nested ones included, in
+/// file order of their blocks. Blocks missing their end keyword, and
+/// choices with neither prompt nor name, are left out.
+pub fn end_comments(source: &str, entries: &[Entry]) -> Vec<EndComment> {
+    let mut out = Vec::new();
+    collect(source, entries, &mut out);
+    out
+}
+
+fn collect(source: &str, entries: &[Entry], out: &mut Vec<EndComment>) {
+    for entry in entries {
+        let block = match entry {
+            Entry::Menu(m) => Some(("endmenu", text(source, m.prompt_span).to_string())),
+            Entry::If(i) => Some(("endif", i.condition.to_string())),
+            Entry::Choice(ch) => ch
+                .attributes
+                .iter()
+                .find_map(|a| match a {
+                    Attribute::Type(TypeAttr {
+                        prompt: Some(p), ..
+                    })
+                    | Attribute::Prompt(p) => Some(text(source, p.text_span).to_string()),
+                    _ => None,
+                })
+                .or_else(|| ch.name.as_ref().map(|(name, _)| name.clone()))
+                .map(|expected| ("endchoice", expected)),
+            _ => None,
+        };
+        if let Some((keyword, expected)) = block {
+            let end = entry.span().end;
+            if source.get(..end).is_some_and(|s| s.ends_with(keyword)) {
+                out.push(EndComment {
+                    keyword: Span::new(end - keyword.len(), end),
+                    comment: comment_after(source, end),
+                    expected,
+                });
+            }
+        }
+        collect(source, entry.children(), out);
+    }
+}
+
+/// The text of the `#` comment on the rest of the line from `offset`,
+/// without the `#` and the blanks around it.
+pub fn comment_after(source: &str, offset: usize) -> Option<Span> {
+    let line_end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    let comment = source[offset..line_end].trim_start().strip_prefix('#')?;
+    let start = line_end - comment.len() + (comment.len() - comment.trim_start().len());
+    Some(Span::new(start, start + comment.trim().len()))
+}
+
+fn text(source: &str, span: Span) -> &str {
+    &source[span.start..span.end]
+}
+
+/// A comment or expected text reduced to what tells blocks apart: no
+/// leading block keyword, quotes, `CONFIG_` prefixes, blanks or case.
+fn normalize(text: &str) -> String {
+    let text = ["menu ", "if ", "choice "]
+        .iter()
+        .find_map(|k| text.strip_prefix(k))
+        .unwrap_or(text);
+    text.replace("CONFIG_", "")
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '"' | '\''))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
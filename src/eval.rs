@@ -0,0 +1,360 @@
+use std::cmp::Ordering;
+
+use crate::analysis::{Symbol, SymbolDef, WorldIndex};
+use crate::ast::{DefaultAttr, Expr, SelectImplyAttr, TypeKind};
+use crate::dotconfig::DotConfig;
+
+/// Kconfig tristate logic value. Ordered so that `n < m < y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tristate {
+    N,
+    M,
+    Y,
+}
+
+impl Tristate {
+    pub fn parse(s: &str) -> Tristate {
+        match s {
+            "y" => Tristate::Y,
+            "m" => Tristate::M,
+            _ => Tristate::N,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tristate::N => "n",
+            Tristate::M => "m",
+            Tristate::Y => "y",
+        }
+    }
+
+    pub fn and(self, other: Tristate) -> Tristate {
+        self.min(other)
+    }
+
+    pub fn or(self, other: Tristate) -> Tristate {
+        self.max(other)
+    }
+}
+
+impl std::ops::Not for Tristate {
+    type Output = Tristate;
+
+    fn not(self) -> Tristate {
+        match self {
+            Tristate::N => Tristate::Y,
+            Tristate::M => Tristate::M,
+            Tristate::Y => Tristate::N,
+        }
+    }
+}
+
+/// What an `imply` currently does. Unlike `select`, `imply` only raises the
+/// implied symbol's default: it is limited by that symbol's dependencies,
+/// and the user can still set the symbol lower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImplyEffect {
+    /// The symbol is at least the implied value.
+    Effective(Tristate),
+    /// The implying symbol or the `if` condition is `n`.
+    Inactive,
+    /// The implied symbol's dependencies are `n`.
+    DependenciesUnmet,
+    /// Implied, but set lower in `.config`.
+    Overridden(Tristate),
+}
+
+/// Evaluates expressions against the symbol values of a loaded `.config`.
+///
+/// Symbols absent from the `.config` evaluate to `n` (or the empty string for
+/// non-boolean types); names that are not defined anywhere are treated as
+/// constants, the way kconfig treats `y`, `0x10` or `"foo"`.
+pub struct Evaluator<'a> {
+    index: &'a WorldIndex,
+    config: &'a DotConfig,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(index: &'a WorldIndex, config: &'a DotConfig) -> Self {
+        Self { index, config }
+    }
+
+    pub fn eval(&self, expr: &Expr) -> Tristate {
+        match expr {
+            Expr::Symbol(name, _) => self.symbol_tristate(name),
+            Expr::StringLit(s, _) => Tristate::parse(s),
+            Expr::Not(e) => !self.eval(e),
+            Expr::Paren(e) => self.eval(e),
+            Expr::And(..) | Expr::Or(..) => {
+                // Chains nest to the left; walk down them instead of
+                // recursing, then combine from the innermost operand out.
+                let mut rights = Vec::new();
+                let mut left = expr;
+                while let Expr::And(a, b) | Expr::Or(a, b) = left {
+                    rights.push((matches!(left, Expr::And(..)), b));
+                    left = a;
+                }
+                let mut value = self.eval(left);
+                for (and, right) in rights.into_iter().rev() {
+                    let right = self.eval(right);
+                    value = if and {
+                        value.and(right)
+                    } else {
+                        value.or(right)
+                    };
+                }
+                value
+            }
+            Expr::Eq(a, b) => bool_tri(self.compare(a, b) == Some(Ordering::Equal)),
+            Expr::NotEq(a, b) => bool_tri(self.compare(a, b) != Some(Ordering::Equal)),
+            Expr::Less(a, b) => bool_tri(self.compare(a, b) == Some(Ordering::Less)),
+            Expr::LessEq(a, b) => bool_tri(matches!(
+                self.compare(a, b),
+                Some(Ordering::Less | Ordering::Equal)
+            )),
+            Expr::Greater(a, b) => bool_tri(self.compare(a, b) == Some(Ordering::Greater)),
+            Expr::GreaterEq(a, b) => bool_tri(matches!(
+                self.compare(a, b),
+                Some(Ordering::Greater | Ordering::Equal)
+            )),
+        }
+    }
+
+    /// Evaluate the conjunction of several expressions (`y` when empty), as
+    /// kconfig does for repeated `depends on` lines.
+    pub fn eval_all<'e>(&self, exprs: impl IntoIterator<Item = &'e Expr>) -> Tristate {
+        exprs
+            .into_iter()
+            .fold(Tristate::Y, |acc, e| acc.and(self.eval(e)))
+    }
+
+    /// The dependencies of one definition: its own `depends on` lines plus
+    /// those inherited from enclosing `if` blocks, menus and choices.
+    pub fn dependencies(&self, def: &SymbolDef) -> Tristate {
+        self.eval_all(def.depends_on.iter().chain(&def.inherited_depends))
+    }
+
+    /// Whether the definition's prompt can be shown: its dependencies plus
+    /// the `visible if` conditions of enclosing menus.
+    pub fn prompt_visibility(&self, def: &SymbolDef) -> Tristate {
+        self.dependencies(def).and(self.eval_all(&def.visible_if))
+    }
+
+    /// Whether the `visible if` of an enclosing menu hides the definition's
+    /// prompt although its dependencies hold: the value can then still be
+    /// set by `select`, `imply`, or a default, only not by the user.
+    pub fn hidden_by_visible_if(&self, def: &SymbolDef) -> bool {
+        !def.visible_if.is_empty()
+            && self.dependencies(def) != Tristate::N
+            && self.eval_all(&def.visible_if) == Tristate::N
+    }
+
+    /// Whether any definition's dependencies hold. Each definition keeps its
+    /// own `depends on`; kconfig ORs them across definitions.
+    pub fn symbol_dependencies(&self, sym: &Symbol) -> Tristate {
+        sym.definitions
+            .iter()
+            .fold(Tristate::N, |acc, d| acc.or(self.dependencies(d)))
+    }
+
+    /// Whether any of the symbol's prompts is shown.
+    pub fn symbol_visibility(&self, sym: &Symbol) -> Tristate {
+        sym.prompts().fold(Tristate::N, |acc, d| {
+            let cond = d
+                .prompt_condition
+                .as_ref()
+                .map_or(Tristate::Y, |c| self.eval(c));
+            acc.or(self.prompt_visibility(d).and(cond))
+        })
+    }
+
+    /// The default kconfig uses: the first, in parse order, whose condition
+    /// and whose definition's dependencies hold.
+    pub fn active_default<'s>(&self, sym: &Symbol<'s>) -> Option<(&'s SymbolDef, &'s DefaultAttr)> {
+        sym.defaults().find(|(d, a)| {
+            let cond = a.condition.as_ref().map_or(Tristate::Y, |c| self.eval(c));
+            self.dependencies(d).and(cond) != Tristate::N
+        })
+    }
+
+    /// The value default `attr` of `def` gives symbol `name`. For `bool`
+    /// and `tristate`, kconfig limits the expression by the condition and
+    /// the definition's dependencies; other types take the literal, or the
+    /// value of the symbol it names.
+    pub fn default_value(&self, name: &str, def: &SymbolDef, attr: &DefaultAttr) -> String {
+        match self.symbol_type(name) {
+            Some(kind @ (TypeKind::Bool | TypeKind::Tristate)) => {
+                let cond = attr
+                    .condition
+                    .as_ref()
+                    .map_or(Tristate::Y, |c| self.eval(c));
+                let value = self.eval(&attr.value).and(cond).and(self.dependencies(def));
+                match value {
+                    Tristate::M if kind == TypeKind::Bool => "y".to_string(),
+                    v => v.as_str().to_string(),
+                }
+            }
+            _ => match &attr.value {
+                Expr::Symbol(s, _) => self.symbol_string(s),
+                Expr::StringLit(s, _) => s.clone(),
+                e => e.to_string(),
+            },
+        }
+    }
+
+    /// What `implier`'s `imply` line `attr` currently does to `target`.
+    pub fn imply_effect(
+        &self,
+        implier: &str,
+        attr: &SelectImplyAttr,
+        target: &Symbol,
+    ) -> ImplyEffect {
+        let cond = attr
+            .condition
+            .as_ref()
+            .map_or(Tristate::Y, |c| self.eval(c));
+        let raw = self.symbol_tristate(implier).and(cond);
+        if raw == Tristate::N {
+            return ImplyEffect::Inactive;
+        }
+        let deps = self.symbol_dependencies(target);
+        if deps == Tristate::N {
+            return ImplyEffect::DependenciesUnmet;
+        }
+        let mut implied = raw.and(deps);
+        if implied == Tristate::M && self.symbol_type(target.name) == Some(TypeKind::Bool) {
+            implied = Tristate::Y;
+        }
+        let actual = self.symbol_tristate(target.name);
+        if actual < implied {
+            ImplyEffect::Overridden(actual)
+        } else {
+            ImplyEffect::Effective(implied)
+        }
+    }
+
+    pub fn symbol_tristate(&self, name: &str) -> Tristate {
+        match self.symbol_type(name) {
+            Some(TypeKind::Bool | TypeKind::Tristate) => {
+                Tristate::parse(self.config.get(name).unwrap_or("n"))
+            }
+            Some(_) => Tristate::N,
+            None if self.index.get_definitions(name).is_empty() => Tristate::parse(name),
+            None => Tristate::N,
+        }
+    }
+
+    pub fn symbol_string(&self, name: &str) -> String {
+        if let Some(v) = self.config.get(name) {
+            return v.to_string();
+        }
+        match self.symbol_type(name) {
+            Some(TypeKind::Bool | TypeKind::Tristate) => "n".to_string(),
+            Some(_) => String::new(),
+            None if self.index.get_definitions(name).is_empty() => name.to_string(),
+            None => String::new(),
+        }
+    }
+
+    fn symbol_type(&self, name: &str) -> Option<TypeKind> {
+        self.index.symbol_type(name)
+    }
+
+    /// The numeric value of a `range` bound or `default` operand: a literal,
+    /// or the `.config` value of a symbol.
+    pub fn number(&self, expr: &Expr, hex: bool) -> Option<i64> {
+        literal_number(self.index, expr, hex).or_else(|| match expr {
+            Expr::Symbol(name, _) => parse_number(self.config.get(name)?, hex),
+            _ => None,
+        })
+    }
+
+    fn operand(&self, expr: &Expr) -> (String, Option<TypeKind>) {
+        match expr {
+            Expr::Symbol(name, _) => (self.symbol_string(name), self.symbol_type(name)),
+            Expr::StringLit(s, _) => (s.clone(), None),
+            other => (self.eval(other).as_str().to_string(), None),
+        }
+    }
+
+    fn compare(&self, a: &Expr, b: &Expr) -> Option<Ordering> {
+        let (va, ta) = self.operand(a);
+        let (vb, tb) = self.operand(b);
+        let hex = ta == Some(TypeKind::Hex) || tb == Some(TypeKind::Hex);
+        let numeric = matches!(ta, Some(TypeKind::Int | TypeKind::Hex))
+            || matches!(tb, Some(TypeKind::Int | TypeKind::Hex));
+        if numeric && let (Some(x), Some(y)) = (parse_number(&va, hex), parse_number(&vb, hex)) {
+            return Some(x.cmp(&y));
+        }
+        Some(va.cmp(&vb))
+    }
+}
+
+fn bool_tri(b: bool) -> Tristate {
+    if b { Tristate::Y } else { Tristate::N }
+}
+
+/// Parse an `int` or `hex` value. Hex values accept an optional `0x`/`0X`
+/// prefix, as kconfig does.
+pub fn parse_number(s: &str, hex: bool) -> Option<i64> {
+    let s = s.trim();
+    if let Some(digits) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return i64::from_str_radix(digits, 16).ok();
+    }
+    if hex {
+        i64::from_str_radix(s, 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+/// The value of a numeric literal operand. Names of defined symbols are never
+/// literals, even when they happen to consist of hex digits.
+pub fn literal_number(index: &WorldIndex, expr: &Expr, hex: bool) -> Option<i64> {
+    match expr {
+        Expr::Symbol(name, _) if index.get_definitions(name).is_empty() => parse_number(name, hex),
+        Expr::StringLit(s, _) => parse_number(s, hex),
+        _ => None,
+    }
+}
+
+/// Render a number in the given base, as kconfig writes it to `.config`.
+pub fn format_number(n: i64, hex: bool) -> String {
+    if hex {
+        format!("{:#x}", n)
+    } else {
+        n.to_string()
+    }
+}
+
+/// Whether `expr` is `n` regardless of configuration: undefined symbols are
+/// always `n`.
+pub fn always_false(index: &WorldIndex, expr: &Expr) -> bool {
+    match expr {
+        Expr::Symbol(name, _) => {
+            name == "n"
+                || (name != "y"
+                    && name != "m"
+                    && !name.starts_with("$(")
+                    && !name.bytes().all(|b| b.is_ascii_digit())
+                    && index.get_definitions(name).is_empty())
+        }
+        Expr::Not(e) => always_true(index, e),
+        Expr::Paren(e) => always_false(index, e),
+        Expr::And(a, b) => always_false(index, a) || always_false(index, b),
+        Expr::Or(a, b) => always_false(index, a) && always_false(index, b),
+        _ => false,
+    }
+}
+
+pub fn always_true(index: &WorldIndex, expr: &Expr) -> bool {
+    match expr {
+        Expr::Symbol(name, _) => name == "y",
+        Expr::Not(e) => always_false(index, e),
+        Expr::Paren(e) => always_true(index, e),
+        Expr::And(a, b) => always_true(index, a) && always_true(index, b),
+        Expr::Or(a, b) => always_true(index, a) || always_true(index, b),
+        _ => false,
+    }
+}
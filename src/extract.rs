@@ -0,0 +1,325 @@
+//! "Extract to file": moves the selected entries into a new `Kconfig.<name>`
+//! next to the current file and leaves a `source` line in their place.
+//!
+//! The entries move as text, with their attached `#` comments, so the
+//! `source` line sits in the same menu or `if` block and the moved entries
+//! keep their inherited dependencies. Entries of a `choice` cannot be
+//! extracted: a choice's members must be written inside it.
+//!
+//! "Inline source" is the inverse: a `source` line is replaced by the
+//! sourced file's text, unchanged. A second action also deletes the file,
+//! offered when nothing else sources it.
+
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::WorldIndex;
+use crate::ast::*;
+use crate::lint::Edit;
+use crate::sort_entries::{attached_start, body_len};
+use crate::uri;
+use crate::util::{lines_of, range};
+
+pub struct Extraction {
+    pub path: PathBuf,
+    /// Contents of the new file.
+    pub text: String,
+    /// Replaces the moved entries with the `source` line.
+    pub edit: Edit,
+    pub entries: usize,
+}
+
+/// Extract the entries overlapping `start..end` of `path`.
+pub fn extract(index: &WorldIndex, path: &Path, start: usize, end: usize) -> Option<Extraction> {
+    if start >= end {
+        return None;
+    }
+    let fa = index.files.get(path)?;
+    let source = fa.source.as_str();
+    let (entries, floor, limit) = container(source, &fa.file.entries, start, end, 0, source.len())?;
+
+    let first = entries
+        .iter()
+        .position(|e| overlaps(e.span(), start, end))?;
+    let count = entries[first..]
+        .iter()
+        .take_while(|e| overlaps(e.span(), start, end))
+        .count();
+    let floor = first
+        .checked_sub(1)
+        .map_or(floor, |prev| entries[prev].span().end);
+    let region_start = attached_start(source, entries[first].span().start, floor);
+    let last_end = entries[first + count - 1].span().end;
+    let region_end = entries.get(first + count).map_or(limit, |next| {
+        attached_start(source, next.span().start, last_end)
+    });
+    let body = &source[region_start..region_start + body_len(&source[region_start..region_end])];
+
+    let new_path = unused_path(index, path, &stem(&entries[first]))?;
+    let source_path = index
+        .root
+        .as_deref()
+        .and_then(|root| new_path.strip_prefix(root).ok())
+        .unwrap_or_else(|| Path::new(new_path.file_name().unwrap_or_default()));
+    let line_start = source[..entries[first].span().start]
+        .rfind('\n')
+        .map_or(0, |p| p + 1);
+    let indent = &source[line_start..entries[first].span().start];
+    let indent = if indent.trim().is_empty() { indent } else { "" };
+
+    let mut text = body.to_string();
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    let mut new_text = format!("{}source \"{}\"", indent, source_path.display());
+    if body.ends_with('\n') {
+        new_text.push('\n');
+    }
+    Some(Extraction {
+        path: new_path,
+        text,
+        edit: Edit {
+            span: Span::new(region_start, region_start + body.len()),
+            new_text,
+        },
+        entries: count,
+    })
+}
+
+pub struct Inlining {
+    /// The sourced file.
+    pub target: PathBuf,
+    /// Whether no other `source` statement reads the file, which can then
+    /// be deleted once inlined.
+    pub sole_source: bool,
+    /// Replaces the `source` line with the file's text.
+    pub edit: Edit,
+}
+
+/// Inline the `source` statement on the line of `offset`. `None` when there
+/// is none; an error when the file cannot be inlined.
+pub fn inline(index: &WorldIndex, path: &Path, offset: usize) -> Option<Result<Inlining, String>> {
+    let fa = index.files.get(path)?;
+    let source = fa.source.as_str();
+    let line = lines_of(source, Span::new(offset, offset));
+    let stmt = fa
+        .sources
+        .iter()
+        .find(|s| line.start <= s.path_span.start && s.path_span.end <= line.end)?;
+    let Some(target) = index.resolve_source(path, &stmt.path, stmt.relative) else {
+        return Some(Err(format!("\"{}\" is not an indexed file", stmt.path)));
+    };
+    if target == path {
+        return Some(Err("a file cannot inline itself".into()));
+    }
+    let sourcers = index
+        .files
+        .iter()
+        .flat_map(|(from, fa)| fa.sources.iter().map(move |s| (from, s)))
+        .filter(|(from, s)| {
+            index.resolve_source(from, &s.path, s.relative).as_ref() == Some(&target)
+        })
+        .count();
+    let mut text = index.files[&target].source.clone();
+    if !text.is_empty() && !text.ends_with('\n') && source[..line.end].ends_with('\n') {
+        text.push('\n');
+    }
+    Some(Ok(Inlining {
+        target,
+        sole_source: sourcers == 1,
+        edit: Edit {
+            span: line,
+            new_text: text,
+        },
+    }))
+}
+
+/// "Inline sourced file" for the `source` line at `offset`, and "Inline
+/// sourced file and delete it" when nothing else sources the file.
+pub fn inline_code_actions(
+    index: &WorldIndex,
+    uri: &Url,
+    path: &Path,
+    offset: usize,
+) -> Vec<CodeActionOrCommand> {
+    let Some(fa) = index.files.get(path) else {
+        return Vec::new();
+    };
+    let title = "Inline sourced file".to_string();
+    let inlining = match inline(index, path, offset) {
+        None => return Vec::new(),
+        Some(Ok(inlining)) => inlining,
+        Some(Err(reason)) => {
+            return vec![CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                kind: Some(CodeActionKind::REFACTOR_INLINE),
+                disabled: Some(CodeActionDisabled { reason }),
+                ..Default::default()
+            })];
+        }
+    };
+    let edit = DocumentChangeOperation::Edit(TextDocumentEdit {
+        text_document: OptionalVersionedTextDocumentIdentifier {
+            uri: uri.clone(),
+            version: None,
+        },
+        edits: vec![OneOf::Left(TextEdit {
+            range: range(&fa.line_index, inlining.edit.span),
+            new_text: inlining.edit.new_text,
+        })],
+    });
+    let action = |title: String, changes: Vec<DocumentChangeOperation>| {
+        CodeActionOrCommand::CodeAction(CodeAction {
+            title,
+            kind: Some(CodeActionKind::REFACTOR_INLINE),
+            edit: Some(WorkspaceEdit {
+                document_changes: Some(DocumentChanges::Operations(changes)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    };
+    let mut actions = vec![action(title, vec![edit.clone()])];
+    if inlining.sole_source
+        && let Some(target_uri) = uri::from_path(&inlining.target)
+    {
+        let delete = DocumentChangeOperation::Op(ResourceOp::Delete(DeleteFile {
+            uri: target_uri,
+            options: None,
+        }));
+        actions.push(action(
+            "Inline sourced file and delete it".into(),
+            vec![edit, delete],
+        ));
+    }
+    actions
+}
+
+pub fn code_action(
+    index: &WorldIndex,
+    uri: &Url,
+    path: &Path,
+    start: usize,
+    end: usize,
+) -> Option<CodeActionOrCommand> {
+    let fa = index.files.get(path)?;
+    let ex = extract(index, path, start, end)?;
+    let new_uri = uri::from_path(&ex.path)?;
+    let file_name = ex.path.file_name()?.to_string_lossy().into_owned();
+
+    let edit = |uri: Url, range: Range, new_text: String| {
+        DocumentChangeOperation::Edit(TextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+            edits: vec![OneOf::Left(TextEdit { range, new_text })],
+        })
+    };
+    let changes = vec![
+        DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+            uri: new_uri.clone(),
+            options: None,
+            annotation_id: None,
+        })),
+        edit(new_uri, Range::default(), ex.text),
+        edit(
+            uri.clone(),
+            range(&fa.line_index, ex.edit.span),
+            ex.edit.new_text,
+        ),
+    ];
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: match ex.entries {
+            1 => format!("Extract entry to {}", file_name),
+            n => format!("Extract {} entries to {}", n, file_name),
+        },
+        kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+        edit: Some(WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(changes)),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))
+}
+
+fn overlaps(span: Span, start: usize, end: usize) -> bool {
+    span.start < end && start <= span.end
+}
+
+/// The innermost list of sibling entries the selection falls in, with the
+/// offsets its entries may extend from and to. `None` inside a `choice`.
+fn container<'a>(
+    source: &str,
+    entries: &'a [Entry],
+    start: usize,
+    end: usize,
+    floor: usize,
+    limit: usize,
+) -> Option<(&'a [Entry], usize, usize)> {
+    let around = entries.iter().find(|e| {
+        let span = e.span();
+        span.start <= start && end <= span.end
+    });
+    let Some(entry) = around else {
+        return Some((entries, floor, limit));
+    };
+    if !entry
+        .children()
+        .iter()
+        .any(|c| overlaps(c.span(), start, end))
+    {
+        return Some((entries, floor, limit));
+    }
+    // Children end before the `endmenu`/`endif` line.
+    let inner_limit = source[..entry.span().end]
+        .rfind('\n')
+        .map_or(limit, |p| p + 1);
+    match entry {
+        Entry::Menu(m) => {
+            let inner_floor = m
+                .attributes
+                .last()
+                .map_or(m.prompt_span.end, |a| a.span().end);
+            container(source, &m.entries, start, end, inner_floor, inner_limit)
+        }
+        Entry::If(i) => container(
+            source,
+            &i.entries,
+            start,
+            end,
+            i.condition.span().end,
+            inner_limit,
+        ),
+        _ => None,
+    }
+}
+
+/// `<name>` of `Kconfig.<name>`, from the first extracted entry.
+fn stem(entry: &Entry) -> String {
+    let text = match entry {
+        Entry::Config(c) | Entry::MenuConfig(c) => c.name.as_str(),
+        Entry::Menu(m) => m.prompt.as_str(),
+        Entry::Comment(c) => c.prompt.as_str(),
+        _ => "extracted",
+    };
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        "extracted".into()
+    } else {
+        words.join("-")
+    }
+}
+
+/// `Kconfig.<stem>` beside `path`, numbered if that name is taken.
+fn unused_path(index: &WorldIndex, path: &Path, stem: &str) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("Kconfig.{}", stem)),
+            n => dir.join(format!("Kconfig.{}-{}", stem, n)),
+        })
+        .find(|p| !index.files.contains_key(p) && !p.exists())
+}
@@ -0,0 +1,226 @@
+//! Whitespace-only formatter for Kconfig files.
+//!
+//! Entry keywords start at column 0, attributes are indented one level and
+//! help text one level plus two spaces, the layout used throughout the
+//! kernel tree. Help text keeps its indentation relative to its first line.
+//! Runs of blank lines are collapsed, trailing whitespace is removed, and the
+//! file ends with exactly one newline. Tokens are never rewritten, and
+//! `\r\n` line breaks are kept.
+
+use crate::ast::Span;
+use crate::lint::{Edit, indent_width};
+
+/// Options shared by `textDocument/formatting` and `kconfig-lsp fmt`,
+/// mirroring the LSP `FormattingOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub tab_size: u32,
+    /// Indent with `tab_size` spaces instead of a tab.
+    pub insert_spaces: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            tab_size: 8,
+            insert_spaces: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    fn indent(&self) -> String {
+        if self.insert_spaces {
+            " ".repeat(self.tab_size as usize)
+        } else {
+            "\t".into()
+        }
+    }
+}
+
+enum Help {
+    /// Not inside help text.
+    None,
+    /// After a `help` keyword, before the first line of text.
+    Pending,
+    /// Inside help text whose first line is indented `base` columns.
+    Text { base: usize },
+}
+
+pub fn format(source: &str, options: &FormatOptions) -> String {
+    format_lines(source, options).concat()
+}
+
+/// The edits turning `source` into its formatted text: one per run of
+/// changed lines, none when it is formatted already.
+pub fn edits(source: &str, options: &FormatOptions) -> Vec<Edit> {
+    let mut edits: Vec<Edit> = Vec::new();
+    let mut offset = 0;
+    for (raw, formatted) in source
+        .split_inclusive('\n')
+        .zip(format_lines(source, options))
+    {
+        let span = Span::new(offset, offset + raw.len());
+        offset = span.end;
+        if raw == formatted {
+            continue;
+        }
+        match edits.last_mut() {
+            Some(last) if last.span.end == span.start => {
+                last.span.end = span.end;
+                last.new_text.push_str(&formatted);
+            }
+            _ => edits.push(Edit {
+                span,
+                new_text: formatted,
+            }),
+        }
+    }
+    edits
+}
+
+/// What each line of `source` becomes, line break included: empty for
+/// lines that are dropped. Lines end the way the first line of `source`
+/// does, `\r\n` or `\n`.
+fn format_lines(source: &str, options: &FormatOptions) -> Vec<String> {
+    let unit = options.indent();
+    let newline = match source.find('\n') {
+        Some(i) if source[..i].ends_with('\r') => "\r\n",
+        _ => "\n",
+    };
+    let mut out: Vec<String> = Vec::new();
+    let mut help = Help::None;
+    let mut in_entry = false;
+    let mut continuation = false;
+    let mut emitted = false;
+    // The first of a run of blank lines, which becomes the one kept.
+    let mut pending_blank: Option<usize> = None;
+
+    for raw in source.lines() {
+        let line = raw.trim_end();
+        let content = line.trim_start();
+        let width = indent_width(&line[..line.len() - content.len()]);
+        out.push(String::new());
+
+        if continuation {
+            // Continued expressions are aligned by hand; leave them alone.
+            let chunk = out.last_mut().unwrap();
+            chunk.push_str(line);
+            chunk.push_str(newline);
+            continuation = line.ends_with('\\');
+            continue;
+        }
+
+        if content.is_empty() {
+            if emitted && pending_blank.is_none() {
+                pending_blank = Some(out.len() - 1);
+            }
+            continue;
+        }
+
+        match help {
+            Help::Pending if width > 0 => help = Help::Text { base: width },
+            Help::Text { base } if width >= base => {}
+            _ => help = Help::None,
+        }
+
+        if let Some(blank) = pending_blank.take() {
+            out[blank].push_str(newline);
+        }
+        emitted = true;
+        let chunk = out.last_mut().unwrap();
+
+        if let Help::Text { base } = help {
+            chunk.push_str(&unit);
+            chunk.push_str("  ");
+            chunk.push_str(&" ".repeat(width - base));
+            chunk.push_str(content);
+            chunk.push_str(newline);
+            continue;
+        }
+
+        let word = first_word(content);
+        if content.starts_with('#') {
+            if in_entry && width > 0 {
+                chunk.push_str(&unit);
+            }
+        } else if is_entry_keyword(word) {
+            in_entry = matches!(
+                word,
+                "config" | "menuconfig" | "choice" | "comment" | "menu"
+            );
+        } else if in_entry && is_attribute_keyword(word) {
+            chunk.push_str(&unit);
+            if matches!(word, "help" | "---help---") {
+                help = Help::Pending;
+            }
+        } else {
+            // Preprocessor assignments and anything unrecognized.
+            chunk.push_str(line);
+            chunk.push_str(newline);
+            continuation = line.ends_with('\\');
+            continue;
+        }
+        chunk.push_str(content);
+        chunk.push_str(newline);
+        continuation = line.ends_with('\\');
+    }
+
+    out
+}
+
+fn first_word(content: &str) -> &str {
+    content
+        .split(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+        .next()
+        .unwrap_or("")
+}
+
+fn is_entry_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "config"
+            | "menuconfig"
+            | "choice"
+            | "endchoice"
+            | "comment"
+            | "menu"
+            | "endmenu"
+            | "if"
+            | "endif"
+            | "source"
+            | "osource"
+            | "rsource"
+            | "orsource"
+            | "mainmenu"
+    )
+}
+
+fn is_attribute_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "bool"
+            | "tristate"
+            | "string"
+            | "hex"
+            | "int"
+            | "prompt"
+            | "default"
+            | "def_bool"
+            | "def_tristate"
+            | "def_int"
+            | "def_hex"
+            | "def_string"
+            | "depends"
+            | "select"
+            | "imply"
+            | "visible"
+            | "range"
+            | "help"
+            | "---help---"
+            | "modules"
+            | "transitional"
+            | "optional"
+            | "option"
+    )
+}
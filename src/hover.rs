@@ -1,75 +1,892 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 
 use tower_lsp::lsp_types::*;
 
-use crate::analysis::WorldIndex;
-
-pub fn hover(index: &WorldIndex, path: &Path, pos: Position) -> Option<Hover> {
+use crate::analysis::{FileAnalysis, RefKind, SymbolDef, WorldIndex};
+use crate::ast::{
+    Attribute, ConfigEntry, DefaultAttr, Entry, Expr, SelectImplyAttr, Span, TypeKind,
+};
+use crate::dotconfig::DotConfig;
+use crate::eval::{self, Evaluator, ImplyEffect, Tristate};
+use crate::include_graph::{self, TreeOrder};
+use crate::preprocess::{Expander, Flavor};
+use crate::resolved;
+use crate::select_chain;
+use crate::settings::HoverSettings;
+use crate::tree_info;
+use crate::uri;
+use crate::util::{place, word_at_offset};
+
+/// The hover at `pos`, rendered as `kind` (plain text for clients that
+/// cannot show Markdown).
+pub fn hover(
+    index: &WorldIndex,
+    path: &Path,
+    pos: Position,
+    kind: MarkupKind,
+    settings: &HoverSettings,
+) -> Option<Hover> {
     let fa = index.files.get(path)?;
     let offset = fa.line_index.offset(pos.line, pos.character);
-    let word = word_at_offset(&fa.source, offset)?;
-
-    if let Some(doc) = keyword_docs(&word) {
-        return Some(Hover {
-            contents: HoverContents::Markup(MarkupContent {
-                kind: MarkupKind::Markdown,
-                value: doc.to_string(),
-            }),
-            range: None,
-        });
-    }
-
-    let defs = index.get_definitions(&word);
-    if !defs.is_empty() {
-        let mut parts: Vec<String> = Vec::new();
-        for d in defs {
-            let mut section = format!("**{}** ({})", d.name, def_kind_label(d.kind));
-            if let Some(tk) = d.type_kind {
-                section.push_str(&format!(" `{}`", tk.as_str()));
+    let markdown = kind == MarkupKind::Markdown;
+    let converted = |value: String| {
+        if markdown { value } else { plain_text(&value) }
+    };
+    let value = match macro_hover(index, fa, path, offset) {
+        Some(value) => converted(value),
+        None => {
+            let word = word_at_offset(&fa.source, offset);
+            if let Some(value) = expression_hover(index, fa, offset, word.as_deref(), markdown) {
+                return Some(Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind,
+                        value: converted(value),
+                    }),
+                    range: None,
+                });
             }
-            if let Some(prompt) = &d.prompt {
-                section.push_str(&format!("\n\n*\"{}\"*", prompt));
+            let word = word?;
+            if let Some(doc) = keyword_docs(&word) {
+                converted(doc.to_string())
+            } else if let Some(value) = numeric_default_hover(index, fa, path, offset) {
+                converted(value)
+            } else {
+                let mut doc = symbol_documentation_as(
+                    index,
+                    &word,
+                    kind.clone(),
+                    settings.expand_definitions,
+                )?;
+                // Name the tree the answer comes from.
+                if let Some(title) = tree_info::title(index) {
+                    if markdown {
+                        doc.push_str(&format!("\n\n---\n\n*{}*", title));
+                    } else {
+                        doc.push_str(&format!("\n\n---\n\n{}", title));
+                    }
+                }
+                doc
             }
-            section.push_str(&format!("\n\nDefined in `{}`", d.file.display()));
-            if let Some(help) = &d.help {
-                section.push_str(&format!("\n\n---\n\n{}", help));
+        }
+    };
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent { kind, value }),
+        range: None,
+    })
+}
+
+/// `markdown` as produced here, with the markup taken out, for clients that
+/// only show plain text. Code blocks are indented instead of fenced.
+pub fn plain_text(markdown: &str) -> String {
+    let mut out = Vec::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            out.push(format!("    {}", line));
+            continue;
+        }
+        let line = line.replace("**", "").replace('`', "");
+        let line = match line.strip_prefix('*').and_then(|l| l.strip_suffix('*')) {
+            Some(emphasized) => emphasized.to_string(),
+            None => line,
+        };
+        out.push(line);
+    }
+    out.join("\n")
+}
+
+/// The `select` and `imply` statements naming a symbol, with the definition
+/// each is in.
+#[derive(Debug, Default)]
+pub struct ReverseDependencies<'a> {
+    pub selects: Vec<(&'a SymbolDef, &'a SelectImplyAttr)>,
+    pub implies: Vec<(&'a SymbolDef, &'a SelectImplyAttr)>,
+}
+
+/// The reverse dependencies of `name`, found through the reference index.
+pub fn reverse_dependencies<'a>(index: &'a WorldIndex, name: &str) -> ReverseDependencies<'a> {
+    let mut out = ReverseDependencies::default();
+    for r in index.get_references(name) {
+        let (found, attrs): (_, fn(&SymbolDef) -> &[SelectImplyAttr]) = match r.kind {
+            RefKind::Select => (&mut out.selects, |d| &d.selects),
+            RefKind::Imply => (&mut out.implies, |d| &d.implies),
+            _ => continue,
+        };
+        // The name of the selected symbol, not one in the `if` condition.
+        found.extend(index.symbols_in_file(&r.file).iter().find_map(|d| {
+            attrs(d)
+                .iter()
+                .find(|a| a.symbol_span == r.span)
+                .map(|a| (d, a))
+        }));
+    }
+    out
+}
+
+/// The reverse dependencies of every symbol, in one pass over the
+/// definitions, for documenting many symbols at once.
+pub fn all_reverse_dependencies(index: &WorldIndex) -> HashMap<&str, ReverseDependencies<'_>> {
+    let mut out: HashMap<&str, ReverseDependencies> = HashMap::new();
+    for d in index.definitions.values().flatten() {
+        for a in &d.selects {
+            out.entry(&a.symbol).or_default().selects.push((d, a));
+        }
+        for a in &d.implies {
+            out.entry(&a.symbol).or_default().implies.push((d, a));
+        }
+    }
+    out
+}
+
+/// The documentation of symbol `name`: each definition with its help, then
+/// defaults, reverse dependencies and the merged view, rendered as `kind`,
+/// as hover shows it. `None` if nothing defines it. Plain text puts the
+/// fields of a definition on consecutive lines and keeps the line breaks of
+/// its help text. Past the first `expand` definitions in tree order (all if
+/// `0`), each gets one "also defined in" line, so symbols defined by every
+/// architecture stay readable.
+pub fn symbol_documentation_as(
+    index: &WorldIndex,
+    name: &str,
+    kind: MarkupKind,
+    expand: usize,
+) -> Option<String> {
+    let order = index.tree_order();
+    let reverse = reverse_dependencies(index, name);
+    documentation(index, &order, &reverse, name, kind, expand)
+}
+
+/// [`symbol_documentation_as`] from a parse order and reverse dependencies
+/// the caller built, so that documenting many symbols builds them once.
+pub fn documentation(
+    index: &WorldIndex,
+    order: &TreeOrder,
+    reverse: &ReverseDependencies,
+    name: &str,
+    kind: MarkupKind,
+    expand: usize,
+) -> Option<String> {
+    let defs = order.sort_definitions(index.get_definitions(name));
+    if defs.is_empty() {
+        return None;
+    }
+    let expand = if expand == 0 { defs.len() } else { expand };
+    let markdown = kind == MarkupKind::Markdown;
+    let code = |text: &dyn std::fmt::Display| {
+        if markdown {
+            format!("`{}`", text)
+        } else {
+            text.to_string()
+        }
+    };
+    let converted = |value: String| {
+        if markdown { value } else { plain_text(&value) }
+    };
+    let mut parts: Vec<String> = Vec::new();
+    for d in &defs[..expand.min(defs.len())] {
+        let mut fields = Vec::new();
+        let mut header = if markdown {
+            format!("**{}** ({})", d.name, def_kind_label(d.kind))
+        } else {
+            format!("{} ({})", d.name, def_kind_label(d.kind))
+        };
+        if let Some(tk) = d.type_kind {
+            header.push_str(&format!(" {}", code(&tk.as_str())));
+        }
+        fields.push(header);
+        if let Some(prompt) = &d.prompt {
+            fields.push(if markdown {
+                format!("*\"{}\"*", prompt)
+            } else {
+                format!("\"{}\"", prompt)
+            });
+        }
+        if let Some(choice) = index.choice_of(d) {
+            let options = match choice.members.len() {
+                1 => "1 option".to_string(),
+                n => format!("{} options", n),
+            };
+            fields.push(match &choice.prompt {
+                Some(p) => format!("Member of choice '{}' ({})", p, options),
+                None => format!("Member of a choice ({})", options),
+            });
+        }
+        if !d.inherited_depends.is_empty() {
+            fields.push(format!(
+                "Inherited dependencies: {}",
+                code(&join_exprs(&d.inherited_depends))
+            ));
+        }
+        if !d.visible_if.is_empty() {
+            fields.push(format!(
+                "Visible only if {}",
+                code(&join_exprs(&d.visible_if))
+            ));
+            if let Some(config) = &index.config
+                && Evaluator::new(index, config).hidden_by_visible_if(d)
+            {
+                fields.push(format!(
+                    "Prompt hidden because {} is n; the value may still be selected",
+                    code(&format!("visible if {}", join_exprs(&d.visible_if)))
+                ));
             }
-            parts.push(section);
         }
-        return Some(Hover {
-            contents: HoverContents::Markup(MarkupContent {
-                kind: MarkupKind::Markdown,
-                value: parts.join("\n\n---\n\n"),
-            }),
-            range: None,
-        });
+        fields.extend(range_lines(index, d).into_iter().map(converted));
+        fields.push(format!(
+            "Defined in {}",
+            code(&place(index, &d.file, d.name_span))
+        ));
+        if let Some(cond) = include_graph::inherited_condition(index, &d.file) {
+            fields.push(format!("Sourced under {}", code(&cond)));
+        }
+        let mut section = fields.join(if markdown { "\n\n" } else { "\n" });
+        if let Some(help) = &d.help {
+            let rule = if markdown { "\n\n---\n\n" } else { "\n\n" };
+            section.push_str(&format!("{}{}", rule, help));
+        } else if let Some(comment) = &d.doc_comment {
+            section.push_str(if markdown {
+                "\n\n---\n\n*Source comment:*\n"
+            } else {
+                "\n\nSource comment:"
+            });
+            for line in comment.lines() {
+                section.push_str(&format!("\n> {}", line));
+            }
+        }
+        parts.push(section);
+    }
+    if defs.len() > expand {
+        let also: Vec<String> = defs[expand..]
+            .iter()
+            .map(|d| {
+                let link = definition_link(index, d, markdown);
+                match &d.prompt {
+                    Some(prompt) => format!("Also defined in {}: \"{}\"", link, prompt),
+                    None => format!("Also defined in {}", link),
+                }
+            })
+            .collect();
+        parts.push(also.join(if markdown { "  \n" } else { "\n" }));
     }
+    if let Some(build) = build_value_section(index, name) {
+        parts.push(converted(build));
+    }
+    if let Some(defaults) = defaults_section(index, order, name) {
+        parts.push(converted(defaults));
+    }
+    if let Some(reverse) = reverse_dependencies_section(index, order, reverse, name) {
+        parts.push(converted(reverse));
+    }
+    if let Some(chain) = select_chain::hover_section(index, name) {
+        parts.push(converted(chain));
+    }
+    if defs.len() > 1
+        && let Some(merged) = resolved::resolve(index, name)
+    {
+        parts.push(converted(format!(
+            "**Merged view**\n\n```kconfig\n{}```",
+            merged.text
+        )));
+    }
+    Some(parts.join("\n\n---\n\n"))
+}
 
-    None
+/// The expansion of the `$(...)` under the cursor, and where the variable it
+/// names is assigned.
+fn macro_hover(
+    index: &WorldIndex,
+    fa: &FileAnalysis,
+    path: &Path,
+    offset: usize,
+) -> Option<String> {
+    let (span, body) = crate::preprocess::macro_at(&fa.source, offset)?;
+    let text = &fa.source[span.start..span.end];
+    let line = fa.line_index.line_col(span.start).0 + 1;
+    let expander = Expander::new(index, path, line);
+
+    let mut out = format!("`{}`", text);
+    match expander.expand(text) {
+        Ok(value) if value.is_empty() => out.push_str("\n\nExpands to nothing"),
+        Ok(value) => out.push_str(&format!("\n\nExpands to `{}`", value)),
+        Err(e) => out.push_str(&format!("\n\nNot expanded: {}", e)),
+    }
+    for command in expander.dry_run_commands() {
+        out.push_str(&format!("\n\nWould run `{}`", command));
+    }
+
+    let name = body.split(',').next().unwrap_or(body).trim();
+    let assignments = expander.assignments(name);
+    if assignments.is_empty() {
+        if index.preprocessor.variables.contains_key(name) {
+            out.push_str(&format!(
+                "\n\n`{}` is set by the `preprocessor.variables` setting",
+                name
+            ));
+        }
+        return Some(out);
+    }
+    for (file, a) in assignments {
+        let op = match a.flavor {
+            Flavor::Recursive => "=",
+            Flavor::Simple => ":=",
+            Flavor::Append => "+=",
+        };
+        let line = index
+            .files
+            .get(file)
+            .map_or(0, |f| f.line_index.line_col(a.name_span.start).0 + 1);
+        let file = index
+            .root
+            .as_deref()
+            .and_then(|root| file.strip_prefix(root).ok())
+            .unwrap_or(file);
+        out.push_str(&format!(
+            "\n\nAssigned in `{}:{}`: `{} {} {}`",
+            file.display(),
+            line,
+            a.name,
+            op,
+            a.value
+        ));
+    }
+    Some(out)
 }
 
-fn word_at_offset(source: &str, offset: usize) -> Option<String> {
-    let bytes = source.as_bytes();
-    if offset >= bytes.len() {
+/// The value the build uses for `name` when it differs from the `.config`'s:
+/// kconfig drops what the `.config` sets against unmet dependencies when it
+/// generates the build configuration, and that configuration is stale until
+/// the next build.
+fn build_value_section(index: &WorldIndex, name: &str) -> Option<String> {
+    let (config, build) = (index.config.as_ref()?, index.build_config.as_ref()?);
+    let boolean = index
+        .get_definitions(name)
+        .iter()
+        .any(|d| matches!(d.type_kind, Some(TypeKind::Bool | TypeKind::Tristate)));
+    let header = build.path.extension().is_some_and(|e| e == "h");
+    // Neither file writes out symbols that are `n`.
+    fn value(v: Option<&str>, boolean: bool, from_header: bool) -> Option<&str> {
+        match v {
+            Some("1") if boolean && from_header => Some("y"),
+            None | Some("n") if boolean => Some("n"),
+            v => v,
+        }
+    }
+    let set = value(config.get(name), boolean, false);
+    let built = value(build.get(name), boolean, header);
+    if set == built {
         return None;
     }
+    let shown = |v: Option<&str>| v.map_or_else(|| "no value".to_string(), |v| format!("`{}`", v));
+    let file = index
+        .root
+        .as_deref()
+        .and_then(|root| build.path.strip_prefix(root).ok())
+        .unwrap_or(&build.path);
+    Some(format!(
+        "**Build value:** {} in `{}`, but {} in `.config`",
+        shown(built),
+        file.display(),
+        shown(set)
+    ))
+}
 
-    let mut start = offset;
-    while start > 0 && is_word_char(bytes[start - 1]) {
-        start -= 1;
+/// The defaults of every definition in parse order. Kconfig takes the first
+/// one whose condition and definition dependencies hold, so with a `.config`
+/// loaded the active default is singled out and the later ones are listed as
+/// shadowed. When the `.config` does not set the symbol, or none is loaded,
+/// the value that default computes to is shown with the condition deciding
+/// it.
+fn defaults_section(index: &WorldIndex, order: &TreeOrder, name: &str) -> Option<String> {
+    let sym = index.symbol(name, order)?;
+    let defaults: Vec<(&SymbolDef, &DefaultAttr)> = sym.defaults().collect();
+    let describe = |(d, a): &(&SymbolDef, &DefaultAttr)| {
+        let cond = match &a.condition {
+            Some(c) => format!(" if `{}`", c),
+            None => String::new(),
+        };
+        format!(
+            "`{}`{} (from {})",
+            a.value,
+            cond,
+            place(index, &d.file, a.span)
+        )
+    };
+
+    // What the first applying default computes to, and why it applies.
+    let computed = |eval: &Evaluator, unset: &str| {
+        let (d, a) = eval.active_default(&sym)?;
+        let why = match &a.condition {
+            Some(c) => format!("its condition `{}` holds", c),
+            None => "it has no condition".to_string(),
+        };
+        Some(format!(
+            "Computed default: `{}` from {}, since {} ({})",
+            eval.default_value(name, d, a),
+            place(index, &d.file, a.span),
+            why,
+            unset
+        ))
+    };
+
+    let Some(config) = &index.config else {
+        let empty = DotConfig::default();
+        let computed = computed(
+            &Evaluator::new(index, &empty),
+            "no `.config` is loaded, so every symbol counts as unset",
+        );
+        if defaults.len() < 2 {
+            return computed;
+        }
+        let mut out = String::from("Defaults, first match wins:\n");
+        for (i, d) in defaults.iter().enumerate() {
+            out.push_str(&format!("\n{}. {}", i + 1, describe(d)));
+        }
+        if let Some(computed) = computed {
+            out.push_str(&format!("\n\n{}", computed));
+        }
+        return Some(out);
+    };
+
+    let eval = Evaluator::new(index, config);
+    let active = eval.active_default(&sym);
+    match active.and_then(|(_, a)| defaults.iter().position(|(_, b)| std::ptr::eq(a, *b))) {
+        Some(i) => {
+            let mut out = format!("Active default: {}", describe(&defaults[i]));
+            let shadowed: Vec<String> = defaults[i + 1..].iter().map(describe).collect();
+            if !shadowed.is_empty() {
+                out.push_str(&format!("\n\nShadowed defaults: {}", shadowed.join(", ")));
+            }
+            if config.get(name).is_none()
+                && let Some(computed) = computed(&eval, "the `.config` does not set it")
+            {
+                out.push_str(&format!("\n\n{}", computed));
+            }
+            Some(out)
+        }
+        None if defaults.is_empty() => None,
+        None => Some(format!(
+            "No default applies: {}",
+            defaults.iter().map(describe).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// The symbols that `select` or `imply` `name`. With a loaded `.config`, each
+/// `imply` says whether it currently has an effect, since that is where the
+/// two differ: `select` forces the value past the symbol's dependencies,
+/// `imply` only raises its default and can be overridden.
+fn reverse_dependencies_section(
+    index: &WorldIndex,
+    order: &TreeOrder,
+    reverse: &ReverseDependencies,
+    name: &str,
+) -> Option<String> {
+    if reverse.selects.is_empty() && reverse.implies.is_empty() {
+        return None;
+    }
+    let by_symbol = |a: &&(&SymbolDef, &SelectImplyAttr), b: &&(&SymbolDef, &SelectImplyAttr)| {
+        (&a.0.name, &a.0.file).cmp(&(&b.0.name, &b.0.file))
+    };
+    let mut selects: Vec<_> = reverse.selects.iter().collect();
+    let mut implies: Vec<_> = reverse.implies.iter().collect();
+    selects.sort_by(by_symbol);
+    implies.sort_by(by_symbol);
+
+    let target = index.symbol(name, order)?;
+    let eval = index.config.as_ref().map(|c| Evaluator::new(index, c));
+    let describe = |(d, a): &(&SymbolDef, &SelectImplyAttr)| {
+        let cond = match &a.condition {
+            Some(c) => format!(" if `{}`", c),
+            None => String::new(),
+        };
+        format!(
+            "`{}`{} (from {})",
+            d.name,
+            cond,
+            place(index, &d.file, a.span)
+        )
+    };
+
+    let mut out = Vec::new();
+    if !selects.is_empty() {
+        let mut section =
+            String::from("**Selected by** (forces the value, even past its dependencies):\n");
+        for s in &selects {
+            section.push_str(&format!("\n- {}", describe(s)));
+        }
+        out.push(section);
+    }
+    if !implies.is_empty() {
+        let mut section = String::from(
+            "**Implied by** (raises the default within its dependencies; can be set lower):\n",
+        );
+        for i in &implies {
+            section.push_str(&format!("\n- {}", describe(i)));
+            let Some(eval) = &eval else {
+                continue;
+            };
+            let status = match eval.imply_effect(&i.0.name, i.1, &target) {
+                ImplyEffect::Effective(v) => format!("effective, at least `{}`", v.as_str()),
+                ImplyEffect::Inactive => match &i.1.condition {
+                    Some(c) if eval.eval(c) == Tristate::N => format!("ineffective, `{}` is n", c),
+                    _ => format!("ineffective, `{}` is n", i.0.name),
+                },
+                ImplyEffect::DependenciesUnmet => {
+                    format!("ineffective, the dependencies of `{}` are not met", name)
+                }
+                ImplyEffect::Overridden(v) => {
+                    format!("ineffective, overridden to `{}` in .config", v.as_str())
+                }
+            };
+            section.push_str(&format!(": currently {}", status));
+        }
+        out.push(section);
     }
-    let mut end = offset;
-    while end < bytes.len() && is_word_char(bytes[end]) {
-        end += 1;
+    Some(out.join("\n\n"))
+}
+
+/// Where `def` is defined: a Markdown link to the line, or `path:line` in
+/// plain text.
+fn definition_link(index: &WorldIndex, def: &SymbolDef, markdown: bool) -> String {
+    let place = place(index, &def.file, def.name_span);
+    match uri::from_path(&def.file) {
+        Some(uri) if markdown => {
+            let line = index
+                .files
+                .get(&def.file)
+                .map_or(0, |fa| fa.line_index.line_col(def.name_span.start).0 + 1);
+            format!("[`{}`]({}#L{})", place, uri, line)
+        }
+        _ => place,
     }
-    if start == end {
+}
+
+fn join_exprs(exprs: &[Expr]) -> String {
+    exprs
+        .iter()
+        .map(|e| match e {
+            Expr::Or(..) => format!("({})", e),
+            _ => e.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" && ")
+}
+
+/// The `range` lines of an `int`/`hex` definition, with each literal bound
+/// shown in both decimal and hex.
+fn range_lines(index: &WorldIndex, def: &SymbolDef) -> Vec<String> {
+    let hex = match def.type_kind.or_else(|| index.symbol_type(&def.name)) {
+        Some(TypeKind::Hex) => true,
+        Some(TypeKind::Int) => false,
+        _ => return Vec::new(),
+    };
+    def.ranges
+        .iter()
+        .map(|r| {
+            let mut line = format!(
+                "Range: {} .. {}",
+                range_bound(index, &r.low, hex),
+                range_bound(index, &r.high, hex)
+            );
+            if let Some(cond) = &r.condition {
+                line.push_str(&format!(" if `{}`", cond));
+            }
+            line
+        })
+        .collect()
+}
+
+fn range_bound(index: &WorldIndex, expr: &Expr, hex: bool) -> String {
+    match eval::literal_number(index, expr, hex) {
+        Some(n) => format!(
+            "`{}` ({})",
+            eval::format_number(n, hex),
+            eval::format_number(n, !hex)
+        ),
+        None => format!("`{}`", expr),
+    }
+}
+
+/// Hover for a number literal on a `default` line of an `int`/`hex` symbol:
+/// the value in the other base, and how it compares to the symbol's ranges.
+fn numeric_default_hover(
+    index: &WorldIndex,
+    fa: &FileAnalysis,
+    path: &Path,
+    offset: usize,
+) -> Option<String> {
+    let (entry, literal) = default_literal_at(&fa.file.entries, offset)?;
+    let def = index
+        .get_definitions(&entry.name)
+        .iter()
+        .find(|d| d.file == path && d.name_span == entry.name_span)?;
+    let hex = match def.type_kind.or_else(|| index.symbol_type(&def.name))? {
+        TypeKind::Hex => true,
+        TypeKind::Int => false,
+        _ => return None,
+    };
+    let n = eval::parse_number(literal, hex)?;
+
+    let mut out = format!(
+        "`{}` = `{}`",
+        eval::format_number(n, hex),
+        eval::format_number(n, !hex)
+    );
+    for r in &def.ranges {
+        let (Some(low), Some(high)) = (
+            eval::literal_number(index, &r.low, hex),
+            eval::literal_number(index, &r.high, hex),
+        ) else {
+            continue;
+        };
+        let verdict = if (low..=high).contains(&n) {
+            "Within"
+        } else {
+            "Outside"
+        };
+        out.push_str(&format!(
+            "\n\n{} range `{}` .. `{}`",
+            verdict,
+            eval::format_number(low, hex),
+            eval::format_number(high, hex)
+        ));
+        if let Some(cond) = &r.condition {
+            out.push_str(&format!(" if `{}`", cond));
+        }
+    }
+    Some(out)
+}
+
+/// The config entry and bare literal of the `default` value under `offset`.
+fn default_literal_at(entries: &[Entry], offset: usize) -> Option<(&ConfigEntry, &str)> {
+    for entry in entries {
+        let span = entry.span();
+        if offset < span.start || offset > span.end {
+            continue;
+        }
+        if let Entry::Config(c) | Entry::MenuConfig(c) = entry {
+            return c.attributes.iter().find_map(|a| match a {
+                Attribute::Default(d) => match &d.value {
+                    Expr::Symbol(text, s) if s.start <= offset && offset <= s.end => {
+                        Some((c, text.as_str()))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            });
+        }
+        return default_literal_at(entry.children(), offset);
+    }
+    None
+}
+
+/// A breakdown of the `&&`/`||` expression on the line under the cursor,
+/// one operand per line, for hovers off its symbols: on the keyword, an
+/// operator, a parenthesis or whitespace. Each operand shows its value when a
+/// `.config` is loaded, and each symbol links to its definition, which keeps
+/// the dependency lines of large trees readable.
+fn expression_hover(
+    index: &WorldIndex,
+    fa: &FileAnalysis,
+    offset: usize,
+    word: Option<&str>,
+    markdown: bool,
+) -> Option<String> {
+    if word
+        .is_some_and(|w| w.as_bytes()[0].is_ascii_digit() || !index.get_definitions(w).is_empty())
+    {
         return None;
     }
-    Some(source[start..end].to_string())
+    let start = fa.source[..offset.min(fa.source.len())]
+        .rfind('\n')
+        .map_or(0, |i| i + 1);
+    let end = fa.source[start..]
+        .find('\n')
+        .map_or(fa.source.len(), |i| start + i);
+    let (label, expr) = expression_on_line(&fa.file.entries, Span::new(start, end))?;
+
+    let breakdown = Breakdown {
+        index,
+        order: index.tree_order(),
+        eval: index.config.as_ref().map(|c| Evaluator::new(index, c)),
+        markdown,
+    };
+    let (words, operands) = breakdown.operands(expr);
+    let mut out = format!("**{}**", label);
+    if let Some(value) = breakdown.value(expr, false) {
+        out.push_str(&format!(" = `{}`", value));
+    }
+    out.push_str(&format!(",{}\n", words.unwrap_or_default()));
+    for operand in operands {
+        breakdown.push_line(&mut out, operand, 0, false);
+    }
+    Some(out)
+}
+
+/// The first `&&`/`||` expression of an attribute or `if` on `line`, with
+/// the keyword it follows. Attributes continued over several lines count
+/// on each of them.
+fn expression_on_line(entries: &[Entry], line: Span) -> Option<(String, &Expr)> {
+    let touches = |span: Span| span.start <= line.end && line.start <= span.end;
+    for entry in entries {
+        if !touches(entry.span()) {
+            continue;
+        }
+        if let Entry::If(i) = entry
+            && touches(i.condition.span())
+            && is_chain(&i.condition)
+        {
+            return Some(("if".to_string(), &i.condition));
+        }
+        for attr in entry.attributes().iter().filter(|a| touches(a.span())) {
+            let Some(expr) = attr.expressions().into_iter().find(|e| is_chain(e)) else {
+                continue;
+            };
+            let label = match attr {
+                Attribute::DependsOn(_) => "depends on".to_string(),
+                Attribute::VisibleIf(_) => "visible if".to_string(),
+                Attribute::Default(d) if std::ptr::eq(&d.value, expr) => "default".to_string(),
+                Attribute::DefType(d) if std::ptr::eq(&d.value, expr) => {
+                    format!("def_{}", d.kind.as_str())
+                }
+                Attribute::Range(r)
+                    if !r.condition.as_ref().is_some_and(|c| std::ptr::eq(c, expr)) =>
+                {
+                    "range".to_string()
+                }
+                _ => "if".to_string(),
+            };
+            return Some((label, expr));
+        }
+        if let Some(found) = expression_on_line(entry.children(), line) {
+            return Some(found);
+        }
+    }
+    None
 }
 
-fn is_word_char(b: u8) -> bool {
-    b.is_ascii_alphanumeric() || b == b'_'
+/// `expr` without the parentheses around it.
+fn unparenthesized(mut expr: &Expr) -> &Expr {
+    while let Expr::Paren(inner) = expr {
+        expr = inner;
+    }
+    expr
+}
+
+/// Whether `expr` is an `&&` or `||` chain, possibly negated.
+fn is_chain(expr: &Expr) -> bool {
+    match unparenthesized(expr) {
+        Expr::And(..) | Expr::Or(..) => true,
+        Expr::Not(inner) => matches!(unparenthesized(inner), Expr::And(..) | Expr::Or(..)),
+        _ => false,
+    }
+}
+
+/// The operands of the `&&` or `||` chain `expr`, left to right. Walks the
+/// chain rather than recursing, as long chains nest deeply.
+fn chain_operands(expr: &Expr) -> Vec<&Expr> {
+    let mut out = Vec::new();
+    let mut pending = vec![expr];
+    while let Some(e) = pending.pop() {
+        match (expr, e) {
+            (Expr::And(..), Expr::And(a, b)) | (Expr::Or(..), Expr::Or(a, b)) => {
+                pending.push(b);
+                pending.push(a);
+            }
+            _ => out.push(e),
+        }
+    }
+    out
+}
+
+/// Renders the lines of [`expression_hover`].
+struct Breakdown<'a> {
+    index: &'a WorldIndex,
+    order: Arc<TreeOrder>,
+    eval: Option<Evaluator<'a>>,
+    markdown: bool,
+}
+
+impl Breakdown<'_> {
+    /// The value of `expr` with the loaded `.config`. Symbols compared with
+    /// `=`, `<` and the like show their value as set rather than as a
+    /// tristate.
+    fn value(&self, expr: &Expr, compared: bool) -> Option<String> {
+        let eval = self.eval.as_ref()?;
+        Some(match unparenthesized(expr) {
+            Expr::Symbol(name, _) if compared => match eval.symbol_string(name) {
+                value if value.is_empty() => "unset".to_string(),
+                value => value,
+            },
+            expr => eval.eval(expr).as_str().to_string(),
+        })
+    }
+
+    /// The words introducing the operands listed under `expr`, and those
+    /// operands: the chain of an `&&` or `||`, and the defined symbols of a
+    /// comparison, which need no introduction.
+    fn operands<'e>(&self, expr: &'e Expr) -> (Option<&'static str>, Vec<&'e Expr>) {
+        let expr = unparenthesized(expr);
+        match expr {
+            Expr::And(..) => (Some(" all of:"), chain_operands(expr)),
+            Expr::Or(..) => (Some(" any of:"), chain_operands(expr)),
+            Expr::Not(inner) => match unparenthesized(inner) {
+                inner @ Expr::And(..) => (Some(" not all of:"), chain_operands(inner)),
+                inner @ Expr::Or(..) => (Some(" none of:"), chain_operands(inner)),
+                _ => (None, Vec::new()),
+            },
+            _ => {
+                let sides = expr.operands().map_or_else(Vec::new, |(a, b)| vec![a, b]);
+                let defined = sides
+                    .into_iter()
+                    .filter(|side| {
+                        matches!(unparenthesized(side), Expr::Symbol(name, _)
+                            if !self.index.get_definitions(name).is_empty())
+                    })
+                    .collect();
+                (None, defined)
+            }
+        }
+    }
+
+    /// A list item for `expr` at nesting `level`, then its own operands one
+    /// level down.
+    fn push_line(&self, out: &mut String, expr: &Expr, level: usize, compared: bool) {
+        out.push_str(&format!("\n{}- `{}`", "  ".repeat(level), expr));
+        if let Some(value) = self.value(expr, compared) {
+            out.push_str(&format!(" = `{}`", value));
+        }
+        let symbol = match unparenthesized(expr) {
+            Expr::Not(inner) => unparenthesized(inner),
+            expr => expr,
+        };
+        if let Expr::Symbol(name, _) = symbol {
+            if let Some(def) = self
+                .order
+                .sort_definitions(self.index.get_definitions(name))
+                .first()
+            {
+                out.push_str(&format!(
+                    " ({})",
+                    definition_link(self.index, def, self.markdown)
+                ));
+            }
+            return;
+        }
+        let (words, operands) = self.operands(expr);
+        if let Some(words) = words {
+            out.push_str(&format!(",{}", words));
+        }
+        for operand in operands {
+            self.push_line(out, operand, level + 1, words.is_none());
+        }
+    }
 }
 
 fn def_kind_label(kind: crate::analysis::DefKind) -> &'static str {
@@ -170,6 +987,15 @@ enclosed menu entries."
 Reads the specified configuration file. This file is always parsed."
         }
 
+        "osource" | "rsource" | "orsource" => {
+            "\
+**osource** / **rsource** / **orsource** `<path>`
+
+Like **source**. With **osource**, a path that matches no file is not \
+an error; **rsource** takes the path relative to the directory of the \
+current file instead of the source tree. **orsource** does both."
+        }
+
         "mainmenu" => {
             "\
 **mainmenu** `<prompt>`
@@ -340,6 +1166,19 @@ files, and cannot have any other properties."
 Marks a choice as optional — the user may leave all options unselected."
         }
 
+        "option" => {
+            "\
+**option** `<name>`[`=<value>`]
+
+Older syntax for special symbol properties:
+
+- `defconfig_list`: the symbol's defaults list the files to read an \
+initial configuration from; the first existing one is used.
+- `allnoconfig_y`: the symbol is set to `y` even by `allnoconfig`.
+- `modules`: same as the `modules` attribute.
+- `env=\"VAR\"`: the symbol's value is imported from the environment."
+        }
+
         "on" => {
             "\
 Part of the **depends on** syntax. See `depends`."
@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::{RefKind, SymbolDef, WorldIndex};
+use crate::uri;
+use crate::util::{location, range, word_at_offset};
+
+/// Client-side command the code lens and code action invoke; its argument is
+/// a [`RemovalImpactParams`] to send back as `kconfig/removalImpact`.
+pub const SHOW_REMOVAL_IMPACT: &str = "kconfig.showRemovalImpact";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovalImpactParams {
+    /// Remove every definition of this symbol.
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// Or remove the single definition whose name is at `position` in `uri`.
+    #[serde(default)]
+    pub uri: Option<Url>,
+    #[serde(default)]
+    pub position: Option<Position>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovalImpact {
+    pub symbol: String,
+    /// Definitions that survive the removal. When non-empty, no reference
+    /// becomes dangling.
+    pub remaining_definitions: Vec<Location>,
+    /// References left without any definition.
+    pub dangling_references: Vec<ImpactReference>,
+    /// `default` lines that mention the symbol, whether or not it stays
+    /// defined elsewhere.
+    pub defaults: Vec<Location>,
+    /// Choices the removed definitions are members of.
+    pub choices: Vec<ImpactChoice>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactReference {
+    pub kind: RefKind,
+    pub location: Location,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactChoice {
+    pub prompt: Option<String>,
+    /// Members left after the removal.
+    pub remaining_members: usize,
+    pub location: Location,
+}
+
+/// What would break if the symbol (or one of its definitions) were removed.
+pub fn removal_impact(index: &WorldIndex, params: &RemovalImpactParams) -> Option<RemovalImpact> {
+    let (name, removed) = match (&params.symbol, &params.uri, params.position) {
+        (Some(name), _, _) => {
+            let defs: Vec<&SymbolDef> = index.get_definitions(name).iter().collect();
+            (name.clone(), defs)
+        }
+        (None, Some(uri), Some(pos)) => {
+            let path = uri::to_path(uri)?;
+            let def = definition_at(index, &path, pos)?;
+            (def.name.clone(), vec![def])
+        }
+        _ => return None,
+    };
+    if removed.is_empty() {
+        return None;
+    }
+
+    let remaining: Vec<&SymbolDef> = index
+        .get_definitions(&name)
+        .iter()
+        .filter(|d| !removed.iter().any(|r| std::ptr::eq(*r, *d)))
+        .collect();
+
+    let refs = index.get_references(&name);
+    let dangling_references = if remaining.is_empty() {
+        refs.iter()
+            .filter_map(|r| {
+                Some(ImpactReference {
+                    kind: r.kind,
+                    location: location(index, &r.file, r.span)?,
+                })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let defaults = refs
+        .iter()
+        .filter(|r| r.kind == RefKind::Default)
+        .filter_map(|r| location(index, &r.file, r.span))
+        .collect();
+
+    let mut choices = Vec::new();
+    for def in &removed {
+        let Some(choice) = index.choice_of(def) else {
+            continue;
+        };
+        let Some(location) = location(index, &choice.file, choice.span) else {
+            continue;
+        };
+        choices.push(ImpactChoice {
+            prompt: choice.prompt.clone(),
+            remaining_members: choice.members.iter().filter(|m| **m != name).count(),
+            location,
+        });
+    }
+
+    Some(RemovalImpact {
+        symbol: name,
+        remaining_definitions: remaining
+            .iter()
+            .filter_map(|d| location(index, &d.file, d.name_span))
+            .collect(),
+        dangling_references,
+        defaults,
+        choices,
+    })
+}
+
+/// A "removal impact" lens on every `transitional` definition in the file,
+/// since those are the symbols on their way out.
+pub fn code_lenses(index: &WorldIndex, uri: &Url, path: &Path) -> Vec<CodeLens> {
+    let Some(fa) = index.files.get(path) else {
+        return Vec::new();
+    };
+    fa.symbols
+        .iter()
+        .filter(|d| d.transitional)
+        .map(|d| {
+            let refs = index.get_references(&d.name).len();
+            let title = format!(
+                "Removal impact: {} reference{}",
+                refs,
+                if refs == 1 { "" } else { "s" }
+            );
+            let range = range(&fa.line_index, d.name_span);
+            CodeLens {
+                range,
+                command: Some(show_command(title, uri, range.start)),
+                data: None,
+            }
+        })
+        .collect()
+}
+
+/// The "Preview removal impact" code action for the definition at `pos`.
+pub fn code_action(
+    index: &WorldIndex,
+    uri: &Url,
+    path: &Path,
+    pos: Position,
+) -> Option<CodeActionOrCommand> {
+    let def = definition_at(index, path, pos)?;
+    Some(CodeActionOrCommand::Command(show_command(
+        format!("Preview removal impact of {}", def.name),
+        uri,
+        pos,
+    )))
+}
+
+fn show_command(title: String, uri: &Url, position: Position) -> Command {
+    let params = RemovalImpactParams {
+        symbol: None,
+        uri: Some(uri.clone()),
+        position: Some(position),
+    };
+    Command {
+        title,
+        command: SHOW_REMOVAL_IMPACT.into(),
+        arguments: serde_json::to_value(params).ok().map(|v| vec![v]),
+    }
+}
+
+/// The definition whose name is under `pos`.
+fn definition_at<'a>(index: &'a WorldIndex, path: &Path, pos: Position) -> Option<&'a SymbolDef> {
+    let fa = index.files.get(path)?;
+    let offset = fa.line_index.offset(pos.line, pos.character);
+    let word = word_at_offset(&fa.source, offset)?;
+    index
+        .get_definitions(&word)
+        .iter()
+        .find(|d| d.file == path && d.name_span.start <= offset && offset <= d.name_span.end)
+}
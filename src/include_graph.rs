@@ -0,0 +1,314 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::analysis::{SourceStmt, SymbolDef, SymbolRef, WorldIndex};
+use crate::ast::{Expr, Span};
+
+/// Kconfig's parse order over the whole tree: a depth-first walk from the
+/// root Kconfig that descends into each `source` where it appears. This is
+/// the order in which properties of a symbol defined in several places are
+/// concatenated, so it decides which `default` wins.
+#[derive(Debug, Default)]
+pub struct TreeOrder {
+    /// Per reached file, the start offset and sequence number of each stretch
+    /// of the file between `source` statements.
+    segments: HashMap<PathBuf, Vec<(usize, u32)>>,
+}
+
+impl TreeOrder {
+    pub fn new(index: &WorldIndex) -> Self {
+        let mut order = Self::default();
+        if let Some(root) = &index.root_kconfig
+            && index.files.contains_key(root)
+        {
+            let mut next = 0;
+            order.walk(index, root, &mut next);
+        }
+        order
+    }
+
+    /// A file sourced more than once keeps the position of its first
+    /// inclusion.
+    fn walk(&mut self, index: &WorldIndex, path: &Path, next: &mut u32) {
+        if self.segments.contains_key(path) {
+            return;
+        }
+        let Some(fa) = index.files.get(path) else {
+            return;
+        };
+        self.segments.insert(path.to_path_buf(), vec![(0, *next)]);
+        *next += 1;
+        for stmt in &fa.sources {
+            if let Some(target) = index.resolve_source(path, &stmt.path, stmt.relative) {
+                self.walk(index, &target, next);
+            }
+            if let Some(segs) = self.segments.get_mut(path) {
+                segs.push((stmt.path_span.end, *next));
+            }
+            *next += 1;
+        }
+    }
+
+    /// How many files the walk reached, the root included.
+    pub fn files(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Sequence number of the statement at `offset` in `path`; `None` when
+    /// the file is not reachable from the root Kconfig.
+    pub fn sequence(&self, path: &Path, offset: usize) -> Option<u32> {
+        self.segments
+            .get(path)?
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= offset)
+            .map(|(_, seq)| *seq)
+    }
+
+    /// Compare two positions in parse order. Positions in files outside the
+    /// tree sort after everything in it, by path.
+    pub fn compare(&self, a: (&Path, usize), b: (&Path, usize)) -> Ordering {
+        let key = |(path, offset): (&Path, usize)| {
+            let seq = self.sequence(path, offset);
+            (seq.is_none(), seq)
+        };
+        key(a)
+            .cmp(&key(b))
+            .then_with(|| a.0.cmp(b.0))
+            .then(a.1.cmp(&b.1))
+    }
+
+    /// `defs` in parse order.
+    pub fn sort_definitions<'a>(&self, defs: &'a [SymbolDef]) -> Vec<&'a SymbolDef> {
+        let mut out: Vec<&SymbolDef> = defs.iter().collect();
+        out.sort_by(|a, b| {
+            self.compare((&a.file, a.name_span.start), (&b.file, b.name_span.start))
+        });
+        out
+    }
+
+    /// `refs` in parse order.
+    pub fn sort_references<'a>(&self, refs: &'a [SymbolRef]) -> Vec<&'a SymbolRef> {
+        let mut out: Vec<&SymbolRef> = refs.iter().collect();
+        out.sort_by(|a, b| self.compare((&a.file, a.span.start), (&b.file, b.span.start)));
+        out
+    }
+}
+
+/// A `source` statement that kconfig would follow into trouble.
+#[derive(Debug, Clone)]
+pub struct SourceProblem {
+    pub kind: SourceProblemKind,
+    /// File containing the offending `source`, and the span of its path.
+    pub file: PathBuf,
+    pub span: Span,
+    /// `source` statements leading to the offending one, which comes last:
+    /// from the root for a duplicate, around the loop for a cycle.
+    pub chain: Vec<(PathBuf, Span)>,
+    /// For a duplicate, the statements through which the file was first
+    /// sourced.
+    pub first: Vec<(PathBuf, Span)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceProblemKind {
+    /// The file is being parsed already; kconfig would recurse forever.
+    Cycle,
+    /// The file was sourced earlier in the walk, so its symbols get a second
+    /// set of definitions.
+    Duplicate,
+}
+
+/// Which `source` statements resolve to each file, and the problems of the
+/// walk from the root, built once per epoch by [`WorldIndex::include_graph`].
+#[derive(Debug, Default)]
+pub struct IncludeGraph {
+    /// Per sourced file, the files sourcing it with the position of each
+    /// statement in their `sources`, sorted by file and offset.
+    includers: HashMap<PathBuf, Vec<(PathBuf, usize)>>,
+    pub problems: Vec<SourceProblem>,
+    /// [`inherited_condition`] of the files asked about so far.
+    inherited: Mutex<HashMap<PathBuf, Option<Expr>>>,
+}
+
+impl IncludeGraph {
+    pub fn new(index: &WorldIndex) -> Self {
+        let mut includers: HashMap<PathBuf, Vec<(PathBuf, usize)>> = HashMap::new();
+        for (from, fa) in &index.files {
+            for (i, s) in fa.sources.iter().enumerate() {
+                if let Some(target) = index.resolve_source(from, &s.path, s.relative) {
+                    includers.entry(target).or_default().push((from.clone(), i));
+                }
+            }
+        }
+        for stmts in includers.values_mut() {
+            stmts.sort_by(|a, b| {
+                a.0.cmp(&b.0).then_with(|| {
+                    let offset = |(file, i): &(PathBuf, usize)| {
+                        index.files[file].sources[*i].path_span.start
+                    };
+                    offset(a).cmp(&offset(b))
+                })
+            });
+        }
+        let mut graph = Self {
+            includers,
+            ..Self::default()
+        };
+        graph.problems = source_problems(index, &graph);
+        graph
+    }
+}
+
+/// Cycles and repeated inclusions in the walk from the root Kconfig. Without
+/// an indexed root, the walk starts from every file nothing sources.
+fn source_problems(index: &WorldIndex, graph: &IncludeGraph) -> Vec<SourceProblem> {
+    let roots: Vec<PathBuf> = match &index.root_kconfig {
+        Some(root) if index.files.contains_key(root) => vec![root.clone()],
+        _ => {
+            let mut roots: Vec<PathBuf> = index
+                .files
+                .keys()
+                .filter(|p| !graph.includers.contains_key(*p))
+                .cloned()
+                .collect();
+            roots.sort();
+            roots
+        }
+    };
+    let mut walk = ProblemWalk {
+        index,
+        first: HashMap::new(),
+        stack: Vec::new(),
+        problems: Vec::new(),
+    };
+    for root in roots {
+        walk.first.entry(root.clone()).or_default();
+        walk.visit(&root, Vec::new());
+    }
+    walk.problems
+}
+
+struct ProblemWalk<'a> {
+    index: &'a WorldIndex,
+    /// Chain by which each reached file was first sourced.
+    first: HashMap<PathBuf, Vec<(PathBuf, Span)>>,
+    /// Files being walked, outermost first.
+    stack: Vec<PathBuf>,
+    problems: Vec<SourceProblem>,
+}
+
+impl ProblemWalk<'_> {
+    fn visit(&mut self, path: &Path, chain: Vec<(PathBuf, Span)>) {
+        let Some(fa) = self.index.files.get(path) else {
+            return;
+        };
+        self.stack.push(path.to_path_buf());
+        for stmt in &fa.sources {
+            let Some(target) = self.index.resolve_source(path, &stmt.path, stmt.relative) else {
+                continue;
+            };
+            let mut next = chain.clone();
+            next.push((path.to_path_buf(), stmt.path_span));
+            if let Some(depth) = self.stack.iter().position(|p| *p == target) {
+                // `chain[i]` is the statement that sourced `stack[i + 1]`.
+                let cycle = next.split_off(depth);
+                self.problems.push(SourceProblem {
+                    kind: SourceProblemKind::Cycle,
+                    file: path.to_path_buf(),
+                    span: stmt.path_span,
+                    chain: cycle,
+                    first: Vec::new(),
+                });
+            } else if let Some(first) = self.first.get(&target) {
+                self.problems.push(SourceProblem {
+                    kind: SourceProblemKind::Duplicate,
+                    file: path.to_path_buf(),
+                    span: stmt.path_span,
+                    first: first.clone(),
+                    chain: next,
+                });
+            } else {
+                self.first.insert(target.clone(), next.clone());
+                self.visit(&target, next);
+            }
+        }
+        self.stack.pop();
+    }
+}
+
+/// Every indexed `source` statement that resolves to `path`, with the file
+/// containing it.
+pub fn includers<'a>(index: &'a WorldIndex, path: &Path) -> Vec<(&'a Path, &'a SourceStmt)> {
+    let graph = index.include_graph();
+    let Some(stmts) = graph.includers.get(path) else {
+        return Vec::new();
+    };
+    stmts
+        .iter()
+        .filter_map(|(from, i)| {
+            let (from, fa) = index.files.get_key_value(from)?;
+            Some((from.as_path(), fa.sources.get(*i)?))
+        })
+        .collect()
+}
+
+/// The condition under which the contents of `path` are included, combining
+/// the `if`/menu/choice conditions around every `source` of it (transitively).
+/// `None` means the file is included unconditionally, or not sourced at all.
+/// Computed once per file and epoch.
+pub fn inherited_condition(index: &WorldIndex, path: &Path) -> Option<Expr> {
+    let graph = index.include_graph();
+    if let Some(cond) = graph.inherited.lock().unwrap().get(path) {
+        return cond.clone();
+    }
+    let mut visiting = vec![path.to_path_buf()];
+    let cond = inherited(index, path, &mut visiting);
+    graph
+        .inherited
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), cond.clone());
+    cond
+}
+
+fn inherited(index: &WorldIndex, path: &Path, visiting: &mut Vec<PathBuf>) -> Option<Expr> {
+    let mut alternatives: Vec<Expr> = Vec::new();
+    for (from, stmt) in includers(index, path) {
+        if visiting.iter().any(|v| v == from) {
+            continue;
+        }
+        visiting.push(from.to_path_buf());
+        let parent = inherited(index, from, visiting);
+        visiting.pop();
+
+        let terms: Vec<Expr> = parent
+            .into_iter()
+            .chain(stmt.conditions.iter().cloned())
+            .collect();
+        match conjunction(terms) {
+            Some(expr) => alternatives.push(expr),
+            // One unconditional inclusion makes the file unconditional.
+            None => return None,
+        }
+    }
+    alternatives
+        .into_iter()
+        .reduce(|a, b| Expr::Or(Box::new(a), Box::new(b)))
+}
+
+fn conjunction(terms: Vec<Expr>) -> Option<Expr> {
+    terms
+        .into_iter()
+        .map(parenthesize)
+        .reduce(|a, b| Expr::And(Box::new(a), Box::new(b)))
+}
+
+fn parenthesize(expr: Expr) -> Expr {
+    match expr {
+        Expr::Or(..) => Expr::Paren(Box::new(expr)),
+        other => other,
+    }
+}
@@ -0,0 +1,87 @@
+//! Order in which the startup scan indexes files. Files the user has open,
+//! and the files around them in the include graph, go first so the first
+//! hovers and diagnostics are accurate before the whole tree is read.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use crate::analysis::FileAnalysis;
+
+#[derive(Debug, Default)]
+pub struct IndexQueue {
+    /// Prioritized files, in the order they were prioritized.
+    urgent: VecDeque<PathBuf>,
+    /// Everything else, in discovery order.
+    rest: VecDeque<PathBuf>,
+    /// Files not yet popped.
+    pending: HashSet<PathBuf>,
+}
+
+impl IndexQueue {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self {
+            pending: paths.iter().cloned().collect(),
+            rest: paths.into(),
+            urgent: VecDeque::new(),
+        }
+    }
+
+    /// Move `path` ahead of every file not yet prioritized. Files already
+    /// indexed, or never discovered, are ignored.
+    pub fn prioritize(&mut self, path: &Path) {
+        if self.pending.contains(path) && !self.urgent.iter().any(|p| p == path) {
+            self.urgent.push_back(path.to_path_buf());
+        }
+    }
+
+    /// The next file to index, and whether it was prioritized.
+    pub fn pop(&mut self) -> Option<(PathBuf, bool)> {
+        while let Some(path) = self.urgent.pop_front() {
+            if self.pending.remove(&path) {
+                return Some((path, true));
+            }
+        }
+        while let Some(path) = self.rest.pop_front() {
+            if self.pending.remove(&path) {
+                return Some((path, false));
+            }
+        }
+        None
+    }
+
+    /// The files not popped yet, in discovery order.
+    pub fn into_pending(self) -> Vec<PathBuf> {
+        let pending = self.pending;
+        self.rest
+            .into_iter()
+            .filter(|p| pending.contains(p))
+            .collect()
+    }
+
+    /// Whether prioritized files remain.
+    pub fn has_urgent(&self) -> bool {
+        self.urgent.iter().any(|p| self.pending.contains(p))
+    }
+}
+
+/// Files likely to be next to `path` in the include graph: what it sources,
+/// resolved against `root` and its own directory, and the `Kconfig` of each
+/// directory from its own up to `root`, the usual chain of files sourcing it.
+pub fn neighbours(root: &Path, path: &Path, fa: &FileAnalysis) -> Vec<PathBuf> {
+    let dir = path.parent().unwrap_or(root);
+    let mut out = Vec::new();
+    for stmt in &fa.sources {
+        if stmt.path.contains("$(") {
+            continue;
+        }
+        out.push(root.join(&stmt.path));
+        out.push(dir.join(&stmt.path));
+    }
+    out.extend(
+        dir.ancestors()
+            .take_while(|d| d.starts_with(root))
+            .map(|d| d.join("Kconfig")),
+    );
+    out.retain(|p| p != path);
+    out
+}
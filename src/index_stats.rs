@@ -0,0 +1,81 @@
+//! `kconfig/indexStats`: the size of the index, the files the workspace
+//! scan left out of it because they are too large or not text, and how
+//! long requests take.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::analysis::WorldIndex;
+use crate::request_metrics::RequestLatency;
+use crate::tree_info::{self, TreeInfo};
+
+/// Bytes sniffed for NUL characters before a file is read as Kconfig.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    pub files: usize,
+    pub symbols: usize,
+    pub definitions: usize,
+    pub references: usize,
+    pub skipped: Vec<SkippedFile>,
+    /// The tree rooted at the root Kconfig, once it is indexed.
+    pub tree: Option<TreeInfo>,
+    /// The startup scan or a reindex is running, so results may be
+    /// incomplete or out of date.
+    pub indexing: bool,
+    /// Latencies of the requests answered, by LSP method.
+    pub requests: Vec<RequestLatency>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// The stats of `index`, with whether it is being built and the latencies
+/// of the requests answered, which the server keeps.
+pub fn index_stats(
+    index: &WorldIndex,
+    indexing: bool,
+    requests: Vec<RequestLatency>,
+) -> IndexStats {
+    let mut skipped = index.skipped.clone();
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    IndexStats {
+        files: index.files.len(),
+        symbols: index.definitions.len(),
+        definitions: index.definitions.values().map(Vec::len).sum(),
+        references: index.references.values().map(Vec::len).sum(),
+        skipped,
+        tree: tree_info::tree_info(index),
+        indexing,
+        requests,
+    }
+}
+
+/// Read a file found by the workspace scan, refusing files over `max_size`
+/// bytes (`0` for no limit) and files that look binary or are not UTF-8.
+/// The error is the reason, for [`SkippedFile`].
+pub fn read_kconfig(path: &Path, max_size: u64) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    if max_size > 0 && len > max_size {
+        return Err(format!(
+            "{} bytes, over the `maxFileSize` limit of {}",
+            len, max_size
+        ));
+    }
+    let mut bytes = Vec::with_capacity(len as usize);
+    file.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    if bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return Err("binary content".into());
+    }
+    String::from_utf8(bytes)
+        .map_err(|e| format!("not valid UTF-8 (at byte {})", e.utf8_error().valid_up_to()))
+}
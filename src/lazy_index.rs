@@ -0,0 +1,148 @@
+//! The `indexing: "lazy"` mode. The startup scan indexes only what the root
+//! Kconfig and the open documents source, directly or not; the other files
+//! it finds are kept here, with the names their `config` and `menuconfig`
+//! lines define, and parsed only when a symbol lookup misses to find the
+//! ones defining the symbol.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::analysis::WorldIndex;
+use crate::index_queue::{self, IndexQueue};
+use crate::index_stats::{self, SkippedFile};
+
+/// A `config` or `menuconfig` line, and the name it defines.
+static DEFINITION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^[ \t]*(?:menu)?config[ \t]+(\w+)").unwrap());
+
+/// Files found by the workspace scan and not indexed yet.
+#[derive(Debug, Default)]
+pub struct LazyFiles {
+    /// The names each pending file defines. Files defining none are left
+    /// out: no lookup would take them.
+    defines: HashMap<PathBuf, Vec<String>>,
+    /// The pending files defining each name.
+    defined_in: HashMap<String, Vec<PathBuf>>,
+}
+
+impl LazyFiles {
+    /// Look through `pending` for the names each file defines. This reads
+    /// every file, so it belongs on a blocking task. Files that cannot be
+    /// read are dropped.
+    pub fn scan(pending: Vec<PathBuf>, max_file_size: u64) -> Self {
+        let mut lazy = Self::default();
+        for path in pending {
+            let source = match index_stats::read_kconfig(&path, max_file_size) {
+                Ok(source) => source,
+                Err(reason) => {
+                    log::warn!("not indexing {}: {}", path.display(), reason);
+                    continue;
+                }
+            };
+            let mut names: Vec<String> = DEFINITION
+                .captures_iter(&source)
+                .map(|c| c[1].to_string())
+                .collect();
+            names.sort();
+            names.dedup();
+            if names.is_empty() {
+                continue;
+            }
+            for name in &names {
+                lazy.defined_in
+                    .entry(name.clone())
+                    .or_default()
+                    .push(path.clone());
+            }
+            lazy.defines.insert(path, names);
+        }
+        lazy
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defines.is_empty()
+    }
+
+    /// The pending files defining any of `names`, each once.
+    pub fn defining(&self, names: &BTreeSet<String>) -> Vec<PathBuf> {
+        let files: BTreeSet<&PathBuf> = names
+            .iter()
+            .filter_map(|n| self.defined_in.get(n))
+            .flatten()
+            .collect();
+        files.into_iter().cloned().collect()
+    }
+
+    /// Stop offering `path`, now indexed or gone.
+    pub fn remove(&mut self, path: &Path) {
+        let Some(names) = self.defines.remove(path) else {
+            return;
+        };
+        for name in names {
+            if let Some(files) = self.defined_in.get_mut(&name) {
+                files.retain(|p| p != path);
+                if files.is_empty() {
+                    self.defined_in.remove(&name);
+                }
+            }
+        }
+    }
+}
+
+/// What the lazy startup scan builds, for a reindex to build aside and
+/// swap in: `index` with the `files` that the open documents and the root
+/// Kconfig source, directly or not, and their neighbours, read from disk;
+/// the open documents analyzed from the editor's `open` text; and the rest
+/// left pending. This reads from disk, so it belongs on a blocking task.
+pub fn build(
+    mut index: WorldIndex,
+    files: Vec<PathBuf>,
+    open: Vec<(PathBuf, String)>,
+    root: &Path,
+    max_file_size: u64,
+) -> (WorldIndex, HashSet<PathBuf>, LazyFiles) {
+    let mut queue = IndexQueue::new(files);
+    for (path, _) in &open {
+        queue.prioritize(path);
+    }
+    if let Some(root_kconfig) = index.root_kconfig.clone() {
+        queue.prioritize(&root_kconfig);
+    }
+    let open: HashMap<PathBuf, String> = open.into_iter().collect();
+    let mut indexed = HashSet::new();
+    while queue.has_urgent() {
+        let Some((path, _)) = queue.pop() else {
+            break;
+        };
+        match open.get(&path) {
+            Some(text) => index.analyze_file(&path, text),
+            None => match index_stats::read_kconfig(&path, max_file_size) {
+                Ok(source) => index.analyze_file(&path, &source),
+                Err(reason) => {
+                    log::warn!("not indexing {}: {}", path.display(), reason);
+                    index.skipped.push(SkippedFile { path, reason });
+                    continue;
+                }
+            },
+        }
+        let fa = &index.files[&path];
+        let mut next: Vec<PathBuf> = fa
+            .sources
+            .iter()
+            .flat_map(|stmt| index.source_candidates(&path, &stmt.path, stmt.relative))
+            .collect();
+        if open.contains_key(&path) {
+            next.extend(index_queue::neighbours(root, &path, fa));
+        }
+        for p in next {
+            queue.prioritize(&p);
+        }
+        indexed.insert(path);
+    }
+    let pending = queue.into_pending();
+    log::info!("{} Kconfig files left to index on demand", pending.len());
+    (index, indexed, LazyFiles::scan(pending, max_file_size))
+}
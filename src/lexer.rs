@@ -1,4 +1,7 @@
+use serde::Deserialize;
+
 use crate::ast::Span;
+use crate::preprocess;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenKind {
@@ -13,6 +16,9 @@ pub enum TokenKind {
     If,
     EndIf,
     Source,
+    OSource,  // optional: a missing file is not an error
+    RSource,  // relative to the directory of the current file
+    ORSource, // both
     MainMenu,
 
     // Type keywords
@@ -27,6 +33,11 @@ pub enum TokenKind {
     Default,
     DefBool,
     DefTristate,
+    // `def_int`, `def_hex` and `def_string`, keywords only in the
+    // `kconfiglib` dialect
+    DefInt,
+    DefHex,
+    DefString,
     Depends,
     On,
     Select,
@@ -37,6 +48,7 @@ pub enum TokenKind {
     Modules,
     Transitional,
     Optional,
+    OptionKw, // `option` (distinct from `optional`)
 
     // Operators
     Eq,         // =
@@ -58,12 +70,20 @@ pub enum TokenKind {
     // Macro invocation $(...)
     Macro(String),
 
+    /// A whole preprocessor assignment line (`cc-name := gcc`), which
+    /// kconfig handles before parsing.
+    Assignment,
+
     // Line comment: # ...
     LineComment(String),
 
     // Whitespace / structure
     Newline,
     Eof,
+
+    /// Input kconfig's lexer warns about and skips, with the message. The
+    /// parser reports these and drops them.
+    Error(String),
 }
 
 #[derive(Debug, Clone)]
@@ -72,12 +92,42 @@ pub struct Token {
     pub span: Span,
 }
 
+/// The Kconfig grammar files are written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Dialect {
+    /// The Linux kernel's kconfig.
+    #[default]
+    Kconfig,
+    /// Kconfig as Kconfiglib and the tools built on it read it, with the
+    /// `def_int`, `def_hex` and `def_string` shorthands.
+    Kconfiglib,
+}
+
+impl Dialect {
+    /// Keywords of the dialect on top of [`keyword`]'s.
+    fn keyword(self, s: &str) -> Option<TokenKind> {
+        if self != Dialect::Kconfiglib {
+            return None;
+        }
+        Some(match s {
+            "def_int" => TokenKind::DefInt,
+            "def_hex" => TokenKind::DefHex,
+            "def_string" => TokenKind::DefString,
+            _ => return None,
+        })
+    }
+}
+
 // ---------------------------------------------------------------------------
 
 pub struct Lexer<'a> {
     src: &'a str,
     bytes: &'a [u8],
     pos: usize,
+    dialect: Dialect,
+    /// Error tokens to emit after the token just lexed.
+    pending: Vec<Token>,
 }
 
 impl<'a> Lexer<'a> {
@@ -86,15 +136,24 @@ impl<'a> Lexer<'a> {
             src,
             bytes: src.as_bytes(),
             pos: 0,
+            dialect: Dialect::default(),
+            pending: Vec::new(),
         }
     }
 
+    /// Lex the keywords of `dialect` too.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
     pub fn tokenize(mut self) -> Vec<Token> {
         let mut tokens = Vec::new();
         loop {
             let tok = self.next_token();
             let is_eof = tok.kind == TokenKind::Eof;
             tokens.push(tok);
+            tokens.append(&mut self.pending);
             if is_eof {
                 break;
             }
@@ -120,7 +179,7 @@ impl<'a> Lexer<'a> {
 
     fn skip_spaces(&mut self) {
         while let Some(b) = self.peek() {
-            if b == b' ' || b == b'\t' {
+            if matches!(b, b' ' | b'\t' | b'\r') {
                 self.pos += 1;
             } else {
                 break;
@@ -128,14 +187,11 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    /// Skip a `\` immediately followed by `\n` (line continuation).
+    /// Skip a `\` immediately followed by a newline (line continuation).
     fn skip_line_continuation(&mut self) -> bool {
-        if self.peek() == Some(b'\\') && self.peek2() == Some(b'\n') {
-            self.pos += 2;
-            true
-        } else {
-            false
-        }
+        let len = continuation_len(&self.bytes[self.pos..]);
+        self.pos += len;
+        len > 0
     }
 
     fn next_token(&mut self) -> Token {
@@ -246,29 +302,55 @@ impl<'a> Lexer<'a> {
                 }
             }
 
-            _ if is_ident_start(ch) => self.lex_ident(start),
+            _ if is_word_char(ch) && self.at_line_start(start) => self.lex_assignment(start),
+            _ if is_word_char(ch) => self.lex_ident(start),
 
-            // Skip any unexpected byte gracefully (error recovery).
-            _ => self.next_token(),
+            // kconfig warns about and ignores any other character.
+            _ => {
+                let c = self.src[start..].chars().next().unwrap_or('\u{fffd}');
+                self.pos = start + c.len_utf8();
+                Token {
+                    kind: TokenKind::Error(format!("unexpected character `{}`", c)),
+                    span: Span::new(start, self.pos),
+                }
+            }
         }
     }
 
+    /// A quoted string. As in kconfig, the other quote character needs no
+    /// escaping, a backslash takes the next character literally, and a
+    /// string ends at the end of the line even without its closing quote.
     fn lex_string(&mut self, start: usize, quote: u8) -> Token {
-        let mut value = String::new();
+        let mut value = Vec::new();
         loop {
-            match self.advance() {
-                Some(b) if b == quote => break,
+            match self.peek() {
+                Some(b) if b == quote => {
+                    self.pos += 1;
+                    break;
+                }
+                // Unterminated; the newline is left for the next token.
+                Some(b'\n') | None => {
+                    self.pending.push(Token {
+                        kind: TokenKind::Error("unterminated string literal".into()),
+                        span: Span::new(start, self.pos),
+                    });
+                    break;
+                }
                 Some(b'\\') => {
-                    if let Some(esc) = self.advance() {
-                        value.push(esc as char);
+                    self.pos += 1;
+                    if let Some(esc) = self.peek().filter(|&b| b != b'\n') {
+                        self.pos += 1;
+                        value.push(esc);
                     }
                 }
-                Some(b'\n') | None => break, // unterminated string
-                Some(b) => value.push(b as char),
+                Some(b) => {
+                    self.pos += 1;
+                    value.push(b);
+                }
             }
         }
         Token {
-            kind: TokenKind::StringLit(value),
+            kind: TokenKind::StringLit(String::from_utf8_lossy(&value).into_owned()),
             span: Span::new(start, self.pos),
         }
     }
@@ -293,16 +375,47 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// A word: a keyword, symbol name or number. kconfig makes no lexical
+    /// difference between `FOO`, `123abc`, `-1` and `0x10`; numbers are
+    /// symbols nobody defines, whose value is their name. A `$` not opening
+    /// a macro is part of the word, as is `/` and `.`.
+    /// Whether only whitespace precedes `offset` on its line.
+    fn at_line_start(&self, offset: usize) -> bool {
+        let line_start = self.src[..offset].rfind('\n').map_or(0, |p| p + 1);
+        self.src[line_start..offset]
+            .bytes()
+            .all(|b| matches!(b, b' ' | b'\t'))
+    }
+
+    /// The assignment line starting at `start`, or the word there if the
+    /// line is not an assignment.
+    fn lex_assignment(&mut self, start: usize) -> Token {
+        let end = self.src[start..]
+            .find('\n')
+            .map_or(self.src.len(), |p| start + p);
+        let line = self.src[start..end].trim_end_matches('\r');
+        if preprocess::parse_assignment(line, start).is_none() {
+            return self.lex_ident(start);
+        }
+        self.pos = start + line.len();
+        Token {
+            kind: TokenKind::Assignment,
+            span: Span::new(start, self.pos),
+        }
+    }
+
     fn lex_ident(&mut self, start: usize) -> Token {
         while let Some(b) = self.peek() {
-            if is_ident_cont(b) {
+            if is_word_char(b) && !(b == b'$' && self.peek2() == Some(b'(')) {
                 self.pos += 1;
             } else {
                 break;
             }
         }
         let text = &self.src[start..self.pos];
-        let kind = keyword(text).unwrap_or_else(|| TokenKind::Ident(text.to_string()));
+        let kind = keyword(text)
+            .or_else(|| self.dialect.keyword(text))
+            .unwrap_or_else(|| TokenKind::Ident(text.to_string()));
         Token {
             kind,
             span: Span::new(start, self.pos),
@@ -310,15 +423,53 @@ impl<'a> Lexer<'a> {
     }
 }
 
-fn is_ident_start(b: u8) -> bool {
-    b.is_ascii_alphanumeric() || b == b'_'
+/// Length of the line continuation (`\` and a `\n` or `\r\n`) at the start
+/// of `bytes`, or `0`.
+fn continuation_len(bytes: &[u8]) -> usize {
+    match bytes {
+        [b'\\', b'\n', ..] => 2,
+        [b'\\', b'\r', b'\n', ..] => 3,
+        _ => 0,
+    }
+}
+
+/// The statement `offset` is in, from the start of its first line up to
+/// `offset`, with line continuations replaced by a space. Tokens may be
+/// spread over several physical lines this way, so code that looks at "the
+/// current line" should look at this instead.
+pub fn logical_line_before(source: &str, offset: usize) -> String {
+    let mut start = source[..offset].rfind('\n').map_or(0, |p| p + 1);
+    while start > 0 {
+        let prev = source[..start - 1].trim_end_matches('\r');
+        if !prev.ends_with('\\') {
+            break;
+        }
+        start = prev.rfind('\n').map_or(0, |p| p + 1);
+    }
+    let bytes = &source.as_bytes()[start..offset];
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match continuation_len(&bytes[i..]) {
+            0 => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+            len => {
+                out.push(b' ');
+                i += len;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
 }
 
-fn is_ident_cont(b: u8) -> bool {
-    b.is_ascii_alphanumeric() || b == b'_' || b == b'-'
+/// `({n}|[/.$])` of kconfig's lexer, where `n` is `[A-Za-z0-9_-]`.
+fn is_word_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'/' | b'.' | b'$')
 }
 
-fn keyword(s: &str) -> Option<TokenKind> {
+pub fn keyword(s: &str) -> Option<TokenKind> {
     Some(match s {
         "config" => TokenKind::Config,
         "menuconfig" => TokenKind::MenuConfig,
@@ -330,6 +481,9 @@ fn keyword(s: &str) -> Option<TokenKind> {
         "if" => TokenKind::If,
         "endif" => TokenKind::EndIf,
         "source" => TokenKind::Source,
+        "osource" => TokenKind::OSource,
+        "rsource" => TokenKind::RSource,
+        "orsource" => TokenKind::ORSource,
         "mainmenu" => TokenKind::MainMenu,
         "bool" => TokenKind::Bool,
         "tristate" => TokenKind::Tristate,
@@ -351,6 +505,7 @@ fn keyword(s: &str) -> Option<TokenKind> {
         "modules" => TokenKind::Modules,
         "transitional" => TokenKind::Transitional,
         "optional" => TokenKind::Optional,
+        "option" => TokenKind::OptionKw,
         _ => return None,
     })
 }
@@ -1,10 +1,59 @@
 pub mod analysis;
 pub mod ast;
+pub mod cli;
+pub mod client_caps;
+pub mod code_action;
+pub mod codes;
 pub mod completion;
+pub mod config_doc;
+pub mod debug_dump;
 pub mod definition;
+pub mod dependency_path;
 pub mod diagnostics;
+pub mod doc_site;
+pub mod dotconfig;
+pub mod end_comments;
+pub mod eval;
+pub mod extract;
+pub mod format;
 pub mod hover;
+pub mod impact;
+pub mod include_graph;
+pub mod index_queue;
+pub mod index_stats;
+pub mod lazy_index;
 pub mod lexer;
+pub mod linked_editing;
+pub mod lint;
+pub mod lint_config;
+pub mod menu_tree;
+pub mod merge_config;
 pub mod parser;
+pub mod patch;
+pub mod preprocess;
+pub mod profile;
+pub mod project_stats;
 pub mod references;
+pub mod related;
+pub mod rename;
+pub mod report;
+pub mod request_metrics;
+pub mod resolved;
+pub mod sarif;
+pub mod search;
+pub mod select_chain;
 pub mod server;
+pub mod settings;
+pub mod shell_trust;
+pub mod simulate;
+pub mod sort_entries;
+pub mod sourced_files;
+pub mod symbol_docs;
+pub mod symbol_metrics;
+pub mod text_index;
+pub mod transitional;
+pub mod tree_diff;
+pub mod tree_info;
+pub mod type_hierarchy;
+pub mod uri;
+pub mod util;
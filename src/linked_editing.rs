@@ -0,0 +1,73 @@
+//! `textDocument/linkedEditingRange` for the convention of repeating a
+//! menu's title in a comment after its `endmenu`, as in
+//! `endmenu # "General setup"`: editing either title edits the other.
+
+use std::path::Path;
+
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::WorldIndex;
+use crate::ast::{Entry, MenuEntry, Span};
+use crate::end_comments;
+use crate::util::range;
+
+/// The title of the menu at `pos` and the comment after its `endmenu`, when
+/// `pos` is on either one and the two read the same.
+pub fn linked_editing_ranges(
+    index: &WorldIndex,
+    path: &Path,
+    pos: Position,
+) -> Option<LinkedEditingRanges> {
+    let fa = index.files.get(path)?;
+    let offset = fa.line_index.offset(pos.line, pos.character);
+    let mut pairs = Vec::new();
+    linked_titles(&fa.file.entries, &fa.source, &mut pairs);
+    let (title, comment) = pairs
+        .into_iter()
+        .find(|(title, comment)| contains(*title, offset) || contains(*comment, offset))?;
+    Some(LinkedEditingRanges {
+        ranges: vec![range(&fa.line_index, title), range(&fa.line_index, comment)],
+        word_pattern: None,
+    })
+}
+
+/// The linked titles of the menus in `entries`, nested ones included.
+fn linked_titles(entries: &[Entry], source: &str, out: &mut Vec<(Span, Span)>) {
+    for entry in entries {
+        if let Entry::Menu(m) = entry
+            && let Some(pair) = linked_title(m, source)
+        {
+            out.push(pair);
+        }
+        linked_titles(entry.children(), source, out);
+    }
+}
+
+/// The text of the menu's title, without its quotes, and that of the
+/// comment on its `endmenu` line if the comment repeats it, quoted or not.
+fn linked_title(menu: &MenuEntry, source: &str) -> Option<(Span, Span)> {
+    let end = menu.span.end;
+    if !source.get(..end)?.ends_with("endmenu") {
+        return None;
+    }
+    let comment = end_comments::comment_after(source, end)?;
+    let title = unquote(source, menu.prompt_span);
+    let comment = unquote(source, comment);
+    (source[title.start..title.end] == source[comment.start..comment.end])
+        .then_some((title, comment))
+}
+
+/// `span` without the quotes around it, if it is a quoted string.
+fn unquote(source: &str, span: Span) -> Span {
+    let text = &source[span.start..span.end];
+    match text.chars().next() {
+        Some(q @ ('"' | '\'')) if text.len() >= 2 && text.ends_with(q) => {
+            Span::new(span.start + 1, span.end - 1)
+        }
+        _ => span,
+    }
+}
+
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
@@ -0,0 +1,741 @@
+use std::path::Path;
+
+use crate::analysis::{FileAnalysis, WorldIndex};
+use crate::ast::*;
+use crate::codes::{self, Code};
+use crate::settings::{LintSettings, NamingRule};
+use crate::{end_comments, eval};
+
+/// A style or correctness problem found by a lint rule, with the edits that
+/// would fix it.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub code: Code,
+    pub message: String,
+    pub span: Span,
+    pub severity: DiagSeverity,
+    pub fixes: Vec<Fix>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub title: String,
+    pub edits: Vec<Edit>,
+}
+
+/// Replace the text at `span` (byte offsets) with `new_text`.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub span: Span,
+    pub new_text: String,
+}
+
+impl Finding {
+    fn with_fix(code: Code, span: Span, message: String, title: &str, edits: Vec<Edit>) -> Self {
+        Self {
+            code,
+            message,
+            span,
+            severity: DiagSeverity::Warning,
+            fixes: vec![Fix {
+                title: title.to_string(),
+                edits,
+            }],
+        }
+    }
+}
+
+/// Run every enabled lint rule over one file.
+pub fn check_file(index: &WorldIndex, path: &Path, settings: &LintSettings) -> Vec<Finding> {
+    let Some(fa) = index.files.get(path) else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    check_default_quoting(index, &fa.file.entries, &mut findings);
+    check_default_values(index, &fa.file.entries, &mut findings);
+    check_comparisons(index, &fa.file.entries, &mut findings);
+    check_menu_titles(&fa.source, &fa.file.entries, &mut findings);
+    check_end_comments(&fa.source, &fa.file.entries, &mut findings);
+    if settings.whitespace {
+        check_whitespace(fa, &mut findings);
+    }
+    let rel = index
+        .root
+        .as_deref()
+        .and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path);
+    let rules: Vec<&NamingRule> = settings.naming.iter().filter(|r| r.covers(rel)).collect();
+    if !rules.is_empty() {
+        check_naming(&fa.file.entries, &rules, &mut findings);
+    }
+    findings.retain(|f| !settings.disable.iter().any(|r| f.code.matches(r)));
+    findings
+}
+
+// ---------------------------------------------------------------------------
+// default-quoting
+// ---------------------------------------------------------------------------
+
+/// `string` defaults must be quoted (unless they name a symbol), and
+/// `int`/`hex` defaults must not be.
+fn check_default_quoting(index: &WorldIndex, entries: &[Entry], out: &mut Vec<Finding>) {
+    for entry in entries {
+        if let Entry::Config(c) | Entry::MenuConfig(c) = entry
+            && let Some(kind) = index.symbol_type(&c.name)
+        {
+            for attr in &c.attributes {
+                let Attribute::Default(d) = attr else {
+                    continue;
+                };
+                match (kind, &d.value) {
+                    (TypeKind::String, Expr::Symbol(word, span))
+                        if index.get_definitions(word).is_empty() && !word.starts_with("$(") =>
+                    {
+                        out.push(Finding::with_fix(
+                            codes::DEFAULT_QUOTING,
+                            *span,
+                            format!(
+                                "default of string symbol `{}` should be a quoted string",
+                                c.name
+                            ),
+                            "Add quotes",
+                            vec![Edit {
+                                span: *span,
+                                new_text: format!("\"{}\"", word),
+                            }],
+                        ));
+                    }
+                    (TypeKind::Int | TypeKind::Hex, Expr::StringLit(text, span)) => {
+                        let mut finding = Finding::with_fix(
+                            codes::DEFAULT_QUOTING,
+                            *span,
+                            format!(
+                                "default of {} symbol `{}` should not be quoted",
+                                kind.as_str(),
+                                c.name
+                            ),
+                            "Remove quotes",
+                            vec![Edit {
+                                span: *span,
+                                new_text: text.clone(),
+                            }],
+                        );
+                        if eval::parse_number(text, kind == TypeKind::Hex).is_none() {
+                            finding.fixes.clear();
+                        }
+                        out.push(finding);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        check_default_quoting(index, entry.children(), out);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// default-value
+// ---------------------------------------------------------------------------
+
+/// Defaults that can't be a value of the symbol's type: numbers, strings and
+/// symbols of other types as the default of a bool or tristate, and `y`,
+/// `m`, `n` or bool, tristate and string symbols as that of an int or hex.
+fn check_default_values(index: &WorldIndex, entries: &[Entry], out: &mut Vec<Finding>) {
+    for entry in entries {
+        if let Entry::Config(c) | Entry::MenuConfig(c) = entry {
+            for attr in &c.attributes {
+                let (kind, value) = match attr {
+                    Attribute::Default(d) => match index.symbol_type(&c.name) {
+                        Some(kind) => (kind, &d.value),
+                        None => continue,
+                    },
+                    Attribute::DefType(d) => (d.kind, &d.value),
+                    _ => continue,
+                };
+                out.extend(check_default_value(index, &c.name, kind, value));
+            }
+        }
+        check_default_values(index, entry.children(), out);
+    }
+}
+
+fn check_default_value(
+    index: &WorldIndex,
+    name: &str,
+    kind: TypeKind,
+    value: &Expr,
+) -> Option<Finding> {
+    let finding = |span: Span, message: String| Finding {
+        code: codes::DEFAULT_VALUE,
+        message,
+        span,
+        severity: DiagSeverity::Warning,
+        fixes: Vec::new(),
+    };
+    match (kind, value) {
+        (TypeKind::Bool | TypeKind::Tristate, Expr::StringLit(text, span))
+            if matches!(text.as_str(), "y" | "m" | "n") =>
+        {
+            Some(Finding::with_fix(
+                codes::DEFAULT_VALUE,
+                *span,
+                format!(
+                    "default of {} symbol `{}` is unquoted `{}`, not a string",
+                    kind.as_str(),
+                    name,
+                    text
+                ),
+                "Remove quotes",
+                vec![Edit {
+                    span: *span,
+                    new_text: text.clone(),
+                }],
+            ))
+        }
+        (TypeKind::Bool | TypeKind::Tristate, Expr::StringLit(_, span)) => Some(finding(
+            *span,
+            format!(
+                "default of {} symbol `{}` should be {}, not a string",
+                kind.as_str(),
+                name,
+                tristate_values(kind)
+            ),
+        )),
+        (TypeKind::Bool | TypeKind::Tristate, Expr::Symbol(word, span)) => {
+            match index.symbol_type(word) {
+                Some(TypeKind::Bool | TypeKind::Tristate) => None,
+                Some(other) => Some(finding(
+                    *span,
+                    format!(
+                        "`{}` has type {}; the default of {} symbol `{}` should be {}",
+                        word,
+                        other.as_str(),
+                        kind.as_str(),
+                        name,
+                        tristate_values(kind)
+                    ),
+                )),
+                None if index.get_definitions(word).is_empty()
+                    && eval::parse_number(word, false).is_some() =>
+                {
+                    Some(finding(
+                        *span,
+                        format!(
+                            "default of {} symbol `{}` should be {}, not the number `{}`",
+                            kind.as_str(),
+                            name,
+                            tristate_values(kind),
+                            word
+                        ),
+                    ))
+                }
+                None => None,
+            }
+        }
+        (TypeKind::Int | TypeKind::Hex, Expr::Symbol(word, span)) => {
+            let what = match index.symbol_type(word) {
+                Some(TypeKind::Int | TypeKind::Hex) => return None,
+                Some(other) => format!("has type {}", other.as_str()),
+                None if matches!(word.as_str(), "y" | "m" | "n") => "is a tristate value".into(),
+                None => return None,
+            };
+            Some(finding(
+                *span,
+                format!(
+                    "`{}` {}, not a valid default of {} symbol `{}`",
+                    word,
+                    what,
+                    kind.as_str(),
+                    name
+                ),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// The values a default of a bool or tristate symbol can take, for messages.
+fn tristate_values(kind: TypeKind) -> &'static str {
+    match kind {
+        TypeKind::Tristate => "`y`, `m`, `n` or a bool or tristate symbol",
+        _ => "`y`, `n` or a bool or tristate symbol",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// comparison-types
+// ---------------------------------------------------------------------------
+
+/// Comparisons whose operands don't match the compared symbol's type: a
+/// bool/tristate compared with a quoted `"y"`, a string symbol compared with
+/// an unquoted word that is not a symbol, and `<`/`>` on non-numbers.
+fn check_comparisons(index: &WorldIndex, entries: &[Entry], out: &mut Vec<Finding>) {
+    for entry in entries {
+        if let Entry::If(i) = entry {
+            check_expr(index, &i.condition, out);
+        }
+        for attr in entry.attributes() {
+            for expr in attr.expressions() {
+                check_expr(index, expr, out);
+            }
+        }
+        check_comparisons(index, entry.children(), out);
+    }
+}
+
+fn check_expr(index: &WorldIndex, expr: &Expr, out: &mut Vec<Finding>) {
+    match expr {
+        Expr::Symbol(..) | Expr::StringLit(..) => {}
+        Expr::Not(e) | Expr::Paren(e) => check_expr(index, e, out),
+        Expr::And(a, b) | Expr::Or(a, b) => {
+            check_expr(index, a, out);
+            check_expr(index, b, out);
+        }
+        Expr::Eq(a, b) | Expr::NotEq(a, b) => {
+            check_equality(index, a, b, out);
+            check_equality(index, b, a, out);
+        }
+        Expr::Less(a, b) | Expr::LessEq(a, b) | Expr::Greater(a, b) | Expr::GreaterEq(a, b) => {
+            let hex = [a, b]
+                .iter()
+                .any(|e| operand_type(index, e) == Some(TypeKind::Hex));
+            for operand in [a, b] {
+                if let Some(what) = non_numeric(index, operand, hex) {
+                    out.push(Finding {
+                        code: codes::COMPARISON_TYPES,
+                        message: format!(
+                            "`{}` is {}; `<`, `>`, `<=` and `>=` compare int/hex values",
+                            operand, what
+                        ),
+                        span: operand.span(),
+                        severity: DiagSeverity::Warning,
+                        fixes: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Check `sym = other` from the side of `sym`.
+fn check_equality(index: &WorldIndex, sym: &Expr, other: &Expr, out: &mut Vec<Finding>) {
+    let Expr::Symbol(name, _) = sym else {
+        return;
+    };
+    match (index.symbol_type(name), other) {
+        (Some(TypeKind::Bool | TypeKind::Tristate), Expr::StringLit(text, span))
+            if matches!(text.as_str(), "y" | "m" | "n") =>
+        {
+            out.push(Finding::with_fix(
+                codes::COMPARISON_TYPES,
+                *span,
+                format!(
+                    "`{}` is a {} symbol; compare it with unquoted `{}`",
+                    name,
+                    index.symbol_type(name).map_or("", TypeKind::as_str),
+                    text
+                ),
+                "Remove quotes",
+                vec![Edit {
+                    span: *span,
+                    new_text: text.clone(),
+                }],
+            ));
+        }
+        (Some(TypeKind::String), Expr::Symbol(word, span)) if is_bare_word(index, word) => {
+            out.push(Finding::with_fix(
+                codes::COMPARISON_TYPES,
+                *span,
+                format!(
+                    "`{}` is not a symbol; string `{}` is probably meant to be compared with \"{}\"",
+                    word, name, word
+                ),
+                "Add quotes",
+                vec![Edit {
+                    span: *span,
+                    new_text: format!("\"{}\"", word),
+                }],
+            ));
+        }
+        _ => {}
+    }
+}
+
+/// A word that no file defines and that is not a constant or a macro.
+fn is_bare_word(index: &WorldIndex, word: &str) -> bool {
+    !matches!(word, "y" | "m" | "n")
+        && !word.starts_with("$(")
+        && eval::parse_number(word, false).is_none()
+        && index.get_definitions(word).is_empty()
+}
+
+fn operand_type(index: &WorldIndex, expr: &Expr) -> Option<TypeKind> {
+    match expr {
+        Expr::Symbol(name, _) => index.symbol_type(name),
+        _ => None,
+    }
+}
+
+/// Why `expr` can't be an operand of an ordering comparison, if it can't.
+fn non_numeric(index: &WorldIndex, expr: &Expr, hex: bool) -> Option<String> {
+    match expr {
+        Expr::Paren(e) => non_numeric(index, e, hex),
+        Expr::Symbol(name, _) if name.starts_with("$(") => None,
+        Expr::Symbol(name, _) => match index.symbol_type(name) {
+            Some(TypeKind::Int | TypeKind::Hex) => None,
+            Some(kind) => Some(format!("a {} symbol", kind.as_str())),
+            None if !index.get_definitions(name).is_empty() => None,
+            None => eval::parse_number(name, hex)
+                .is_none()
+                .then(|| "not a number".to_string()),
+        },
+        Expr::StringLit(text, _) => eval::parse_number(text, hex)
+            .is_none()
+            .then(|| "not a number".to_string()),
+        _ => Some("a boolean expression".into()),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// menu-titles
+// ---------------------------------------------------------------------------
+
+/// Among the entries of one block: menus with an empty prompt, menus titled
+/// like an earlier sibling, and comments repeating the title of the menu next
+/// to them or of the menu they open. An `if` block is a block of its own, as
+/// menus guarded by different conditions often share a title.
+fn check_menu_titles(src: &str, entries: &[Entry], out: &mut Vec<Finding>) {
+    let same = |a: &str, b: &str| a.trim().eq_ignore_ascii_case(b.trim());
+    let mut titles: Vec<&str> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        match entry {
+            Entry::Menu(m) if m.prompt.trim().is_empty() => out.push(Finding {
+                code: codes::MENU_TITLES,
+                message: "menu has an empty prompt".into(),
+                span: m.prompt_span,
+                severity: DiagSeverity::Warning,
+                fixes: Vec::new(),
+            }),
+            Entry::Menu(m) => {
+                if titles.iter().any(|t| same(t, &m.prompt)) {
+                    out.push(Finding {
+                        code: codes::MENU_TITLES,
+                        message: format!(
+                            "menu \"{}\" has the title of an earlier menu in the same block",
+                            m.prompt
+                        ),
+                        span: m.prompt_span,
+                        severity: DiagSeverity::Warning,
+                        fixes: Vec::new(),
+                    });
+                }
+                titles.push(&m.prompt);
+                if let Some(Entry::Comment(c)) = m.entries.first()
+                    && same(&c.prompt, &m.prompt)
+                {
+                    out.push(comment_repeats_title(src, c, "the menu it opens"));
+                }
+            }
+            Entry::Comment(c) => {
+                let menu_next_to = [i.checked_sub(1), Some(i + 1)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|j| entries.get(j))
+                    .any(|e| matches!(e, Entry::Menu(m) if same(&m.prompt, &c.prompt)));
+                if menu_next_to {
+                    out.push(comment_repeats_title(src, c, "the menu next to it"));
+                }
+            }
+            _ => {}
+        }
+        check_menu_titles(src, entry.children(), out);
+    }
+}
+
+/// A comment repeating the title of `menu`, fixed by removing its lines.
+fn comment_repeats_title(src: &str, c: &CommentEntry, menu: &str) -> Finding {
+    let start = src[..c.span.start].rfind('\n').map_or(0, |p| p + 1);
+    let end = src[c.span.end..]
+        .find('\n')
+        .map_or(src.len(), |p| c.span.end + p + 1);
+    Finding::with_fix(
+        codes::MENU_TITLES,
+        c.prompt_span,
+        format!("comment \"{}\" repeats the title of {}", c.prompt, menu),
+        "Remove the comment",
+        vec![Edit {
+            span: Span::new(start, end),
+            new_text: String::new(),
+        }],
+    )
+}
+
+// ---------------------------------------------------------------------------
+// end-comment
+// ---------------------------------------------------------------------------
+
+/// Comments after `endmenu`, `endif` and `endchoice` that name another
+/// block than the one closed, fixed by naming the right one.
+fn check_end_comments(src: &str, entries: &[Entry], out: &mut Vec<Finding>) {
+    for end in end_comments::end_comments(src, entries) {
+        let Some(comment) = end.comment.filter(|_| end.is_stale(src)) else {
+            continue;
+        };
+        let keyword = &src[end.keyword.start..end.keyword.end];
+        out.push(Finding::with_fix(
+            codes::END_COMMENT,
+            comment,
+            format!(
+                "comment after `{}` does not name the block it closes (`{}`)",
+                keyword, end.expected
+            ),
+            "Update the comment",
+            end.edit(src).into_iter().collect(),
+        ));
+    }
+}
+
+// ---------------------------------------------------------------------------
+// symbol-naming
+// ---------------------------------------------------------------------------
+
+/// Symbols defined in the file against the naming conventions of `rules`:
+/// lowercase letters, a pattern the name must match, and prefixes it must
+/// start with.
+fn check_naming(entries: &[Entry], rules: &[&NamingRule], out: &mut Vec<Finding>) {
+    for entry in entries {
+        let name = match entry {
+            Entry::Config(c) | Entry::MenuConfig(c) => Some((&c.name, c.name_span)),
+            Entry::Choice(c) => c.name.as_ref().map(|(name, span)| (name, *span)),
+            _ => None,
+        };
+        if let Some((name, span)) = name {
+            let mut messages = Vec::new();
+            if rules.iter().any(|r| r.uppercase) && name.chars().any(|c| c.is_lowercase()) {
+                messages.push(format!("symbol `{}` has lowercase letters", name));
+            }
+            for rule in rules {
+                if let Some(pattern) = &rule.pattern
+                    && !pattern.is_match(name)
+                {
+                    messages.push(format!(
+                        "symbol `{}` does not match the naming pattern `{}`",
+                        name, pattern.source
+                    ));
+                }
+                if !rule.prefixes.is_empty() && !rule.prefixes.iter().any(|p| name.starts_with(p)) {
+                    let prefixes: Vec<String> =
+                        rule.prefixes.iter().map(|p| format!("`{}`", p)).collect();
+                    messages.push(format!(
+                        "symbol `{}` does not start with {}",
+                        name,
+                        match prefixes.as_slice() {
+                            [one] => one.clone(),
+                            _ => format!("any of {}", prefixes.join(", ")),
+                        }
+                    ));
+                }
+            }
+            let mut seen = Vec::new();
+            for message in messages {
+                // Rules for nested paths may repeat a convention.
+                if seen.contains(&message) {
+                    continue;
+                }
+                seen.push(message.clone());
+                out.push(Finding {
+                    code: codes::SYMBOL_NAMING,
+                    message,
+                    span,
+                    severity: DiagSeverity::Warning,
+                    fixes: Vec::new(),
+                });
+            }
+        }
+        check_naming(entry.children(), rules, out);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// whitespace
+// ---------------------------------------------------------------------------
+
+/// Attribute lines indented with spaces, trailing whitespace, and help lines
+/// indented less than the first help line (which silently ends the help text
+/// in kconfig).
+fn check_whitespace(fa: &FileAnalysis, out: &mut Vec<Finding>) {
+    let src = fa.source.as_str();
+
+    let mut attr_starts = Vec::new();
+    collect_attr_starts(&fa.file.entries, &mut attr_starts);
+    for start in attr_starts {
+        let line_start = src[..start].rfind('\n').map_or(0, |p| p + 1);
+        let indent = &src[line_start..start];
+        if !indent.trim().is_empty() || indent == "\t" || !indent.contains(' ') {
+            continue;
+        }
+        let span = Span::new(line_start, start);
+        out.push(Finding::with_fix(
+            codes::WHITESPACE,
+            span,
+            "attribute is indented with spaces instead of a tab".into(),
+            "Indent with a tab",
+            vec![Edit {
+                span,
+                new_text: "\t".into(),
+            }],
+        ));
+    }
+
+    let mut offset = 0;
+    for line in src.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let trimmed = content.trim_end_matches([' ', '\t']);
+        if trimmed.len() < content.len() {
+            let span = Span::new(offset + trimmed.len(), offset + content.len());
+            out.push(Finding::with_fix(
+                codes::WHITESPACE,
+                span,
+                "trailing whitespace".into(),
+                "Remove trailing whitespace",
+                vec![Edit {
+                    span,
+                    new_text: String::new(),
+                }],
+            ));
+        }
+        offset += line.len();
+    }
+
+    let mut help_starts = Vec::new();
+    collect_help_starts(&fa.file.entries, &mut help_starts);
+    for help in help_starts {
+        check_help_indent(src, help, out);
+    }
+}
+
+fn check_help_indent(src: &str, help: usize, out: &mut Vec<Finding>) {
+    let Some(nl) = src[help..].find('\n') else {
+        return;
+    };
+    let mut offset = help + nl + 1;
+    let mut base: Option<(usize, &str)> = None;
+    for line in src[offset..].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let body = content.trim_start();
+        if body.is_empty() {
+            offset += line.len();
+            continue;
+        }
+        let indent = &content[..content.len() - body.len()];
+        let width = indent_width(indent);
+        match base {
+            None => base = Some((width, indent)),
+            Some((base_width, base_indent)) if width < base_width => {
+                // A less-indented line that still looks like help prose (not
+                // a keyword) was meant to continue the help text.
+                let first_word = body.split_whitespace().next().unwrap_or("");
+                if width > 0 && !is_statement_keyword(first_word) {
+                    let span = Span::new(offset, offset + indent.len());
+                    out.push(Finding::with_fix(
+                        codes::WHITESPACE,
+                        span,
+                        "help line is indented less than the first help line, which ends the help text".into(),
+                        "Align with the help text",
+                        vec![Edit {
+                            span,
+                            new_text: base_indent.to_string(),
+                        }],
+                    ));
+                }
+                return;
+            }
+            Some(_) => {}
+        }
+        offset += line.len();
+    }
+}
+
+/// Indentation width with tabs expanded to 8 columns, as kconfig measures it.
+pub fn indent_width(indent: &str) -> usize {
+    indent.chars().fold(0, |w, c| match c {
+        '\t' => (w & !7) + 8,
+        _ => w + 1,
+    })
+}
+
+fn is_statement_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "config"
+            | "menuconfig"
+            | "choice"
+            | "endchoice"
+            | "comment"
+            | "menu"
+            | "endmenu"
+            | "if"
+            | "endif"
+            | "source"
+            | "osource"
+            | "rsource"
+            | "orsource"
+            | "mainmenu"
+            | "bool"
+            | "tristate"
+            | "string"
+            | "hex"
+            | "int"
+            | "prompt"
+            | "default"
+            | "def_bool"
+            | "def_tristate"
+            | "def_int"
+            | "def_hex"
+            | "def_string"
+            | "depends"
+            | "select"
+            | "imply"
+            | "visible"
+            | "range"
+            | "help"
+            | "modules"
+            | "transitional"
+            | "optional"
+            | "option"
+    )
+}
+
+fn collect_attr_starts(entries: &[Entry], out: &mut Vec<usize>) {
+    for entry in entries {
+        out.extend(entry.attributes().iter().map(|a| a.span().start));
+        collect_attr_starts(entry.children(), out);
+    }
+}
+
+/// Offsets of the `help` keywords of all entries.
+fn collect_help_starts(entries: &[Entry], out: &mut Vec<usize>) {
+    for entry in entries {
+        for attr in entry.attributes() {
+            if let Attribute::Help(h) = attr {
+                out.push(h.span.start);
+            }
+        }
+        collect_help_starts(entry.children(), out);
+    }
+}
+
+/// Start offsets of every entry in the file, in source order. An entry
+/// extends from its start to the start of the next one.
+pub fn entry_starts(entries: &[Entry]) -> Vec<usize> {
+    fn walk(entries: &[Entry], out: &mut Vec<usize>) {
+        for entry in entries {
+            out.push(entry.span().start);
+            walk(entry.children(), out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(entries, &mut out);
+    out.sort_unstable();
+    out
+}
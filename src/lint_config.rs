@@ -0,0 +1,119 @@
+//! Configuration for `kconfig-lsp lint`: per-path lint settings from a TOML
+//! file, and a baseline of accepted problems.
+//!
+//! ```toml
+//! whitespace = true
+//!
+//! [[paths]]
+//! prefix = "drivers/"
+//! disable = []
+//!
+//! [[paths]]
+//! prefix = "vendor/"
+//! whitespace = false
+//! disable = ["default-quoting"]
+//!
+//! [[naming]]
+//! path = "soc/"
+//! prefixes = ["SOC_"]
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::settings::LintSettings;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Settings for every file, same keys as the `lint` server setting.
+    #[serde(flatten)]
+    pub base: LintSettings,
+    /// Overrides for files under a path prefix (relative to the tree root).
+    /// Later entries win over earlier ones.
+    pub paths: Vec<PathLints>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PathLints {
+    pub prefix: String,
+    pub whitespace: Option<bool>,
+    pub disable: Option<Vec<String>>,
+}
+
+impl LintConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// Effective settings for a file, given its path relative to the root.
+    pub fn settings_for(&self, rel: &Path) -> LintSettings {
+        let mut settings = self.base.clone();
+        for over in &self.paths {
+            if !rel.starts_with(over.prefix.trim_end_matches('/')) {
+                continue;
+            }
+            if let Some(whitespace) = over.whitespace {
+                settings.whitespace = whitespace;
+            }
+            if let Some(disable) = &over.disable {
+                settings.disable = disable.clone();
+            }
+        }
+        settings
+    }
+}
+
+/// Problems accepted when lints were adopted, so only new ones fail CI.
+///
+/// One problem per line as `path: [code] message`. Line numbers are left out
+/// so that unrelated edits don't invalidate the baseline; a problem repeated
+/// `n` times in a file is accepted up to `n` times.
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    counts: HashMap<String, usize>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut counts = HashMap::new();
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            *counts.entry(line.to_string()).or_default() += 1;
+        }
+        Self { counts }
+    }
+
+    pub fn key(rel: &Path, code: &str, message: &str) -> String {
+        format!("{}: [{}] {}", rel.display(), code, message)
+    }
+
+    /// Consume one accepted occurrence of `key`; `false` if it is new.
+    pub fn take(&mut self, key: &str) -> bool {
+        match self.counts.get_mut(key) {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Render `keys` in the baseline file format.
+    pub fn render(keys: &[String]) -> String {
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        sorted.iter().map(|k| format!("{}\n", k)).collect()
+    }
+}
@@ -1,23 +1,80 @@
 mod analysis;
 mod ast;
+mod cli;
+mod client_caps;
+mod code_action;
+mod codes;
 mod completion;
+mod config_doc;
+mod debug_dump;
 mod definition;
+mod dependency_path;
 mod diagnostics;
+mod doc_site;
+mod dotconfig;
+mod end_comments;
+mod eval;
+mod extract;
+mod format;
 mod hover;
+mod impact;
+mod include_graph;
+mod index_queue;
+mod index_stats;
+mod lazy_index;
 mod lexer;
+mod linked_editing;
+mod lint;
+mod lint_config;
+mod menu_tree;
+mod merge_config;
 mod parser;
+mod patch;
+mod preprocess;
+mod profile;
+mod project_stats;
 mod references;
+mod related;
+mod rename;
+mod report;
+mod request_metrics;
+mod resolved;
+mod sarif;
+mod search;
+mod select_chain;
 mod server;
+mod settings;
+mod shell_trust;
+mod simulate;
+mod sort_entries;
+mod sourced_files;
+mod symbol_docs;
+mod symbol_metrics;
+mod text_index;
+mod transitional;
+mod tree_diff;
+mod tree_info;
+mod type_hierarchy;
+mod uri;
+mod util;
 
-use tower_lsp::{LspService, Server};
+use std::process::ExitCode;
+
+use tower_lsp::Server;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     env_logger::init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        return code;
+    }
+
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(server::Backend::new);
+    let (service, socket) = server::service();
     Server::new(stdin, stdout, socket).serve(service).await;
+    ExitCode::SUCCESS
 }
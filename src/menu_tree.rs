@@ -0,0 +1,325 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::WorldIndex;
+use crate::ast::*;
+use crate::eval::{Evaluator, Tristate};
+use crate::util::location;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MenuTreeParams {
+    /// File to start from; defaults to the configured root Kconfig.
+    #[serde(default)]
+    pub uri: Option<Url>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MenuNodeKind {
+    Root,
+    Menu,
+    MenuConfig,
+    Config,
+    Choice,
+    Comment,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MenuNode {
+    pub kind: MenuNodeKind,
+    /// Prompt text, or the symbol name for prompt-less configs.
+    pub label: String,
+    pub symbol: Option<String>,
+    /// Every condition the entry's visibility depends on: enclosing `if`
+    /// blocks and menu dependencies, its own `depends on`, its prompt's
+    /// `if`, and `visible if`.
+    pub conditions: Vec<String>,
+    /// The `visible if` conditions of the menu and the menus around it.
+    /// They hide prompts without making the values unselectable.
+    pub visible_if: Vec<String>,
+    /// Evaluated visibility when a `.config` is loaded.
+    pub visible: Option<bool>,
+    /// Whether the dependencies hold when a `.config` is loaded, leaving
+    /// out the prompt-only conditions: a hidden entry whose dependencies
+    /// hold can still be set by `select` or a default.
+    pub selectable: Option<bool>,
+    pub location: Location,
+    pub children: Vec<MenuNode>,
+}
+
+impl MenuNode {
+    /// The nodes from this one down to the entry at `line` of `uri`, or
+    /// `None` if no entry is there. A file sourced twice is found where it
+    /// is sourced first.
+    pub fn path_to(&self, uri: &Url, line: u32) -> Option<Vec<&MenuNode>> {
+        if self.kind != MenuNodeKind::Root
+            && self.location.uri == *uri
+            && self.location.range.start.line == line
+        {
+            return Some(vec![self]);
+        }
+        self.children.iter().find_map(|child| {
+            let mut path = child.path_to(uri, line)?;
+            path.insert(0, self);
+            Some(path)
+        })
+    }
+}
+
+/// Build the menu hierarchy rooted at `root`, following `source` statements
+/// into indexed files.
+pub fn menu_tree(index: &WorldIndex, root: &Path) -> Option<MenuNode> {
+    let fa = index.files.get(root)?;
+    let eval = index.config.as_ref().map(|c| Evaluator::new(index, c));
+    let mut builder = Builder {
+        index,
+        eval,
+        stack: vec![root.to_path_buf()],
+    };
+    let label = fa
+        .file
+        .entries
+        .iter()
+        .find_map(|e| match e {
+            Entry::MainMenu(m) => Some(m.prompt.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| root.display().to_string());
+    let children = builder.entries(root, &fa.file.entries, &[], &[]);
+    Some(MenuNode {
+        kind: MenuNodeKind::Root,
+        label,
+        symbol: None,
+        conditions: Vec::new(),
+        visible_if: Vec::new(),
+        visible: None,
+        selectable: None,
+        location: location(index, root, Span::new(0, 0))?,
+        children,
+    })
+}
+
+struct Builder<'a> {
+    index: &'a WorldIndex,
+    eval: Option<Evaluator<'a>>,
+    /// Files currently being expanded, to break `source` cycles.
+    stack: Vec<PathBuf>,
+}
+
+impl Builder<'_> {
+    /// Nodes for `entries`, under the dependencies `inherited` and the
+    /// `visible if` conditions `visible_if` of the enclosing menus.
+    fn entries(
+        &mut self,
+        path: &Path,
+        entries: &[Entry],
+        inherited: &[&Expr],
+        visible_if: &[&Expr],
+    ) -> Vec<MenuNode> {
+        let mut nodes: Vec<MenuNode> = Vec::new();
+        for entry in entries {
+            match entry {
+                Entry::Config(c) | Entry::MenuConfig(c) => {
+                    let kind = if matches!(entry, Entry::MenuConfig(_)) {
+                        MenuNodeKind::MenuConfig
+                    } else {
+                        MenuNodeKind::Config
+                    };
+                    let mut conds = inherited.to_vec();
+                    let mut prompt = None;
+                    let mut prompt_if = None;
+                    for attr in &c.attributes {
+                        match attr {
+                            Attribute::DependsOn(d) => conds.push(&d.expr),
+                            Attribute::Type(TypeAttr {
+                                prompt: Some(p), ..
+                            })
+                            | Attribute::Prompt(p) => {
+                                prompt = Some(p.text.clone());
+                                prompt_if = p.condition.as_ref();
+                            }
+                            _ => {}
+                        }
+                    }
+                    let Some(node) = self.node(
+                        kind,
+                        prompt.unwrap_or_else(|| c.name.clone()),
+                        Some(c.name.clone()),
+                        &conds,
+                        prompt_if,
+                        visible_if,
+                        path,
+                        c.name_span,
+                        Vec::new(),
+                    ) else {
+                        continue;
+                    };
+                    push_node(&mut nodes, node, &conds);
+                }
+                Entry::Choice(ch) => {
+                    let mut conds = inherited.to_vec();
+                    let mut prompt = None;
+                    for attr in &ch.attributes {
+                        match attr {
+                            Attribute::DependsOn(d) => conds.push(&d.expr),
+                            Attribute::Type(TypeAttr {
+                                prompt: Some(p), ..
+                            })
+                            | Attribute::Prompt(p) => prompt = Some(p.text.clone()),
+                            _ => {}
+                        }
+                    }
+                    let children = self.entries(path, &ch.entries, &conds, visible_if);
+                    nodes.extend(self.node(
+                        MenuNodeKind::Choice,
+                        prompt.unwrap_or_else(|| "choice".into()),
+                        None,
+                        &conds,
+                        None,
+                        visible_if,
+                        path,
+                        Span::new(ch.span.start, ch.span.start + "choice".len()),
+                        children,
+                    ));
+                }
+                Entry::Comment(cm) => {
+                    let mut conds = inherited.to_vec();
+                    conds.extend(cm.attributes.iter().filter_map(|a| match a {
+                        Attribute::DependsOn(d) => Some(&d.expr),
+                        _ => None,
+                    }));
+                    nodes.extend(self.node(
+                        MenuNodeKind::Comment,
+                        cm.prompt.clone(),
+                        None,
+                        &conds,
+                        None,
+                        visible_if,
+                        path,
+                        cm.prompt_span,
+                        Vec::new(),
+                    ));
+                }
+                Entry::Menu(m) => {
+                    let mut deps = inherited.to_vec();
+                    let mut visible_if = visible_if.to_vec();
+                    for attr in &m.attributes {
+                        match attr {
+                            Attribute::DependsOn(d) => deps.push(&d.expr),
+                            Attribute::VisibleIf(v) => visible_if.push(&v.expr),
+                            _ => {}
+                        }
+                    }
+                    // `visible if` hides the menu and the prompts inside
+                    // it, but the entries' values only depend on `deps`.
+                    let children = self.entries(path, &m.entries, &deps, &visible_if);
+                    nodes.extend(self.node(
+                        MenuNodeKind::Menu,
+                        m.prompt.clone(),
+                        None,
+                        &deps,
+                        None,
+                        &visible_if,
+                        path,
+                        m.prompt_span,
+                        children,
+                    ));
+                }
+                Entry::If(i) => {
+                    let mut conds = inherited.to_vec();
+                    conds.push(&i.condition);
+                    for node in self.entries(path, &i.entries, &conds, visible_if) {
+                        push_node(&mut nodes, node, &conds);
+                    }
+                }
+                Entry::Source(s) => {
+                    let Some(target) = self.index.resolve_source(path, &s.path, s.relative) else {
+                        continue;
+                    };
+                    if self.stack.contains(&target) {
+                        continue;
+                    }
+                    let Some(target_fa) = self.index.files.get(&target) else {
+                        continue;
+                    };
+                    self.stack.push(target.clone());
+                    let sourced =
+                        self.entries(&target, &target_fa.file.entries, inherited, visible_if);
+                    self.stack.pop();
+                    nodes.extend(sourced);
+                }
+                Entry::MainMenu(_) => {}
+            }
+        }
+        nodes
+    }
+
+    /// A node whose value depends on `deps`, and whose prompt is further
+    /// conditioned on `prompt_if` and `visible_if`.
+    #[allow(clippy::too_many_arguments)]
+    fn node(
+        &self,
+        kind: MenuNodeKind,
+        label: String,
+        symbol: Option<String>,
+        deps: &[&Expr],
+        prompt_if: Option<&Expr>,
+        visible_if: &[&Expr],
+        path: &Path,
+        span: Span,
+        children: Vec<MenuNode>,
+    ) -> Option<MenuNode> {
+        let prompt_only: Vec<&Expr> = prompt_if
+            .into_iter()
+            .chain(visible_if.iter().copied())
+            .collect();
+        let selectable = self
+            .eval
+            .as_ref()
+            .map(|ev| ev.eval_all(deps.iter().copied()) != Tristate::N);
+        Some(MenuNode {
+            kind,
+            label,
+            symbol,
+            conditions: deps
+                .iter()
+                .chain(&prompt_only)
+                .map(|e| e.to_string())
+                .collect(),
+            visible_if: visible_if.iter().map(|e| e.to_string()).collect(),
+            visible: self.eval.as_ref().map(|ev| {
+                selectable == Some(true) && ev.eval_all(prompt_only.iter().copied()) != Tristate::N
+            }),
+            selectable,
+            location: location(self.index, path, span)?,
+            children,
+        })
+    }
+}
+
+/// Append `node`, nesting it under a preceding `menuconfig` whose symbol it
+/// depends on – the way menuconfig front ends display such entries.
+fn push_node(nodes: &mut Vec<MenuNode>, node: MenuNode, conds: &[&Expr]) {
+    if let Some(parent) = nodes.last_mut()
+        && parent.kind == MenuNodeKind::MenuConfig
+        && let Some(sym) = &parent.symbol
+        && conds.iter().any(|e| depends_on_symbol(e, sym))
+    {
+        push_node(&mut parent.children, node, conds);
+        return;
+    }
+    nodes.push(node);
+}
+
+fn depends_on_symbol(expr: &Expr, sym: &str) -> bool {
+    match expr {
+        Expr::Symbol(name, _) => name == sym,
+        Expr::Paren(e) => depends_on_symbol(e, sym),
+        Expr::And(a, b) => depends_on_symbol(a, sym) || depends_on_symbol(b, sym),
+        _ => false,
+    }
+}
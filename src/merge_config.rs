@@ -0,0 +1,260 @@
+//! `kconfig/mergeConfig`: the configuration `scripts/kconfig/merge_config.sh`
+//! would make of a base configuration and fragments, such as the kernel's
+//! `kvm_guest.config` or Zephyr's `OVERLAY_CONFIG` files, with the
+//! assignments each fragment overrides and the script's warnings.
+//!
+//! Like the script, fragments are appended in order to the base, and an
+//! assignment removes the earlier lines mentioning the same symbol. The
+//! merged values are then checked against the tree the way the script runs
+//! `make alldefconfig` on them, unless only merging is asked for.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::WorldIndex;
+use crate::ast::TypeKind;
+use crate::dotconfig::{self, DotConfig};
+use crate::simulate::{self, Preset};
+use crate::uri;
+use crate::util::is_word_char;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConfigParams {
+    /// The base configuration, then the fragments in the order they apply.
+    pub fragments: Vec<Url>,
+    /// Keep `y` when a later fragment sets `m` (the script's `-y`).
+    #[serde(default)]
+    pub builtin: bool,
+    /// Also warn about assignments a later fragment repeats (`-r`).
+    #[serde(default)]
+    pub warn_redundant: bool,
+    /// Skip the check against the tree (`-m`).
+    #[serde(default)]
+    pub merge_only: bool,
+    /// Where to write the merged configuration.
+    #[serde(default)]
+    pub output: Option<Url>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConfigResult {
+    /// The merged configuration, as the script hands it to `make`.
+    pub text: String,
+    pub overrides: Vec<Override>,
+    /// The script's warnings, in the order it prints them.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OverrideKind {
+    /// A later fragment sets a different value.
+    Redefined,
+    /// A later fragment sets the same value again.
+    Redundant,
+    /// A later fragment sets `m`, but `builtin` keeps the earlier `y`.
+    NotDemoted,
+}
+
+/// An assignment that replaced an earlier one of the same symbol, or was
+/// dropped in its favour.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Override {
+    pub symbol: String,
+    pub kind: OverrideKind,
+    /// The assignment in the merged configuration.
+    pub winner: Assignment,
+    /// The assignment it took the place of.
+    pub loser: Assignment,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Assignment {
+    /// The line as written, e.g. `CONFIG_FOO=y` or `# CONFIG_FOO is not set`.
+    pub text: String,
+    pub location: Option<Location>,
+}
+
+/// A line of the merged configuration, and where it came from.
+struct Line<'a> {
+    text: &'a str,
+    file: usize,
+    line: u32,
+}
+
+/// Merge `inputs`, each a path and its text, as `merge_config.sh` does,
+/// without checking the result against a tree.
+pub fn merge(
+    inputs: &[(PathBuf, String)],
+    prefix: &str,
+    params: &MergeConfigParams,
+) -> MergeConfigResult {
+    let lines_of = |file: usize| {
+        inputs[file]
+            .1
+            .lines()
+            .enumerate()
+            .map(move |(i, text)| Line {
+                text,
+                file,
+                line: i as u32,
+            })
+    };
+    let assignment = |l: &Line| Assignment {
+        text: l.text.to_string(),
+        location: uri::from_path(&inputs[l.file].0).map(|uri| Location {
+            uri,
+            range: Range {
+                start: Position::new(l.line, 0),
+                end: Position::new(l.line, l.text.len() as u32),
+            },
+        }),
+    };
+
+    let mut merged: Vec<Line> = Vec::new();
+    let mut overrides = Vec::new();
+    let mut warnings = Vec::new();
+    for (file, (path, _)) in inputs.iter().enumerate() {
+        let mut fragment: Vec<Line> = lines_of(file).collect();
+        if file == 0 {
+            merged = fragment;
+            continue;
+        }
+        let names: Vec<&str> = fragment
+            .iter()
+            .filter_map(|l| assigned(l.text, prefix).map(|(name, _)| name))
+            .collect();
+        for name in names {
+            let assigns = |l: &Line| assigned(l.text, prefix).is_some_and(|(n, _)| n == name);
+            let Some(prev) = merged.iter().position(assigns) else {
+                continue;
+            };
+            let Some(new) = fragment.iter().position(assigns) else {
+                continue;
+            };
+            let cfg = format!("{}{}", prefix, name);
+            let (prev_line, new_line) = (&merged[prev], &fragment[new]);
+            let value = |text| assigned(text, prefix).map(|(_, v)| v);
+            let kind = if params.builtin
+                && value(new_line.text) == Some("m")
+                && value(prev_line.text) == Some("y")
+            {
+                warnings.push(format!(
+                    "Previous  value: {}\nNew value:       {}\n-y passed, will not demote y to m",
+                    prev_line.text, new_line.text
+                ));
+                OverrideKind::NotDemoted
+            } else if prev_line.text != new_line.text {
+                warnings.push(format!(
+                    "Value of {} is redefined by fragment {}:\nPrevious  value: {}\nNew value:       {}",
+                    cfg,
+                    path.display(),
+                    prev_line.text,
+                    new_line.text
+                ));
+                OverrideKind::Redefined
+            } else {
+                if params.warn_redundant {
+                    warnings.push(format!(
+                        "Value of {} is redundant by fragment {}:",
+                        cfg,
+                        path.display()
+                    ));
+                }
+                OverrideKind::Redundant
+            };
+            if kind == OverrideKind::NotDemoted {
+                overrides.push(Override {
+                    symbol: name.to_string(),
+                    kind,
+                    winner: assignment(prev_line),
+                    loser: assignment(new_line),
+                });
+                fragment.retain(|l| !mentions(l.text, &cfg));
+            } else {
+                overrides.push(Override {
+                    symbol: name.to_string(),
+                    kind,
+                    winner: assignment(new_line),
+                    loser: assignment(prev_line),
+                });
+                merged.retain(|l| !mentions(l.text, &cfg));
+            }
+        }
+        merged.push(Line {
+            text: "",
+            file,
+            line: 0,
+        });
+        merged.extend(fragment);
+    }
+
+    let text = merged.iter().flat_map(|l| [l.text, "\n"]).collect();
+    MergeConfigResult {
+        text,
+        overrides,
+        warnings,
+    }
+}
+
+/// Add the script's warnings about merged values the tree does not keep:
+/// those `make alldefconfig` changes because the symbol is undefined, its
+/// dependencies fail, or something selects it.
+pub fn check(index: &WorldIndex, result: &mut MergeConfigResult, prefix: &str) {
+    let requested = DotConfig::parse(Path::new(""), &result.text, prefix);
+    let lines: Vec<(String, String)> = result
+        .text
+        .lines()
+        .filter_map(|line| Some((assigned(line, prefix)?.0.to_string(), line.to_string())))
+        .collect();
+    let names: Vec<String> = lines.iter().map(|(name, _)| name.clone()).collect();
+    let simulated = simulate::simulate(index, Preset::Defconfig, Some(&requested), Some(&names));
+    for value in simulated.values {
+        let wanted = requested.get(&value.symbol);
+        let actual = value.value.as_deref();
+        // Kconfig leaves out symbols it sets to `n` only when they are
+        // invisible, which the simulation does not tell apart.
+        if wanted == actual || (wanted == Some("n") && actual.is_none()) {
+            continue;
+        }
+        let actual = match actual {
+            None => String::new(),
+            Some("n") => format!("# {}{} is not set", prefix, value.symbol),
+            Some(v) if index.symbol_type(&value.symbol) == Some(TypeKind::String) => {
+                format!("{}{}=\"{}\"", prefix, value.symbol, v)
+            }
+            Some(v) => format!("{}{}={}", prefix, value.symbol, v),
+        };
+        let line = lines
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == value.symbol)
+            .map_or("", |(_, line)| line.as_str());
+        result.warnings.push(format!(
+            "Value requested for {}{} not in final .config\nRequested value:  {}\nActual value:     {}",
+            prefix, value.symbol, line, actual
+        ));
+    }
+}
+
+/// The symbol a line assigns, without the prefix, and its value: `n` for
+/// `# CONFIG_FOO is not set`.
+fn assigned<'a>(line: &'a str, prefix: &str) -> Option<(&'a str, &'a str)> {
+    if line.starts_with('#') {
+        return dotconfig::not_set(line, prefix).map(|name| (name, "n"));
+    }
+    let (name, value) = line.strip_prefix(prefix)?.split_once('=')?;
+    name.bytes().all(is_word_char).then_some((name, value))
+}
+
+/// Whether `line` has `cfg` followed by `=` or a space: the lines the
+/// script deletes when the symbol is assigned again.
+fn mentions(line: &str, cfg: &str) -> bool {
+    line.contains(&format!("{}=", cfg)) || line.contains(&format!("{} ", cfg))
+}
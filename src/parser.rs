@@ -1,21 +1,48 @@
 use crate::ast::*;
+use crate::codes;
 use crate::lexer::{Token, TokenKind};
+use crate::preprocess::{self, MacroAssignment};
+
+/// Most `(` and `!` an expression may nest. Deeper expressions only come
+/// from generators gone wrong, and would exhaust the stack of the recursive
+/// parser and of everything walking the tree.
+pub const MAX_EXPR_DEPTH: usize = 128;
+
+/// Most symbols and strings in one expression. Longer `&&` and `||` chains
+/// make equally deep trees.
+pub const MAX_EXPR_OPERANDS: usize = 1024;
 
 pub struct ParseResult {
     pub file: KconfigFile,
     pub diagnostics: Vec<ParseDiagnostic>,
+    /// Preprocessor variable assignments, in file order.
+    pub assignments: Vec<MacroAssignment>,
 }
 
 pub fn parse(source: &str, tokens: Vec<Token>) -> ParseResult {
+    let (errors, tokens): (Vec<Token>, Vec<Token>) = tokens
+        .into_iter()
+        .partition(|t| matches!(t.kind, TokenKind::Error(_)));
     let mut p = Parser {
         source,
         tokens,
         pos: 0,
         diagnostics: Vec::new(),
+        depth: 0,
+        operands: 0,
+        truncated: false,
+        assignments: Vec::new(),
+        statements: 0,
     };
+    for t in errors {
+        if let TokenKind::Error(message) = &t.kind {
+            p.diag(t.span, message, DiagSeverity::Warning);
+        }
+    }
     let entries = p.parse_entries(&[]);
     ParseResult {
         file: KconfigFile { entries },
+        assignments: p.assignments,
         diagnostics: p.diagnostics,
     }
 }
@@ -25,6 +52,15 @@ struct Parser<'a> {
     tokens: Vec<Token>,
     pos: usize,
     diagnostics: Vec<ParseDiagnostic>,
+    /// `(` and `!` open around the expression being parsed.
+    depth: usize,
+    /// Operands of the expression being parsed so far.
+    operands: usize,
+    /// The expression hit a limit and the rest of its line was skipped.
+    truncated: bool,
+    assignments: Vec<MacroAssignment>,
+    /// Statements parsed so far; `mainmenu` has to be the first.
+    statements: usize,
 }
 
 impl<'a> Parser<'a> {
@@ -85,6 +121,18 @@ impl<'a> Parser<'a> {
             message: msg.to_string(),
             span,
             severity,
+            code: codes::SYNTAX,
+        });
+    }
+
+    /// A statement kconfig rejects where it is, reported as an error under
+    /// [`codes::MISPLACED_STATEMENT`].
+    fn misplaced(&mut self, span: Span, message: String) {
+        self.diagnostics.push(ParseDiagnostic {
+            message,
+            span,
+            severity: DiagSeverity::Error,
+            code: codes::MISPLACED_STATEMENT,
         });
     }
 
@@ -110,6 +158,9 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_entry(&mut self) -> Option<Entry> {
+        if !matches!(self.peek(), TokenKind::Assignment | TokenKind::Macro(_)) {
+            self.statements += 1;
+        }
         match self.peek().clone() {
             TokenKind::Config => Some(self.parse_config(false)),
             TokenKind::MenuConfig => Some(self.parse_config(true)),
@@ -117,8 +168,78 @@ impl<'a> Parser<'a> {
             TokenKind::CommentKw => Some(self.parse_comment()),
             TokenKind::Menu => Some(self.parse_menu()),
             TokenKind::If => Some(self.parse_if()),
-            TokenKind::Source => Some(self.parse_source()),
-            TokenKind::MainMenu => Some(self.parse_mainmenu()),
+            TokenKind::Source => Some(self.parse_source(false, false)),
+            TokenKind::OSource => Some(self.parse_source(true, false)),
+            TokenKind::RSource => Some(self.parse_source(false, true)),
+            TokenKind::ORSource => Some(self.parse_source(true, true)),
+            TokenKind::MainMenu => {
+                if self.statements > 1 {
+                    let span = self.current_span();
+                    self.misplaced(
+                        span,
+                        "`mainmenu` must be the first statement; kconfig rejects it after others"
+                            .into(),
+                    );
+                }
+                Some(self.parse_mainmenu())
+            }
+            TokenKind::Assignment => {
+                let span = self.current_span();
+                self.assignments.extend(preprocess::parse_assignment(
+                    &self.source[span.start..span.end],
+                    span.start,
+                ));
+                self.pos += 1;
+                self.expect_newline();
+                None
+            }
+            // A line that is only a macro call, like `$(error-if,...)` in
+            // `scripts/Kconfig.include`, is expanded for its effect.
+            TokenKind::Macro(_) => {
+                self.skip_to_eol();
+                None
+            }
+            kind @ (TokenKind::EndMenu | TokenKind::EndIf | TokenKind::EndChoice) => {
+                let span = self.current_span();
+                let opener = match kind {
+                    TokenKind::EndMenu => "menu",
+                    TokenKind::EndIf => "if",
+                    _ => "choice",
+                };
+                self.misplaced(
+                    span,
+                    format!(
+                        "`{}` without a matching `{}`; remove it or add the `{}` it closes",
+                        &self.source[span.start..span.end],
+                        opener,
+                        opener
+                    ),
+                );
+                self.skip_to_eol();
+                None
+            }
+            kind if is_attribute(&kind) => {
+                let span = self.current_span();
+                let mut keyword = self.source[span.start..span.end].to_string();
+                if kind == TokenKind::Depends {
+                    keyword.push_str(" on");
+                }
+                self.misplaced(
+                    span,
+                    format!(
+                        "`{}` outside any entry; move it under the `config`, `choice` or `menu` \
+                         it belongs to",
+                        keyword
+                    ),
+                );
+                if kind == TokenKind::Help {
+                    // Skip the text too, so its lines are not each an error.
+                    self.parse_help();
+                } else {
+                    self.skip_to_eol();
+                }
+                None
+            }
             _ => {
                 let span = self.current_span();
                 self.diag(span, "unexpected token at top level", DiagSeverity::Error);
@@ -133,6 +254,7 @@ impl<'a> Parser<'a> {
     // -----------------------------------------------------------------------
 
     fn parse_config(&mut self, is_menuconfig: bool) -> Entry {
+        let doc_comment = self.leading_comment();
         let start_span = self.current_span();
         self.pos += 1; // skip `config` / `menuconfig`
 
@@ -140,12 +262,13 @@ impl<'a> Parser<'a> {
         self.expect_newline();
 
         let attributes = self.parse_config_attributes();
-        let span = start_span.merge(attributes.last().map(attr_span).unwrap_or(name_span));
+        let span = start_span.merge(attributes.last().map(Attribute::span).unwrap_or(name_span));
 
         let entry = ConfigEntry {
             name,
             name_span,
             attributes,
+            doc_comment,
             span,
         };
         if is_menuconfig {
@@ -155,6 +278,28 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Collect the block of whole-line `#` comments immediately preceding the
+    /// current token (no blank line in between).
+    fn leading_comment(&self) -> Option<String> {
+        let mut lines = Vec::new();
+        let mut i = self.pos;
+        while i >= 2 {
+            let (TokenKind::Newline, TokenKind::LineComment(text)) =
+                (&self.tokens[i - 1].kind, &self.tokens[i - 2].kind)
+            else {
+                break;
+            };
+            if i > 2 && self.tokens[i - 3].kind != TokenKind::Newline {
+                break; // trailing comment on an earlier statement
+            }
+            lines.push(text.trim_start_matches('#').trim());
+            i -= 2;
+        }
+        lines.reverse();
+        let text = lines.join("\n").trim().to_string();
+        if text.is_empty() { None } else { Some(text) }
+    }
+
     fn parse_config_attributes(&mut self) -> Vec<Attribute> {
         let mut attrs = Vec::new();
         loop {
@@ -170,6 +315,9 @@ impl<'a> Parser<'a> {
                 TokenKind::Default => attrs.push(self.parse_default_attr()),
                 TokenKind::DefBool => attrs.push(self.parse_def_type_attr(TypeKind::Bool)),
                 TokenKind::DefTristate => attrs.push(self.parse_def_type_attr(TypeKind::Tristate)),
+                TokenKind::DefInt => attrs.push(self.parse_def_type_attr(TypeKind::Int)),
+                TokenKind::DefHex => attrs.push(self.parse_def_type_attr(TypeKind::Hex)),
+                TokenKind::DefString => attrs.push(self.parse_def_type_attr(TypeKind::String)),
                 TokenKind::Depends => attrs.push(self.parse_depends_on()),
                 TokenKind::Select => attrs.push(self.parse_select_imply(true)),
                 TokenKind::Imply => attrs.push(self.parse_select_imply(false)),
@@ -194,6 +342,7 @@ impl<'a> Parser<'a> {
                     self.expect_newline();
                     attrs.push(Attribute::Optional(span));
                 }
+                TokenKind::OptionKw => attrs.push(self.parse_option()),
                 _ => break,
             }
         }
@@ -213,6 +362,34 @@ impl<'a> Parser<'a> {
         Attribute::Type(TypeAttr { kind, prompt, span })
     }
 
+    /// `option <name>[=<value>]`, e.g. `option env="ARCH"`.
+    fn parse_option(&mut self) -> Attribute {
+        let start = self.current_span();
+        self.pos += 1; // skip `option`
+        let (name, name_span) = self.expect_ident();
+        let mut span = start.merge(name_span);
+        let mut value = None;
+        if *self.peek() == TokenKind::Eq {
+            self.pos += 1;
+            let value_span = self.current_span();
+            match self.peek().clone() {
+                TokenKind::StringLit(s) | TokenKind::Ident(s) => {
+                    self.pos += 1;
+                    value = Some(s);
+                    span = span.merge(value_span);
+                }
+                _ => self.diag(value_span, "expected option value", DiagSeverity::Error),
+            }
+        }
+        self.expect_newline();
+        Attribute::Option(OptionAttr {
+            name,
+            name_span,
+            value,
+            span,
+        })
+    }
+
     fn parse_prompt_attr(&mut self) -> Attribute {
         let start = self.current_span();
         self.pos += 1; // skip `prompt`
@@ -315,15 +492,15 @@ impl<'a> Parser<'a> {
         self.pos += 1; // skip `help`
         self.skip_to_eol();
 
-        let help_text = self.consume_help_text();
-        let end_offset = start.end + help_text.len();
+        let (help_text, content_end) = self.consume_help_text();
         Attribute::Help(HelpAttr {
             text: help_text,
-            span: start.merge(Span::new(start.start, end_offset)),
+            span: start.merge(Span::new(start.start, content_end.unwrap_or(start.end))),
         })
     }
 
-    fn consume_help_text(&mut self) -> String {
+    /// The help text, and the offset where its last non-blank line ends.
+    fn consume_help_text(&mut self) -> (String, Option<usize>) {
         let mut lines: Vec<&str> = Vec::new();
         let mut base_indent: Option<usize> = None;
 
@@ -335,12 +512,14 @@ impl<'a> Parser<'a> {
             .map_or(token_offset, |p| p + 1);
         let remaining = &src[raw_start..];
 
-        let mut consumed = 0usize;
-        for raw_line in remaining.lines() {
+        let mut end_offset = raw_start;
+        let mut content_end = None;
+        for raw in remaining.split_inclusive('\n') {
+            let raw_line = raw.trim_end_matches('\n').trim_end_matches('\r');
             let trimmed = raw_line.trim_start();
             if trimmed.is_empty() {
                 lines.push("");
-                consumed += raw_line.len() + 1;
+                end_offset += raw.len();
                 continue;
             }
             let indent = raw_line.len() - trimmed.len();
@@ -355,11 +534,11 @@ impl<'a> Parser<'a> {
                 }
             }
             lines.push(raw_line);
-            consumed += raw_line.len() + 1;
+            content_end = Some(end_offset + raw_line.trim_end().len());
+            end_offset += raw.len();
         }
 
         // Advance the token stream past the consumed help text.
-        let end_offset = raw_start + consumed;
         while self.pos < self.tokens.len() {
             if self.tokens[self.pos].span.start >= end_offset {
                 break;
@@ -369,7 +548,7 @@ impl<'a> Parser<'a> {
 
         // Strip the base indent from each line.
         let bi = base_indent.unwrap_or(0);
-        lines
+        let text = lines
             .iter()
             .map(|l| {
                 if l.len() > bi {
@@ -381,7 +560,8 @@ impl<'a> Parser<'a> {
             .collect::<Vec<_>>()
             .join("\n")
             .trim_end()
-            .to_string()
+            .to_string();
+        (text, content_end)
     }
 
     // -----------------------------------------------------------------------
@@ -391,9 +571,26 @@ impl<'a> Parser<'a> {
     fn parse_choice(&mut self) -> Entry {
         let start = self.current_span();
         self.pos += 1; // skip `choice`
+        let name = match self.peek() {
+            TokenKind::Ident(_) => Some(self.expect_ident()),
+            _ => None,
+        };
         self.expect_newline();
 
+        // Attributes may also follow members, as some trees put the
+        // `default` last; after a `config` member they are that member's.
+        let ends = [
+            TokenKind::EndChoice,
+            TokenKind::Prompt,
+            TokenKind::Default,
+            TokenKind::Depends,
+            TokenKind::Help,
+            TokenKind::Bool,
+            TokenKind::Tristate,
+            TokenKind::Optional,
+        ];
         let mut attributes = Vec::new();
+        let mut entries = Vec::new();
         loop {
             self.skip_newlines();
             match self.peek() {
@@ -408,11 +605,10 @@ impl<'a> Parser<'a> {
                     self.expect_newline();
                     attributes.push(Attribute::Optional(span));
                 }
-                _ => break,
+                TokenKind::EndChoice | TokenKind::Eof => break,
+                _ => entries.extend(self.parse_entries(&ends)),
             }
         }
-
-        let entries = self.parse_entries(&[TokenKind::EndChoice]);
         self.skip_newlines();
         let end_span = self.current_span();
         if *self.peek() == TokenKind::EndChoice {
@@ -423,6 +619,7 @@ impl<'a> Parser<'a> {
         }
 
         Entry::Choice(ChoiceEntry {
+            name,
             attributes,
             entries,
             span: start.merge(end_span),
@@ -436,7 +633,12 @@ impl<'a> Parser<'a> {
         self.expect_newline();
 
         let attributes = self.parse_comment_menu_attrs();
-        let span = start.merge(attributes.last().map(attr_span).unwrap_or(prompt_span));
+        let span = start.merge(
+            attributes
+                .last()
+                .map(Attribute::span)
+                .unwrap_or(prompt_span),
+        );
         Entry::Comment(CommentEntry {
             prompt,
             prompt_span,
@@ -507,14 +709,16 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn parse_source(&mut self) -> Entry {
+    fn parse_source(&mut self, optional: bool, relative: bool) -> Entry {
         let start = self.current_span();
-        self.pos += 1; // skip `source`
+        self.pos += 1; // skip `source`, `osource`, `rsource` or `orsource`
         let (path, path_span) = self.expect_string();
         self.expect_newline();
         Entry::Source(SourceEntry {
             path,
             path_span,
+            optional,
+            relative,
             span: start.merge(path_span),
         })
     }
@@ -536,18 +740,38 @@ impl<'a> Parser<'a> {
     //
     // Precedence (highest to lowest):
     //   1. primary: symbol, string, '(' expr ')', '!' expr
-    //   2. comparison: =, !=, <, >, <=, >=
+    //   2. comparison: =, !=, <, >, <=, >= (between symbols only, unchained)
     //   3. AND: &&
     //   4. OR:  ||
+    // `!` before a symbol takes the whole comparison the symbol starts.
     // -----------------------------------------------------------------------
 
     fn parse_expr(&mut self) -> Expr {
+        if self.depth == 0 {
+            self.operands = 0;
+            self.truncated = false;
+        }
         self.parse_or_expr()
     }
 
+    /// Report an expression over a limit and skip the rest of its line, so
+    /// the levels already open unwind without further complaints.
+    fn truncate_expr(&mut self, msg: &str) -> Expr {
+        let span = self.current_span();
+        self.diag(span, msg, DiagSeverity::Error);
+        self.truncated = true;
+        while !matches!(
+            self.peek(),
+            TokenKind::Newline | TokenKind::Eof | TokenKind::LineComment(_)
+        ) {
+            self.pos += 1;
+        }
+        Expr::Symbol(String::new(), span)
+    }
+
     fn parse_or_expr(&mut self) -> Expr {
         let mut left = self.parse_and_expr();
-        while *self.peek() == TokenKind::Or {
+        while !self.truncated && *self.peek() == TokenKind::Or {
             self.pos += 1;
             let right = self.parse_and_expr();
             left = Expr::Or(Box::new(left), Box::new(right));
@@ -557,7 +781,7 @@ impl<'a> Parser<'a> {
 
     fn parse_and_expr(&mut self) -> Expr {
         let mut left = self.parse_comparison_expr();
-        while *self.peek() == TokenKind::And {
+        while !self.truncated && *self.peek() == TokenKind::And {
             self.pos += 1;
             let right = self.parse_comparison_expr();
             left = Expr::And(Box::new(left), Box::new(right));
@@ -565,56 +789,82 @@ impl<'a> Parser<'a> {
         left
     }
 
+    /// `symbol OP symbol`, as in kconfig's grammar (`scripts/kconfig/parser.y`):
+    /// both sides are symbols or strings, and comparisons do not chain.
     fn parse_comparison_expr(&mut self) -> Expr {
-        let left = self.parse_primary_expr();
-        match self.peek().clone() {
-            TokenKind::Eq => {
-                self.pos += 1;
-                let right = self.parse_primary_expr();
-                Expr::Eq(Box::new(left), Box::new(right))
-            }
-            TokenKind::NotEq => {
-                self.pos += 1;
-                let right = self.parse_primary_expr();
-                Expr::NotEq(Box::new(left), Box::new(right))
-            }
-            TokenKind::Less => {
-                self.pos += 1;
-                let right = self.parse_primary_expr();
-                Expr::Less(Box::new(left), Box::new(right))
-            }
-            TokenKind::LessEq => {
-                self.pos += 1;
-                let right = self.parse_primary_expr();
-                Expr::LessEq(Box::new(left), Box::new(right))
-            }
-            TokenKind::Greater => {
-                self.pos += 1;
-                let right = self.parse_primary_expr();
-                Expr::Greater(Box::new(left), Box::new(right))
+        let mut left = self.parse_primary_expr();
+        let mut compared = false;
+        while let Some(op) = comparison_op(self.peek()).filter(|_| !self.truncated) {
+            let op_span = self.current_span();
+            if compared {
+                self.diag(
+                    op_span,
+                    "comparisons cannot be chained; kconfig rejects `A = B = C`",
+                    DiagSeverity::Error,
+                );
+            } else if !is_comparison_operand(&left) {
+                self.diag(
+                    left.span(),
+                    "only a symbol or a string can be compared",
+                    DiagSeverity::Error,
+                );
             }
-            TokenKind::GreaterEq => {
-                self.pos += 1;
-                let right = self.parse_primary_expr();
-                Expr::GreaterEq(Box::new(left), Box::new(right))
+            self.pos += 1;
+            let right = self.parse_primary_expr();
+            if !is_comparison_operand(&right) {
+                self.diag(
+                    right.span(),
+                    "only a symbol or a string can be compared",
+                    DiagSeverity::Error,
+                );
             }
-            _ => left,
+            left = op(Box::new(left), Box::new(right));
+            compared = true;
         }
+        left
     }
 
     fn parse_primary_expr(&mut self) -> Expr {
+        if self.truncated {
+            return Expr::Symbol(String::new(), self.current_span());
+        }
+        let nests = matches!(self.peek(), TokenKind::Not | TokenKind::OpenParen);
+        if nests && self.depth >= MAX_EXPR_DEPTH {
+            return self.truncate_expr(&format!(
+                "expression nested more than {} levels deep; the rest of the line is ignored",
+                MAX_EXPR_DEPTH
+            ));
+        }
+        if !nests {
+            self.operands += 1;
+            if self.operands > MAX_EXPR_OPERANDS {
+                return self.truncate_expr(&format!(
+                    "expression has more than {} operands; the rest of the line is ignored",
+                    MAX_EXPR_OPERANDS
+                ));
+            }
+        }
         match self.peek().clone() {
             TokenKind::Not => {
                 self.pos += 1;
-                let inner = self.parse_primary_expr();
+                self.depth += 1;
+                // `!A = B` negates the comparison: kconfig's grammar only
+                // compares symbols, so the `=` cannot apply to `!A`.
+                let inner = match self.peek() {
+                    TokenKind::Not | TokenKind::OpenParen => self.parse_primary_expr(),
+                    _ => self.parse_comparison_expr(),
+                };
+                self.depth -= 1;
                 Expr::Not(Box::new(inner))
             }
             TokenKind::OpenParen => {
                 self.pos += 1;
+                self.depth += 1;
                 let inner = self.parse_expr();
+                self.depth -= 1;
                 if *self.peek() == TokenKind::CloseParen {
                     self.pos += 1;
-                } else {
+                } else if !self.truncated {
                     let span = self.current_span();
                     self.diag(span, "expected `)`", DiagSeverity::Error);
                 }
@@ -746,12 +996,60 @@ impl<'a> Parser<'a> {
     }
 }
 
+type Comparison = fn(Box<Expr>, Box<Expr>) -> Expr;
+
+fn comparison_op(tk: &TokenKind) -> Option<Comparison> {
+    Some(match tk {
+        TokenKind::Eq => Expr::Eq,
+        TokenKind::NotEq => Expr::NotEq,
+        TokenKind::Less => Expr::Less,
+        TokenKind::LessEq => Expr::LessEq,
+        TokenKind::Greater => Expr::Greater,
+        TokenKind::GreaterEq => Expr::GreaterEq,
+        _ => return None,
+    })
+}
+
+fn is_comparison_operand(e: &Expr) -> bool {
+    matches!(e, Expr::Symbol(..) | Expr::StringLit(..))
+}
+
+/// Keywords that start an attribute line of an entry.
+fn is_attribute(tk: &TokenKind) -> bool {
+    matches!(
+        tk,
+        TokenKind::Bool
+            | TokenKind::Tristate
+            | TokenKind::StringType
+            | TokenKind::Hex
+            | TokenKind::Int
+            | TokenKind::Prompt
+            | TokenKind::Default
+            | TokenKind::DefBool
+            | TokenKind::DefTristate
+            | TokenKind::DefInt
+            | TokenKind::DefHex
+            | TokenKind::DefString
+            | TokenKind::Depends
+            | TokenKind::Select
+            | TokenKind::Imply
+            | TokenKind::Visible
+            | TokenKind::Range
+            | TokenKind::Help
+            | TokenKind::Modules
+            | TokenKind::Transitional
+            | TokenKind::Optional
+            | TokenKind::OptionKw
+    )
+}
+
 fn is_symbol_like_keyword(tk: &TokenKind) -> bool {
     matches!(
         tk,
         TokenKind::On
             | TokenKind::Modules
             | TokenKind::Optional
+            | TokenKind::OptionKw
             | TokenKind::Transitional
             | TokenKind::Bool
             | TokenKind::Tristate
@@ -765,6 +1063,7 @@ fn keyword_to_str(tk: &TokenKind) -> &'static str {
         TokenKind::On => "on",
         TokenKind::Modules => "modules",
         TokenKind::Optional => "optional",
+        TokenKind::OptionKw => "option",
         TokenKind::Transitional => "transitional",
         TokenKind::Bool => "bool",
         TokenKind::Tristate => "tristate",
@@ -773,19 +1072,3 @@ fn keyword_to_str(tk: &TokenKind) -> &'static str {
         _ => "",
     }
 }
-
-fn attr_span(a: &Attribute) -> Span {
-    match a {
-        Attribute::Type(t) => t.span,
-        Attribute::Prompt(p) => p.span,
-        Attribute::Default(d) => d.span,
-        Attribute::DefType(d) => d.span,
-        Attribute::DependsOn(d) => d.span,
-        Attribute::Select(s) => s.span,
-        Attribute::Imply(i) => i.span,
-        Attribute::VisibleIf(v) => v.span,
-        Attribute::Range(r) => r.span,
-        Attribute::Help(h) => h.span,
-        Attribute::Modules(s) | Attribute::Transitional(s) | Attribute::Optional(s) => *s,
-    }
-}
@@ -0,0 +1,140 @@
+//! Minimal unified-diff reader for `kconfig-lsp check-diff`: which lines a
+//! patch adds to each file, and which symbol definitions it removes.
+
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default)]
+pub struct Patch {
+    pub files: Vec<FilePatch>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FilePatch {
+    /// Path before the change; `None` for a new file.
+    pub old_path: Option<PathBuf>,
+    /// Path after the change; `None` for a deleted file.
+    pub new_path: Option<PathBuf>,
+    /// 1-based line numbers, in the new file, of added lines.
+    pub added: Vec<u32>,
+    /// `config`/`menuconfig` definitions on removed lines.
+    pub removed_symbols: Vec<RemovedSymbol>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedSymbol {
+    pub name: String,
+    /// 1-based line number in the old file.
+    pub line: u32,
+}
+
+impl FilePatch {
+    /// Whether 0-based `line` of the new file was added by the patch.
+    pub fn adds_line(&self, line: u32) -> bool {
+        self.added.binary_search(&(line + 1)).is_ok()
+    }
+}
+
+impl Patch {
+    pub fn parse(text: &str) -> Self {
+        let mut files: Vec<FilePatch> = Vec::new();
+        let mut old_line = 0u32;
+        let mut new_line = 0u32;
+        // Lines left in the current hunk, per side.
+        let mut old_left = 0u32;
+        let mut new_left = 0u32;
+
+        for line in text.lines() {
+            // A hunk whose header overstates its length ends with its file.
+            if line.starts_with("diff --git ") {
+                (old_left, new_left) = (0, 0);
+                continue;
+            }
+            let in_hunk = old_left > 0 || new_left > 0;
+            if !in_hunk && let Some(path) = line.strip_prefix("--- ") {
+                files.push(FilePatch {
+                    old_path: diff_path(path, "a/"),
+                    ..Default::default()
+                });
+                continue;
+            }
+            if !in_hunk && let Some(path) = line.strip_prefix("+++ ") {
+                if let Some(file) = files.last_mut() {
+                    file.new_path = diff_path(path, "b/");
+                }
+                continue;
+            }
+            if !in_hunk && let Some(header) = line.strip_prefix("@@ ") {
+                if let Some(((old, old_len), (new, new_len))) = hunk_header(header) {
+                    (old_line, old_left) = (old, old_len);
+                    (new_line, new_left) = (new, new_len);
+                }
+                continue;
+            }
+            if !in_hunk {
+                continue;
+            }
+            let Some(file) = files.last_mut() else {
+                continue;
+            };
+            match line.as_bytes().first() {
+                Some(b'+') => {
+                    file.added.push(new_line);
+                    new_line += 1;
+                    new_left = new_left.saturating_sub(1);
+                }
+                Some(b'-') => {
+                    if let Some(name) = defined_symbol(&line[1..]) {
+                        file.removed_symbols.push(RemovedSymbol {
+                            name: name.to_string(),
+                            line: old_line,
+                        });
+                    }
+                    old_line += 1;
+                    old_left = old_left.saturating_sub(1);
+                }
+                Some(b'\\') => {}
+                _ => {
+                    old_line += 1;
+                    new_line += 1;
+                    old_left = old_left.saturating_sub(1);
+                    new_left = new_left.saturating_sub(1);
+                }
+            }
+        }
+
+        Self { files }
+    }
+}
+
+/// The path on a `---`/`+++` line, without git's `a/`/`b/` prefix or a
+/// trailing timestamp.
+fn diff_path(raw: &str, prefix: &str) -> Option<PathBuf> {
+    let path = raw.split('\t').next().unwrap_or(raw).trim_end();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(PathBuf::from(path.strip_prefix(prefix).unwrap_or(path)))
+}
+
+/// `(start, length)` of both sides from `-a,b +c,d @@`. A missing length
+/// means one line.
+fn hunk_header(header: &str) -> Option<((u32, u32), (u32, u32))> {
+    let mut parts = header.split_whitespace();
+    let old = parts.next()?.strip_prefix('-')?;
+    let new = parts.next()?.strip_prefix('+')?;
+    let range = |s: &str| -> Option<(u32, u32)> {
+        match s.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((s.parse().ok()?, 1)),
+        }
+    };
+    Some((range(old)?, range(new)?))
+}
+
+fn defined_symbol(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    match words.next()? {
+        "config" | "menuconfig" => words.next(),
+        _ => None,
+    }
+}
@@ -0,0 +1,471 @@
+//! Kconfig's macro language: variable assignments (`CC := gcc`,
+//! `cc-option = $(success,...)`) and `$(...)` references, expanded the way
+//! `scripts/kconfig/preprocess.c` does.
+//!
+//! Variables come from assignments in the indexed files, then from the
+//! `preprocessor.variables` setting (standing in for the environment).
+//! Undefined variables expand to nothing.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::analysis::WorldIndex;
+use crate::ast::{Attribute, Entry, Span};
+use crate::include_graph::TreeOrder;
+use crate::settings::ShellPolicy;
+
+/// Deeper recursion than this is almost certainly a self-referencing
+/// variable.
+const MAX_DEPTH: usize = 64;
+
+/// How long a `$(shell,...)` command may run before it is killed.
+const SHELL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Characters that would need a shell to mean what they say. Commands are
+/// run without one, so those using them are refused.
+const SHELL_SYNTAX: &[char] = &[';', '&', '|', '$', '<', '>', '`', '\n', '\'', '"', '\\'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    /// `=`: expanded each time it is referenced.
+    Recursive,
+    /// `:=`: expanded once, at the assignment.
+    Simple,
+    /// `+=`: appended to the previous value, with a space.
+    Append,
+}
+
+#[derive(Debug, Clone)]
+pub struct MacroAssignment {
+    pub name: String,
+    pub name_span: Span,
+    pub flavor: Flavor,
+    /// Right-hand side, unexpanded.
+    pub value: String,
+}
+
+/// The assignment on `line` (without its line break), which starts at
+/// `offset` in the file: `NAME := value`, `NAME = value`, or
+/// `NAME += value`, with `NAME` made of letters, digits, `_` and `-`.
+pub fn parse_assignment(line: &str, offset: usize) -> Option<MacroAssignment> {
+    let lead = line.len() - line.trim_start().len();
+    let rest = &line[lead..];
+    let name_len = rest
+        .bytes()
+        .take_while(|b| b.is_ascii_alphanumeric() || *b == b'_' || *b == b'-')
+        .count();
+    if name_len == 0 {
+        return None;
+    }
+    let after = rest[name_len..].trim_start();
+    let (flavor, value) = if let Some(v) = after.strip_prefix(":=") {
+        (Flavor::Simple, v)
+    } else if let Some(v) = after.strip_prefix("+=") {
+        (Flavor::Append, v)
+    } else if let Some(v) = after.strip_prefix('=') {
+        (Flavor::Recursive, v)
+    } else {
+        return None;
+    };
+    let name_start = offset + lead;
+    Some(MacroAssignment {
+        name: rest[..name_len].to_string(),
+        name_span: Span::new(name_start, name_start + name_len),
+        flavor,
+        value: value.trim().to_string(),
+    })
+}
+
+/// The innermost `$(...)` around `offset`, as its span and body. Only the
+/// logical line of `offset` is scanned.
+pub fn macro_at(source: &str, offset: usize) -> Option<(Span, &str)> {
+    let bytes = source.as_bytes();
+    let (start, end) = logical_line(bytes, offset);
+    let mut best: Option<Span> = None;
+    let mut i = start;
+    while i + 1 < end {
+        if bytes[i] == b'$' && bytes[i + 1] == b'(' {
+            // An unterminated `$(` encloses nothing; the scan goes on past it.
+            if let Some(close) = closing_paren(&source[..end], i + 2) {
+                let span = Span::new(i, close + 1);
+                if span.start <= offset && offset < span.end {
+                    // Later matches are nested inside this one.
+                    best = Some(span);
+                }
+            }
+            i += 2;
+            continue;
+        }
+        i += 1;
+    }
+    best.map(|s| (s, &source[s.start + 2..s.end - 1]))
+}
+
+/// The bounds of the line around `offset`, with the lines a trailing `\`
+/// continues.
+fn logical_line(bytes: &[u8], offset: usize) -> (usize, usize) {
+    let offset = offset.min(bytes.len());
+    let line_start = |at: usize| {
+        bytes[..at]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1)
+    };
+    let line_end = |at: usize| {
+        bytes[at..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |i| at + i)
+    };
+    let continued = |end: usize| {
+        bytes[..end]
+            .strip_suffix(b"\r")
+            .unwrap_or(&bytes[..end])
+            .ends_with(b"\\")
+    };
+    let mut start = line_start(offset);
+    while start > 0 && continued(start - 1) {
+        start = line_start(start - 1);
+    }
+    let mut end = line_end(offset);
+    while end < bytes.len() && continued(end) {
+        end = line_end(end + 1);
+    }
+    (start, end)
+}
+
+/// Index of the `)` closing a `$(` whose body starts at `from`.
+fn closing_paren(text: &str, from: usize) -> Option<usize> {
+    let mut depth = 1;
+    for (i, b) in text.bytes().enumerate().skip(from) {
+        match b {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            b'\n' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split a macro body at top-level commas.
+fn split_args(body: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, b) in body.bytes().enumerate() {
+        match b {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            b',' if depth == 0 => {
+                out.push(&body[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(&body[start..]);
+    out
+}
+
+/// Expands macro text against the index's assignments and settings.
+pub struct Expander<'a> {
+    index: &'a WorldIndex,
+    order: Arc<TreeOrder>,
+    /// File and 1-based line for `$(filename)` and `$(lineno)`.
+    file: &'a Path,
+    line: u32,
+    /// Commands skipped under [`ShellPolicy::DryRun`].
+    dry_run: RefCell<Vec<String>>,
+    /// Commands allowed to run that have not run yet.
+    pending: RefCell<Vec<String>>,
+}
+
+impl<'a> Expander<'a> {
+    pub fn new(index: &'a WorldIndex, file: &'a Path, line: u32) -> Self {
+        Self {
+            index,
+            order: index.tree_order(),
+            file,
+            line,
+            dry_run: RefCell::new(Vec::new()),
+            pending: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// An expander that takes assignments in path order rather than parse
+    /// order, for expanding `source` paths while the parse order itself is
+    /// being worked out.
+    pub fn unordered(index: &'a WorldIndex, file: &'a Path) -> Self {
+        Self {
+            index,
+            order: Arc::default(),
+            file,
+            line: 0,
+            dry_run: RefCell::new(Vec::new()),
+            pending: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The `$(shell,...)` commands that expansion would have run, under
+    /// [`ShellPolicy::DryRun`].
+    pub fn dry_run_commands(&self) -> Vec<String> {
+        self.dry_run.borrow().clone()
+    }
+
+    /// Every assignment to `name`, in parse order, with its file.
+    pub fn assignments(&self, name: &str) -> Vec<(&'a Path, &'a MacroAssignment)> {
+        let mut out: Vec<(&Path, &MacroAssignment)> = self
+            .index
+            .files
+            .iter()
+            .flat_map(|(path, fa)| fa.assignments.iter().map(move |a| (path.as_path(), a)))
+            .filter(|(_, a)| a.name == name)
+            .collect();
+        out.sort_by(|a, b| {
+            self.order
+                .compare((a.0, a.1.name_span.start), (b.0, b.1.name_span.start))
+        });
+        out
+    }
+
+    /// Expand every `$(...)` in `text`.
+    pub fn expand(&self, text: &str) -> Result<String, String> {
+        self.expand_text(text, &[], 0)
+    }
+
+    fn expand_text(&self, text: &str, args: &[String], depth: usize) -> Result<String, String> {
+        if depth > MAX_DEPTH {
+            return Err("recursive variable reference".into());
+        }
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(pos) = rest.find("$(") {
+            out.push_str(&rest[..pos]);
+            let Some(end) = closing_paren(rest, pos + 2) else {
+                return Err("unterminated `$(`".into());
+            };
+            out.push_str(&self.call(&rest[pos + 2..end], args, depth + 1)?);
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Expand one `$(body)`.
+    fn call(&self, body: &str, args: &[String], depth: usize) -> Result<String, String> {
+        let parts = split_args(body);
+        let name = self.expand_text(parts[0].trim(), args, depth)?;
+        let call_args = parts[1..]
+            .iter()
+            .map(|a| self.expand_text(a, args, depth))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Ok(n) = name.parse::<usize>() {
+            return Ok(args.get(n.wrapping_sub(1)).cloned().unwrap_or_default());
+        }
+        match name.as_str() {
+            "filename" => return Ok(self.file.display().to_string()),
+            "lineno" => return Ok(self.line.to_string()),
+            "info" | "warning-if" => return Ok(String::new()),
+            "error-if" => {
+                return match call_args.as_slice() {
+                    [cond, msg] if cond == "y" => Err(msg.clone()),
+                    _ => Ok(String::new()),
+                };
+            }
+            "shell" => return self.shell(call_args.first().map_or("", String::as_str)),
+            _ => {}
+        }
+        self.variable(&name, &call_args, depth)
+    }
+
+    /// The value of a variable, with `$(1)`, `$(2)`, ... bound to `args`.
+    fn variable(&self, name: &str, args: &[String], depth: usize) -> Result<String, String> {
+        let assignments = self.assignments(name);
+        if assignments.is_empty() {
+            return Ok(self
+                .index
+                .preprocessor
+                .variables
+                .get(name)
+                .cloned()
+                .unwrap_or_default());
+        }
+        let mut value = String::new();
+        let mut recursive = false;
+        for (_, a) in assignments {
+            match a.flavor {
+                Flavor::Simple => {
+                    value = self.expand_text(&a.value, &[], depth)?;
+                    recursive = false;
+                }
+                Flavor::Recursive => {
+                    value = a.value.clone();
+                    recursive = true;
+                }
+                Flavor::Append => {
+                    let more = if recursive {
+                        a.value.clone()
+                    } else {
+                        self.expand_text(&a.value, &[], depth)?
+                    };
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(&more);
+                }
+            }
+        }
+        if recursive {
+            self.expand_text(&value, args, depth)
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn shell(&self, command: &str) -> Result<String, String> {
+        let settings = &self.index.preprocessor;
+        match settings.shell {
+            ShellPolicy::Enabled => {}
+            ShellPolicy::DryRun => {
+                self.dry_run.borrow_mut().push(command.to_string());
+                return Ok(String::new());
+            }
+            ShellPolicy::Prompt => {
+                return Err(format!(
+                    "`$(shell,{})` is not run until the workspace is trusted",
+                    command
+                ));
+            }
+            ShellPolicy::Disabled => {
+                return Err(format!(
+                    "`$(shell,{})` is not run unless `preprocessor.shell` is enabled",
+                    command
+                ));
+            }
+        }
+        let program = command.split_whitespace().next().unwrap_or("");
+        if !settings.shell_allowlist.is_empty()
+            && !settings.shell_allowlist.iter().any(|p| p == program)
+        {
+            return Err(format!(
+                "`{}` is not in `preprocessor.shellAllowlist`",
+                program
+            ));
+        }
+        match self.index.shell_outputs.get(command) {
+            Some(output) => output.clone(),
+            None => {
+                self.pending.borrow_mut().push(command.to_string());
+                Err(format!("`$(shell,{})` has not run yet", command))
+            }
+        }
+    }
+}
+
+/// The `$(shell,...)` commands the indexed files expand to that are allowed
+/// and have not run yet, for the server to run with [`run_shell`] outside
+/// the index lock and record in [`WorldIndex::shell_outputs`]. Help texts
+/// are not expanded, nor the bodies of `=` variables outside their uses.
+pub fn shell_commands(index: &WorldIndex) -> Vec<String> {
+    let mut commands = BTreeSet::new();
+    for (path, fa) in &index.files {
+        if !fa.source.contains("$(") {
+            continue;
+        }
+        let mut help = Vec::new();
+        help_spans(&fa.file.entries, &mut help);
+        let mut offset = 0;
+        for (line, text) in fa.source.split('\n').enumerate() {
+            let start = offset;
+            offset += text.len() + 1;
+            if !text.contains("$(") || help.iter().any(|h| h.start <= start && start < h.end) {
+                continue;
+            }
+            let text = match parse_assignment(text, start) {
+                Some(a) if a.flavor == Flavor::Simple => a.value,
+                Some(_) => continue,
+                None => text.to_string(),
+            };
+            let expander = Expander::new(index, path, line as u32 + 1);
+            let _ = expander.expand(&text);
+            commands.extend(expander.pending.into_inner());
+        }
+    }
+    commands.into_iter().collect()
+}
+
+fn help_spans(entries: &[Entry], out: &mut Vec<Span>) {
+    for entry in entries {
+        out.extend(entry.attributes().iter().filter_map(|a| match a {
+            Attribute::Help(h) => Some(h.span),
+            _ => None,
+        }));
+        help_spans(entry.children(), out);
+    }
+}
+
+/// Run a `$(shell,...)` command in `dir`: split at blanks and run without a
+/// shell, killed after [`SHELL_TIMEOUT`]. Like make, newlines in the output
+/// become spaces.
+pub fn run_shell(command: &str, dir: &Path) -> Result<String, String> {
+    if command.contains(SHELL_SYNTAX) {
+        return Err(format!(
+            "`{}` uses shell syntax, which is not supported",
+            command
+        ));
+    }
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(String::new());
+    };
+    let mut child = Command::new(program)
+        .args(words)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to run `{}`: {}", command, e))?;
+    let mut stdout = child.stdout.take().expect("piped stdout");
+    // Read on another thread, so that a chatty command cannot fill the pipe
+    // and outlive its deadline, nor a process it leaves behind holding the
+    // pipe open keep us waiting for the end of the output.
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut out = Vec::new();
+        let _ = stdout.read_to_end(&mut out);
+        let _ = tx.send(out);
+    });
+    let deadline = Instant::now() + SHELL_TIMEOUT;
+    let timed_out = || format!("`{}` did not finish within {:?}", command, SHELL_TIMEOUT);
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(timed_out());
+            }
+            Err(e) => return Err(format!("failed to run `{}`: {}", command, e)),
+        }
+    }
+    let output = rx
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .map_err(|_| timed_out())?;
+    let text = String::from_utf8_lossy(&output);
+    Ok(text.trim_end_matches('\n').replace('\n', " "))
+}
@@ -0,0 +1,265 @@
+//! Built-in knowledge of Kconfig trees that need more than the defaults:
+//! where their root Kconfig is, what their files are called, which
+//! environment variables their `source` paths use, and which sibling trees
+//! they pull in. The profile of a workspace is detected from files
+//! characteristic of each project unless the `profile` setting names one.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Profile {
+    /// Detect the profile from the workspace contents.
+    #[default]
+    Auto,
+    /// No project-specific defaults.
+    Generic,
+    Linux,
+    /// The Zephyr tree itself, or an application built against it.
+    Zephyr,
+    Buildroot,
+    /// ESP-IDF itself, or a project built with it.
+    #[serde(rename = "esp-idf")]
+    EspIdf,
+    #[serde(rename = "u-boot")]
+    UBoot,
+    #[serde(rename = "nuttx")]
+    NuttX,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Auto => "auto",
+            Profile::Generic => "generic",
+            Profile::Linux => "Linux kernel",
+            Profile::Zephyr => "Zephyr",
+            Profile::Buildroot => "Buildroot",
+            Profile::EspIdf => "ESP-IDF",
+            Profile::UBoot => "U-Boot",
+            Profile::NuttX => "NuttX",
+        }
+    }
+}
+
+/// The profile of the tree at `root`, from its characteristic files.
+pub fn detect(root: &Path) -> Profile {
+    let is_file = |p: &str| root.join(p).is_file();
+    let is_dir = |p: &str| root.join(p).is_dir();
+    let cmake_mentions = |what: &str| {
+        std::fs::read_to_string(root.join("CMakeLists.txt")).is_ok_and(|t| t.contains(what))
+    };
+    if is_file("Kconfig") && is_file("tools/mkimage.c") && is_dir("include/configs") {
+        Profile::UBoot
+    } else if is_file("Kconfig") && is_file("sched/Kconfig") && is_dir("include/nuttx") {
+        Profile::NuttX
+    } else if is_file("Config.in") && is_file("package/Config.in") {
+        Profile::Buildroot
+    } else if is_file("Kconfig") && is_file("init/Kconfig") && is_dir("scripts/kconfig") {
+        Profile::Linux
+    } else if is_file("Kconfig.zephyr") || (is_file("prj.conf") && cmake_mentions("Zephyr")) {
+        Profile::Zephyr
+    } else if is_file("tools/idf.py") || cmake_mentions("tools/cmake/project.cmake") {
+        Profile::EspIdf
+    } else {
+        Profile::Generic
+    }
+}
+
+/// Fill in what the profile of `root` knows and the settings leave open:
+/// settings still at their default value, and variables not set in
+/// `preprocessor.variables`. Returns the profile applied, with `Auto`
+/// resolved.
+pub fn apply(settings: &mut Settings, root: &Path) -> Profile {
+    let profile = match settings.profile {
+        Profile::Auto => detect(root),
+        named => named,
+    };
+    let defaults = Settings::default();
+    match profile {
+        Profile::Auto | Profile::Generic => {}
+        Profile::Linux => {
+            set_variable(settings, "SRCARCH", host_srcarch().into());
+            set_variable(settings, "ARCH", host_srcarch().into());
+            if let Some(version) = makefile_version(root) {
+                set_variable(settings, "KERNELVERSION", version);
+            }
+        }
+        Profile::UBoot => {
+            if let Some(version) = makefile_version(root) {
+                set_variable(settings, "UBOOTVERSION", version);
+            }
+        }
+        Profile::NuttX => {
+            // NuttX sources the application tree through `$APPSDIR`, which
+            // its build points at the `apps` checkout next to `nuttx`.
+            set_variable(settings, "APPSDIR", "../apps".into());
+            set_variable(settings, "APPSBINDIR", "../apps".into());
+            if root.join("../apps/Kconfig").is_file() {
+                add_module_root(settings, "../apps");
+            }
+        }
+        Profile::Zephyr => {
+            let base = if root.join("Kconfig.zephyr").is_file() {
+                Some(root.to_path_buf())
+            } else {
+                env_dir("ZEPHYR_BASE", "Kconfig.zephyr")
+                    .or_else(|| sibling_tree(root, "zephyr", "Kconfig.zephyr"))
+            };
+            if let Some(base) = &base {
+                let base_str = base.display().to_string();
+                set_variable(settings, "ZEPHYR_BASE", base_str.clone());
+                set_variable(settings, "srctree", base_str.clone());
+                if base != root {
+                    add_module_root(settings, &base_str);
+                    // A west workspace keeps the modules next to zephyr.
+                    if let Some(modules) = base.parent().map(|p| p.join("modules"))
+                        && modules.is_dir()
+                    {
+                        add_module_root(settings, &modules.display().to_string());
+                    }
+                    // An application without a Kconfig of its own gets
+                    // Zephyr's.
+                    if settings.root_kconfig == defaults.root_kconfig
+                        && !root.join("Kconfig").is_file()
+                    {
+                        settings.root_kconfig = base.join("Kconfig").display().to_string();
+                    }
+                }
+            }
+            if settings.binary_dir.is_none() && base.as_deref() != Some(root) {
+                settings.binary_dir = Some("build/Kconfig".into());
+            }
+        }
+        Profile::Buildroot => {
+            if settings.root_kconfig == defaults.root_kconfig {
+                settings.root_kconfig = "Config.in".into();
+            }
+            if settings.config_prefix == defaults.config_prefix {
+                settings.config_prefix = "BR2_".into();
+            }
+            add_file_names(settings, &["Config.in", "Config.in.*"]);
+        }
+        Profile::EspIdf => {
+            let idf = if root.join("tools/idf.py").is_file() {
+                Some(root.to_path_buf())
+            } else {
+                env_dir("IDF_PATH", "tools/idf.py")
+            };
+            if let Some(idf) = &idf {
+                set_variable(settings, "IDF_PATH", idf.display().to_string());
+                if idf != root {
+                    add_module_root(settings, &idf.display().to_string());
+                    if settings.root_kconfig == defaults.root_kconfig
+                        && !root.join("Kconfig").is_file()
+                    {
+                        settings.root_kconfig = idf.join("Kconfig").display().to_string();
+                    }
+                }
+            }
+            // The build lists the Kconfig files of every component in
+            // generated files that the root Kconfig sources.
+            let build = root.join("build");
+            set_variable(
+                settings,
+                "COMPONENT_KCONFIGS_SOURCE_FILE",
+                build.join("kconfigs.in").display().to_string(),
+            );
+            set_variable(
+                settings,
+                "COMPONENT_KCONFIGS_PROJBUILD_SOURCE_FILE",
+                build.join("kconfigs_projbuild.in").display().to_string(),
+            );
+            if settings.binary_dir.is_none() && idf.as_deref() != Some(root) {
+                settings.binary_dir = Some("build".into());
+            }
+            add_file_names(settings, &["Kconfig.projbuild", "kconfigs*.in"]);
+            if settings.config_path.is_none() && root.join("sdkconfig").is_file() {
+                settings.config_path = Some("sdkconfig".into());
+            }
+        }
+    }
+    profile
+}
+
+/// The `window/showMessage` text announcing a detected profile.
+pub fn describe(profile: Profile, settings: &Settings) -> String {
+    format!(
+        "Detected a {} tree: root Kconfig `{}`, `.config` prefix `{}`. Set `profile` to override.",
+        profile.name(),
+        settings.root_kconfig,
+        settings.config_prefix
+    )
+}
+
+fn set_variable(settings: &mut Settings, name: &str, value: String) {
+    settings
+        .preprocessor
+        .variables
+        .entry(name.to_string())
+        .or_insert(value);
+}
+
+fn add_module_root(settings: &mut Settings, dir: &str) {
+    if !settings.module_roots.iter().any(|r| r == dir) {
+        settings.module_roots.push(dir.to_string());
+    }
+}
+
+fn add_file_names(settings: &mut Settings, patterns: &[&str]) {
+    for pattern in patterns {
+        if !settings.file_names.iter().any(|p| p == pattern) {
+            settings.file_names.push(pattern.to_string());
+        }
+    }
+}
+
+/// The directory in environment variable `var`, if it contains `marker`.
+fn env_dir(var: &str, marker: &str) -> Option<PathBuf> {
+    let dir = PathBuf::from(std::env::var_os(var)?);
+    dir.join(marker).is_file().then_some(dir)
+}
+
+/// A `name` directory containing `marker` next to `root` or next to one of
+/// its ancestors, the way west and similar tools lay out checkouts.
+fn sibling_tree(root: &Path, name: &str, marker: &str) -> Option<PathBuf> {
+    root.ancestors()
+        .skip(1)
+        .map(|dir| dir.join(name))
+        .find(|dir| dir.join(marker).is_file())
+}
+
+/// The kernel's `SRCARCH` for the machine we run on.
+fn host_srcarch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86" | "x86_64" => "x86",
+        "aarch64" => "arm64",
+        "powerpc" | "powerpc64" => "powerpc",
+        "riscv64" => "riscv",
+        "loongarch64" => "loongarch",
+        other => other,
+    }
+}
+
+/// `VERSION.PATCHLEVEL[.SUBLEVEL]EXTRAVERSION` from the top-level Makefile,
+/// as the kernel and U-Boot builds compute their version.
+fn makefile_version(root: &Path) -> Option<String> {
+    let makefile = std::fs::read_to_string(root.join("Makefile")).ok()?;
+    let var = |name: &str| {
+        makefile.lines().find_map(|l| {
+            let (key, value) = l.split_once('=')?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+    };
+    let mut version = format!("{}.{}", var("VERSION")?, var("PATCHLEVEL")?);
+    if let Some(sub) = var("SUBLEVEL").filter(|s| !s.is_empty()) {
+        version.push('.');
+        version.push_str(&sub);
+    }
+    version.push_str(&var("EXTRAVERSION").unwrap_or_default());
+    Some(version)
+}
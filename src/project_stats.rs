@@ -0,0 +1,306 @@
+//! `kconfig-lsp stats` and `kconfig/projectStats`: where a tree's symbols
+//! live and its largest offenders – files with the most parse errors, the
+//! most referenced symbols, the longest `depends on` chains, and the longest
+//! help texts – to pick targets for cleanup.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::WorldIndex;
+
+/// Entries listed per section unless the request asks for another number.
+pub const DEFAULT_LIMIT: usize = 10;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStatsParams {
+    /// Entries listed per section.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    /// Directories by the number of symbols their Kconfig files define.
+    pub directories: Vec<DirectoryCount>,
+    /// Files by the number of parse errors and warnings.
+    pub parse_diagnostics: Vec<FileCount>,
+    pub most_referenced: Vec<SymbolCount>,
+    pub deepest_chains: Vec<DependencyChain>,
+    pub largest_help: Vec<HelpSize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryCount {
+    /// Relative to the source tree root when under it.
+    pub path: PathBuf,
+    pub files: usize,
+    /// Definitions in the directory's files, not counting subdirectories.
+    pub symbols: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCount {
+    pub path: PathBuf,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolCount {
+    pub symbol: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyChain {
+    /// The symbol, then what it depends on, down to a symbol without
+    /// dependencies.
+    pub chain: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelpSize {
+    pub symbol: String,
+    pub path: PathBuf,
+    pub lines: usize,
+    pub bytes: usize,
+}
+
+pub fn project_stats(index: &WorldIndex, params: &ProjectStatsParams) -> ProjectStats {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    let relative = |path: &Path| -> PathBuf {
+        index
+            .root
+            .as_deref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+            .to_path_buf()
+    };
+
+    let mut by_dir: BTreeMap<PathBuf, (usize, usize)> = BTreeMap::new();
+    for (path, fa) in &index.files {
+        let dir = relative(path.parent().unwrap_or(path));
+        let counts = by_dir.entry(dir).or_default();
+        counts.0 += 1;
+        counts.1 += fa.symbols.len();
+    }
+    let mut directories: Vec<DirectoryCount> = by_dir
+        .into_iter()
+        .map(|(path, (files, symbols))| DirectoryCount {
+            path,
+            files,
+            symbols,
+        })
+        .collect();
+    directories.sort_by(|a, b| b.symbols.cmp(&a.symbols).then_with(|| a.path.cmp(&b.path)));
+    directories.truncate(limit);
+
+    let mut parse_diagnostics: Vec<FileCount> = index
+        .files
+        .iter()
+        .filter(|(_, fa)| !fa.diagnostics.is_empty())
+        .map(|(path, fa)| FileCount {
+            path: relative(path),
+            count: fa.diagnostics.len(),
+        })
+        .collect();
+    parse_diagnostics.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.path.cmp(&b.path)));
+    parse_diagnostics.truncate(limit);
+
+    let mut most_referenced: Vec<SymbolCount> = index
+        .references
+        .iter()
+        .map(|(name, refs)| SymbolCount {
+            symbol: name.clone(),
+            count: refs.len(),
+        })
+        .collect();
+    most_referenced.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.symbol.cmp(&b.symbol)));
+    most_referenced.truncate(limit);
+
+    let mut chains = Chains::default();
+    let mut deepest_chains: Vec<DependencyChain> = index
+        .all_symbols
+        .iter()
+        .map(|name| DependencyChain {
+            chain: chains.chain(index, name),
+        })
+        .collect();
+    deepest_chains.sort_by(|a, b| {
+        b.chain
+            .len()
+            .cmp(&a.chain.len())
+            .then_with(|| a.chain.cmp(&b.chain))
+    });
+    deepest_chains.truncate(limit);
+
+    let mut largest_help: Vec<HelpSize> = index
+        .definitions
+        .values()
+        .flatten()
+        .filter_map(|d| {
+            let help = d.help.as_deref()?;
+            Some(HelpSize {
+                symbol: d.name.clone(),
+                path: relative(&d.file),
+                lines: help.lines().count(),
+                bytes: help.len(),
+            })
+        })
+        .collect();
+    largest_help.sort_by(|a, b| {
+        b.lines
+            .cmp(&a.lines)
+            .then(b.bytes.cmp(&a.bytes))
+            .then_with(|| a.symbol.cmp(&b.symbol))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    largest_help.truncate(limit);
+
+    ProjectStats {
+        directories,
+        parse_diagnostics,
+        most_referenced,
+        deepest_chains,
+        largest_help,
+    }
+}
+
+/// Longest `depends on` chains, memoized by symbol. Dependencies inherited
+/// from `if` blocks and menus count like the entry's own.
+#[derive(Default)]
+struct Chains {
+    /// The next symbol down each symbol's longest chain, and its length.
+    /// `None` while the symbol's chain is being worked out, which cuts
+    /// dependency loops: the chain ends where it would come back around.
+    longest: HashMap<String, Option<(usize, Option<String>)>>,
+}
+
+impl Chains {
+    fn chain(&mut self, index: &WorldIndex, name: &str) -> Vec<String> {
+        self.depth(index, name);
+        let mut chain = vec![name.to_string()];
+        while let Some(Some((_, Some(next)))) = self.longest.get(chain.last().unwrap()) {
+            if chain.contains(next) {
+                break;
+            }
+            chain.push(next.clone());
+        }
+        chain
+    }
+
+    fn depth(&mut self, index: &WorldIndex, name: &str) -> usize {
+        match self.longest.get(name) {
+            Some(Some((depth, _))) => return *depth,
+            Some(None) => return 1,
+            None => {}
+        }
+        self.longest.insert(name.to_string(), None);
+        let mut found = Vec::new();
+        for def in index.get_definitions(name) {
+            for expr in def.depends_on.iter().chain(&def.inherited_depends) {
+                expr.collect_symbols(&mut found);
+            }
+        }
+        let mut deps: Vec<String> = found
+            .into_iter()
+            .map(|(dep, _)| dep)
+            .filter(|dep| dep != name && index.definitions.contains_key(dep))
+            .collect();
+        deps.sort();
+        deps.dedup();
+        let mut best: (usize, Option<String>) = (1, None);
+        for dep in deps {
+            let depth = 1 + self.depth(index, &dep);
+            if depth > best.0 {
+                best = (depth, Some(dep));
+            }
+        }
+        let depth = best.0;
+        self.longest.insert(name.to_string(), Some(best));
+        depth
+    }
+}
+
+/// The statistics as tables, one section each.
+pub fn render_text(stats: &ProjectStats) -> String {
+    let mut out = String::new();
+    let mut section = |title: &str, rows: Vec<(String, String)>| {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "{}", title);
+        if rows.is_empty() {
+            let _ = writeln!(out, "  (none)");
+        }
+        let width = rows.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+        for (number, text) in rows {
+            let _ = writeln!(out, "  {:>width$}  {}", number, text, width = width);
+        }
+    };
+    section(
+        "Symbols by directory",
+        stats
+            .directories
+            .iter()
+            .map(|d| {
+                let files = if d.files == 1 { "file" } else { "files" };
+                let path = match d.path.as_os_str().is_empty() {
+                    true => ".".to_string(),
+                    false => d.path.display().to_string(),
+                };
+                (
+                    d.symbols.to_string(),
+                    format!("{} ({} {})", path, d.files, files),
+                )
+            })
+            .collect(),
+    );
+    section(
+        "Parse diagnostics by file",
+        stats
+            .parse_diagnostics
+            .iter()
+            .map(|f| (f.count.to_string(), f.path.display().to_string()))
+            .collect(),
+    );
+    section(
+        "Most referenced symbols",
+        stats
+            .most_referenced
+            .iter()
+            .map(|s| (s.count.to_string(), s.symbol.clone()))
+            .collect(),
+    );
+    section(
+        "Deepest dependency chains",
+        stats
+            .deepest_chains
+            .iter()
+            .map(|c| (c.chain.len().to_string(), c.chain.join(" -> ")))
+            .collect(),
+    );
+    section(
+        "Largest help texts (lines)",
+        stats
+            .largest_help
+            .iter()
+            .map(|h| {
+                (
+                    h.lines.to_string(),
+                    format!("{} ({})", h.symbol, h.path.display()),
+                )
+            })
+            .collect(),
+    );
+    out
+}
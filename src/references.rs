@@ -1,48 +1,58 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::*;
 
-use crate::analysis::WorldIndex;
+use crate::analysis::{RefKind, WorldIndex};
+use crate::ast::Span;
+use crate::settings::ReferenceSort;
+use crate::uri;
+use crate::util::{location, range, word_at_offset};
 
-pub fn find_references(index: &WorldIndex, path: &Path, pos: Position) -> Option<Vec<Location>> {
+pub fn find_references(
+    index: &WorldIndex,
+    path: &Path,
+    pos: Position,
+    include_declaration: bool,
+    sort: ReferenceSort,
+) -> Option<Vec<Location>> {
     let fa = index.files.get(path)?;
     let offset = fa.line_index.offset(pos.line, pos.character);
     let word = word_at_offset(&fa.source, offset)?;
 
-    let mut locations: Vec<Location> = Vec::new();
-
-    for d in index.get_definitions(&word) {
-        if let Some(target_fa) = index.files.get(&d.file) {
-            let (line, col) = target_fa.line_index.line_col(d.name_span.start);
-            let (end_line, end_col) = target_fa.line_index.line_col(d.name_span.end);
-            if let Ok(uri) = Url::from_file_path(&d.file) {
-                locations.push(Location {
-                    uri,
-                    range: Range {
-                        start: Position::new(line, col),
-                        end: Position::new(end_line, end_col),
-                    },
-                });
-            }
+    // (file, kind rank, span) – declarations rank before every reference kind.
+    let mut hits: Vec<(&Path, u8, Span)> = Vec::new();
+
+    let order = index.tree_order();
+    if include_declaration {
+        for d in order.sort_definitions(index.get_definitions(&word)) {
+            hits.push((&d.file, 0, d.name_span));
         }
     }
+    for r in order.sort_references(index.get_references(&word)) {
+        hits.push((&r.file, 1 + ref_kind_rank(r.kind), r.span));
+    }
 
-    for r in index.get_references(&word) {
-        if let Some(target_fa) = index.files.get(&r.file) {
-            let (line, col) = target_fa.line_index.line_col(r.span.start);
-            let (end_line, end_col) = target_fa.line_index.line_col(r.span.end);
-            if let Ok(uri) = Url::from_file_path(&r.file) {
-                locations.push(Location {
-                    uri,
-                    range: Range {
-                        start: Position::new(line, col),
-                        end: Position::new(end_line, end_col),
-                    },
-                });
-            }
+    match sort {
+        ReferenceSort::None => {}
+        ReferenceSort::File => {
+            hits.sort_by(|a, b| a.0.cmp(b.0).then(a.2.start.cmp(&b.2.start)));
+        }
+        ReferenceSort::FileAndKind => {
+            hits.sort_by(|a, b| {
+                a.0.cmp(b.0)
+                    .then(a.1.cmp(&b.1))
+                    .then(a.2.start.cmp(&b.2.start))
+            });
         }
     }
 
+    let locations: Vec<Location> = hits
+        .into_iter()
+        .filter_map(|(file, _, span)| location(index, file, span))
+        .collect();
+
     if locations.is_empty() {
         None
     } else {
@@ -50,25 +60,137 @@ pub fn find_references(index: &WorldIndex, path: &Path, pos: Position) -> Option
     }
 }
 
-fn word_at_offset(source: &str, offset: usize) -> Option<String> {
-    let bytes = source.as_bytes();
-    if offset >= bytes.len() {
-        return None;
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferencesTreeParams {
+    /// The symbol to group references of.
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// Or the symbol at `position` in this document.
+    #[serde(default)]
+    pub text_document: Option<TextDocumentIdentifier>,
+    #[serde(default)]
+    pub position: Option<Position>,
+    /// Include the definitions, as their own group.
+    #[serde(default)]
+    pub include_declaration: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferencesTree {
+    pub symbol: String,
+    pub count: usize,
+    /// By file path.
+    pub files: Vec<FileReferences>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReferences {
+    pub uri: Url,
+    pub count: usize,
+    /// Definitions first, then by reference kind as `references.sort`
+    /// orders them.
+    pub groups: Vec<ReferenceGroup>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceGroup {
+    pub kind: GroupKind,
+    pub count: usize,
+    /// In file order.
+    pub ranges: Vec<Range>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GroupKind {
+    Definition,
+    #[serde(untagged)]
+    Reference(RefKind),
+}
+
+/// The references of a symbol grouped by file, then by kind, with counts
+/// at each level – what a references panel shows as a tree.
+pub fn references_tree(
+    index: &WorldIndex,
+    params: &ReferencesTreeParams,
+) -> Option<ReferencesTree> {
+    let symbol = match (&params.symbol, &params.text_document, params.position) {
+        (Some(symbol), _, _) => symbol.clone(),
+        (None, Some(doc), Some(pos)) => {
+            let fa = index.files.get(&uri::to_path(&doc.uri)?)?;
+            let offset = fa.line_index.offset(pos.line, pos.character);
+            word_at_offset(&fa.source, offset)?
+        }
+        _ => return None,
+    };
+
+    let mut hits: Vec<(&Path, GroupKind, Span)> = Vec::new();
+    if params.include_declaration {
+        for d in index.get_definitions(&symbol) {
+            hits.push((&d.file, GroupKind::Definition, d.name_span));
+        }
     }
-    let mut start = offset;
-    while start > 0 && is_word_char(bytes[start - 1]) {
-        start -= 1;
+    for r in index.get_references(&symbol) {
+        hits.push((&r.file, GroupKind::Reference(r.kind), r.span));
     }
-    let mut end = offset;
-    while end < bytes.len() && is_word_char(bytes[end]) {
-        end += 1;
+    let rank = |kind: GroupKind| match kind {
+        GroupKind::Definition => 0,
+        GroupKind::Reference(kind) => 1 + ref_kind_rank(kind),
+    };
+
+    let mut by_file: BTreeMap<&Path, BTreeMap<u8, (GroupKind, Vec<Span>)>> = BTreeMap::new();
+    for (file, kind, span) in hits {
+        by_file
+            .entry(file)
+            .or_default()
+            .entry(rank(kind))
+            .or_insert_with(|| (kind, Vec::new()))
+            .1
+            .push(span);
     }
-    if start == end {
-        return None;
+
+    let mut files = Vec::new();
+    for (file, groups) in by_file {
+        let (Some(fa), Some(uri)) = (index.files.get(file), uri::from_path(file)) else {
+            continue;
+        };
+        let groups: Vec<ReferenceGroup> = groups
+            .into_values()
+            .map(|(kind, mut spans)| {
+                spans.sort_by_key(|s| s.start);
+                let ranges: Vec<Range> = spans.iter().map(|s| range(&fa.line_index, *s)).collect();
+                ReferenceGroup {
+                    kind,
+                    count: ranges.len(),
+                    ranges,
+                }
+            })
+            .collect();
+        files.push(FileReferences {
+            uri,
+            count: groups.iter().map(|g| g.count).sum(),
+            groups,
+        });
     }
-    Some(source[start..end].to_string())
+    Some(ReferencesTree {
+        symbol,
+        count: files.iter().map(|f| f.count).sum(),
+        files,
+    })
 }
 
-fn is_word_char(b: u8) -> bool {
-    b.is_ascii_alphanumeric() || b == b'_'
+fn ref_kind_rank(kind: RefKind) -> u8 {
+    match kind {
+        RefKind::DependsOn => 0,
+        RefKind::Select => 1,
+        RefKind::Imply => 2,
+        RefKind::Default => 3,
+        RefKind::Range => 4,
+        RefKind::VisibleIf => 5,
+        RefKind::IfCondition => 6,
+    }
 }
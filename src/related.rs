@@ -0,0 +1,203 @@
+//! `kconfig/relatedSymbols`: the symbols worth looking at next when exploring
+//! an unfamiliar subsystem – those depending on the same symbols, those
+//! selected together with it, and those whose help text mentions it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::{RefKind, SymbolRef, WorldIndex};
+use crate::ast::Entry;
+use crate::util::location;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedSymbolsParams {
+    pub symbol: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Relation {
+    /// Both depend on these symbols, directly or through an enclosing `if`
+    /// or menu.
+    SharedDependency { terms: Vec<String> },
+    /// These symbols select or imply both.
+    SelectedAlongside { by: Vec<String> },
+    /// The related symbol's help text mentions the queried one.
+    MentionedInHelp,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedSymbol {
+    pub symbol: String,
+    pub prompt: Option<String>,
+    pub relations: Vec<Relation>,
+    pub location: Option<Location>,
+}
+
+/// Symbols related to `params.symbol`, the most closely related first: by
+/// the number of shared dependencies and common selectors, then by name.
+pub fn related_symbols(index: &WorldIndex, params: &RelatedSymbolsParams) -> Vec<RelatedSymbol> {
+    let name = params.symbol.as_str();
+    let mut shared: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut alongside: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+
+    for term in dependency_terms(index, name) {
+        for r in index.get_references(term) {
+            if !matches!(r.kind, RefKind::DependsOn | RefKind::IfCondition) {
+                continue;
+            }
+            for owner in owners(index, r) {
+                shared.entry(owner).or_default().insert(term);
+            }
+        }
+    }
+
+    for r in index.get_references(name) {
+        if !matches!(r.kind, RefKind::Select | RefKind::Imply) {
+            continue;
+        }
+        for selector in owners(index, r) {
+            let targets = index
+                .get_definitions(selector)
+                .iter()
+                .flat_map(|d| d.selects.iter().chain(&d.implies));
+            for target in targets {
+                alongside
+                    .entry(target.symbol.as_str())
+                    .or_default()
+                    .insert(selector);
+            }
+        }
+    }
+
+    // The word index is case-insensitive; symbol names in help text are
+    // written in capitals, unlike the prose words some of them spell.
+    let config_name = format!("CONFIG_{}", name);
+    let mentioned: BTreeSet<&str> = index
+        .text
+        .mentioning(name)
+        .into_iter()
+        .filter(|&s| {
+            index.get_definitions(s).iter().any(|d| {
+                d.help.as_deref().is_some_and(|h| {
+                    h.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .any(|w| w == name || w == config_name)
+                })
+            })
+        })
+        .collect();
+
+    let mut related: Vec<(usize, &str, Vec<Relation>)> = Vec::new();
+    let candidates: BTreeSet<&str> = shared
+        .keys()
+        .chain(alongside.keys())
+        .chain(&mentioned)
+        .copied()
+        .filter(|&c| c != name && index.definitions.contains_key(c))
+        .collect();
+    for candidate in candidates {
+        let mut relations = Vec::new();
+        let mut score = 0;
+        if let Some(terms) = shared.get(candidate) {
+            score += terms.len();
+            relations.push(Relation::SharedDependency {
+                terms: terms.iter().map(|t| t.to_string()).collect(),
+            });
+        }
+        if let Some(by) = alongside.get(candidate) {
+            score += by.len();
+            relations.push(Relation::SelectedAlongside {
+                by: by.iter().map(|s| s.to_string()).collect(),
+            });
+        }
+        if mentioned.contains(candidate) {
+            score += 1;
+            relations.push(Relation::MentionedInHelp);
+        }
+        related.push((score, candidate, relations));
+    }
+    related.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+    related
+        .into_iter()
+        .take(params.limit.unwrap_or(usize::MAX))
+        .map(|(_, symbol, relations)| {
+            let defs = index.get_definitions(symbol);
+            RelatedSymbol {
+                symbol: symbol.to_string(),
+                prompt: defs.iter().find_map(|d| d.prompt.clone()),
+                relations,
+                location: defs
+                    .first()
+                    .and_then(|d| location(index, &d.file, d.name_span)),
+            }
+        })
+        .collect()
+}
+
+/// The symbols `name` depends on, through its own `depends on` lines and the
+/// `if` blocks and menus around its definitions.
+fn dependency_terms<'a>(index: &'a WorldIndex, name: &str) -> BTreeSet<&'a str> {
+    let mut symbols = Vec::new();
+    for d in index.get_definitions(name) {
+        for e in d.depends_on.iter().chain(&d.inherited_depends) {
+            e.collect_symbols(&mut symbols);
+        }
+    }
+    let mut terms = BTreeSet::new();
+    for (term, _) in symbols {
+        // Borrow the name from the index rather than the expression copy.
+        if let Some((term, _)) = index.definitions.get_key_value(&term)
+            && term != name
+        {
+            terms.insert(term.as_str());
+        }
+    }
+    terms
+}
+
+/// The symbols a reference applies to: the `config` it is written in, or
+/// every `config` inside the `if`, menu or choice whose condition it is in.
+fn owners<'a>(index: &'a WorldIndex, r: &SymbolRef) -> Vec<&'a str> {
+    let Some(fa) = index.files.get(&r.file) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    owners_in(&fa.file.entries, r.span.start, &mut out);
+    out
+}
+
+fn owners_in<'a>(entries: &'a [Entry], offset: usize, out: &mut Vec<&'a str>) {
+    let Some(entry) = entries.iter().find(|e| {
+        let span = e.span();
+        span.start <= offset && offset <= span.end
+    }) else {
+        return;
+    };
+    match entry {
+        Entry::Config(c) | Entry::MenuConfig(c) => out.push(&c.name),
+        _ if entry.children().iter().any(|c| {
+            let span = c.span();
+            span.start <= offset && offset <= span.end
+        }) =>
+        {
+            owners_in(entry.children(), offset, out)
+        }
+        _ => configs_in(entry.children(), out),
+    }
+}
+
+fn configs_in<'a>(entries: &'a [Entry], out: &mut Vec<&'a str>) {
+    for entry in entries {
+        match entry {
+            Entry::Config(c) | Entry::MenuConfig(c) => out.push(&c.name),
+            _ => configs_in(entry.children(), out),
+        }
+    }
+}
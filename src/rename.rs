@@ -0,0 +1,288 @@
+//! `textDocument/prepareRename` and `textDocument/rename` for symbols.
+//! Renaming only the Kconfig side of a symbol breaks every Makefile, C
+//! source and `.config` that spells it `CONFIG_FOO`, so with
+//! `rename.buildFiles` set the edit covers those too, as a change the user
+//! confirms. Renaming onto a name another architecture's tree defines is
+//! allowed but warned about, since builds of that architecture would see
+//! the two symbols as one.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::WorldIndex;
+use crate::ast::{LineIndex, Span};
+use crate::lexer;
+use crate::uri;
+use crate::util::{is_word_char, range, word_at_offset};
+
+/// Names of the files searched for `CONFIG_` spellings of a renamed symbol,
+/// besides the `.config` itself.
+pub const BUILD_FILE_NAMES: &[&str] = &[
+    "Makefile*",
+    "Kbuild*",
+    "*.mk",
+    "*.c",
+    "*.h",
+    "*.S",
+    "*defconfig",
+    "*.conf",
+];
+
+const BUILD_FILES_ANNOTATION: &str = "buildFiles";
+
+/// The build files to extend a rename to, and the prefix they spell symbols
+/// with.
+pub struct BuildFiles {
+    pub files: Vec<PathBuf>,
+    pub prefix: String,
+}
+
+/// The edits renaming a symbol in the build files, read ahead of the rename
+/// and outside the index lock by [`build_file_edits`].
+pub struct BuildEdits {
+    /// The symbol renamed; the edits are dropped if the symbol at the
+    /// rename position is another one by the time they are applied.
+    pub old_name: String,
+    pub prefix: String,
+    pub edits: Vec<(Url, Vec<TextEdit>)>,
+}
+
+/// An existing definition of the name a symbol is being renamed to.
+#[derive(Debug)]
+pub struct Collision {
+    /// `path:line`, relative to the workspace root.
+    pub place: String,
+    /// Whether the root Kconfig reaches the definition. Definitions outside
+    /// the tree, like another architecture's, only collide in the builds
+    /// that do reach them.
+    pub in_tree: bool,
+}
+
+/// The definition of `name` a rename to it would merge with, preferring one
+/// the root Kconfig reaches.
+pub fn collision(index: &WorldIndex, name: &str) -> Option<Collision> {
+    let order = index.tree_order();
+    // Without a tree every file counts as part of it.
+    let in_tree =
+        |path: &Path, offset| order.files() == 0 || order.sequence(path, offset).is_some();
+    let defs = index.get_definitions(name);
+    let def = defs
+        .iter()
+        .find(|d| in_tree(&d.file, d.name_span.start))
+        .or_else(|| defs.first())?;
+    let (line, _) = index
+        .files
+        .get(&def.file)?
+        .line_index
+        .line_col(def.name_span.start);
+    let shown = index
+        .root
+        .as_deref()
+        .and_then(|root| def.file.strip_prefix(root).ok())
+        .unwrap_or(&def.file);
+    Some(Collision {
+        place: format!("{}:{}", shown.display(), line + 1),
+        in_tree: in_tree(&def.file, def.name_span.start),
+    })
+}
+
+/// The range of the symbol at `pos`, or why it cannot be renamed.
+pub fn prepare_rename(index: &WorldIndex, path: &Path, pos: Position) -> Result<Range, String> {
+    let fa = index
+        .files
+        .get(path)
+        .ok_or_else(|| "The file is not indexed".to_string())?;
+    let offset = fa.line_index.offset(pos.line, pos.character);
+    let (name, span) = symbol_at(index, path, offset).ok_or_else(|| {
+        match word_at_offset(&fa.source, offset).as_deref() {
+            Some(w) if lexer::keyword(w).is_some() => format!("`{}` is a keyword", w),
+            Some(w @ ("y" | "m" | "n")) => format!("`{}` is a tristate value, not a symbol", w),
+            _ => "Only symbols can be renamed".to_string(),
+        }
+    })?;
+    if index.get_definitions(&name).is_empty() {
+        return Err(format!("`{}` is not defined anywhere", name));
+    }
+    Ok(range(&fa.line_index, span))
+}
+
+/// The symbol at `pos` that a rename to `new_name` would rename, or why it
+/// cannot be.
+pub fn renamed_symbol(
+    index: &WorldIndex,
+    path: &Path,
+    pos: Position,
+    new_name: &str,
+) -> Result<String, String> {
+    prepare_rename(index, path, pos)?;
+    let fa = &index.files[path];
+    let offset = fa.line_index.offset(pos.line, pos.character);
+    let (old_name, _) = symbol_at(index, path, offset).expect("checked by prepare_rename");
+    check_name(new_name)?;
+    if let Some(c) = collision(index, new_name)
+        && c.in_tree
+        && new_name != old_name
+    {
+        return Err(format!("`{}` is already defined at {}", new_name, c.place));
+    }
+    Ok(old_name)
+}
+
+/// Rename the symbol at `pos` to `new_name` in every definition and
+/// reference, and in the build files `build` was read from when given.
+/// Build file edits carry a change annotation that needs confirmation.
+pub fn rename(
+    index: &WorldIndex,
+    path: &Path,
+    pos: Position,
+    new_name: &str,
+    build: Option<&BuildEdits>,
+) -> Result<WorkspaceEdit, String> {
+    let old_name = renamed_symbol(index, path, pos, new_name)?;
+
+    let mut spans: BTreeMap<&Path, Vec<Span>> = BTreeMap::new();
+    for d in index.get_definitions(&old_name) {
+        spans.entry(&d.file).or_default().push(d.name_span);
+    }
+    for r in index.get_references(&old_name) {
+        spans.entry(&r.file).or_default().push(r.span);
+    }
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    for (file, spans) in spans {
+        let (Some(fa), Some(uri)) = (index.files.get(file), uri::from_path(file)) else {
+            continue;
+        };
+        changes.insert(uri, edits(&fa.line_index, &spans, new_name));
+    }
+
+    let Some(build) = build.filter(|b| b.old_name == old_name && !b.edits.is_empty()) else {
+        return Ok(WorkspaceEdit::new(changes));
+    };
+
+    let count: usize = build.edits.iter().map(|(_, e)| e.len()).sum();
+    let prefix = &build.prefix;
+    let mut document_changes: Vec<TextDocumentEdit> = changes
+        .into_iter()
+        .map(|(uri, edits)| TextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+            edits: edits.into_iter().map(OneOf::Left).collect(),
+        })
+        .collect();
+    document_changes.sort_by(|a, b| a.text_document.uri.cmp(&b.text_document.uri));
+    for (uri, edits) in &build.edits {
+        document_changes.push(TextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version: None,
+            },
+            edits: edits
+                .iter()
+                .map(|text_edit| {
+                    OneOf::Right(AnnotatedTextEdit {
+                        text_edit: text_edit.clone(),
+                        annotation_id: BUILD_FILES_ANNOTATION.into(),
+                    })
+                })
+                .collect(),
+        });
+    }
+    Ok(WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Edits(document_changes)),
+        change_annotations: Some(HashMap::from([(
+            BUILD_FILES_ANNOTATION.to_string(),
+            ChangeAnnotation {
+                label: format!("Rename {}{} in build files", prefix, old_name),
+                needs_confirmation: Some(true),
+                description: Some(format!(
+                    "{} occurrences in Makefiles, C sources and .config files",
+                    count
+                )),
+            },
+        )])),
+        ..Default::default()
+    })
+}
+
+/// The symbol whose definition or reference covers `offset` in `path`.
+fn symbol_at(index: &WorldIndex, path: &Path, offset: usize) -> Option<(String, Span)> {
+    let covers = |span: &Span| span.start <= offset && offset <= span.end;
+    let word = word_at_offset(&index.files.get(path)?.source, offset)?;
+    let defs = index.get_definitions(&word).iter();
+    let def = defs
+        .filter(|d| d.file == path)
+        .map(|d| d.name_span)
+        .find(covers);
+    let refs = index.get_references(&word).iter();
+    let span = def.or_else(|| refs.filter(|r| r.file == path).map(|r| r.span).find(covers))?;
+    Some((word, span))
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.bytes().all(is_word_char) {
+        Err(format!(
+            "`{}` is not a symbol name: use letters, digits and `_`",
+            name
+        ))
+    } else if lexer::keyword(name).is_some() {
+        Err(format!("`{}` is a keyword", name))
+    } else if matches!(name, "y" | "m" | "n") {
+        Err(format!("`{}` is a tristate value", name))
+    } else {
+        Ok(())
+    }
+}
+
+fn edits(line_index: &LineIndex, spans: &[Span], new_name: &str) -> Vec<TextEdit> {
+    spans
+        .iter()
+        .map(|s| TextEdit {
+            range: range(line_index, *s),
+            new_text: new_name.to_string(),
+        })
+        .collect()
+}
+
+/// Edits renaming `{prefix}{old}`, and the `{prefix}{old}_MODULE` kconfig
+/// generates for tristates, in the build files, by URI. Reads every file,
+/// so it belongs off the async runtime.
+pub fn build_file_edits(build: &BuildFiles, old: &str, new: &str) -> BuildEdits {
+    let needle = format!("{}{}", build.prefix, old);
+    let mut out = Vec::new();
+    for file in &build.files {
+        let Ok(text) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        let spans = occurrences(&text, &needle, build.prefix.len());
+        if spans.is_empty() {
+            continue;
+        }
+        if let Some(uri) = uri::from_path(file) {
+            out.push((uri, edits(&LineIndex::new(&text), &spans, new)));
+        }
+    }
+    BuildEdits {
+        old_name: old.to_string(),
+        prefix: build.prefix.clone(),
+        edits: out,
+    }
+}
+
+/// Spans of the symbol name in whole-word occurrences of `needle`, skipping
+/// the first `skip` bytes (the prefix) of each.
+fn occurrences(text: &str, needle: &str, skip: usize) -> Vec<Span> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::new();
+    for (start, _) in text.match_indices(needle) {
+        let end = start + needle.len();
+        let rest = &text[end..];
+        let after = rest.strip_prefix("_MODULE").unwrap_or(rest);
+        let word_start = start == 0 || !is_word_char(bytes[start - 1]);
+        let word_end = after.bytes().next().is_none_or(|b| !is_word_char(b));
+        if word_start && word_end {
+            out.push(Span::new(start + skip, end));
+        }
+    }
+    out
+}
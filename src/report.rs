@@ -0,0 +1,251 @@
+//! Read-only generated documents under the `kconfig-report:` URI scheme.
+//! The client sends the URI in a `kconfig/report` request and shows the
+//! returned text as a virtual document.
+//!
+//! - `kconfig-report://resolved-tree` – the root Kconfig with every `source`
+//!   inlined
+//! - `kconfig-report://symbol/<NAME>` – definitions, dependencies, and users
+//!   of a symbol
+//! - `kconfig-report://dead-branches` – `if` blocks and `depends on` lines
+//!   whose condition can never hold
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::Url;
+
+use crate::analysis::{RefKind, WorldIndex};
+use crate::ast::*;
+use crate::eval::always_false;
+use crate::include_graph;
+
+pub const SCHEME: &str = "kconfig-report";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportParams {
+    pub uri: Url,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportDocument {
+    pub uri: Url,
+    /// `kconfig` or `markdown`.
+    pub language_id: String,
+    pub text: String,
+}
+
+pub fn report(index: &WorldIndex, root: Option<&Path>, uri: &Url) -> Option<ReportDocument> {
+    if uri.scheme() != SCHEME {
+        return None;
+    }
+    let (language_id, text) = match (uri.host_str()?, uri.path().trim_start_matches('/')) {
+        ("resolved-tree", _) => ("kconfig", resolved_tree(index, root?)?),
+        ("symbol", name) if !name.is_empty() => ("markdown", symbol_report(index, name)?),
+        ("dead-branches", _) => ("markdown", dead_branches(index)),
+        _ => return None,
+    };
+    Some(ReportDocument {
+        uri: uri.clone(),
+        language_id: language_id.into(),
+        text,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// resolved-tree
+// ---------------------------------------------------------------------------
+
+fn resolved_tree(index: &WorldIndex, root: &Path) -> Option<String> {
+    index.files.get(root)?;
+    let mut out = String::new();
+    let mut stack = HashSet::new();
+    inline_file(index, root, &mut stack, &mut out);
+    Some(out)
+}
+
+/// Append `path` with its `source` lines replaced by the sourced files.
+fn inline_file(index: &WorldIndex, path: &Path, stack: &mut HashSet<PathBuf>, out: &mut String) {
+    let Some(fa) = index.files.get(path) else {
+        return;
+    };
+    stack.insert(path.to_path_buf());
+    let src = fa.source.as_str();
+    let mut pos = 0;
+    for stmt in &fa.sources {
+        let line_start = src[..stmt.path_span.start].rfind('\n').map_or(0, |p| p + 1);
+        let line_end = src[stmt.path_span.end..]
+            .find('\n')
+            .map_or(src.len(), |p| stmt.path_span.end + p + 1);
+        if line_start < pos {
+            continue;
+        }
+        out.push_str(&src[pos..line_start]);
+        pos = line_end;
+
+        match index.resolve_source(path, &stmt.path, stmt.relative) {
+            Some(target) if stack.contains(&target) => {
+                let _ = writeln!(out, "# source \"{}\" (cycle, not inlined)", stmt.path);
+            }
+            Some(target) => {
+                let _ = writeln!(out, "# >>> source \"{}\" ({})", stmt.path, target.display());
+                inline_file(index, &target, stack, out);
+                if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                let _ = writeln!(out, "# <<< {}", stmt.path);
+            }
+            None => {
+                let _ = writeln!(out, "# source \"{}\" (not found)", stmt.path);
+            }
+        }
+    }
+    out.push_str(&src[pos..]);
+    stack.remove(path);
+}
+
+// ---------------------------------------------------------------------------
+// symbol
+// ---------------------------------------------------------------------------
+
+fn symbol_report(index: &WorldIndex, name: &str) -> Option<String> {
+    let defs = index.get_definitions(name);
+    let refs = index.get_references(name);
+    if defs.is_empty() && refs.is_empty() {
+        return None;
+    }
+
+    let mut out = format!("# {}\n", name);
+    let _ = write!(out, "\n## Definitions ({})\n", defs.len());
+    for d in defs {
+        let _ = write!(out, "\n### {}\n\n", place(index, &d.file, d.name_span));
+        if let Some(tk) = d.type_kind {
+            let _ = writeln!(out, "- type: `{}`", tk.as_str());
+        }
+        if let Some(prompt) = &d.prompt {
+            let _ = writeln!(out, "- prompt: \"{}\"", prompt);
+        }
+        for e in &d.depends_on {
+            let _ = writeln!(out, "- depends on: `{}`", e);
+        }
+        for e in &d.inherited_depends {
+            let _ = writeln!(out, "- inherited: `{}`", e);
+        }
+        for e in &d.visible_if {
+            let _ = writeln!(out, "- visible if: `{}`", e);
+        }
+        for def in &d.defaults {
+            match &def.condition {
+                Some(c) => writeln!(out, "- default: `{}` if `{}`", def.value, c),
+                None => writeln!(out, "- default: `{}`", def.value),
+            }
+            .ok();
+        }
+    }
+
+    for (kind, title) in [
+        (RefKind::Select, "Selected by"),
+        (RefKind::Imply, "Implied by"),
+        (RefKind::DependsOn, "Depended on by"),
+        (RefKind::Default, "Used in defaults of"),
+        (RefKind::Range, "Used in ranges of"),
+        (RefKind::VisibleIf, "Used in visible if of"),
+        (RefKind::IfCondition, "Guards if blocks in"),
+    ] {
+        let users: Vec<String> = refs
+            .iter()
+            .filter(|r| r.kind == kind)
+            .map(|r| {
+                let owner = index
+                    .files
+                    .get(&r.file)
+                    .and_then(|fa| fa.file.config_at(r.span.start))
+                    .map(|c| format!("`{}` ", c.name))
+                    .unwrap_or_default();
+                format!("- {}({})", owner, place(index, &r.file, r.span))
+            })
+            .collect();
+        if !users.is_empty() {
+            let _ = write!(out, "\n## {}\n\n{}\n", title, users.join("\n"));
+        }
+    }
+    Some(out)
+}
+
+fn place(index: &WorldIndex, path: &Path, span: Span) -> String {
+    let line = index
+        .files
+        .get(path)
+        .map_or(0, |fa| fa.line_index.line_col(span.start).0 + 1);
+    format!("{}:{}", path.display(), line)
+}
+
+// ---------------------------------------------------------------------------
+// dead-branches
+// ---------------------------------------------------------------------------
+
+fn dead_branches(index: &WorldIndex) -> String {
+    let mut paths: Vec<&PathBuf> = index.files.keys().collect();
+    paths.sort();
+    let mut found: Vec<String> = Vec::new();
+    for path in paths {
+        // Everything in a file sourced under a dead condition is dead with
+        // it; the condition is listed where the file is sourced.
+        if include_graph::inherited_condition(index, path).is_some_and(|c| always_false(index, &c))
+        {
+            continue;
+        }
+        collect_dead(index, path, &index.files[path].file.entries, &mut found);
+    }
+
+    let mut out = String::from("# Dead branches\n\n");
+    if found.is_empty() {
+        out.push_str("No condition is always false.\n");
+    } else {
+        out.push_str(
+            "Conditions that can never hold: they test `n` or only symbols that no indexed file defines.\n\n",
+        );
+        out.push_str(&found.join("\n"));
+        out.push('\n');
+    }
+    out
+}
+
+fn collect_dead(index: &WorldIndex, path: &Path, entries: &[Entry], out: &mut Vec<String>) {
+    for entry in entries {
+        if let Entry::If(i) = entry
+            && always_false(index, &i.condition)
+        {
+            let n = i.entries.len();
+            out.push(format!(
+                "- `if {}` ({}, {} entr{})",
+                i.condition,
+                place(index, path, i.span),
+                n,
+                if n == 1 { "y" } else { "ies" }
+            ));
+            continue;
+        }
+        for attr in entry.attributes() {
+            if let Attribute::DependsOn(d) = attr
+                && always_false(index, &d.expr)
+            {
+                let what = match entry {
+                    Entry::Config(c) | Entry::MenuConfig(c) => format!("`{}`", c.name),
+                    Entry::Menu(m) => format!("menu \"{}\"", m.prompt),
+                    _ => "entry".into(),
+                };
+                out.push(format!(
+                    "- {} `depends on {}` ({})",
+                    what,
+                    d.expr,
+                    place(index, path, d.span)
+                ));
+            }
+        }
+        collect_dead(index, path, entry.children(), out);
+    }
+}
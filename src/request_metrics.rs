@@ -0,0 +1,129 @@
+//! How long the server takes to answer, per LSP method, for
+//! `kconfig/indexStats`: a report that completion is slow on some tree can
+//! come with numbers. Each request also runs under a `tracing` span named
+//! after its method, entered whenever its handler is polled; without a
+//! subscriber, its events go to the log at the `debug` level.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::Instrument;
+
+/// Latencies kept per method for the percentiles.
+const RECENT: usize = 1024;
+
+#[derive(Debug, Default)]
+pub struct RequestMetrics {
+    methods: Mutex<BTreeMap<&'static str, Latencies>>,
+}
+
+#[derive(Debug, Default)]
+struct Latencies {
+    count: u64,
+    total: Duration,
+    max: Duration,
+    /// The last [`RECENT`] latencies, oldest first.
+    recent: VecDeque<Duration>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestLatency {
+    pub method: String,
+    pub count: u64,
+    pub mean_ms: f64,
+    /// Percentiles of the last 1024 requests.
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl RequestMetrics {
+    /// Time a request to `method` until the returned timer is dropped.
+    pub fn start(&self, method: &'static str) -> RequestTimer<'_> {
+        RequestTimer {
+            metrics: self,
+            method,
+            span: tracing::debug_span!("request", method),
+            start: Instant::now(),
+        }
+    }
+
+    /// Answer a request to `method` with `answer`, timed and run in the
+    /// method's span.
+    pub async fn time<F: Future>(&self, method: &'static str, answer: F) -> F::Output {
+        let timer = self.start(method);
+        answer.instrument(timer.span.clone()).await
+    }
+
+    pub fn record(&self, method: &'static str, elapsed: Duration) {
+        let mut methods = self.methods.lock().unwrap();
+        let latencies = methods.entry(method).or_default();
+        latencies.count += 1;
+        latencies.total += elapsed;
+        latencies.max = latencies.max.max(elapsed);
+        if latencies.recent.len() == RECENT {
+            latencies.recent.pop_front();
+        }
+        latencies.recent.push_back(elapsed);
+    }
+
+    /// The latencies of each method answered so far, by method.
+    pub fn latencies(&self) -> Vec<RequestLatency> {
+        let methods = self.methods.lock().unwrap();
+        methods
+            .iter()
+            .map(|(method, l)| {
+                let mut recent: Vec<Duration> = l.recent.iter().copied().collect();
+                recent.sort();
+                RequestLatency {
+                    method: method.to_string(),
+                    count: l.count,
+                    mean_ms: millis(l.total) / l.count as f64,
+                    p50_ms: percentile(&recent, 50),
+                    p90_ms: percentile(&recent, 90),
+                    p99_ms: percentile(&recent, 99),
+                    max_ms: millis(l.max),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A request being answered; recorded when dropped.
+pub struct RequestTimer<'a> {
+    metrics: &'a RequestMetrics,
+    method: &'static str,
+    span: tracing::Span,
+    start: Instant,
+}
+
+impl Drop for RequestTimer<'_> {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        self.metrics.record(self.method, elapsed);
+        tracing::debug!(
+            parent: &self.span,
+            elapsed_ms = millis(elapsed),
+            "{} answered",
+            self.method
+        );
+    }
+}
+
+/// The nearest-rank `p`th percentile of `sorted`, in milliseconds.
+fn percentile(sorted: &[Duration], p: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    millis(sorted[rank - 1])
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,160 @@
+//! A symbol's definitions merged into one synthetic entry, the way kconfig
+//! combines them: properties accumulate, each guarded by the dependencies of
+//! the definition it came from.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::Location;
+
+use crate::analysis::{SymbolDef, WorldIndex};
+use crate::ast::{Expr, SelectImplyAttr};
+use crate::util::location;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedSymbolParams {
+    pub symbol: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedSymbol {
+    pub name: String,
+    /// `bool`, `tristate`, ...; from whichever definition declares it.
+    pub type_kind: Option<String>,
+    /// One group per definition, in parse order.
+    pub definitions: Vec<DefinitionGroup>,
+    /// The merged entry as Kconfig text, annotated with originating files.
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefinitionGroup {
+    pub location: Location,
+    /// Own and inherited dependencies, AND-ed. `None` when unconditional.
+    pub depends_on: Option<String>,
+    pub prompt: Option<String>,
+    /// `default` lines as `value [if cond]`.
+    pub defaults: Vec<String>,
+    pub selects: Vec<String>,
+    pub implies: Vec<String>,
+}
+
+pub fn resolve(index: &WorldIndex, name: &str) -> Option<ResolvedSymbol> {
+    let order = index.tree_order();
+    let defs = index.symbol(name, &order)?.definitions;
+    let type_kind = defs
+        .iter()
+        .find_map(|d| d.type_kind)
+        .map(|t| t.as_str().to_string());
+    let definitions: Vec<DefinitionGroup> = defs.iter().filter_map(|d| group(index, d)).collect();
+    let text = render(index, name, type_kind.as_deref(), &defs);
+    Some(ResolvedSymbol {
+        name: name.to_string(),
+        type_kind,
+        definitions,
+        text,
+    })
+}
+
+fn group(index: &WorldIndex, def: &SymbolDef) -> Option<DefinitionGroup> {
+    Some(DefinitionGroup {
+        location: location(index, &def.file, def.name_span)?,
+        depends_on: dependencies(def),
+        prompt: def.prompt.as_ref().map(|p| match &def.prompt_condition {
+            Some(c) => format!("\"{}\" if {}", p, c),
+            None => format!("\"{}\"", p),
+        }),
+        defaults: def
+            .defaults
+            .iter()
+            .map(|d| conditional(&d.value.to_string(), d.condition.as_ref()))
+            .collect(),
+        selects: def.selects.iter().map(select_text).collect(),
+        implies: def.implies.iter().map(select_text).collect(),
+    })
+}
+
+/// The merged entry, one commented group of lines per definition.
+fn render(index: &WorldIndex, name: &str, type_kind: Option<&str>, defs: &[&SymbolDef]) -> String {
+    let mut out = format!("config {}\n", name);
+    if let Some(tk) = type_kind {
+        let _ = writeln!(out, "\t{}", tk);
+    }
+    for def in defs {
+        let line = index
+            .files
+            .get(&def.file)
+            .map_or(0, |fa| fa.line_index.line_col(def.name_span.start).0 + 1);
+        let _ = write!(out, "\t# {}:{}", display_path(index, &def.file), line);
+        match dependencies(def) {
+            Some(deps) => {
+                let _ = writeln!(out, ", depends on {}", deps);
+            }
+            None => out.push('\n'),
+        }
+        if let Some(prompt) = &def.prompt {
+            let text = format!("\"{}\"", prompt);
+            let _ = writeln!(
+                out,
+                "\tprompt {}",
+                conditional(&text, def.prompt_condition.as_ref())
+            );
+        }
+        for d in &def.defaults {
+            let _ = writeln!(
+                out,
+                "\tdefault {}",
+                conditional(&d.value.to_string(), d.condition.as_ref())
+            );
+        }
+        for s in &def.selects {
+            let _ = writeln!(out, "\tselect {}", select_text(s));
+        }
+        for s in &def.implies {
+            let _ = writeln!(out, "\timply {}", select_text(s));
+        }
+    }
+    out
+}
+
+fn dependencies(def: &SymbolDef) -> Option<String> {
+    let deps: Vec<String> = def
+        .depends_on
+        .iter()
+        .chain(&def.inherited_depends)
+        .map(|e| match e {
+            Expr::Or(..) => format!("({})", e),
+            _ => e.to_string(),
+        })
+        .collect();
+    if deps.is_empty() {
+        None
+    } else {
+        Some(deps.join(" && "))
+    }
+}
+
+fn conditional(value: &str, cond: Option<&Expr>) -> String {
+    match cond {
+        Some(c) => format!("{} if {}", value, c),
+        None => value.to_string(),
+    }
+}
+
+fn select_text(s: &SelectImplyAttr) -> String {
+    conditional(&s.symbol, s.condition.as_ref())
+}
+
+fn display_path(index: &WorldIndex, path: &Path) -> String {
+    index
+        .root
+        .as_deref()
+        .and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
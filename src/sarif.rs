@@ -0,0 +1,289 @@
+//! `kconfig-lsp lint --format sarif`: the problems found as a SARIF 2.1.0
+//! log, with the quick fixes the server offers for them as SARIF fixes, so
+//! code review platforms can show them as suggested changes.
+
+use std::path::Path;
+
+use serde::Serialize;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use crate::analysis::FileAnalysis;
+use crate::code_action::QuickFix;
+use crate::codes;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The base the artifact paths of a log are relative to.
+const SRCROOT: &str = "SRCROOT";
+
+/// A log of one run over the tree at a root, built a problem at a time.
+pub struct SarifLog {
+    root: Option<String>,
+    results: Vec<SarifResult>,
+}
+
+impl SarifLog {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: Url::from_directory_path(root).ok().map(String::from),
+            results: Vec::new(),
+        }
+    }
+
+    /// Record `diag`, reported in the file `fa` at `rel` under the root,
+    /// with those of `fixes` that fix it.
+    pub fn add(&mut self, rel: &Path, fa: &FileAnalysis, diag: &Diagnostic, fixes: &[QuickFix]) {
+        let code = match &diag.code {
+            Some(NumberOrString::String(s)) => codes::by_id(s),
+            _ => None,
+        };
+        let start = fa
+            .line_index
+            .offset(diag.range.start.line, diag.range.start.character);
+        let end = fa
+            .line_index
+            .offset(diag.range.end.line, diag.range.end.character);
+        let location = artifact_location(rel);
+        let fixes = fixes
+            .iter()
+            .filter(|q| code.is_some_and(|c| c.id == q.code.id) && q.span.start == start)
+            .map(|q| SarifFix {
+                description: Message {
+                    text: q.fix.title.clone(),
+                },
+                artifact_changes: vec![ArtifactChange {
+                    artifact_location: location.clone(),
+                    replacements: q
+                        .fix
+                        .edits
+                        .iter()
+                        .map(|e| Replacement {
+                            deleted_region: region(&fa.source, e.span.start, e.span.end),
+                            inserted_content: (!e.new_text.is_empty()).then(|| ArtifactContent {
+                                text: e.new_text.clone(),
+                            }),
+                        })
+                        .collect(),
+                }],
+            })
+            .collect();
+        self.results.push(SarifResult {
+            rule_id: code.map(|c| c.id),
+            rule_index: code.and_then(|c| codes::ALL.iter().position(|a| a.id == c.id)),
+            level: match diag.severity {
+                Some(DiagnosticSeverity::ERROR) => "error",
+                Some(DiagnosticSeverity::WARNING) => "warning",
+                _ => "note",
+            },
+            message: Message {
+                text: diag.message.clone(),
+            },
+            locations: vec![Location {
+                physical_location: PhysicalLocation {
+                    artifact_location: location,
+                    region: region(&fa.source, start, end),
+                },
+            }],
+            fixes,
+        });
+    }
+
+    /// The log as pretty-printed JSON.
+    pub fn render(self) -> String {
+        let rules = codes::ALL
+            .iter()
+            .map(|c| Rule {
+                id: c.id,
+                name: c.name,
+                short_description: Message {
+                    text: c.summary.to_string(),
+                },
+                full_description: Message {
+                    text: c.explanation.to_string(),
+                },
+                help_uri: c.docs_url(),
+            })
+            .collect();
+        let log = Log {
+            schema: SCHEMA,
+            version: "2.1.0",
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: env!("CARGO_PKG_NAME"),
+                        version: env!("CARGO_PKG_VERSION"),
+                        information_uri: "https://github.com/cccheng/kconfig-lsp",
+                        rules,
+                    },
+                },
+                original_uri_base_ids: self.root.map(|uri| OriginalUriBaseIds {
+                    srcroot: ArtifactLocationUri { uri },
+                }),
+                column_kind: "unicodeCodePoints",
+                results: self.results,
+            }],
+        };
+        serde_json::to_string_pretty(&log).unwrap_or_default()
+    }
+}
+
+fn artifact_location(rel: &Path) -> ArtifactLocation {
+    let uri = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    ArtifactLocation {
+        uri,
+        uri_base_id: SRCROOT,
+    }
+}
+
+/// The region between two byte offsets of `source`, with 1-based lines and
+/// columns counted in characters.
+fn region(source: &str, start: usize, end: usize) -> Region {
+    let position = |offset: usize| {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |p| p + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    };
+    let (start_line, start_column) = position(start);
+    let (end_line, end_column) = position(end);
+    Region {
+        start_line,
+        start_column,
+        end_line,
+        end_column,
+    }
+}
+
+#[derive(Serialize)]
+struct Log {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_uri_base_ids: Option<OriginalUriBaseIds>,
+    column_kind: &'static str,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: &'static str,
+    name: &'static str,
+    short_description: Message,
+    full_description: Message,
+    help_uri: String,
+}
+
+#[derive(Serialize)]
+struct OriginalUriBaseIds {
+    #[serde(rename = "SRCROOT")]
+    srcroot: ArtifactLocationUri,
+}
+
+#[derive(Serialize)]
+struct ArtifactLocationUri {
+    uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule_id: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule_index: Option<usize>,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fixes: Vec<SarifFix>,
+}
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    region: Region,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    uri_base_id: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifFix {
+    description: Message,
+    artifact_changes: Vec<ArtifactChange>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactChange {
+    artifact_location: ArtifactLocation,
+    replacements: Vec<Replacement>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Replacement {
+    deleted_region: Region,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inserted_content: Option<ArtifactContent>,
+}
+
+#[derive(Serialize)]
+struct ArtifactContent {
+    text: String,
+}
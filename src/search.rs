@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::{SymbolDef, WorldIndex};
+use crate::text_index;
+use crate::util::location;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchTextParams {
+    /// Words that must all begin a word of the prompt, or all of the help
+    /// text (case-insensitive).
+    pub query: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchField {
+    Prompt,
+    Help,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchTextMatch {
+    pub symbol: String,
+    pub prompt: Option<String>,
+    /// Which text matched; prompt matches are listed before help matches.
+    pub field: MatchField,
+    pub location: Location,
+}
+
+/// Search prompt strings and help text for `query`, returning the owning
+/// symbol definitions. Users remember what menuconfig shows, not the name.
+pub fn search_text(index: &WorldIndex, params: &SearchTextParams) -> Vec<SearchTextMatch> {
+    let terms: Vec<String> = text_index::words(&params.query).collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    // Narrow down to the symbols the word index has every term for, then
+    // check each of their definitions.
+    let candidates = |field| {
+        terms
+            .iter()
+            .map(|t| index.text.with_prefix(t, field))
+            .reduce(|a, b| a.intersection(&b).copied().collect())
+            .unwrap_or_default()
+    };
+    let mut symbols: Vec<&str> = candidates(MatchField::Prompt)
+        .union(&candidates(MatchField::Help))
+        .copied()
+        .collect();
+    symbols.sort_unstable();
+
+    let mut hits: Vec<(MatchField, &SymbolDef)> = Vec::new();
+    for def in symbols.into_iter().flat_map(|s| index.get_definitions(s)) {
+        let field = if def
+            .prompt
+            .as_deref()
+            .is_some_and(|p| matches_all(p, &terms))
+        {
+            MatchField::Prompt
+        } else if def.help.as_deref().is_some_and(|h| matches_all(h, &terms)) {
+            MatchField::Help
+        } else {
+            continue;
+        };
+        hits.push((field, def));
+    }
+    hits.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then_with(|| a.1.name.cmp(&b.1.name))
+            .then_with(|| a.1.file.cmp(&b.1.file))
+    });
+
+    hits.into_iter()
+        .filter_map(|(field, def)| {
+            Some(SearchTextMatch {
+                symbol: def.name.clone(),
+                prompt: def.prompt.clone(),
+                field,
+                location: location(index, &def.file, def.name_span)?,
+            })
+        })
+        .take(params.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+fn matches_all(text: &str, terms: &[String]) -> bool {
+    let words: Vec<String> = text_index::words(text).collect();
+    terms
+        .iter()
+        .all(|t| words.iter().any(|w| w.starts_with(t.as_str())))
+}
@@ -0,0 +1,207 @@
+//! `kconfig/selectChain` and the "Forced on by" hover section: with a
+//! `.config` loaded, the `select` lines that currently force a symbol on,
+//! the ones forcing each selector on in turn, and where the symbols the
+//! chains start from are set. This is menuconfig's "Selected by", with
+//! values.
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::{SymbolDef, WorldIndex};
+use crate::ast::SelectImplyAttr;
+use crate::eval::{Evaluator, Tristate};
+use crate::{uri, util};
+
+/// Selectors followed before a chain is cut short.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectChainParams {
+    pub symbol: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectChain {
+    pub symbol: String,
+    /// The value of the symbol in the `.config`.
+    pub value: String,
+    /// The active `select` lines forcing it on; empty if none does.
+    pub selected_by: Vec<Selector>,
+}
+
+/// A symbol whose `select` line forces the symbol above it on.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Selector {
+    pub symbol: String,
+    pub value: String,
+    /// The `if` condition of the `select` line, and its value.
+    pub condition: Option<String>,
+    pub condition_value: Option<String>,
+    /// The `select` line.
+    pub location: Option<Location>,
+    /// The active `select` lines forcing this symbol on in turn.
+    pub selected_by: Vec<Selector>,
+    /// Where a symbol no `select` forces on is set: the last fragment of
+    /// the `configFragments` setting that assigns it, or else the
+    /// `.config`.
+    pub assignment: Option<ConfigAssignment>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigAssignment {
+    pub uri: Url,
+    /// 0-based line of the assignment.
+    pub line: u32,
+    pub value: String,
+}
+
+/// The select chains forcing `params.symbol` on, or `None` without a
+/// `.config` or a definition of the symbol.
+pub fn select_chain(index: &WorldIndex, params: &SelectChainParams) -> Option<SelectChain> {
+    let config = index.config.as_ref()?;
+    if index.get_definitions(&params.symbol).is_empty() {
+        return None;
+    }
+    let eval = Evaluator::new(index, config);
+    let mut path = vec![params.symbol.as_str()];
+    Some(SelectChain {
+        symbol: params.symbol.clone(),
+        value: eval.symbol_tristate(&params.symbol).as_str().to_string(),
+        selected_by: selectors(index, &eval, &mut path),
+    })
+}
+
+/// The active selectors of the last symbol of `path`, each with its own.
+/// Symbols already on the path are left out, so a cycle of selects ends.
+fn selectors<'a>(
+    index: &'a WorldIndex,
+    eval: &Evaluator,
+    path: &mut Vec<&'a str>,
+) -> Vec<Selector> {
+    let name = *path.last().unwrap();
+    if path.len() > MAX_DEPTH {
+        return Vec::new();
+    }
+    let mut active: Vec<(&SymbolDef, &SelectImplyAttr)> = index
+        .definitions
+        .values()
+        .flatten()
+        .flat_map(|d| d.selects.iter().map(move |a| (d, a)))
+        .filter(|(d, a)| {
+            a.symbol == name
+                && !path.contains(&d.name.as_str())
+                && eval.symbol_tristate(&d.name) != Tristate::N
+                && a.condition
+                    .as_ref()
+                    .is_none_or(|c| eval.eval(c) != Tristate::N)
+        })
+        .collect();
+    active.sort_by(|a, b| (&a.0.name, &a.0.file).cmp(&(&b.0.name, &b.0.file)));
+
+    active
+        .into_iter()
+        .map(|(d, a)| {
+            path.push(&d.name);
+            let selected_by = selectors(index, eval, path);
+            path.pop();
+            let assignment = if selected_by.is_empty() {
+                assignment(index, &d.name)
+            } else {
+                None
+            };
+            Selector {
+                symbol: d.name.clone(),
+                value: eval.symbol_tristate(&d.name).as_str().to_string(),
+                condition: a.condition.as_ref().map(|c| c.to_string()),
+                condition_value: a
+                    .condition
+                    .as_ref()
+                    .map(|c| eval.eval(c).as_str().to_string()),
+                location: location(index, d, a),
+                selected_by,
+                assignment,
+            }
+        })
+        .collect()
+}
+
+/// Where `name` is set: the last fragment assigning it, or the `.config`.
+fn assignment(index: &WorldIndex, name: &str) -> Option<ConfigAssignment> {
+    let config = index
+        .fragments
+        .iter()
+        .rev()
+        .chain(index.config.as_ref())
+        .find(|c| c.values.contains_key(name))?;
+    let value = &config.values[name];
+    Some(ConfigAssignment {
+        uri: uri::from_path(&config.path)?,
+        line: value.line,
+        value: value.value.clone(),
+    })
+}
+
+fn location(index: &WorldIndex, def: &SymbolDef, attr: &SelectImplyAttr) -> Option<Location> {
+    util::location(index, &def.file, attr.span)
+}
+
+/// The Markdown hover section listing the chains forcing `name` on, when a
+/// `.config` is loaded and some `select` does.
+pub fn hover_section(index: &WorldIndex, name: &str) -> Option<String> {
+    let params = SelectChainParams {
+        symbol: name.to_string(),
+    };
+    let chain = select_chain(index, &params)?;
+    if chain.selected_by.is_empty() {
+        return None;
+    }
+    let mut out = format!(
+        "**Forced on by** (`{}` is `{}` in .config):\n",
+        name, chain.value
+    );
+    render(index, &chain.selected_by, 0, &mut out);
+    Some(out)
+}
+
+fn render(index: &WorldIndex, selectors: &[Selector], depth: usize, out: &mut String) {
+    for s in selectors {
+        out.push_str(&format!(
+            "\n{}- `{}` [={}]",
+            "  ".repeat(depth),
+            s.symbol,
+            s.value
+        ));
+        if let (Some(c), Some(v)) = (&s.condition, &s.condition_value) {
+            out.push_str(&format!(" if `{}` [={}]", c, v));
+        }
+        if let Some(location) = &s.location {
+            out.push_str(&format!(
+                " (select at `{}`)",
+                place(index, &location.uri, location.range.start.line)
+            ));
+        }
+        if s.selected_by.is_empty() {
+            match &s.assignment {
+                Some(a) => out.push_str(&format!(
+                    ", set to `{}` in `{}`",
+                    a.value,
+                    place(index, &a.uri, a.line)
+                )),
+                None => out.push_str(", not set in .config"),
+            }
+        }
+        render(index, &s.selected_by, depth + 1, out);
+    }
+}
+
+/// `path:line` of a 0-based line, relative to the source tree.
+fn place(index: &WorldIndex, uri: &Url, line: u32) -> String {
+    match uri::to_path(uri) {
+        Some(path) => util::line_place(index, &path, line),
+        None => format!("{}:{}", uri, line + 1),
+    }
+}
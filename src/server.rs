@@ -1,54 +1,1438 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use dashmap::DashMap;
 use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::notification::Progress;
+use tower_lsp::lsp_types::request::WorkDoneProgressCreate;
 use tower_lsp::lsp_types::*;
-use tower_lsp::{Client, LanguageServer};
+use tower_lsp::{Client, ClientSocket, LanguageServer, LspService};
 
 use crate::analysis::WorldIndex;
-use crate::{completion, definition, diagnostics, hover, references};
+use crate::ast::LineIndex;
+use crate::client_caps::ClientCaps;
+use crate::dependency_path::{DependencyPath, DependencyPathParams};
+use crate::dotconfig::{ConfigReloaded, ConfigReloadedParams, DotConfig};
+use crate::format::FormatOptions;
+use crate::impact::{RemovalImpact, RemovalImpactParams};
+use crate::index_queue::{self, IndexQueue};
+use crate::index_stats::{self, IndexStats, SkippedFile};
+use crate::lazy_index::LazyFiles;
+use crate::lexer::Dialect;
+use crate::menu_tree::{MenuNode, MenuTreeParams};
+use crate::merge_config::{MergeConfigParams, MergeConfigResult};
+use crate::profile::Profile;
+use crate::project_stats::{ProjectStats, ProjectStatsParams};
+use crate::references::{ReferencesTree, ReferencesTreeParams};
+use crate::related::{RelatedSymbol, RelatedSymbolsParams};
+use crate::rename::{self, BuildEdits, BuildFiles};
+use crate::report::{ReportDocument, ReportParams};
+use crate::request_metrics::RequestMetrics;
+use crate::resolved::{ResolvedSymbol, ResolvedSymbolParams};
+use crate::search::{SearchTextMatch, SearchTextParams};
+use crate::select_chain::{SelectChain, SelectChainParams};
+use crate::settings::{IndexingMode, Settings, ShellPolicy};
+use crate::simulate::{Preset, SimulateParams, SimulateResult};
+use crate::sourced_files::{self, FileAssociations, FileAssociationsParams};
+use crate::symbol_docs::{DetailedSymbol, DocumentSymbolsDetailedParams};
+use crate::symbol_metrics::{SymbolMetrics, SymbolMetricsParams};
+use crate::{
+    code_action, completion, config_doc, debug_dump, definition, dependency_path, diagnostics,
+    dotconfig, format, hover, impact, lazy_index, linked_editing, menu_tree, merge_config,
+    preprocess, profile, project_stats, references, related, report, resolved, search,
+    select_chain, shell_trust, simulate, symbol_docs, symbol_metrics, type_hierarchy, uri, util,
+};
 
-pub struct Backend {
+/// The language server. Clones share one state, so that work can outlive
+/// the request that started it.
+#[derive(Clone)]
+pub struct Backend(Arc<BackendState>);
+
+pub struct BackendState {
     client: Client,
+    /// Open documents, by [`uri::normalize`]d URI.
     documents: DashMap<Url, String>,
-    index: Mutex<WorldIndex>,
+    /// The URI each open document was opened with, where it is not the
+    /// normalized one; diagnostics are published under it.
+    opened_as: DashMap<Url, Url>,
+    /// Requests compute their results under one lock of the index, so a
+    /// result spanning files reflects a single state of it. Shared with the
+    /// blocking tasks that compute the slow ones.
+    index: Arc<Mutex<WorldIndex>>,
     /// Root path of the workspace, captured during initialization.
     workspace_root: Mutex<Option<PathBuf>>,
     /// Files discovered and indexed from the workspace (not explicitly opened
     /// by the editor).  Tracked so that `did_close` can restore the on-disk
     /// version instead of dropping the file from the index entirely.
     workspace_files: Mutex<HashSet<PathBuf>>,
+    settings: Mutex<Settings>,
+    /// Files the startup scan has yet to index; `None` once it is done.
+    startup_queue: Mutex<Option<IndexQueue>>,
+    /// In lazy mode, the files the startup scan left to read on demand.
+    lazy: Mutex<LazyFiles>,
+    /// Cancellation flag of the running `kconfig.reindex`, if any.
+    reindexing: Mutex<Option<Arc<AtomicBool>>>,
+    /// Whether the user was told that results come from an index being
+    /// rebuilt; once per scan or reindex.
+    stale_notice_shown: AtomicBool,
+    /// Files that are not open but have diagnostics published because of
+    /// `workspaceDiagnostics`, so they can be cleared when fixed.
+    published: Mutex<HashSet<Url>>,
+    /// Announcement of the profile detected during `initialize`, shown
+    /// once the client is ready.
+    profile_message: Mutex<Option<String>>,
+    /// What was wrong with the settings sent with `initialize`, shown once
+    /// the client is ready.
+    settings_problems: Mutex<Vec<String>>,
+    /// What the client supports, from `initialize`.
+    client_caps: Mutex<ClientCaps>,
+    /// Latencies of the requests answered.
+    request_metrics: RequestMetrics,
+    /// The index epoch of the diagnostics last published for each file, so
+    /// that diagnostics computed before a change never replace those
+    /// computed after it.
+    diagnostics_epochs: tokio::sync::Mutex<DiagnosticEpochs>,
+}
+
+/// The index epoch of the diagnostics shown for each file.
+#[derive(Debug, Default)]
+pub struct DiagnosticEpochs(HashMap<Url, u64>);
+
+impl DiagnosticEpochs {
+    /// Whether diagnostics for `uri` computed at `epoch` may replace those
+    /// shown, which they do unless those are from a later epoch. Records
+    /// `epoch` if so.
+    pub fn admit(&mut self, uri: &Url, epoch: u64) -> bool {
+        if self.0.get(uri).is_some_and(|&e| e > epoch) {
+            return false;
+        }
+        self.0.insert(uri.clone(), epoch);
+        true
+    }
+
+    /// Forget `uri`, whose diagnostics are no longer kept up to date.
+    pub fn forget(&mut self, uri: &Url) {
+        self.0.remove(uri);
+    }
+}
+
+/// `workspace/executeCommand` that rebuilds the index from disk.
+pub const REINDEX_COMMAND: &str = "kconfig.reindex";
+
+/// Registration id of the `.config` file watcher.
+const CONFIG_WATCH_ID: &str = "kconfig-config-watch";
+
+/// Registration id of `textDocument/prepareTypeHierarchy`.
+const TYPE_HIERARCHY_ID: &str = "kconfig-type-hierarchy";
+
+/// Work-done progress token of [`REINDEX_COMMAND`]; only one runs at a time.
+const REINDEX_TOKEN: &str = "kconfig-reindex";
+
+/// Work-done progress token of reading the build files of a rename, when
+/// the client gave none.
+const RENAME_TOKEN: &str = "kconfig-rename";
+
+/// Rounds of `$(shell,...)` commands run after indexing; each round runs
+/// those that expand from the output of the previous ones.
+const SHELL_ROUNDS: usize = 4;
+
+impl std::ops::Deref for Backend {
+    type Target = BackendState;
+
+    fn deref(&self) -> &BackendState {
+        &self.0
+    }
 }
 
 impl Backend {
     pub fn new(client: Client) -> Self {
-        Self {
+        Self(Arc::new(BackendState {
             client,
             documents: DashMap::new(),
-            index: Mutex::new(WorldIndex::new()),
+            opened_as: DashMap::new(),
+            index: Arc::new(Mutex::new(WorldIndex::new())),
             workspace_root: Mutex::new(None),
             workspace_files: Mutex::new(HashSet::new()),
-        }
+            settings: Mutex::new(Settings::default()),
+            startup_queue: Mutex::new(None),
+            lazy: Mutex::new(LazyFiles::default()),
+            reindexing: Mutex::new(None),
+            stale_notice_shown: AtomicBool::new(false),
+            published: Mutex::new(HashSet::new()),
+            profile_message: Mutex::new(None),
+            settings_problems: Mutex::new(Vec::new()),
+            client_caps: Mutex::new(ClientCaps::default()),
+            request_metrics: RequestMetrics::default(),
+            diagnostics_epochs: tokio::sync::Mutex::new(DiagnosticEpochs::default()),
+        }))
     }
 
     fn uri_to_path(uri: &Url) -> Option<PathBuf> {
-        uri.to_file_path().ok()
+        uri::to_path(uri)
     }
 
-    async fn publish_diagnostics(&self, uri: &Url) {
-        let diags = {
+    /// The text of the open document `uri`, however it is spelled.
+    fn document(&self, uri: &Url) -> Option<String> {
+        self.documents.get(&uri::normalize(uri)).map(|t| t.clone())
+    }
+
+    /// The text the server has of `uri`: the open document, or the indexed
+    /// file.
+    fn source(&self, uri: &Url) -> Option<String> {
+        self.document(uri).or_else(|| {
+            let path = Self::uri_to_path(uri)?;
+            let idx = self.index.lock().unwrap();
+            idx.files.get(&path).map(|fa| fa.source.clone())
+        })
+    }
+
+    /// The configured `.config`, or `<root>/.config` when that file exists.
+    fn config_file(&self) -> Option<PathBuf> {
+        let settings = self.settings.lock().unwrap();
+        let root = self.workspace_root.lock().unwrap();
+        match (&settings.config_path, &*root) {
+            (Some(p), Some(root)) => Some(root.join(p)),
+            (Some(p), None) => Some(PathBuf::from(p)),
+            (None, Some(root)) if root.join(".config").is_file() => Some(root.join(".config")),
+            (None, Some(root)) => {
+                let idx = self.index.lock().unwrap();
+                dotconfig::defconfig_list(&idx)
+                    .into_iter()
+                    .map(|p| root.join(p))
+                    .find(|p| p.is_file())
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// The files whose changes reload the `.config`: the one in use and,
+    /// without a `configPath`, `<root>/.config` whether or not it exists
+    /// yet, as it takes over from a `defconfig_list` file once written.
+    fn watched_config_files(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.config_file().into_iter().collect();
+        let configured = self.settings.lock().unwrap().config_path.is_some();
+        let root = self.workspace_root.lock().unwrap().clone();
+        if !configured
+            && let Some(root) = root
+            && let default = root.join(".config")
+            && !paths.contains(&default)
+        {
+            paths.push(default);
+        }
+        paths
+    }
+
+    /// The `buildConfig` file, whether or not the build has written it yet.
+    fn build_config_file(&self) -> Option<PathBuf> {
+        let settings = self.settings.lock().unwrap();
+        let root = self.workspace_root.lock().unwrap();
+        let path = settings.build_config.as_deref()?;
+        Some(match &*root {
+            Some(root) => root.join(path),
+            None => PathBuf::from(path),
+        })
+    }
+
+    /// The `configFragments` files, in merge order.
+    fn config_fragment_files(&self) -> Vec<PathBuf> {
+        let settings = self.settings.lock().unwrap();
+        let root = self.workspace_root.lock().unwrap();
+        settings
+            .config_fragments
+            .iter()
+            .map(|p| match &*root {
+                Some(root) => root.join(p),
+                None => PathBuf::from(p),
+            })
+            .collect()
+    }
+
+    /// Whether the open document at `path` is a configuration file, which
+    /// is colored rather than indexed as Kconfig.
+    fn is_config_document(&self, path: &Path) -> bool {
+        config_doc::is_config_file(path, self.config_file().as_deref())
+    }
+
+    /// Where the build files a rename extends to are found, when
+    /// `rename.buildFiles` is set and the client can ask the user to confirm
+    /// those edits: the scan roots and the `.config`, with the prefix they
+    /// spell symbols with.
+    fn rename_build_files(&self) -> Option<(Vec<PathBuf>, Option<PathBuf>, String)> {
+        let (enabled, prefix, roots) = {
+            let settings = self.settings.lock().unwrap();
+            let root = self.workspace_root.lock().unwrap();
+            (
+                settings.rename.build_files,
+                settings.config_prefix.clone(),
+                root.as_deref().map(|r| settings.scan_roots(r)),
+            )
+        };
+        if !enabled || !self.client_caps.lock().unwrap().change_annotations {
+            return None;
+        }
+        Some((roots.unwrap_or_default(), self.config_file(), prefix))
+    }
+
+    /// The edits renaming `old` to `new` in the build files, found and read
+    /// on a blocking task, with work-done progress under the request's
+    /// `token` or one of the server's.
+    async fn build_file_edits(
+        &self,
+        token: Option<ProgressToken>,
+        old: String,
+        new: String,
+    ) -> Option<BuildEdits> {
+        let (roots, config, prefix) = self.rename_build_files()?;
+        let token = match token {
+            Some(token) => Some(token),
+            None => self.create_progress(RENAME_TOKEN).await,
+        };
+        self.progress(
+            &token,
+            WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: "Renaming in build files".into(),
+                cancellable: Some(false),
+                message: Some(format!("{}{}", prefix, old)),
+                percentage: None,
+            }),
+        )
+        .await;
+        let edits = tokio::task::spawn_blocking(move || {
+            let names: Vec<String> = rename::BUILD_FILE_NAMES
+                .iter()
+                .map(|n| n.to_string())
+                .collect();
+            let mut files = discover_workspace_files(&roots, &names, None);
+            files.extend(config.filter(|f| !files.contains(f)));
+            rename::build_file_edits(&BuildFiles { files, prefix }, &old, &new)
+        })
+        .await
+        .ok();
+        let files = edits.as_ref().map_or(0, |e| e.edits.len());
+        self.progress(
+            &token,
+            WorkDoneProgress::End(WorkDoneProgressEnd {
+                message: Some(format!("{} build files to change", files)),
+            }),
+        )
+        .await;
+        edits
+    }
+
+    /// A work-done progress token named `name`, if the client accepts
+    /// progress the server starts.
+    async fn create_progress(&self, name: &str) -> Option<ProgressToken> {
+        if !self.client_caps.lock().unwrap().work_done_progress {
+            return None;
+        }
+        let token = NumberOrString::String(name.into());
+        self.client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .ok()
+            .map(|()| token)
+    }
+
+    async fn progress(&self, token: &Option<ProgressToken>, value: WorkDoneProgress) {
+        if let Some(token) = token {
+            self.client
+                .send_notification::<Progress>(ProgressParams {
+                    token: token.clone(),
+                    value: ProgressParamsValue::WorkDone(value),
+                })
+                .await;
+        }
+    }
+
+    /// Load the configured `.config` (if any), and the fragments it was
+    /// merged from, into the index. Returns how many symbol values changed
+    /// compared with the previously loaded one.
+    fn load_config(&self) -> Option<usize> {
+        let path = self.config_file()?;
+        let prefix = self.settings.lock().unwrap().config_prefix.clone();
+        let config = match DotConfig::load(&path, &prefix) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                log::warn!("failed to read {}: {}", path.display(), e);
+                None
+            }
+        };
+        if let Some(config) = &config {
+            log::info!(
+                "loaded {} values from {}",
+                config.values.len(),
+                path.display()
+            );
+        }
+        let fragments = self
+            .config_fragment_files()
+            .into_iter()
+            .filter_map(|path| match DotConfig::load(&path, &prefix) {
+                Ok(fragment) => Some(fragment),
+                Err(e) => {
+                    log::warn!("failed to read {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .collect();
+        let empty = DotConfig::default();
+        let mut idx = self.index.lock().unwrap();
+        idx.fragments = fragments;
+        let changed = idx
+            .config
+            .as_ref()
+            .unwrap_or(&empty)
+            .changed_symbols(config.as_ref().unwrap_or(&empty));
+        idx.config = config;
+        idx.epoch += 1;
+        Some(changed)
+    }
+
+    /// Load the `buildConfig` file into the index, or clear it when there is
+    /// none (yet).
+    fn load_build_config(&self) {
+        let prefix = self.settings.lock().unwrap().config_prefix.clone();
+        let config = self.build_config_file().and_then(|path| {
+            match DotConfig::load_generated(&path, &prefix) {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    log::info!("no build configuration at {}: {}", path.display(), e);
+                    None
+                }
+            }
+        });
+        self.index.lock().unwrap().build_config = config;
+    }
+
+    async fn show_settings_problems(&self, problems: Vec<String>) {
+        for problem in problems {
+            self.client
+                .show_message(MessageType::WARNING, problem)
+                .await;
+        }
+    }
+
+    /// Ask the client to report changes to the `.config` file and the build
+    /// configuration, so values can be reloaded after they are regenerated
+    /// outside the editor.
+    async fn watch_config(&self) {
+        if !self.client_caps.lock().unwrap().watched_files {
+            return;
+        }
+        let paths: Vec<PathBuf> = self
+            .watched_config_files()
+            .into_iter()
+            .chain(self.build_config_file())
+            .collect();
+        let Some(path) = paths.first() else {
+            return;
+        };
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: paths
+                .iter()
+                .map(|p| FileSystemWatcher {
+                    glob_pattern: GlobPattern::String(p.display().to_string()),
+                    kind: None,
+                })
+                .collect(),
+        };
+        let registration = Registration {
+            id: CONFIG_WATCH_ID.into(),
+            method: "workspace/didChangeWatchedFiles".into(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            log::warn!("failed to watch {}: {}", path.display(), e);
+        }
+    }
+
+    /// Register the type hierarchy requests. The
+    /// protocol types in use cannot advertise them in `initialize`.
+    async fn register_type_hierarchy(&self) {
+        if !self.client_caps.lock().unwrap().type_hierarchy {
+            return;
+        }
+        // No selector: the documents the client runs the server for.
+        let options = TextDocumentRegistrationOptions {
+            document_selector: None,
+        };
+        let registration = Registration {
+            id: TYPE_HIERARCHY_ID.into(),
+            method: "textDocument/prepareTypeHierarchy".into(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            log::warn!("failed to register the type hierarchy: {}", e);
+        }
+    }
+
+    /// Under [`ShellPolicy::Prompt`], ask whether the workspace may run the
+    /// `$(shell,...)` commands its Kconfig files contain, and settle the
+    /// policy to `Enabled` or `Disabled` for the rest of the session. The
+    /// answer is remembered for the workspace root, which is not asked again.
+    async fn ask_shell_trust(&self) {
+        let (root, commands) = {
+            let idx = self.index.lock().unwrap();
+            if idx.preprocessor.shell != ShellPolicy::Prompt {
+                return;
+            }
+            let commands = idx
+                .files
+                .values()
+                .filter(|fa| fa.source.contains("$(shell,"))
+                .count();
+            if commands == 0 {
+                return;
+            }
+            (idx.root.clone(), commands)
+        };
+        let store = shell_trust::store_path();
+        let remembered = match (&store, &root) {
+            (Some(store), Some(root)) => shell_trust::load(store, root),
+            _ => None,
+        };
+        let trusted = match remembered {
+            Some(trusted) => trusted,
+            None => {
+                let Some(trusted) = self.request_shell_trust(root.as_deref(), commands).await
+                else {
+                    self.set_shell_policy(false);
+                    return;
+                };
+                if let (Some(store), Some(root)) = (&store, &root)
+                    && let Err(e) = shell_trust::save(store, root, trusted)
+                {
+                    log::warn!(
+                        "failed to remember the trust answer in {}: {}",
+                        store.display(),
+                        e
+                    );
+                }
+                trusted
+            }
+        };
+        self.set_shell_policy(trusted);
+    }
+
+    /// Ask through `window/showMessageRequest` whether to run the
+    /// `$(shell,...)` commands of `commands` files; `None` when the message
+    /// was dismissed or the request failed.
+    async fn request_shell_trust(&self, root: Option<&Path>, commands: usize) -> Option<bool> {
+        let root = root.map_or_else(|| "this workspace".into(), |r| r.display().to_string());
+        let message = if commands == 1 {
+            format!("A Kconfig file in {} uses $(shell,...).", root)
+        } else {
+            format!("{} Kconfig files in {} use $(shell,...).", commands, root)
+        } + " Run these commands when expanding macros?";
+        let actions = ["Trust", "Don't Trust"]
+            .into_iter()
+            .map(|title| MessageActionItem {
+                title: title.into(),
+                properties: Default::default(),
+            })
+            .collect();
+        match self
+            .client
+            .show_message_request(MessageType::WARNING, message, Some(actions))
+            .await
+        {
+            Ok(action) => action.map(|a| a.title == "Trust"),
+            Err(e) => {
+                log::warn!("workspace trust request failed: {}", e);
+                None
+            }
+        }
+    }
+
+    fn set_shell_policy(&self, trusted: bool) {
+        log::info!("$(shell,...) trusted: {}", trusted);
+        self.index.lock().unwrap().preprocessor.shell = if trusted {
+            ShellPolicy::Enabled
+        } else {
+            ShellPolicy::Disabled
+        };
+    }
+
+    /// Run the `$(shell,...)` commands the indexed files expand to, outside
+    /// the index lock, and republish diagnostics if any ran. Commands built
+    /// from the output of others run in a later round.
+    async fn run_shell_commands(&self) {
+        let mut ran = false;
+        for _ in 0..SHELL_ROUNDS {
+            let (commands, dir) = {
+                let idx = self.index.lock().unwrap();
+                if idx.preprocessor.shell != ShellPolicy::Enabled {
+                    break;
+                }
+                let dir = idx.root.clone().unwrap_or_else(|| PathBuf::from("."));
+                (preprocess::shell_commands(&idx), dir)
+            };
+            if commands.is_empty() {
+                break;
+            }
+            log::info!("running {} $(shell,...) commands", commands.len());
+            let outputs = tokio::task::spawn_blocking(move || {
+                commands
+                    .into_iter()
+                    .map(|c| {
+                        let output = preprocess::run_shell(&c, &dir);
+                        (c, output)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .unwrap_or_default();
+            let mut idx = self.index.lock().unwrap();
+            idx.shell_outputs.extend(outputs);
+            // Expansions, `source` paths among them, may read differently.
+            idx.epoch += 1;
+            ran = true;
+        }
+        if ran {
+            self.publish_all_diagnostics().await;
+        }
+    }
+
+    /// Publish diagnostics for the open documents and, with
+    /// `workspaceDiagnostics`, for every other indexed file with problems.
+    async fn publish_all_diagnostics(&self) {
+        let open_uris: Vec<Url> = self.documents.iter().map(|e| e.key().clone()).collect();
+        for uri in &open_uris {
+            if let Some(path) = Self::uri_to_path(uri) {
+                self.index_lazily(&path).await;
+            }
+        }
+        let (epoch, mut diags) = self.diagnostics_snapshot(&open_uris, true).await;
+
+        let now: HashSet<Url> = diags
+            .iter()
+            .map(|(uri, _)| uri)
+            .filter(|uri| !self.documents.contains_key(*uri))
+            .cloned()
+            .collect();
+        let before = std::mem::replace(&mut *self.published.lock().unwrap(), now.clone());
+        diags.extend(
+            before
+                .difference(&now)
+                .filter(|uri| !self.documents.contains_key(*uri))
+                .map(|uri| (uri.clone(), Vec::new())),
+        );
+        self.publish_snapshot(epoch, diags).await;
+    }
+
+    /// The startup scan. Files open in the editor, and their neighbours in
+    /// the include graph, are indexed first; the lock is released between
+    /// files so requests on them are answered while the rest is read. In
+    /// lazy mode the scan stops there, once what the root Kconfig sources
+    /// is indexed too, and leaves the rest to [`Backend::index_lazily`].
+    async fn index_workspace(&self, root: &Path) {
+        let (kconfig_files, lazy) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                discover_workspace_files(
+                    &settings.scan_roots(root),
+                    &settings.file_names,
+                    settings.scan_depth,
+                ),
+                settings.indexing == IndexingMode::Lazy,
+            )
+        };
+        log::info!(
+            "discovered {} Kconfig files in workspace",
+            kconfig_files.len()
+        );
+        let mut queue = IndexQueue::new(kconfig_files);
+        for doc in self.documents.iter() {
+            if let Some(path) = Self::uri_to_path(doc.key()) {
+                queue.prioritize(&path);
+            }
+        }
+        if let Some(root_kconfig) = self.root_kconfig() {
+            queue.prioritize(&root_kconfig);
+        }
+        *self.startup_queue.lock().unwrap() = Some(queue);
+        self.stale_notice_shown.store(false, Ordering::Relaxed);
+
+        let mut urgent_done = false;
+        loop {
+            let next = self
+                .startup_queue
+                .lock()
+                .unwrap()
+                .as_mut()
+                .filter(|q| !lazy || q.has_urgent())
+                .and_then(|q| q.pop());
+            let Some((path, urgent)) = next else {
+                break;
+            };
+            let open = uri::from_path(&path).is_some_and(|uri| self.documents.contains_key(&uri));
+            // An open document was indexed from the editor's text already.
+            if !open {
+                let max_file_size = self.settings.lock().unwrap().max_file_size;
+                let read = index_stats::read_kconfig(&path, max_file_size);
+                let mut idx = self.index.lock().unwrap();
+                match read {
+                    Ok(source) => idx.analyze_file(&path, &source),
+                    Err(reason) => {
+                        skip(&mut idx, &path, reason);
+                        continue;
+                    }
+                }
+            }
+            self.workspace_files.lock().unwrap().insert(path.clone());
+            if open {
+                self.prioritize_neighbours(root, &path);
+            }
+            if lazy {
+                self.prioritize_sources(&path);
+            }
+
+            let has_urgent = self
+                .startup_queue
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(IndexQueue::has_urgent);
+            if urgent && !has_urgent && !urgent_done {
+                urgent_done = true;
+                log::info!("indexed open documents and their includes");
+                self.publish_all_diagnostics().await;
+            }
+            tokio::task::yield_now().await;
+        }
+        let rest = self.startup_queue.lock().unwrap().take();
+        if lazy && let Some(rest) = rest {
+            let pending = rest.into_pending();
+            log::info!("{} Kconfig files left to index on demand", pending.len());
+            let max_file_size = self.settings.lock().unwrap().max_file_size;
+            let lazy = tokio::task::spawn_blocking(move || LazyFiles::scan(pending, max_file_size))
+                .await
+                .unwrap_or_default();
+            *self.lazy.lock().unwrap() = lazy;
+        }
+        let indexed: Vec<PathBuf> = self.index.lock().unwrap().files.keys().cloned().collect();
+        self.index_sourced(indexed).await;
+    }
+
+    /// Index what the files `from` source that the scan did not find, since
+    /// their names are not like `fileNames`, and tell the client to open
+    /// them as Kconfig if it asked to be told.
+    async fn index_sourced(&self, from: Vec<PathBuf>) {
+        let (max_file_size, file_names) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.max_file_size, settings.file_names.clone())
+        };
+        let found = {
+            let mut idx = self.index.lock().unwrap();
+            sourced_files::index_sourced_files(&mut idx, from, max_file_size)
+        };
+        if found.is_empty() {
+            return;
+        }
+        log::info!("indexed {} sourced files the scan missed", found.len());
+        {
+            let mut lazy = self.lazy.lock().unwrap();
+            for path in &found {
+                lazy.remove(path);
+            }
+        }
+        let uris: Vec<Url> = found
+            .iter()
+            .filter(|p| !matches_file_names(p, &file_names))
+            .filter_map(|p| uri::from_path(p))
+            .collect();
+        self.workspace_files.lock().unwrap().extend(found);
+        if !uris.is_empty() && self.client_caps.lock().unwrap().file_associations {
+            self.client
+                .send_notification::<FileAssociations>(FileAssociationsParams { uris })
+                .await;
+        }
+    }
+
+    /// Index `path` anew from the editor's `text`. Returns the names that
+    /// gained or lost a definition, and whether what the file sources
+    /// changed.
+    fn reanalyze(&self, path: &Path, text: &str) -> (BTreeSet<String>, bool) {
+        let sourced = |idx: &WorldIndex| -> Vec<(String, bool)> {
+            idx.files.get(path).map_or_else(Vec::new, |fa| {
+                fa.sources
+                    .iter()
+                    .map(|s| (s.path.clone(), s.relative))
+                    .collect()
+            })
+        };
+        let mut idx = self.index.lock().unwrap();
+        let before = sourced(&idx);
+        let changed = idx.reanalyze_file(path, text);
+        (changed, sourced(&idx) != before)
+    }
+
+    /// Move what `path` sources up the startup queue.
+    fn prioritize_sources(&self, path: &Path) {
+        let candidates: Vec<PathBuf> = {
+            let idx = self.index.lock().unwrap();
+            let Some(fa) = idx.files.get(path) else {
+                return;
+            };
+            fa.sources
+                .iter()
+                .flat_map(|stmt| idx.source_candidates(path, &stmt.path, stmt.relative))
+                .collect()
+        };
+        if let Some(queue) = self.startup_queue.lock().unwrap().as_mut() {
+            for p in candidates {
+                queue.prioritize(&p);
+            }
+        }
+    }
+
+    /// In lazy mode, index the files not read yet that define a symbol the
+    /// file at `path` refers to and nothing indexed defines.
+    async fn index_lazily(&self, path: &Path) {
+        let files = {
+            let lazy = self.lazy.lock().unwrap();
+            if lazy.is_empty() {
+                return;
+            }
             let idx = self.index.lock().unwrap();
-            let path = match Self::uri_to_path(uri) {
-                Some(p) => p,
-                None => return,
+            let Some(fa) = idx.files.get(path) else {
+                return;
             };
-            diagnostics::collect(&idx, &path)
+            let missing: BTreeSet<String> = fa
+                .references
+                .iter()
+                .filter(|r| idx.get_definitions(&r.name).is_empty())
+                .map(|r| r.name.clone())
+                .collect();
+            lazy.defining(&missing)
+        };
+        if files.is_empty() {
+            return;
+        }
+        let max_file_size = self.settings.lock().unwrap().max_file_size;
+        let read = tokio::task::spawn_blocking(move || {
+            files
+                .into_iter()
+                .map(|file| {
+                    let read = index_stats::read_kconfig(&file, max_file_size);
+                    (file, read)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+        let mut lazy = self.lazy.lock().unwrap();
+        let mut idx = self.index.lock().unwrap();
+        let mut workspace_files = self.workspace_files.lock().unwrap();
+        for (file, read) in read {
+            lazy.remove(&file);
+            // Sourced by a file indexed since, or taken by another lookup.
+            if idx.files.contains_key(&file) {
+                continue;
+            }
+            match read {
+                Ok(source) => {
+                    log::info!("indexing {} on demand", file.display());
+                    idx.analyze_file(&file, &source);
+                    workspace_files.insert(file);
+                }
+                Err(reason) => log::warn!("not indexing {}: {}", file.display(), reason),
+            }
+        }
+    }
+
+    /// Move the include-graph neighbours of an open document up the startup
+    /// queue.
+    fn prioritize_neighbours(&self, root: &Path, path: &Path) {
+        let neighbours = match self.index.lock().unwrap().files.get(path) {
+            Some(fa) => index_queue::neighbours(root, path, fa),
+            None => return,
         };
+        if let Some(queue) = self.startup_queue.lock().unwrap().as_mut() {
+            for p in neighbours {
+                queue.prioritize(&p);
+            }
+        }
+    }
+
+    /// Rebuild the index from disk without blocking reads: the old index
+    /// keeps serving requests until the new one is complete and swapped in.
+    /// Progress is reported under [`REINDEX_TOKEN`] and can be cancelled.
+    async fn reindex(&self) {
+        let Some(root) = self.workspace_root.lock().unwrap().clone() else {
+            return;
+        };
+        if self.settings.lock().unwrap().indexing == IndexingMode::Lazy {
+            self.reindex_lazily(&root).await;
+            return;
+        }
+        let cancelled = Arc::new(AtomicBool::new(false));
+        {
+            let mut running = self.reindexing.lock().unwrap();
+            if running.is_some() {
+                log::info!("reindex already running");
+                return;
+            }
+            *running = Some(cancelled.clone());
+        }
+        self.stale_notice_shown.store(false, Ordering::Relaxed);
+
+        let token = NumberOrString::String(REINDEX_TOKEN.into());
+        let progress = self.client_caps.lock().unwrap().work_done_progress
+            && self
+                .client
+                .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                    token: token.clone(),
+                })
+                .await
+                .is_ok();
+        let report = |value: WorkDoneProgress| {
+            let token = token.clone();
+            async move {
+                if progress {
+                    self.client
+                        .send_notification::<Progress>(ProgressParams {
+                            token,
+                            value: ProgressParamsValue::WorkDone(value),
+                        })
+                        .await;
+                }
+            }
+        };
+        report(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: "Indexing Kconfig files".into(),
+            cancellable: Some(true),
+            message: None,
+            percentage: Some(0),
+        }))
+        .await;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let flag = cancelled.clone();
+        let (roots, file_names, scan_depth, max_file_size, dialect) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                settings.scan_roots(&root),
+                settings.file_names.clone(),
+                settings.scan_depth,
+                settings.max_file_size,
+                settings.dialect,
+            )
+        };
+        let task = tokio::task::spawn_blocking(move || {
+            build_index(
+                &roots,
+                &file_names,
+                scan_depth,
+                max_file_size,
+                dialect,
+                &flag,
+                |done, total| {
+                    let _ = tx.send((done, total));
+                },
+            )
+        });
+        let mut last = 0;
+        while let Some((done, total)) = rx.recv().await {
+            let percentage = (done * 100 / total.max(1)) as u32;
+            if percentage != last {
+                last = percentage;
+                report(WorkDoneProgress::Report(WorkDoneProgressReport {
+                    cancellable: Some(true),
+                    message: Some(format!("{}/{}", done, total)),
+                    percentage: Some(percentage),
+                }))
+                .await;
+            }
+        }
+        let built = task.await.ok().flatten();
+        *self.reindexing.lock().unwrap() = None;
+
+        let Some((index, files)) = built else {
+            log::info!("reindex cancelled");
+            report(WorkDoneProgress::End(WorkDoneProgressEnd {
+                message: Some("Cancelled".into()),
+            }))
+            .await;
+            return;
+        };
+        let count = files.len();
+        self.swap_index(index, files);
+        let indexed: Vec<PathBuf> = self.index.lock().unwrap().files.keys().cloned().collect();
+        self.index_sourced(indexed).await;
+        log::info!("reindexed {} Kconfig files", count);
+        report(WorkDoneProgress::End(WorkDoneProgressEnd {
+            message: Some(format!("Indexed {} files", count)),
+        }))
+        .await;
+        self.publish_all_diagnostics().await;
+        self.run_shell_commands().await;
+    }
+
+    /// Replace the index with `index` of the workspace `files`, keeping the
+    /// loaded configuration and settings and the open documents' text.
+    fn swap_index(&self, mut index: WorldIndex, files: HashSet<PathBuf>) {
+        let mut idx = self.index.lock().unwrap();
+        index.config = idx.config.take();
+        index.build_config = idx.build_config.take();
+        index.fragments = std::mem::take(&mut idx.fragments);
+        index.root = idx.root.take();
+        index.root_kconfig = idx.root_kconfig.take();
+        index.preprocessor = std::mem::take(&mut idx.preprocessor);
+        index.shell_outputs = std::mem::take(&mut idx.shell_outputs);
+        index.profile = idx.profile;
+        index.dialect = idx.dialect;
+        index.epoch += idx.epoch + 1;
+        // Open documents may differ from what is on disk.
+        for doc in self.documents.iter() {
+            if let Some(path) = Self::uri_to_path(doc.key()) {
+                index.reanalyze_file(&path, doc.value());
+            }
+        }
+        *idx = index;
+        *self.workspace_files.lock().unwrap() = files;
+    }
+
+    /// [`Backend::reindex`] in lazy mode: build what the startup scan
+    /// would, which reads little, aside while the old index keeps serving
+    /// requests, then swap it in.
+    async fn reindex_lazily(&self, root: &Path) {
+        {
+            let mut running = self.reindexing.lock().unwrap();
+            if running.is_some() || self.startup_queue.lock().unwrap().is_some() {
+                log::info!("reindex already running");
+                return;
+            }
+            *running = Some(Arc::new(AtomicBool::new(false)));
+        }
+        self.stale_notice_shown.store(false, Ordering::Relaxed);
+
+        let (roots, file_names, scan_depth, max_file_size) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                settings.scan_roots(root),
+                settings.file_names.clone(),
+                settings.scan_depth,
+                settings.max_file_size,
+            )
+        };
+        let base = {
+            let idx = self.index.lock().unwrap();
+            let mut index = WorldIndex::new();
+            index.root = idx.root.clone();
+            index.root_kconfig = idx.root_kconfig.clone();
+            index.preprocessor = idx.preprocessor.clone();
+            index.shell_outputs = idx.shell_outputs.clone();
+            index.dialect = idx.dialect;
+            index
+        };
+        let open: Vec<(PathBuf, String)> = self
+            .documents
+            .iter()
+            .filter_map(|doc| Some((Self::uri_to_path(doc.key())?, doc.value().clone())))
+            .collect();
+        let root = root.to_path_buf();
+        let built = tokio::task::spawn_blocking(move || {
+            let files = discover_workspace_files(&roots, &file_names, scan_depth);
+            lazy_index::build(base, files, open, &root, max_file_size)
+        })
+        .await;
+        *self.reindexing.lock().unwrap() = None;
+        let Ok((index, files, lazy)) = built else {
+            return;
+        };
+        self.swap_index(index, files);
+        *self.lazy.lock().unwrap() = lazy;
+        let indexed: Vec<PathBuf> = self.index.lock().unwrap().files.keys().cloned().collect();
+        self.index_sourced(indexed).await;
+        log::info!("reindexed the include closure of the root Kconfig");
+        self.publish_all_diagnostics().await;
+    }
+
+    /// `window/workDoneProgress/cancel`: stop a running reindex.
+    pub async fn work_done_progress_cancel(&self, params: WorkDoneProgressCancelParams) {
+        if params.token != NumberOrString::String(REINDEX_TOKEN.into()) {
+            return;
+        }
+        if let Some(cancelled) = &*self.reindexing.lock().unwrap() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// `kconfig/indexStats`: counts of indexed files and symbols, the
+    /// files left out, whether the index is being rebuilt, and how long
+    /// requests take.
+    pub async fn index_stats(&self) -> Result<IndexStats> {
+        self.request_metrics
+            .time("kconfig/indexStats", async move {
+                let indexing = self.indexing();
+                let requests = self.request_metrics.latencies();
+                let idx = self.index.lock().unwrap();
+                Ok(index_stats::index_stats(&idx, indexing, requests))
+            })
+            .await
+    }
+
+    /// Whether the startup scan or a reindex is running. Requests are served
+    /// meanwhile from the files indexed so far, or from the index the
+    /// reindex will replace.
+    fn indexing(&self) -> bool {
+        self.startup_queue.lock().unwrap().is_some() || self.reindexing.lock().unwrap().is_some()
+    }
+
+    /// While the index is being rebuilt, tell the user once that results may
+    /// be incomplete or out of date.
+    async fn note_stale_results(&self) {
+        if !self.indexing() || self.stale_notice_shown.swap(true, Ordering::Relaxed) {
+            return;
+        }
         self.client
-            .publish_diagnostics(uri.clone(), diags, None)
+            .show_message(
+                MessageType::INFO,
+                "Kconfig files are still being indexed; results may be incomplete or out of \
+                 date until indexing finishes.",
+            )
+            .await;
+    }
+
+    /// `kconfig/projectStats`: symbols per directory and the largest
+    /// offenders, as `kconfig-lsp stats` reports them.
+    pub async fn project_stats(&self, params: ProjectStatsParams) -> Result<ProjectStats> {
+        self.request_metrics
+            .time("kconfig/projectStats", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(project_stats::project_stats(&idx, &params))
+            })
+            .await
+    }
+
+    /// `kconfig/symbolMetrics`: definition and reference counts, fan-in,
+    /// fan-out and help size per symbol, as `kconfig-lsp metrics` exports
+    /// them.
+    pub async fn symbol_metrics(&self, params: SymbolMetricsParams) -> Result<Vec<SymbolMetrics>> {
+        self.request_metrics
+            .time("kconfig/symbolMetrics", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(symbol_metrics::symbol_metrics(&idx, &params))
+            })
+            .await
+    }
+
+    /// `kconfig/searchText`: find symbols by prompt or help text.
+    pub async fn search_text(&self, params: SearchTextParams) -> Result<Vec<SearchTextMatch>> {
+        self.request_metrics
+            .time("kconfig/searchText", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(search::search_text(&idx, &params))
+            })
+            .await
+    }
+
+    /// `kconfig/documentSymbolsDetailed`: every entry of a file with its
+    /// hover documentation.
+    pub async fn document_symbols_detailed(
+        &self,
+        params: DocumentSymbolsDetailedParams,
+    ) -> Result<Vec<DetailedSymbol>> {
+        self.request_metrics
+            .time("kconfig/documentSymbolsDetailed", async move {
+                let Some(path) = Self::uri_to_path(&params.text_document.uri) else {
+                    return Ok(Vec::new());
+                };
+                let idx = self.index.lock().unwrap();
+                Ok(symbol_docs::document_symbols_detailed(&idx, &path))
+            })
+            .await
+    }
+
+    /// `kconfig/menuTree`: the menu hierarchy of the root Kconfig (or of the
+    /// given file) as a JSON tree.
+    pub async fn menu_tree(&self, params: MenuTreeParams) -> Result<Option<MenuNode>> {
+        self.request_metrics
+            .time("kconfig/menuTree", async move {
+                let root = match &params.uri {
+                    Some(uri) => Self::uri_to_path(uri),
+                    None => self.root_kconfig(),
+                };
+                let Some(root) = root else {
+                    return Ok(None);
+                };
+                let idx = self.index.lock().unwrap();
+                Ok(menu_tree::menu_tree(&idx, &root))
+            })
+            .await
+    }
+
+    /// `kconfig/mergeConfig`: what `merge_config.sh` makes of a base
+    /// configuration and fragments, optionally written to a file. Open
+    /// fragments are read as edited.
+    pub async fn merge_config(&self, params: MergeConfigParams) -> Result<MergeConfigResult> {
+        self.request_metrics
+            .time("kconfig/mergeConfig", async move {
+                let mut inputs = Vec::new();
+                for uri in &params.fragments {
+                    let path = Self::uri_to_path(uri).ok_or_else(|| {
+                        tower_lsp::jsonrpc::Error::invalid_params(format!("not a file: {}", uri))
+                    })?;
+                    let text = match self.documents.get(&uri::normalize(uri)) {
+                        Some(text) => text.clone(),
+                        None => std::fs::read_to_string(&path).map_err(|e| {
+                            tower_lsp::jsonrpc::Error::invalid_params(format!(
+                                "cannot read {}: {}",
+                                path.display(),
+                                e
+                            ))
+                        })?,
+                    };
+                    inputs.push((path, text));
+                }
+                let prefix = self.settings.lock().unwrap().config_prefix.clone();
+                let mut result = merge_config::merge(&inputs, &prefix, &params);
+                if !params.merge_only {
+                    let idx = self.index.lock().unwrap();
+                    merge_config::check(&idx, &mut result, &prefix);
+                }
+                if let Some(output) = &params.output {
+                    let written = Self::uri_to_path(output)
+                        .ok_or_else(|| format!("not a file: {}", output))
+                        .and_then(|path| {
+                            std::fs::write(&path, &result.text)
+                                .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+                        });
+                    if let Err(e) = written {
+                        return Err(tower_lsp::jsonrpc::Error::invalid_params(e));
+                    }
+                }
+                Ok(result)
+            })
+            .await
+    }
+
+    /// `kconfig/removalImpact`: what would break if a symbol, or one of its
+    /// definitions, were removed.
+    pub async fn removal_impact(
+        &self,
+        params: RemovalImpactParams,
+    ) -> Result<Option<RemovalImpact>> {
+        self.request_metrics
+            .time("kconfig/removalImpact", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(impact::removal_impact(&idx, &params))
+            })
+            .await
+    }
+
+    /// `kconfig/relatedSymbols`: symbols sharing dependencies or selectors
+    /// with a symbol, or mentioning it in their help text.
+    pub async fn related_symbols(
+        &self,
+        params: RelatedSymbolsParams,
+    ) -> Result<Vec<RelatedSymbol>> {
+        self.request_metrics
+            .time("kconfig/relatedSymbols", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(related::related_symbols(&idx, &params))
+            })
+            .await
+    }
+
+    /// `kconfig/dependencyPath`: the chains of `depends on`, `select` and
+    /// `imply` leading from one symbol to another.
+    pub async fn dependency_path(
+        &self,
+        params: DependencyPathParams,
+    ) -> Result<Vec<DependencyPath>> {
+        self.request_metrics
+            .time("kconfig/dependencyPath", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(dependency_path::dependency_paths(&idx, &params))
+            })
+            .await
+    }
+
+    /// `kconfig/selectChain`: the `select` lines forcing a symbol on under
+    /// the loaded `.config`, followed back to where the chains start.
+    pub async fn select_chain(&self, params: SelectChainParams) -> Result<Option<SelectChain>> {
+        self.request_metrics
+            .time("kconfig/selectChain", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(select_chain::select_chain(&idx, &params))
+            })
+            .await
+    }
+
+    /// `kconfig/referencesTree`: the references of a symbol grouped by file
+    /// and kind.
+    pub async fn references_tree(
+        &self,
+        params: ReferencesTreeParams,
+    ) -> Result<Option<ReferencesTree>> {
+        self.request_metrics
+            .time("kconfig/referencesTree", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(references::references_tree(&idx, &params))
+            })
+            .await
+    }
+
+    /// `kconfig/report`: the contents of a `kconfig-report:` virtual
+    /// document.
+    pub async fn report(&self, params: ReportParams) -> Result<Option<ReportDocument>> {
+        self.request_metrics
+            .time("kconfig/report", async move {
+                let root = self.root_kconfig();
+                let idx = self.index.lock().unwrap();
+                Ok(report::report(&idx, root.as_deref(), &params.uri))
+            })
+            .await
+    }
+
+    /// `kconfig/resolvedSymbol`: every definition of a symbol merged into
+    /// one entry.
+    pub async fn resolved_symbol(
+        &self,
+        params: ResolvedSymbolParams,
+    ) -> Result<Option<ResolvedSymbol>> {
+        self.request_metrics
+            .time("kconfig/resolvedSymbol", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(resolved::resolve(&idx, &params.symbol))
+            })
+            .await
+    }
+
+    /// `kconfig/simulate`: symbol values under an `allnoconfig`-style preset.
+    pub async fn simulate(&self, params: SimulateParams) -> Result<SimulateResult> {
+        self.request_metrics
+            .time("kconfig/simulate", async move {
+                let root = self.workspace_root.lock().unwrap().clone();
+                let prefix = self.settings.lock().unwrap().config_prefix.clone();
+                let base = match (params.preset, root) {
+                    (Preset::Defconfig, Some(root)) => {
+                        let candidates = dotconfig::defconfig_list(&self.index.lock().unwrap());
+                        simulate::load_defconfig(&candidates, &root, &prefix)
+                    }
+                    _ => None,
+                };
+                let idx = self.index.lock().unwrap();
+                Ok(simulate::simulate(
+                    &idx,
+                    params.preset,
+                    base.as_ref(),
+                    params.symbols.as_deref(),
+                ))
+            })
+            .await
+    }
+
+    /// The configured root Kconfig file, resolved against the workspace root.
+    fn root_kconfig(&self) -> Option<PathBuf> {
+        let setting = self.settings.lock().unwrap().root_kconfig.clone();
+        let root = self.workspace_root.lock().unwrap().clone();
+        match root {
+            Some(root) => Some(root.join(setting)),
+            None if Path::new(&setting).is_absolute() => Some(PathBuf::from(setting)),
+            None => None,
+        }
+    }
+
+    /// Republish the diagnostics of the files other than `uri` that
+    /// reference a name in `changed` and have diagnostics showing: open
+    /// files, and unopened ones with workspace diagnostics.
+    async fn refresh_referencing(&self, uri: &Url, changed: &BTreeSet<String>) {
+        if changed.is_empty() {
+            return;
+        }
+        let files = self.index.lock().unwrap().files_referencing(changed);
+        let uris: Vec<Url> = {
+            let published = self.published.lock().unwrap();
+            files
+                .iter()
+                .filter_map(|p| uri::from_path(p))
+                .filter(|u| u != uri && (self.documents.contains_key(u) || published.contains(u)))
+                .collect()
+        };
+        for u in &uris {
+            if let Some(path) = Self::uri_to_path(u) {
+                self.index_lazily(&path).await;
+            }
+        }
+        let (epoch, diags) = self.diagnostics_snapshot(&uris, false).await;
+        self.publish_snapshot(epoch, diags).await;
+    }
+
+    async fn publish_diagnostics(&self, uri: &Url) {
+        if let Some(path) = Self::uri_to_path(uri) {
+            self.index_lazily(&path).await;
+        }
+        let (epoch, diags) = self
+            .diagnostics_snapshot(std::slice::from_ref(uri), false)
             .await;
+        self.publish_snapshot(epoch, diags).await;
+    }
+
+    /// The diagnostics of the documents `uris` and, with `workspace` and
+    /// the `workspaceDiagnostics` setting, of the unopened files, all
+    /// computed from one state of the index, with its epoch. A workspace
+    /// pass visits every file, so it runs on a blocking thread.
+    async fn diagnostics_snapshot(
+        &self,
+        uris: &[Url],
+        workspace: bool,
+    ) -> (u64, Vec<(Url, Vec<Diagnostic>)>) {
+        let settings = self.settings.lock().unwrap().clone();
+        let documents: Vec<(Url, PathBuf)> = uris
+            .iter()
+            .filter_map(|uri| Some((uri.clone(), Self::uri_to_path(uri)?)))
+            .collect();
+        let open: HashSet<Url> = self.documents.iter().map(|d| d.key().clone()).collect();
+        let index = self.index.clone();
+        let snapshot = move || {
+            let idx = index.lock().unwrap();
+            let mut diags: Vec<(Url, Vec<Diagnostic>)> = documents
+                .into_iter()
+                .map(|(uri, path)| (uri, diagnostics::collect(&idx, &path, &settings)))
+                .collect();
+            if workspace && settings.workspace_diagnostics {
+                diags.extend(
+                    diagnostics::collect_workspace(&idx, &settings)
+                        .into_iter()
+                        .filter_map(|(path, diags)| Some((uri::from_path(path)?, diags)))
+                        .filter(|(uri, _)| !open.contains(uri)),
+                );
+            }
+            (idx.epoch, diags)
+        };
+        if workspace {
+            tokio::task::spawn_blocking(snapshot)
+                .await
+                .unwrap_or_default()
+        } else {
+            snapshot()
+        }
+    }
+
+    /// Publish diagnostics computed at index epoch `epoch`, except for the
+    /// files that already show diagnostics from a later epoch.
+    async fn publish_snapshot(&self, epoch: u64, diags: Vec<(Url, Vec<Diagnostic>)>) {
+        let caps = *self.client_caps.lock().unwrap();
+        let mut epochs = self.diagnostics_epochs.lock().await;
+        for (uri, diags) in diags {
+            if !epochs.admit(&uri, epoch) {
+                continue;
+            }
+            let uri = self
+                .opened_as
+                .get(&uri)
+                .map_or_else(|| uri.clone(), |u| u.clone());
+            self.client
+                .publish_diagnostics(uri, caps.diagnostics(diags), None)
+                .await;
+        }
     }
 }
 
@@ -58,18 +1442,47 @@ impl LanguageServer for Backend {
         let root = params
             .root_uri
             .as_ref()
-            .and_then(|u| u.to_file_path().ok())
+            .filter(|u| u.scheme() == "file")
+            .and_then(uri::to_path)
             .or_else(|| {
                 params
                     .workspace_folders
                     .as_ref()
                     .and_then(|wf| wf.first())
-                    .and_then(|f| f.uri.to_file_path().ok())
+                    .filter(|f| f.uri.scheme() == "file")
+                    .and_then(|f| uri::to_path(&f.uri))
             });
         if let Some(root) = root {
             log::info!("workspace root: {}", root.display());
+            self.index.lock().unwrap().root = Some(root.clone());
             *self.workspace_root.lock().unwrap() = Some(root);
         }
+        let caps = ClientCaps::new(&params.capabilities);
+        *self.client_caps.lock().unwrap() = caps;
+        let (mut settings, problems) = Settings::from_json(params.initialization_options);
+        *self.settings_problems.lock().unwrap() = problems;
+        let root = self.workspace_root.lock().unwrap().clone();
+        let mut applied = Profile::Auto;
+        if let Some(root) = &root {
+            let auto = settings.profile == Profile::Auto;
+            applied = profile::apply(&mut settings, root);
+            log::info!("workspace profile: {}", applied.name());
+            if auto && applied != Profile::Generic {
+                *self.profile_message.lock().unwrap() = Some(profile::describe(applied, &settings));
+            }
+        }
+        let preprocessor = settings.effective_preprocessor(root.as_deref());
+        let trigger_characters = settings.completion.trigger_characters.clone();
+        let dialect = settings.dialect;
+        *self.settings.lock().unwrap() = settings;
+        let root_kconfig = self.root_kconfig();
+        {
+            let mut idx = self.index.lock().unwrap();
+            idx.root_kconfig = root_kconfig;
+            idx.preprocessor = preprocessor;
+            idx.profile = applied;
+            idx.dialect = dialect;
+        }
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
@@ -78,9 +1491,52 @@ impl LanguageServer for Backend {
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 definition_provider: Some(OneOf::Left(true)),
+                type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
                 references_provider: Some(OneOf::Left(true)),
-                completion_provider: Some(CompletionOptions {
-                    trigger_characters: Some(vec![" ".into(), "\t".into()]),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
+                linked_editing_range_provider: Some(LinkedEditingRangeServerCapabilities::Simple(
+                    true,
+                )),
+                code_action_provider: caps.code_actions.then(|| {
+                    CodeActionProviderCapability::Options(CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::SOURCE_FIX_ALL,
+                            CodeActionKind::SOURCE,
+                            CodeActionKind::REFACTOR_EXTRACT,
+                            CodeActionKind::REFACTOR_INLINE,
+                        ]),
+                        ..Default::default()
+                    })
+                }),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        SemanticTokensOptions {
+                            legend: config_doc::legend(),
+                            full: Some(SemanticTokensFullOptions::Bool(true)),
+                            ..Default::default()
+                        },
+                    ),
+                ),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        REINDEX_COMMAND.into(),
+                        debug_dump::DUMP_TOKENS_COMMAND.into(),
+                        debug_dump::DUMP_AST_COMMAND.into(),
+                    ],
+                    ..Default::default()
+                }),
+                code_lens_provider: caps.code_lens.then_some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                completion_provider: caps.completion.then(|| CompletionOptions {
+                    trigger_characters: Some(trigger_characters),
+                    resolve_provider: Some(true),
                     ..Default::default()
                 }),
                 ..Default::default()
@@ -95,35 +1551,178 @@ impl LanguageServer for Backend {
     async fn initialized(&self, _params: InitializedParams) {
         log::info!("kconfig-lsp initialized");
 
+        let message = self.profile_message.lock().unwrap().take();
+        if let Some(message) = message {
+            self.client.show_message(MessageType::INFO, message).await;
+        }
+        let problems = std::mem::take(&mut *self.settings_problems.lock().unwrap());
+        self.show_settings_problems(problems).await;
+
         let root = self.workspace_root.lock().unwrap().clone();
         if let Some(root) = root {
-            let kconfig_files = discover_kconfig_files(&root);
-            log::info!(
-                "discovered {} Kconfig files in workspace",
-                kconfig_files.len()
-            );
+            self.index_workspace(&root).await;
+        }
+
+        self.load_config();
+        self.load_build_config();
+        self.watch_config().await;
+        self.register_type_hierarchy().await;
+
+        // Re-publish diagnostics for any already-open files so that symbols
+        // resolved by the workspace scan clear their warnings.
+        self.publish_all_diagnostics().await;
 
-            let mut ws_files = self.workspace_files.lock().unwrap();
+        self.ask_shell_trust().await;
+        self.run_shell_commands().await;
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let Some((mut new, problems)) = Settings::from_change(params.settings) else {
+            return;
+        };
+        self.show_settings_problems(problems).await;
+        let root = self.workspace_root.lock().unwrap().clone();
+        let applied = match &root {
+            Some(root) => profile::apply(&mut new, root),
+            None => Profile::Auto,
+        };
+        let old = std::mem::replace(&mut *self.settings.lock().unwrap(), new.clone());
+        log::info!("settings changed");
+
+        let root_kconfig = self.root_kconfig();
+        {
             let mut idx = self.index.lock().unwrap();
-            for path in kconfig_files {
-                match std::fs::read_to_string(&path) {
-                    Ok(source) => {
-                        idx.analyze_file(&path, &source);
-                        ws_files.insert(path);
-                    }
-                    Err(e) => {
-                        log::warn!("failed to read {}: {}", path.display(), e);
-                    }
-                }
+            idx.root_kconfig = root_kconfig;
+            let mut preprocessor = new.effective_preprocessor(root.as_deref());
+            if old.preprocessor.shell == new.preprocessor.shell {
+                // Keep the answer to the trust prompt.
+                preprocessor.shell = idx.preprocessor.shell;
             }
+            idx.preprocessor = preprocessor;
+            idx.profile = applied;
+            idx.dialect = new.dialect;
+            // `source` statements may resolve elsewhere now.
+            idx.epoch += 1;
+        }
+        let scan_roots_changed = root
+            .as_deref()
+            .is_some_and(|root| old.scan_roots(root) != new.scan_roots(root));
+        if old.dialect != new.dialect
+            || old.scan_depth != new.scan_depth
+            || old.indexing != new.indexing
+            || old.file_names != new.file_names
+            || old.max_file_size != new.max_file_size
+            || scan_roots_changed
+        {
+            // Every file has to be lexed again, or the set of files changed;
+            // `reindex` publishes diagnostics once done, without holding up
+            // this notification meanwhile.
+            let backend = self.clone();
+            tokio::spawn(async move { backend.reindex().await });
         }
+        if old.config_path != new.config_path
+            || old.config_prefix != new.config_prefix
+            || old.build_config != new.build_config
+            || old.config_fragments != new.config_fragments
+        {
+            if self.load_config().is_none() {
+                let mut idx = self.index.lock().unwrap();
+                idx.config = None;
+                idx.fragments.clear();
+                idx.epoch += 1;
+            }
+            self.load_build_config();
+            if self.client_caps.lock().unwrap().watched_files {
+                let _ = self
+                    .client
+                    .unregister_capability(vec![Unregistration {
+                        id: CONFIG_WATCH_ID.into(),
+                        method: "workspace/didChangeWatchedFiles".into(),
+                    }])
+                    .await;
+            }
+            self.watch_config().await;
+        }
+        self.publish_all_diagnostics().await;
+        self.ask_shell_trust().await;
+        self.run_shell_commands().await;
+    }
 
-        // Re-publish diagnostics for any already-open files so that symbols
-        // resolved by the workspace scan clear their warnings.
-        let open_uris: Vec<Url> = self.documents.iter().map(|e| e.key().clone()).collect();
-        for uri in open_uris {
-            self.publish_diagnostics(&uri).await;
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        if let Some(build) = self.build_config_file()
+            && params
+                .changes
+                .iter()
+                .any(|c| Self::uri_to_path(&c.uri).as_deref() == Some(build.as_path()))
+        {
+            log::info!("{} changed", build.display());
+            self.load_build_config();
         }
+        let watched = self.watched_config_files();
+        let Some(change) = params
+            .changes
+            .into_iter()
+            .find(|c| Self::uri_to_path(&c.uri).is_some_and(|p| watched.contains(&p)))
+        else {
+            return;
+        };
+        let Some(changed) = self.load_config() else {
+            return;
+        };
+        let Some(config) = self.config_file() else {
+            return;
+        };
+        log::info!("{} changed: {} symbol values", config.display(), changed);
+        self.publish_all_diagnostics().await;
+        self.client
+            .send_notification::<ConfigReloaded>(ConfigReloadedParams {
+                uri: change.uri,
+                changed,
+            })
+            .await;
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        self.request_metrics
+            .time("workspace/executeCommand", async move {
+                match params.command.as_str() {
+                    REINDEX_COMMAND => {
+                        // Answered at once; the rebuild reports through its
+                        // work-done progress.
+                        let backend = self.clone();
+                        tokio::spawn(async move { backend.reindex().await });
+                        Ok(None)
+                    }
+                    debug_dump::DUMP_TOKENS_COMMAND | debug_dump::DUMP_AST_COMMAND => {
+                        let source = params
+                            .arguments
+                            .first()
+                            .and_then(|uri| serde_json::from_value::<Url>(uri.clone()).ok())
+                            .and_then(|uri| self.source(&uri))
+                            .ok_or_else(|| {
+                                tower_lsp::jsonrpc::Error::invalid_params(
+                                    "expected the URI of an open or indexed file",
+                                )
+                            })?;
+                        let dialect = self.settings.lock().unwrap().dialect;
+                        let dump = match params.command.as_str() {
+                            debug_dump::DUMP_TOKENS_COMMAND => {
+                                debug_dump::dump_tokens(&source, dialect)
+                            }
+                            _ => debug_dump::dump_ast(&source, dialect),
+                        };
+                        Ok(Some(serde_json::Value::String(dump)))
+                    }
+                    other => Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                        "unknown command `{}`",
+                        other
+                    ))),
+                }
+            })
+            .await
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -131,103 +1730,557 @@ impl LanguageServer for Backend {
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        let uri = params.text_document.uri;
-        let text = params.text_document.text;
-        self.documents.insert(uri.clone(), text.clone());
+        self.request_metrics
+            .time("textDocument/didOpen", async move {
+                let uri = uri::normalize(&params.text_document.uri);
+                if uri != params.text_document.uri {
+                    self.opened_as.insert(uri.clone(), params.text_document.uri);
+                }
+                let text = params.text_document.text;
+                self.documents.insert(uri.clone(), text.clone());
 
-        if let Some(path) = Self::uri_to_path(&uri) {
-            let mut idx = self.index.lock().unwrap();
-            idx.reanalyze_file(&path, &text);
-        }
-        self.publish_diagnostics(&uri).await;
+                if let Some(path) = Self::uri_to_path(&uri) {
+                    if self.is_config_document(&path) {
+                        return;
+                    }
+                    self.lazy.lock().unwrap().remove(&path);
+                    let (changed, sources_changed) = self.reanalyze(&path, &text);
+                    self.refresh_referencing(&uri, &changed).await;
+                    if sources_changed {
+                        self.index_sourced(vec![path.clone()]).await;
+                    }
+                    // Still starting up: index what this file touches next.
+                    let root = self.workspace_root.lock().unwrap().clone();
+                    if let Some(root) = root {
+                        if let Some(queue) = self.startup_queue.lock().unwrap().as_mut() {
+                            queue.prioritize(&path);
+                        }
+                        self.prioritize_neighbours(&root, &path);
+                    }
+                }
+                self.publish_diagnostics(&uri).await;
+            })
+            .await
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        let uri = params.text_document.uri;
-        if let Some(change) = params.content_changes.into_iter().last() {
-            let text = change.text;
-            self.documents.insert(uri.clone(), text.clone());
+        self.request_metrics
+            .time("textDocument/didChange", async move {
+                let uri = uri::normalize(&params.text_document.uri);
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    let text = change.text;
+                    self.documents.insert(uri.clone(), text.clone());
 
-            if let Some(path) = Self::uri_to_path(&uri) {
-                let mut idx = self.index.lock().unwrap();
-                idx.reanalyze_file(&path, &text);
-            }
-            self.publish_diagnostics(&uri).await;
-        }
+                    if let Some(path) = Self::uri_to_path(&uri) {
+                        if self.is_config_document(&path) {
+                            return;
+                        }
+                        let (changed, sources_changed) = self.reanalyze(&path, &text);
+                        self.refresh_referencing(&uri, &changed).await;
+                        if sources_changed {
+                            self.index_sourced(vec![path]).await;
+                        }
+                    }
+                    self.publish_diagnostics(&uri).await;
+                }
+            })
+            .await
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        let uri = params.text_document.uri;
+        let uri = uri::normalize(&params.text_document.uri);
         self.documents.remove(&uri);
+        // Diagnostics from here on go to the normalized URI; clear the ones
+        // shown under the spelling the editor used.
+        if let Some((_, opened)) = self.opened_as.remove(&uri) {
+            self.client
+                .publish_diagnostics(opened, Vec::new(), None)
+                .await;
+        }
 
         if let Some(path) = Self::uri_to_path(&uri) {
             let is_workspace_file = self.workspace_files.lock().unwrap().contains(&path);
-            if is_workspace_file {
-                if let Ok(source) = std::fs::read_to_string(&path) {
-                    let mut idx = self.index.lock().unwrap();
-                    idx.reanalyze_file(&path, &source);
+            let max_file_size = self.settings.lock().unwrap().max_file_size;
+            if is_workspace_file && let Ok(source) = index_stats::read_kconfig(&path, max_file_size)
+            {
+                let changed = self.index.lock().unwrap().reanalyze_file(&path, &source);
+                self.refresh_referencing(&uri, &changed).await;
+                // The editor's version may have had other problems.
+                if self.settings.lock().unwrap().workspace_diagnostics {
+                    self.publish_diagnostics(&uri).await;
+                    self.published.lock().unwrap().insert(uri.clone());
                 }
+            } else if uri::is_virtual(&path) {
+                // Nothing on disk to fall back to once the buffer is gone.
+                let epoch = {
+                    let mut idx = self.index.lock().unwrap();
+                    idx.remove_file(&path);
+                    idx.epoch
+                };
+                self.publish_snapshot(epoch, vec![(uri.clone(), Vec::new())])
+                    .await;
             }
         }
+        // Unless workspace diagnostics keep showing them, its diagnostics
+        // are not published again until it is reopened.
+        if !self.published.lock().unwrap().contains(&uri) {
+            self.diagnostics_epochs.lock().await.forget(&uri);
+        }
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
-        let uri = &params.text_document_position_params.text_document.uri;
-        let pos = params.text_document_position_params.position;
+        self.request_metrics
+            .time("textDocument/hover", async move {
+                let uri = &params.text_document_position_params.text_document.uri;
+                let pos = params.text_document_position_params.position;
 
-        let idx = self.index.lock().unwrap();
-        let path = match Self::uri_to_path(uri) {
-            Some(p) => p,
-            None => return Ok(None),
-        };
-        Ok(hover::hover(&idx, &path, pos))
+                self.note_stale_results().await;
+                let format = self.client_caps.lock().unwrap().hover_format();
+                let settings = self.settings.lock().unwrap().hover.clone();
+                let path = match Self::uri_to_path(uri) {
+                    Some(p) => p,
+                    None => return Ok(None),
+                };
+                self.index_lazily(&path).await;
+                let idx = self.index.lock().unwrap();
+                Ok(hover::hover(&idx, &path, pos, format, &settings))
+            })
+            .await
     }
 
     async fn goto_definition(
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
-        let uri = &params.text_document_position_params.text_document.uri;
-        let pos = params.text_document_position_params.position;
+        self.request_metrics
+            .time("textDocument/definition", async move {
+                let uri = &params.text_document_position_params.text_document.uri;
+                let pos = params.text_document_position_params.position;
 
-        let idx = self.index.lock().unwrap();
-        let path = match Self::uri_to_path(uri) {
-            Some(p) => p,
-            None => return Ok(None),
-        };
-        Ok(definition::goto_definition(&idx, &path, pos))
+                self.note_stale_results().await;
+                let path = match Self::uri_to_path(uri) {
+                    Some(p) => p,
+                    None => return Ok(None),
+                };
+                self.index_lazily(&path).await;
+                let idx = self.index.lock().unwrap();
+                Ok(definition::goto_definition(&idx, &path, pos))
+            })
+            .await
+    }
+
+    async fn goto_type_definition(
+        &self,
+        params: request::GotoTypeDefinitionParams,
+    ) -> Result<Option<request::GotoTypeDefinitionResponse>> {
+        self.request_metrics
+            .time("textDocument/typeDefinition", async move {
+                let uri = &params.text_document_position_params.text_document.uri;
+                let pos = params.text_document_position_params.position;
+
+                let idx = self.index.lock().unwrap();
+                let path = match Self::uri_to_path(uri) {
+                    Some(p) => p,
+                    None => return Ok(None),
+                };
+                Ok(definition::goto_containing_choice(&idx, &path, pos))
+            })
+            .await
     }
 
     async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
-        let uri = &params.text_document_position.text_document.uri;
-        let pos = params.text_document_position.position;
+        self.request_metrics
+            .time("textDocument/references", async move {
+                let uri = &params.text_document_position.text_document.uri;
+                let pos = params.text_document_position.position;
+                let sort = self.settings.lock().unwrap().references.sort;
 
-        let idx = self.index.lock().unwrap();
-        let path = match Self::uri_to_path(uri) {
-            Some(p) => p,
-            None => return Ok(None),
-        };
-        Ok(references::find_references(&idx, &path, pos))
+                let idx = self.index.lock().unwrap();
+                let path = match Self::uri_to_path(uri) {
+                    Some(p) => p,
+                    None => return Ok(None),
+                };
+                Ok(references::find_references(
+                    &idx,
+                    &path,
+                    pos,
+                    params.context.include_declaration,
+                    sort,
+                ))
+            })
+            .await
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        self.request_metrics
+            .time("textDocument/documentSymbol", async move {
+                let Some(path) = Self::uri_to_path(&params.text_document.uri) else {
+                    return Ok(None);
+                };
+                let idx = self.index.lock().unwrap();
+                Ok(symbol_docs::document_symbols(&idx, &path).map(DocumentSymbolResponse::Nested))
+            })
+            .await
+    }
+
+    async fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        self.request_metrics
+            .time("textDocument/prepareTypeHierarchy", async move {
+                let uri = &params.text_document_position_params.text_document.uri;
+                let pos = params.text_document_position_params.position;
+                let Some(path) = Self::uri_to_path(uri) else {
+                    return Ok(None);
+                };
+                let idx = self.index.lock().unwrap();
+                Ok(type_hierarchy::prepare(&idx, &path, pos))
+            })
+            .await
+    }
+
+    async fn supertypes(
+        &self,
+        params: TypeHierarchySupertypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        self.request_metrics
+            .time("typeHierarchy/supertypes", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(type_hierarchy::supertypes(&idx, &params.item))
+            })
+            .await
+    }
+
+    async fn subtypes(
+        &self,
+        params: TypeHierarchySubtypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        self.request_metrics
+            .time("typeHierarchy/subtypes", async move {
+                let idx = self.index.lock().unwrap();
+                Ok(type_hierarchy::subtypes(&idx, &params.item))
+            })
+            .await
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        self.request_metrics
+            .time("textDocument/semanticTokens/full", async move {
+                let uri = &params.text_document.uri;
+                if !Self::uri_to_path(uri).is_some_and(|p| self.is_config_document(&p)) {
+                    return Ok(None);
+                }
+                let Some(text) = self.document(uri) else {
+                    return Ok(None);
+                };
+                let prefix = self.settings.lock().unwrap().config_prefix.clone();
+                let idx = self.index.lock().unwrap();
+                Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+                    result_id: None,
+                    data: config_doc::semantic_tokens(&idx, &text, &prefix),
+                })))
+            })
+            .await
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        self.request_metrics
+            .time("textDocument/prepareRename", async move {
+                let Some(path) = Self::uri_to_path(&params.text_document.uri) else {
+                    return Ok(None);
+                };
+                let idx = self.index.lock().unwrap();
+                rename::prepare_rename(&idx, &path, params.position)
+                    .map(|range| Some(PrepareRenameResponse::Range(range)))
+                    .map_err(tower_lsp::jsonrpc::Error::invalid_params)
+            })
+            .await
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        self.request_metrics
+            .time("textDocument/rename", async move {
+                let uri = &params.text_document_position.text_document.uri;
+                let pos = params.text_document_position.position;
+                let Some(path) = Self::uri_to_path(uri) else {
+                    return Ok(None);
+                };
+                let old_name = {
+                    let idx = self.index.lock().unwrap();
+                    rename::renamed_symbol(&idx, &path, pos, &params.new_name)
+                        .map_err(tower_lsp::jsonrpc::Error::invalid_params)?
+                };
+                let token = params.work_done_progress_params.work_done_token;
+                let build = self
+                    .build_file_edits(token, old_name, params.new_name.clone())
+                    .await;
+                let (edit, collision) = {
+                    let idx = self.index.lock().unwrap();
+                    let edit = rename::rename(&idx, &path, pos, &params.new_name, build.as_ref())
+                        .map_err(tower_lsp::jsonrpc::Error::invalid_params)?;
+                    (edit, rename::collision(&idx, &params.new_name))
+                };
+                if let Some(c) = collision
+                    && !c.in_tree
+                {
+                    self.client
+                        .show_message(
+                            MessageType::WARNING,
+                            format!(
+                                "`{}` is already defined outside the active tree, at {}; \
+                                 builds that reach both definitions will see one symbol",
+                                params.new_name, c.place
+                            ),
+                        )
+                        .await;
+                }
+                Ok(Some(edit))
+            })
+            .await
+    }
+
+    async fn linked_editing_range(
+        &self,
+        params: LinkedEditingRangeParams,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        self.request_metrics
+            .time("textDocument/linkedEditingRange", async move {
+                let uri = &params.text_document_position_params.text_document.uri;
+                let pos = params.text_document_position_params.position;
+                let Some(path) = Self::uri_to_path(uri) else {
+                    return Ok(None);
+                };
+                let idx = self.index.lock().unwrap();
+                Ok(linked_editing::linked_editing_ranges(&idx, &path, pos))
+            })
+            .await
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        let uri = &params.text_document_position.text_document.uri;
-        let pos = params.text_document_position.position;
+        self.request_metrics
+            .time("textDocument/completion", async move {
+                let uri = &params.text_document_position.text_document.uri;
+                let pos = params.text_document_position.position;
+                // Typing a trigger character in help text or a prompt is writing
+                // prose, not asking for symbols. Characters the settings no longer
+                // list are ignored too, as they stay registered until a restart.
+                let automatic = params.context.and_then(|c| {
+                    (c.trigger_kind == CompletionTriggerKind::TRIGGER_CHARACTER)
+                        .then_some(c.trigger_character)
+                });
+                if let Some(trigger) = &automatic {
+                    let settings = self.settings.lock().unwrap();
+                    let listed = &settings.completion.trigger_characters;
+                    if trigger.as_ref().is_some_and(|t| !listed.contains(t)) {
+                        return Ok(None);
+                    }
+                }
 
-        let idx = self.index.lock().unwrap();
-        let path = match Self::uri_to_path(uri) {
-            Some(p) => p,
-            None => return Ok(None),
-        };
-        Ok(completion::complete(&idx, &path, pos))
+                let snippets = self.client_caps.lock().unwrap().completion_snippets;
+                let idx = self.index.lock().unwrap();
+                let path = match Self::uri_to_path(uri) {
+                    Some(p) => p,
+                    None => return Ok(None),
+                };
+                if automatic.is_some() && completion::in_text(&idx, &path, pos) {
+                    return Ok(None);
+                }
+                Ok(completion::complete(&idx, &path, pos, snippets))
+            })
+            .await
+    }
+
+    async fn completion_resolve(&self, item: CompletionItem) -> Result<CompletionItem> {
+        self.request_metrics
+            .time("completionItem/resolve", async move {
+                let format = self.client_caps.lock().unwrap().completion_format();
+                let settings = self.settings.lock().unwrap().hover.clone();
+                let idx = self.index.lock().unwrap();
+                Ok(completion::resolve(&idx, item, format, &settings))
+            })
+            .await
+    }
+
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        self.request_metrics
+            .time("textDocument/codeLens", async move {
+                let uri = &params.text_document.uri;
+                let idx = self.index.lock().unwrap();
+                let path = match Self::uri_to_path(uri) {
+                    Some(p) => p,
+                    None => return Ok(None),
+                };
+                Ok(Some(impact::code_lenses(&idx, uri, &path)))
+            })
+            .await
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        self.request_metrics
+            .time("textDocument/formatting", async move {
+                let Some(text) = self.document(&params.text_document.uri) else {
+                    return Ok(None);
+                };
+                let options = FormatOptions {
+                    tab_size: params.options.tab_size,
+                    insert_spaces: params.options.insert_spaces,
+                };
+                let index = LineIndex::new(&text);
+                let edits = format::edits(&text, &options)
+                    .into_iter()
+                    .map(|e| TextEdit {
+                        range: util::range(&index, e.span),
+                        new_text: e.new_text,
+                    })
+                    .collect();
+                Ok(Some(edits))
+            })
+            .await
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        self.request_metrics
+            .time("textDocument/codeAction", async move {
+                let uri = &params.text_document.uri;
+                let settings = self.settings.lock().unwrap().clone();
+
+                let path = match Self::uri_to_path(uri) {
+                    Some(p) => p,
+                    None => return Ok(None),
+                };
+                let config_text = self
+                    .is_config_document(&path)
+                    .then(|| self.document(uri))
+                    .flatten();
+                let idx = self.index.lock().unwrap();
+                let actions = match config_text {
+                    Some(text) => config_doc::code_actions(
+                        &idx,
+                        uri,
+                        &text,
+                        params.range,
+                        &settings.config_prefix,
+                    ),
+                    None => code_action::code_actions(&idx, uri, &path, params.range, &settings),
+                };
+                Ok(if actions.is_empty() {
+                    None
+                } else {
+                    Some(actions)
+                })
+            })
+            .await
     }
 }
 
-fn discover_kconfig_files(root: &Path) -> Vec<PathBuf> {
+/// The language server with its `kconfig/...` requests, ready to be served
+/// over a transport.
+pub fn service() -> (LspService<Backend>, ClientSocket) {
+    LspService::build(Backend::new)
+        .custom_method("kconfig/searchText", Backend::search_text)
+        .custom_method("kconfig/simulate", Backend::simulate)
+        .custom_method("kconfig/menuTree", Backend::menu_tree)
+        .custom_method("kconfig/mergeConfig", Backend::merge_config)
+        .custom_method("kconfig/removalImpact", Backend::removal_impact)
+        .custom_method("kconfig/referencesTree", Backend::references_tree)
+        .custom_method("kconfig/relatedSymbols", Backend::related_symbols)
+        .custom_method("kconfig/dependencyPath", Backend::dependency_path)
+        .custom_method("kconfig/selectChain", Backend::select_chain)
+        .custom_method("kconfig/report", Backend::report)
+        .custom_method("kconfig/resolvedSymbol", Backend::resolved_symbol)
+        .custom_method("kconfig/indexStats", Backend::index_stats)
+        .custom_method("kconfig/projectStats", Backend::project_stats)
+        .custom_method("kconfig/symbolMetrics", Backend::symbol_metrics)
+        .custom_method(
+            "kconfig/documentSymbolsDetailed",
+            Backend::document_symbols_detailed,
+        )
+        .custom_method(
+            "window/workDoneProgress/cancel",
+            Backend::work_done_progress_cancel,
+        )
+        .finish()
+}
+
+/// Index every file named like `file_names` under `roots`, down to
+/// `max_depth` levels, lexed as `dialect`, calling `progress(done, total)`
+/// after each file. Files over `max_file_size` bytes or not text are
+/// recorded as skipped. `None` once `cancelled` is set.
+pub fn build_index(
+    roots: &[PathBuf],
+    file_names: &[String],
+    max_depth: Option<usize>,
+    max_file_size: u64,
+    dialect: Dialect,
+    cancelled: &AtomicBool,
+    progress: impl Fn(usize, usize),
+) -> Option<(WorldIndex, HashSet<PathBuf>)> {
+    let paths = discover_workspace_files(roots, file_names, max_depth);
+    let mut index = WorldIndex::new();
+    index.dialect = dialect;
+    let mut files = HashSet::new();
+    for (i, path) in paths.iter().enumerate() {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        match index_stats::read_kconfig(path, max_file_size) {
+            Ok(source) => {
+                index.analyze_file(path, &source);
+                files.insert(path.clone());
+            }
+            Err(reason) => skip(&mut index, path, reason),
+        }
+        progress(i + 1, paths.len());
+    }
+    Some((index, files))
+}
+
+fn skip(index: &mut WorldIndex, path: &Path, reason: String) {
+    log::warn!("not indexing {}: {}", path.display(), reason);
+    index.skipped.push(SkippedFile {
+        path: path.to_path_buf(),
+        reason,
+    });
+}
+
+/// All files under any of `roots`, at most `max_depth` directory levels
+/// down when given, whose name matches one of `file_names`, each once.
+/// Roots that do not exist (yet), like a build directory, contribute
+/// nothing.
+pub fn discover_workspace_files(
+    roots: &[PathBuf],
+    file_names: &[String],
+    max_depth: Option<usize>,
+) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = roots
+        .iter()
+        .flat_map(|r| discover_files(r, file_names, max_depth))
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// All Kconfig files under `root`, skipping VCS and tooling directories.
+pub fn discover_kconfig_files(root: &Path) -> Vec<PathBuf> {
+    discover_files(root, &Settings::default().file_names, None)
+}
+
+fn discover_files(root: &Path, file_names: &[String], max_depth: Option<usize>) -> Vec<PathBuf> {
     let mut result = Vec::new();
-    let mut stack = vec![root.to_path_buf()];
+    let mut stack = vec![(root.to_path_buf(), 0)];
 
-    while let Some(dir) = stack.pop() {
+    while let Some((dir, depth)) = stack.pop() {
         let entries = match std::fs::read_dir(&dir) {
             Ok(e) => e,
             Err(_) => continue,
@@ -235,10 +2288,10 @@ fn discover_kconfig_files(root: &Path) -> Vec<PathBuf> {
         for entry in entries.flatten() {
             let path = entry.path();
             if path.is_dir() {
-                if !is_ignored_dir(&path) {
-                    stack.push(path);
+                if !is_ignored_dir(&path) && max_depth.is_none_or(|max| depth < max) {
+                    stack.push((path, depth + 1));
                 }
-            } else if is_kconfig_file(&path) {
+            } else if matches_file_names(&path, file_names) {
                 result.push(path);
             }
         }
@@ -247,12 +2300,31 @@ fn discover_kconfig_files(root: &Path) -> Vec<PathBuf> {
     result
 }
 
-fn is_kconfig_file(path: &Path) -> bool {
-    let name = match path.file_name().and_then(|n| n.to_str()) {
-        Some(n) => n,
-        None => return false,
+/// Whether `path` is named like a Kconfig file by default.
+pub fn is_kconfig_file(path: &Path) -> bool {
+    matches_file_names(path, &Settings::default().file_names)
+}
+
+fn matches_file_names(path: &Path, patterns: &[String]) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
     };
-    name == "Kconfig" || name.starts_with("Kconfig.") || name.starts_with("Kconfig_")
+    patterns.iter().any(|p| glob_match(p, name))
+}
+
+/// `pattern` against `name`, where `*` matches any run of characters.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((head, tail)) => {
+            let Some(rest) = name.strip_prefix(head) else {
+                return false;
+            };
+            (0..=rest.len())
+                .filter(|&i| rest.is_char_boundary(i))
+                .any(|i| glob_match(tail, &rest[i..]))
+        }
+    }
 }
 
 fn is_ignored_dir(path: &Path) -> bool {
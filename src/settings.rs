@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::analysis::normalize;
+use crate::lexer::Dialect;
+use crate::profile::Profile;
+
+/// User-configurable server settings, supplied by the client through
+/// `initializationOptions`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub completion: CompletionSettings,
+    pub references: ReferenceSettings,
+    pub lint: LintSettings,
+    pub hover: HoverSettings,
+    pub rename: RenameSettings,
+    /// Root Kconfig file, absolute or relative to the workspace root.
+    pub root_kconfig: String,
+    /// Path of the `.config` to evaluate against, absolute or relative to the
+    /// workspace root. Defaults to `<root>/.config` when that file exists.
+    pub config_path: Option<String>,
+    /// Prefix of symbol assignments in the `.config` file.
+    pub config_prefix: String,
+    /// The configuration the build generates from the `.config`, such as
+    /// `include/config/auto.conf` or `include/generated/autoconf.h`.
+    /// Absolute or relative to the workspace root. Hover shows the value
+    /// there when it differs from the `.config`'s.
+    pub build_config: Option<String>,
+    /// The configuration fragments the `.config` was merged from, in merge
+    /// order, absolute or relative to the workspace root. A select chain
+    /// names the one that set the symbol it starts from.
+    pub config_fragments: Vec<String>,
+    /// Most diagnostics published per file; the rest are summarized in one
+    /// extra diagnostic. `0` disables the cap.
+    pub max_diagnostics: usize,
+    /// How much of a construct spanning several lines, like a `choice`
+    /// block, a diagnostic about it underlines.
+    pub diagnostic_ranges: DiagnosticRanges,
+    /// Files found by the workspace scan that are larger than this many
+    /// bytes are not indexed. `0` disables the limit.
+    pub max_file_size: u64,
+    /// Publish diagnostics for every indexed file, not only open ones.
+    pub workspace_diagnostics: bool,
+    /// More directories whose Kconfig files are indexed with the
+    /// workspace's, such as Zephyr modules or the Zephyr tree an application
+    /// builds against. Absolute or relative to the workspace root.
+    pub module_roots: Vec<String>,
+    /// Where the build writes generated Kconfig files, like Zephyr's
+    /// `Kconfig.modules`. Absolute or relative to the workspace root. It is
+    /// indexed once it exists, and is the value of `$(KCONFIG_BINARY_DIR)`
+    /// unless `preprocessor.variables` sets one.
+    pub binary_dir: Option<String>,
+    /// Project whose conventions fill in unset values: `auto` (detected
+    /// from the workspace), `generic`, `linux`, `zephyr`, `buildroot`,
+    /// `esp-idf`, `u-boot` or `nuttx`.
+    pub profile: Profile,
+    /// Grammar of the Kconfig files: `kconfig`, or `kconfiglib` to also
+    /// accept `def_int`, `def_hex` and `def_string`.
+    pub dialect: Dialect,
+    /// Names of the files the workspace scan indexes; `*` matches any run of
+    /// characters.
+    pub file_names: Vec<String>,
+    /// Directory levels below each scan root the workspace scan descends
+    /// into; `None` for no limit.
+    pub scan_depth: Option<usize>,
+    /// Whether the workspace scan indexes every file it finds, or only what
+    /// the root Kconfig and open documents source, reading the rest when a
+    /// symbol lookup misses.
+    pub indexing: IndexingMode,
+    pub preprocessor: PreprocessorSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            completion: CompletionSettings::default(),
+            references: ReferenceSettings::default(),
+            lint: LintSettings::default(),
+            hover: HoverSettings::default(),
+            rename: RenameSettings::default(),
+            root_kconfig: "Kconfig".into(),
+            config_path: None,
+            config_prefix: "CONFIG_".into(),
+            build_config: None,
+            config_fragments: Vec::new(),
+            max_diagnostics: 500,
+            diagnostic_ranges: DiagnosticRanges::default(),
+            max_file_size: 1 << 20,
+            workspace_diagnostics: false,
+            module_roots: Vec::new(),
+            binary_dir: None,
+            profile: Profile::default(),
+            dialect: Dialect::default(),
+            file_names: vec!["Kconfig".into(), "Kconfig.*".into(), "Kconfig_*".into()],
+            scan_depth: None,
+            indexing: IndexingMode::default(),
+            preprocessor: PreprocessorSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompletionSettings {
+    /// Characters after which the client asks for completions on its own.
+    /// Read at startup, since they are announced in the server's
+    /// capabilities. Empty leaves completion to explicit requests.
+    pub trigger_characters: Vec<String>,
+}
+
+impl Default for CompletionSettings {
+    fn default() -> Self {
+        Self {
+            trigger_characters: [" ", "\t", "\"", "/"].map(String::from).to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReferenceSettings {
+    /// Ordering applied to `textDocument/references` results.
+    pub sort: ReferenceSort,
+}
+
+/// Opt-in lint rules.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LintSettings {
+    /// Attributes indented with spaces, trailing whitespace, and mis-indented
+    /// help text.
+    pub whitespace: bool,
+    /// Rules whose findings are dropped, by name or code (e.g.
+    /// `default-quoting` or `KC0011`).
+    pub disable: Vec<String>,
+    /// Naming conventions for the symbols defined under a path. Every rule
+    /// whose path holds the file applies.
+    pub naming: Vec<NamingRule>,
+}
+
+/// A naming convention for the symbols defined by files under `path`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NamingRule {
+    /// Path prefix relative to the source tree root; empty for every file.
+    pub path: String,
+    /// Names must not have lowercase letters.
+    pub uppercase: bool,
+    /// A regular expression names must match in whole.
+    pub pattern: Option<NamePattern>,
+    /// Names must start with one of these, such as a subsystem's or vendor's
+    /// prefix.
+    pub prefixes: Vec<String>,
+}
+
+impl NamingRule {
+    /// Whether the rule covers the file at `rel`, relative to the tree root.
+    pub fn covers(&self, rel: &Path) -> bool {
+        rel.starts_with(self.path.trim_end_matches('/'))
+    }
+}
+
+/// A regular expression anchored at both ends, checked when settings are
+/// read so that a typo is reported instead of matching nothing.
+#[derive(Debug, Clone)]
+pub struct NamePattern {
+    pub source: String,
+    regex: regex::Regex,
+}
+
+impl NamePattern {
+    pub fn new(source: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            source: source.to_string(),
+            regex: regex::Regex::new(&format!("^(?:{})$", source))?,
+        })
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+impl<'de> Deserialize<'de> for NamePattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Self::new(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HoverSettings {
+    /// Definitions of a symbol shown in full; each further one gets a single
+    /// "also defined in" line. `0` shows every definition in full.
+    pub expand_definitions: usize,
+}
+
+impl Default for HoverSettings {
+    fn default() -> Self {
+        Self {
+            expand_definitions: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RenameSettings {
+    /// Also rename the `CONFIG_` spellings of a symbol in Makefiles, C
+    /// sources, defconfigs and the `.config`, once the user confirms.
+    /// Needs a client that supports change annotations.
+    pub build_files: bool,
+}
+
+/// Inputs to `$(...)` macro expansion.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreprocessorSettings {
+    /// Values of variables not assigned in any Kconfig file, standing in for
+    /// the environment (`SRCARCH`, `CC`, ...).
+    pub variables: HashMap<String, String>,
+    /// What `$(shell,...)` does. Disabled by default: Kconfig files from an
+    /// untrusted tree could run anything.
+    pub shell: ShellPolicy,
+    /// Programs `$(shell,...)` may run, matched against the first word of
+    /// the command. Empty allows any program.
+    pub shell_allowlist: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShellPolicy {
+    /// Never run commands; `$(shell,...)` is left unexpanded.
+    #[default]
+    Disabled,
+    /// Ask once per workspace root through `window/showMessageRequest`,
+    /// then behave as `Enabled` or `Disabled`; the answer is remembered
+    /// across sessions. Commands are not run until the user answers.
+    Prompt,
+    /// Never run commands, but report the ones that would run.
+    DryRun,
+    Enabled,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IndexingMode {
+    #[default]
+    Eager,
+    /// Index the include closure of the root Kconfig and of open documents;
+    /// other files are read when a symbol lookup misses.
+    Lazy,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticRanges {
+    /// Up to the end of the first line's content.
+    #[default]
+    FirstLine,
+    /// The whole construct.
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReferenceSort {
+    /// Parse order: declarations first, then references, each in the order
+    /// kconfig reads them from the root Kconfig.
+    #[default]
+    None,
+    /// Group by file (sorted by path), then by position within the file.
+    File,
+    /// Group by file, then by reference kind (declarations, `depends on`,
+    /// `select`, ...), then by position.
+    FileAndKind,
+}
+
+impl Settings {
+    /// The directories the workspace scan covers: the workspace root, then
+    /// the module roots and the binary directory.
+    pub fn scan_roots(&self, root: &Path) -> Vec<PathBuf> {
+        let mut roots = vec![root.to_path_buf()];
+        roots.extend(self.module_roots.iter().map(|r| normalize(&root.join(r))));
+        roots.extend(self.binary_dir.iter().map(|d| normalize(&root.join(d))));
+        roots
+    }
+
+    /// The preprocessor settings, with `KCONFIG_BINARY_DIR` filled in from
+    /// `binary_dir`.
+    pub fn effective_preprocessor(&self, root: Option<&Path>) -> PreprocessorSettings {
+        let mut preprocessor = self.preprocessor.clone();
+        if let Some(dir) = &self.binary_dir {
+            let dir = match root {
+                Some(root) => normalize(&root.join(dir)),
+                None => PathBuf::from(dir),
+            };
+            preprocessor
+                .variables
+                .entry("KCONFIG_BINARY_DIR".into())
+                .or_insert_with(|| dir.display().to_string());
+        }
+        preprocessor
+    }
+
+    /// Settings from JSON, and the problems found in it. A naming rule whose
+    /// pattern does not compile is left out and reported, keeping the rest;
+    /// settings that do not parse at all give the defaults.
+    pub fn from_json(value: Option<serde_json::Value>) -> (Self, Vec<String>) {
+        let Some(mut value) = value else {
+            return (Settings::default(), Vec::new());
+        };
+        let mut problems = Vec::new();
+        if let Some(rules) = value
+            .pointer_mut("/lint/naming")
+            .and_then(|n| n.as_array_mut())
+        {
+            rules.retain(|rule| {
+                let Some(pattern) = rule.get("pattern").and_then(|p| p.as_str()) else {
+                    return true;
+                };
+                match NamePattern::new(pattern) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        problems.push(format!(
+                            "lint.naming: ignoring the rule with pattern `{}`: {}",
+                            pattern, e
+                        ));
+                        false
+                    }
+                }
+            });
+        }
+        let settings = serde_json::from_value(value).unwrap_or_else(|e| {
+            problems.push(format!("invalid settings, using defaults: {}", e));
+            Settings::default()
+        });
+        for problem in &problems {
+            log::warn!("{}", problem);
+        }
+        (settings, problems)
+    }
+
+    /// Settings from `workspace/didChangeConfiguration`, as with
+    /// [`Settings::from_json`]. Clients send either the settings themselves
+    /// or a section keyed by `kconfig`; `None` when they send nothing.
+    pub fn from_change(value: serde_json::Value) -> Option<(Self, Vec<String>)> {
+        match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::Object(mut map) if map.contains_key("kconfig") => {
+                Some(Self::from_json(map.remove("kconfig")))
+            }
+            other => Some(Self::from_json(Some(other))),
+        }
+    }
+}
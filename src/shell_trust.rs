@@ -0,0 +1,43 @@
+//! The answers to the `$(shell,...)` trust prompt of
+//! [`ShellPolicy::Prompt`](crate::settings::ShellPolicy::Prompt), kept per
+//! workspace root so that a workspace is asked about once rather than every
+//! session. They live in `$XDG_STATE_HOME/kconfig-lsp/shell-trust.json`,
+//! or under `~/.local/state` without it, as an object from root to answer.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the answers are kept, if there is a home to keep them in.
+pub fn store_path() -> Option<PathBuf> {
+    let state = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state.join("kconfig-lsp/shell-trust.json"))
+}
+
+/// The answer given for `root`, if any. A missing or unreadable store holds
+/// no answers.
+pub fn load(store: &Path, root: &Path) -> Option<bool> {
+    answers(store).get(&root.display().to_string()).copied()
+}
+
+/// Record the answer for `root`, keeping those for other roots.
+pub fn save(store: &Path, root: &Path, trusted: bool) -> io::Result<()> {
+    let mut answers = answers(store);
+    answers.insert(root.display().to_string(), trusted);
+    if let Some(dir) = store.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = serde_json::to_string_pretty(&answers).map_err(io::Error::other)?;
+    fs::write(store, text)
+}
+
+fn answers(store: &Path) -> BTreeMap<String, bool> {
+    fs::read_to_string(store)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
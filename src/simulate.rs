@@ -0,0 +1,343 @@
+//! Whole-tree evaluation under the kernel's `conf` presets (`allnoconfig`,
+//! `allyesconfig`, ...), answering "would this symbol be enabled under
+//! allmodconfig?" without running the build system.
+//!
+//! Values are computed to a fixed point: every symbol is recomputed from the
+//! previous pass's values until nothing changes. This follows kconfig's
+//! rules for visibility, defaults, `select`/`imply`, and choices, but not
+//! every corner (tristate choices in `m` mode are treated as bool).
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{ChoiceDef, Symbol, WorldIndex};
+use crate::ast::{Expr, TypeKind};
+use crate::dotconfig::{ConfigValue, DotConfig};
+use crate::eval::{Evaluator, Tristate};
+
+/// Give up on configurations that oscillate (e.g. through `!` cycles).
+const MAX_PASSES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Preset {
+    /// Every visible bool/tristate is `n`, except `option allnoconfig_y`.
+    #[serde(rename = "allnoconfig")]
+    AllNo,
+    /// Every visible bool/tristate is `y`.
+    #[serde(rename = "allyesconfig")]
+    AllYes,
+    /// Visible tristates are `m`, bools `y`.
+    #[serde(rename = "allmodconfig")]
+    AllMod,
+    /// Every symbol takes its default.
+    #[serde(rename = "alldefconfig")]
+    AllDef,
+    /// Values from the first file named by `option defconfig_list`, with
+    /// defaults for everything it leaves unset.
+    #[serde(rename = "defconfig")]
+    Defconfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateParams {
+    pub preset: Preset,
+    /// Symbols to report; all defined symbols when omitted.
+    #[serde(default)]
+    pub symbols: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateResult {
+    pub preset: Preset,
+    /// `false` if values were still changing after the pass limit.
+    pub converged: bool,
+    pub values: Vec<SimulatedValue>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedValue {
+    pub symbol: String,
+    /// `y`/`m`/`n`, or the string, int, or hex value. `None` when a
+    /// non-boolean symbol has no value because its dependencies fail.
+    pub value: Option<String>,
+}
+
+/// Evaluate every symbol under `preset`. `base` supplies the user values of
+/// [`Preset::Defconfig`] and is ignored otherwise.
+pub fn simulate(
+    index: &WorldIndex,
+    preset: Preset,
+    base: Option<&DotConfig>,
+    symbols: Option<&[String]>,
+) -> SimulateResult {
+    let (config, converged) = Simulation::new(index, preset, base).run();
+    let mut names: Vec<String> = match symbols {
+        Some(names) => names.to_vec(),
+        None => index.definitions.keys().cloned().collect(),
+    };
+    names.sort_unstable();
+    let values = names
+        .into_iter()
+        .map(|name| SimulatedValue {
+            value: config.get(&name).map(str::to_string),
+            symbol: name,
+        })
+        .collect();
+    SimulateResult {
+        preset,
+        converged,
+        values,
+    }
+}
+
+/// The `.config` to seed [`Preset::Defconfig`] from: the first of
+/// `candidates`, as listed by `option defconfig_list`, that exists under
+/// `root`.
+pub fn load_defconfig(candidates: &[String], root: &Path, prefix: &str) -> Option<DotConfig> {
+    candidates
+        .iter()
+        .map(|p| root.join(p))
+        .find(|p| p.is_file())
+        .and_then(|p| DotConfig::load(&p, prefix).ok())
+}
+
+struct Simulation<'a> {
+    index: &'a WorldIndex,
+    preset: Preset,
+    base: Option<&'a DotConfig>,
+    symbols: Vec<Symbol<'a>>,
+    /// Positions in `symbols` by name.
+    by_name: HashMap<&'a str, usize>,
+    choices: Vec<&'a ChoiceDef>,
+    /// The symbol with the `modules` attribute; without one, `m` is `y`.
+    modules: Option<&'a str>,
+}
+
+impl<'a> Simulation<'a> {
+    fn new(index: &'a WorldIndex, preset: Preset, base: Option<&'a DotConfig>) -> Self {
+        let order = index.tree_order();
+        let mut names: Vec<&str> = index.definitions.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        let symbols: Vec<Symbol> = names
+            .into_iter()
+            .filter_map(|n| index.symbol(n, &order))
+            .collect();
+        let modules = symbols
+            .iter()
+            .find(|s| s.definitions.iter().any(|d| d.has_option("modules")))
+            .map(|s| s.name);
+        let by_name = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.name, i))
+            .collect();
+        let mut paths: Vec<&Path> = index.files.keys().map(|p| p.as_path()).collect();
+        paths.sort();
+        let choices = paths
+            .into_iter()
+            .flat_map(|p| &index.files[p].choices)
+            .collect();
+        Self {
+            index,
+            preset,
+            base,
+            symbols,
+            by_name,
+            choices,
+            modules,
+        }
+    }
+
+    fn run(&self) -> (DotConfig, bool) {
+        let mut config = DotConfig::default();
+        for _ in 0..MAX_PASSES {
+            let next = self.pass(&config);
+            if next.changed_symbols(&config) == 0 {
+                return (next, true);
+            }
+            config = next;
+        }
+        (config, false)
+    }
+
+    /// Recompute every symbol from the values in `config`.
+    fn pass(&self, config: &DotConfig) -> DotConfig {
+        let eval = Evaluator::new(self.index, config);
+        let modules = self
+            .modules
+            .is_some_and(|m| eval.symbol_tristate(m) == Tristate::Y);
+        let (selected, implied) = self.reverse_dependencies(&eval);
+
+        let mut next = DotConfig::default();
+        for sym in &self.symbols {
+            if sym.definitions.iter().any(|d| d.choice.is_some()) {
+                continue;
+            }
+            let value = match self.index.symbol_type(sym.name) {
+                Some(kind @ (TypeKind::Bool | TypeKind::Tristate)) => {
+                    let rev = selected.get(sym.name).copied().unwrap_or(Tristate::N);
+                    let weak = implied.get(sym.name).copied().unwrap_or(Tristate::N);
+                    let v = self.tristate(&eval, sym, kind, weak).or(rev);
+                    let v = if v == Tristate::M && (kind == TypeKind::Bool || !modules) {
+                        Tristate::Y
+                    } else {
+                        v
+                    };
+                    Some(v.as_str().to_string())
+                }
+                _ => self.string(&eval, sym),
+            };
+            if let Some(value) = value {
+                set(&mut next, sym.name, value);
+            }
+        }
+        for choice in &self.choices {
+            let selection = self.choice_selection(&eval, choice);
+            for member in &choice.members {
+                let value = if Some(member.as_str()) == selection {
+                    "y"
+                } else {
+                    "n"
+                };
+                set(&mut next, member, value.into());
+            }
+        }
+        next
+    }
+
+    /// The value of a bool/tristate symbol before `select` is applied.
+    /// `imply` (`weak`) raises the default, within the symbol's dependencies,
+    /// but a user value overrides it.
+    fn tristate(&self, eval: &Evaluator, sym: &Symbol, kind: TypeKind, weak: Tristate) -> Tristate {
+        let deps = eval.symbol_dependencies(sym);
+        let weak = weak.and(deps);
+        let default = eval
+            .active_default(sym)
+            .map_or(Tristate::N, |(_, d)| eval.eval(&d.value));
+        let mut visible = eval.symbol_visibility(sym);
+        if kind == TypeKind::Bool && visible == Tristate::M {
+            visible = Tristate::Y;
+        }
+        if visible == Tristate::N {
+            return default.and(deps).or(weak);
+        }
+        let user = match self.preset {
+            Preset::AllYes => Tristate::Y,
+            Preset::AllMod if kind == TypeKind::Tristate => Tristate::M,
+            Preset::AllMod => Tristate::Y,
+            Preset::AllNo
+                if sym
+                    .definitions
+                    .iter()
+                    .any(|d| d.has_option("allnoconfig_y")) =>
+            {
+                Tristate::Y
+            }
+            Preset::AllNo => Tristate::N,
+            Preset::AllDef | Preset::Defconfig => match self.base.and_then(|b| b.get(sym.name)) {
+                Some(v) if self.preset == Preset::Defconfig => Tristate::parse(v),
+                _ => default.or(weak),
+            },
+        };
+        user.and(visible)
+    }
+
+    /// The value of an int/hex/string symbol: the `.config` value for
+    /// [`Preset::Defconfig`], otherwise its active default.
+    fn string(&self, eval: &Evaluator, sym: &Symbol) -> Option<String> {
+        if eval.symbol_dependencies(sym) == Tristate::N {
+            return None;
+        }
+        if self.preset == Preset::Defconfig
+            && eval.symbol_visibility(sym) != Tristate::N
+            && let Some(v) = self.base.and_then(|b| b.get(sym.name))
+        {
+            return Some(v.to_string());
+        }
+        let value = match eval.active_default(sym).map(|(_, d)| &d.value) {
+            Some(Expr::Symbol(name, _)) => eval.symbol_string(name),
+            Some(Expr::StringLit(s, _)) => s.clone(),
+            Some(other) => eval.eval(other).as_str().to_string(),
+            None => String::new(),
+        };
+        Some(value)
+    }
+
+    /// `select` and `imply` contributions per target: the selecting symbol's
+    /// value, limited by the `if` condition.
+    fn reverse_dependencies(
+        &self,
+        eval: &Evaluator,
+    ) -> (HashMap<&'a str, Tristate>, HashMap<&'a str, Tristate>) {
+        let mut selected: HashMap<&str, Tristate> = HashMap::new();
+        let mut implied: HashMap<&str, Tristate> = HashMap::new();
+        for sym in &self.symbols {
+            let value = eval.symbol_tristate(sym.name);
+            if value == Tristate::N {
+                continue;
+            }
+            for (_, s) in sym.selects() {
+                let cond = s.condition.as_ref().map_or(Tristate::Y, |c| eval.eval(c));
+                let v = selected.entry(s.symbol.as_str()).or_insert(Tristate::N);
+                *v = v.or(value.and(cond));
+            }
+            for (_, s) in sym.implies() {
+                let cond = s.condition.as_ref().map_or(Tristate::Y, |c| eval.eval(c));
+                let v = implied.entry(s.symbol.as_str()).or_insert(Tristate::N);
+                *v = v.or(value.and(cond));
+            }
+        }
+        (selected, implied)
+    }
+
+    /// The member a choice ends up with: the `.config` selection for
+    /// [`Preset::Defconfig`], else the first active `default`, else the first
+    /// visible member. An `optional` choice stays empty under `allnoconfig`.
+    fn choice_selection(&self, eval: &Evaluator, choice: &'a ChoiceDef) -> Option<&'a str> {
+        let usable = |name: &str| {
+            self.by_name
+                .get(name)
+                .is_some_and(|&i| eval.symbol_visibility(&self.symbols[i]) != Tristate::N)
+        };
+        if eval.eval_all(&choice.depends_on) == Tristate::N {
+            return None;
+        }
+        if choice.optional && self.preset == Preset::AllNo {
+            return None;
+        }
+        if self.preset == Preset::Defconfig
+            && let Some(base) = self.base
+            && let Some(m) = choice
+                .members
+                .iter()
+                .find(|m| base.get(m) == Some("y") && usable(m))
+        {
+            return Some(m);
+        }
+        let default = choice.defaults.iter().find_map(|d| match &d.value {
+            Expr::Symbol(name, _)
+                if d.condition
+                    .as_ref()
+                    .is_none_or(|c| eval.eval(c) != Tristate::N)
+                    && usable(name) =>
+            {
+                choice.members.iter().find(|m| *m == name)
+            }
+            _ => None,
+        });
+        default
+            .or_else(|| choice.members.iter().find(|m| usable(m)))
+            .map(String::as_str)
+    }
+}
+
+fn set(config: &mut DotConfig, name: &str, value: String) {
+    config
+        .values
+        .insert(name.to_string(), ConfigValue { value, line: 0 });
+}
@@ -0,0 +1,152 @@
+//! "Sort config entries": reorders the `config` entries of a menu (or of
+//! the top level of a file) alphabetically by symbol name.
+//!
+//! Only runs of consecutive `config` entries move. Anything else — a
+//! `menuconfig` (whose dependents follow it), `comment`, `if`, `source`,
+//! `choice`, or nested `menu` — stays where it is and splits the runs, since
+//! moving it would change the menu structure. `#` comment lines directly
+//! above an entry, with no blank line in between, move with it. Blank lines
+//! between entries stay in place.
+
+use crate::ast::*;
+use crate::lint::Edit;
+
+/// The entries sorted for a cursor at `offset`: those of the innermost menu
+/// around it, or the file's top level. Returns the menu's prompt (`None` for
+/// the file) and the edits, or `None` when everything is already sorted.
+pub fn sort_at<'a>(
+    source: &str,
+    file: &'a KconfigFile,
+    offset: usize,
+) -> Option<(Option<&'a str>, Vec<Edit>)> {
+    let (prompt, edits) = match innermost_menu(&file.entries, offset) {
+        Some(menu) => {
+            // Everything up to the `endmenu` line; no `endmenu`, no sorting.
+            let endmenu = source[..menu.span.end].rfind('\n').map(|p| p + 1)?;
+            let start = menu
+                .attributes
+                .last()
+                .map_or(menu.prompt_span.end, |a| a.span().end);
+            (
+                Some(menu.prompt.as_str()),
+                sort_entries(source, &menu.entries, start, endmenu),
+            )
+        }
+        None => (None, sort_entries(source, &file.entries, 0, source.len())),
+    };
+    if edits.is_empty() {
+        None
+    } else {
+        Some((prompt, edits))
+    }
+}
+
+fn innermost_menu(entries: &[Entry], offset: usize) -> Option<&MenuEntry> {
+    let entry = entries.iter().find(|e| {
+        let span = e.span();
+        span.start <= offset && offset <= span.end
+    })?;
+    let inner = innermost_menu(entry.children(), offset);
+    match entry {
+        Entry::Menu(m) => inner.or(Some(m)),
+        _ => inner,
+    }
+}
+
+/// Edits sorting each run of `config` entries in `entries`, which occupy
+/// `start..end` of `source`.
+fn sort_entries(source: &str, entries: &[Entry], start: usize, end: usize) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    let mut floor = start;
+    let mut i = 0;
+    while i < entries.len() {
+        if !matches!(entries[i], Entry::Config(_)) {
+            floor = entries[i].span().end;
+            i += 1;
+            continue;
+        }
+        let run_start = i;
+        let mut starts = Vec::new();
+        while let Some(Entry::Config(c)) = entries.get(i) {
+            starts.push(attached_start(source, c.span.start, floor));
+            floor = c.span.end;
+            i += 1;
+        }
+        let boundary = entries
+            .get(i)
+            .map_or(end, |next| attached_start(source, next.span().start, floor));
+        starts.push(boundary);
+
+        let names: Vec<&str> = entries[run_start..i]
+            .iter()
+            .map(|e| match e {
+                Entry::Config(c) => c.name.as_str(),
+                _ => unreachable!(),
+            })
+            .collect();
+        if names.is_sorted() {
+            continue;
+        }
+
+        // Split each entry's region into its text and the blank (or
+        // detached comment) lines after it.
+        let mut bodies = Vec::new();
+        let mut gaps = Vec::new();
+        for w in starts.windows(2) {
+            let region = &source[w[0]..w[1]];
+            let split = body_len(region);
+            let mut body = region[..split].to_string();
+            if !body.ends_with('\n') {
+                body.push('\n');
+            }
+            bodies.push(body);
+            gaps.push(&region[split..]);
+        }
+        let mut order: Vec<usize> = (0..names.len()).collect();
+        order.sort_by_key(|&k| names[k]);
+
+        let mut new_text = String::new();
+        for (slot, &k) in order.iter().enumerate() {
+            new_text.push_str(&bodies[k]);
+            new_text.push_str(gaps[slot]);
+        }
+        if !source[..boundary].ends_with('\n') {
+            // The run ends the file without a final newline.
+            new_text.pop();
+        }
+        edits.push(Edit {
+            span: Span::new(starts[0], boundary),
+            new_text,
+        });
+    }
+    edits
+}
+
+/// Start of the line at `offset`, moved up over `#` comment lines directly
+/// above it, but not above `floor`.
+pub(crate) fn attached_start(source: &str, offset: usize, floor: usize) -> usize {
+    let mut start = source[..offset].rfind('\n').map_or(0, |p| p + 1);
+    while start > floor {
+        let prev = source[..start - 1].rfind('\n').map_or(0, |p| p + 1);
+        if prev < floor || !source[prev..start].trim_start().starts_with('#') {
+            break;
+        }
+        start = prev;
+    }
+    start.max(floor)
+}
+
+/// Length of `region` up to the end of its last line that is neither blank
+/// nor a `#` comment.
+pub(crate) fn body_len(region: &str) -> usize {
+    let mut len = 0;
+    let mut offset = 0;
+    for line in region.split_inclusive('\n') {
+        offset += line.len();
+        let text = line.trim();
+        if !text.is_empty() && !text.starts_with('#') {
+            len = offset;
+        }
+    }
+    len
+}
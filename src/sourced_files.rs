@@ -0,0 +1,71 @@
+//! Files reached through `source` whose names the workspace scan does not
+//! look for, like the `Config.mk`-style fragments of vendor SDKs. They are
+//! indexed as Kconfig all the same, and clients that ask are told about
+//! them so they open them as Kconfig too.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::Url;
+use tower_lsp::lsp_types::notification::Notification;
+
+use crate::analysis::WorldIndex;
+use crate::index_stats;
+
+/// `kconfig/fileAssociations`: files to open with the Kconfig language
+/// although their names do not say so. Sent to clients whose capabilities
+/// have `experimental.kconfigFileAssociations` set.
+pub enum FileAssociations {}
+
+impl Notification for FileAssociations {
+    type Params = FileAssociationsParams;
+    const METHOD: &'static str = "kconfig/fileAssociations";
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileAssociationsParams {
+    /// Files found since the last notification.
+    pub uris: Vec<Url>,
+}
+
+/// Index the files that the files `from` source and that are not indexed
+/// yet, then what those source in turn. Returns the files indexed.
+pub fn index_sourced_files(
+    index: &mut WorldIndex,
+    from: Vec<PathBuf>,
+    max_file_size: u64,
+) -> Vec<PathBuf> {
+    let mut pending = from;
+    let mut indexed = Vec::new();
+    while let Some(path) = pending.pop() {
+        let Some(fa) = index.files.get(&path) else {
+            continue;
+        };
+        let targets: Vec<PathBuf> = fa
+            .sources
+            .iter()
+            .filter(|s| index.resolve_source(&path, &s.path, s.relative).is_none())
+            .filter_map(|s| {
+                index
+                    .source_candidates(&path, &s.path, s.relative)
+                    .into_iter()
+                    .find(|c| c.is_file())
+            })
+            .collect();
+        for target in targets {
+            if index.files.contains_key(&target) {
+                continue;
+            }
+            match index_stats::read_kconfig(&target, max_file_size) {
+                Ok(source) => {
+                    index.analyze_file(&target, &source);
+                    pending.push(target.clone());
+                    indexed.push(target);
+                }
+                Err(reason) => log::warn!("not indexing {}: {}", target.display(), reason),
+            }
+        }
+    }
+    indexed
+}
@@ -0,0 +1,144 @@
+//! `textDocument/documentSymbol`, the outline of a file, and
+//! `kconfig/documentSymbolsDetailed`: every entry of a file with the
+//! documentation hover shows for it, so a docs pipeline can render pages for
+//! a tree's options without re-implementing help extraction.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::{FileAnalysis, OutlineEntry, WorldIndex};
+use crate::hover;
+use crate::include_graph::TreeOrder;
+use crate::menu_tree::MenuNodeKind;
+use crate::util::range;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSymbolsDetailedParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailedSymbol {
+    pub kind: MenuNodeKind,
+    pub name: Option<String>,
+    pub prompt: Option<String>,
+    /// Prompts of the enclosing menus and choices in this file, outermost
+    /// first.
+    pub parents: Vec<String>,
+    pub range: Range,
+    /// The symbol name, or the prompt of a menu or comment.
+    pub selection_range: Range,
+    /// The Markdown hover shows for the symbol. `None` for menus, comments
+    /// and unnamed choices.
+    pub documentation: Option<String>,
+}
+
+/// The entries of `path` in file order, nested entries after their parent.
+pub fn document_symbols_detailed(index: &WorldIndex, path: &Path) -> Vec<DetailedSymbol> {
+    let Some(fa) = index.files.get(path) else {
+        return Vec::new();
+    };
+    let mut collector = Collector {
+        index,
+        fa,
+        order: index.tree_order(),
+        docs: HashMap::new(),
+        out: Vec::new(),
+    };
+    collector.entries(&fa.outline, &mut Vec::new());
+    collector.out
+}
+
+/// `textDocument/documentSymbol`: the outline of `path`.
+pub fn document_symbols(index: &WorldIndex, path: &Path) -> Option<Vec<DocumentSymbol>> {
+    let fa = index.files.get(path)?;
+    Some(document_symbol_tree(fa, &fa.outline))
+}
+
+#[allow(deprecated)]
+fn document_symbol_tree(fa: &FileAnalysis, outline: &[OutlineEntry]) -> Vec<DocumentSymbol> {
+    outline
+        .iter()
+        .map(|e| {
+            let (name, detail) = match (&e.name, &e.prompt) {
+                (Some(name), prompt) => (name.clone(), prompt.clone()),
+                (None, Some(prompt)) => (prompt.clone(), None),
+                (None, None) => ("choice".to_string(), None),
+            };
+            DocumentSymbol {
+                name,
+                detail,
+                kind: symbol_kind(e.kind),
+                tags: None,
+                deprecated: None,
+                range: range(&fa.line_index, e.span),
+                selection_range: range(&fa.line_index, e.selection_span),
+                children: (!e.children.is_empty()).then(|| document_symbol_tree(fa, &e.children)),
+            }
+        })
+        .collect()
+}
+
+/// How an entry of kind `kind` is shown in symbol lists and hierarchies.
+pub fn symbol_kind(kind: MenuNodeKind) -> SymbolKind {
+    match kind {
+        MenuNodeKind::Menu | MenuNodeKind::Root => SymbolKind::NAMESPACE,
+        MenuNodeKind::Choice => SymbolKind::ENUM,
+        MenuNodeKind::Comment => SymbolKind::STRING,
+        MenuNodeKind::Config | MenuNodeKind::MenuConfig => SymbolKind::CONSTANT,
+    }
+}
+
+struct Collector<'a> {
+    index: &'a WorldIndex,
+    fa: &'a FileAnalysis,
+    /// One parse order for the documentation of every entry.
+    order: Arc<TreeOrder>,
+    /// Documentation by symbol name; a symbol defined twice in the file
+    /// is rendered once.
+    docs: HashMap<String, Option<String>>,
+    out: Vec<DetailedSymbol>,
+}
+
+impl Collector<'_> {
+    fn entries(&mut self, outline: &[OutlineEntry], parents: &mut Vec<String>) {
+        for e in outline {
+            let documentation = e.name.as_ref().and_then(|name| {
+                self.docs
+                    .entry(name.clone())
+                    .or_insert_with(|| {
+                        hover::documentation(
+                            self.index,
+                            &self.order,
+                            &hover::reverse_dependencies(self.index, name),
+                            name,
+                            MarkupKind::Markdown,
+                            0,
+                        )
+                    })
+                    .clone()
+            });
+            self.out.push(DetailedSymbol {
+                kind: e.kind,
+                name: e.name.clone(),
+                prompt: e.prompt.clone(),
+                parents: parents.to_vec(),
+                range: range(&self.fa.line_index, e.span),
+                selection_range: range(&self.fa.line_index, e.selection_span),
+                documentation,
+            });
+            if !e.children.is_empty() {
+                let label = e.prompt.clone().unwrap_or_else(|| "choice".into());
+                parents.push(label);
+                self.entries(&e.children, parents);
+                parents.pop();
+            }
+        }
+    }
+}
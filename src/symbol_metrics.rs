@@ -0,0 +1,118 @@
+//! `kconfig-lsp metrics` and `kconfig/symbolMetrics`: numbers per symbol –
+//! definitions, references by kind, dependency fan-in and fan-out, help
+//! size – for dashboards tracking how a tree's configuration grows more
+//! complex over time.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{RefKind, WorldIndex};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolMetricsParams {
+    /// The symbols to measure; every defined symbol when empty.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolMetrics {
+    pub symbol: String,
+    /// The declared type, if any definition has one.
+    #[serde(rename = "type")]
+    pub type_name: Option<&'static str>,
+    pub definitions: usize,
+    pub references: ReferenceCounts,
+    /// Symbols that depend on, select or imply this one.
+    pub fan_in: usize,
+    /// Symbols this one depends on, selects or implies, counting the
+    /// dependencies inherited from `if` blocks and menus.
+    pub fan_out: usize,
+    /// Lines and bytes of the longest help text among the definitions.
+    pub help_lines: usize,
+    pub help_bytes: usize,
+}
+
+/// References to a symbol, by the kind of line they are on.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceCounts {
+    pub total: usize,
+    pub depends_on: usize,
+    pub select: usize,
+    pub imply: usize,
+    pub default: usize,
+    pub range: usize,
+    pub visible_if: usize,
+    pub if_condition: usize,
+}
+
+/// The metrics of `params.symbols`, or of every defined symbol, by name.
+pub fn symbol_metrics(index: &WorldIndex, params: &SymbolMetricsParams) -> Vec<SymbolMetrics> {
+    let names: BTreeSet<&str> = if params.symbols.is_empty() {
+        index.all_symbols.iter().map(String::as_str).collect()
+    } else {
+        params.symbols.iter().map(String::as_str).collect()
+    };
+
+    // What each symbol depends on, selects or implies, and the reverse.
+    let mut fan_out: HashMap<&str, BTreeSet<String>> = HashMap::new();
+    let mut fan_in: HashMap<String, BTreeSet<&str>> = HashMap::new();
+    for (name, defs) in &index.definitions {
+        let targets = fan_out.entry(name).or_default();
+        for def in defs {
+            let mut symbols = Vec::new();
+            for expr in def.depends_on.iter().chain(&def.inherited_depends) {
+                expr.collect_symbols(&mut symbols);
+            }
+            targets.extend(symbols.into_iter().map(|(s, _)| s));
+            targets.extend(
+                def.selects
+                    .iter()
+                    .chain(&def.implies)
+                    .map(|a| a.symbol.clone()),
+            );
+        }
+        targets.retain(|t| t != name && !matches!(t.as_str(), "y" | "m" | "n"));
+        for target in targets.iter() {
+            fan_in.entry(target.clone()).or_default().insert(name);
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            let defs = index.get_definitions(name);
+            let mut references = ReferenceCounts::default();
+            for r in index.get_references(name) {
+                references.total += 1;
+                *match r.kind {
+                    RefKind::DependsOn => &mut references.depends_on,
+                    RefKind::Select => &mut references.select,
+                    RefKind::Imply => &mut references.imply,
+                    RefKind::Default => &mut references.default,
+                    RefKind::Range => &mut references.range,
+                    RefKind::VisibleIf => &mut references.visible_if,
+                    RefKind::IfCondition => &mut references.if_condition,
+                } += 1;
+            }
+            let help = defs
+                .iter()
+                .filter_map(|d| d.help.as_deref())
+                .max_by_key(|h| (h.lines().count(), h.len()));
+            SymbolMetrics {
+                symbol: name.to_string(),
+                type_name: index.symbol_type(name).map(|t| t.as_str()),
+                definitions: defs.len(),
+                references,
+                fan_in: fan_in.get(name).map_or(0, BTreeSet::len),
+                fan_out: fan_out.get(name).map_or(0, BTreeSet::len),
+                help_lines: help.map_or(0, |h| h.lines().count()),
+                help_bytes: help.map_or(0, str::len),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,94 @@
+//! Words of prompts and help text → the symbols whose text contains them,
+//! so text search and help-mention lookups on a kernel-sized tree do not
+//! scan every help string per query. Kept up to date file by file along
+//! with the rest of [`WorldIndex`](crate::analysis::WorldIndex).
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::analysis::SymbolDef;
+use crate::search::MatchField;
+
+#[derive(Debug, Default)]
+pub struct TextIndex {
+    /// Lower-cased word → (symbol, field) → number of definitions with the
+    /// word in that field.
+    words: BTreeMap<String, HashMap<(String, MatchField), u32>>,
+    /// The postings each file added, so removing the file takes them back.
+    by_file: HashMap<PathBuf, Vec<(String, String, MatchField)>>,
+}
+
+impl TextIndex {
+    pub fn add_file(&mut self, path: &Path, defs: &[SymbolDef]) {
+        let mut added = Vec::new();
+        for d in defs {
+            let fields = [(MatchField::Prompt, &d.prompt), (MatchField::Help, &d.help)];
+            for (field, text) in fields {
+                let Some(text) = text else {
+                    continue;
+                };
+                let unique: BTreeSet<String> = words(text).collect();
+                for word in unique {
+                    *self
+                        .words
+                        .entry(word.clone())
+                        .or_default()
+                        .entry((d.name.clone(), field))
+                        .or_default() += 1;
+                    added.push((word, d.name.clone(), field));
+                }
+            }
+        }
+        if !added.is_empty() {
+            self.by_file.insert(path.to_path_buf(), added);
+        }
+    }
+
+    pub fn remove_file(&mut self, path: &Path) {
+        for (word, symbol, field) in self.by_file.remove(path).unwrap_or_default() {
+            let Some(postings) = self.words.get_mut(&word) else {
+                continue;
+            };
+            let key = (symbol, field);
+            if let Some(count) = postings.get_mut(&key) {
+                *count -= 1;
+                if *count == 0 {
+                    postings.remove(&key);
+                }
+            }
+            if postings.is_empty() {
+                self.words.remove(&word);
+            }
+        }
+    }
+
+    /// Symbols with a word starting with `prefix` (lower case) in `field`.
+    pub fn with_prefix(&self, prefix: &str, field: MatchField) -> HashSet<&str> {
+        self.words
+            .range(prefix.to_string()..)
+            .take_while(|(word, _)| word.starts_with(prefix))
+            .flat_map(|(_, postings)| postings.keys())
+            .filter(|(_, f)| *f == field)
+            .map(|(symbol, _)| symbol.as_str())
+            .collect()
+    }
+
+    /// Symbols whose help text mentions `name`, as is or as `CONFIG_<name>`.
+    pub fn mentioning(&self, name: &str) -> BTreeSet<&str> {
+        let name = name.to_lowercase();
+        [format!("config_{}", name), name]
+            .iter()
+            .filter_map(|w| self.words.get(w))
+            .flat_map(|postings| postings.keys())
+            .filter(|(_, f)| *f == MatchField::Help)
+            .map(|(symbol, _)| symbol.as_str())
+            .collect()
+    }
+}
+
+/// The lower-cased words of `text`: runs of letters, digits and `_`.
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
@@ -0,0 +1,104 @@
+//! "Convert to transitional": the edits that turn a config entry into a
+//! `transitional` one, kept only so that old `.config` files still migrate.
+//!
+//! A transitional symbol is invisible and gets its value only from the
+//! `.config` being read, so the prompt, help text, defaults, and
+//! `select`/`imply`/`range` lines are dropped. The type and `depends on`
+//! lines stay.
+
+use crate::ast::*;
+use crate::lint::{Edit, indent_width};
+use crate::util::lines_of;
+
+pub fn is_transitional(entry: &ConfigEntry) -> bool {
+    entry
+        .attributes
+        .iter()
+        .any(|a| matches!(a, Attribute::Transitional(_)))
+}
+
+/// Edits converting `entry` to a transitional symbol.
+pub fn convert(source: &str, entry: &ConfigEntry) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    let mut marked = false;
+
+    for attr in &entry.attributes {
+        match attr {
+            Attribute::Type(t) => {
+                edits.push(Edit {
+                    span: t.span,
+                    new_text: with_marker(source, t.span, t.kind),
+                });
+                marked = true;
+            }
+            Attribute::DefType(d) => {
+                edits.push(Edit {
+                    span: d.span,
+                    new_text: with_marker(source, d.span, d.kind),
+                });
+                marked = true;
+            }
+            Attribute::Prompt(_)
+            | Attribute::Default(_)
+            | Attribute::Select(_)
+            | Attribute::Imply(_)
+            | Attribute::Range(_) => edits.push(Edit {
+                span: lines_of(source, attr.span()),
+                new_text: String::new(),
+            }),
+            Attribute::Help(h) => edits.push(Edit {
+                span: help_lines(source, h.span.start),
+                new_text: String::new(),
+            }),
+            _ => {}
+        }
+    }
+
+    if !marked {
+        // The type comes from another definition; mark right after the name.
+        edits.push(Edit {
+            span: Span::new(entry.name_span.end, entry.name_span.end),
+            new_text: "\n\ttransitional".into(),
+        });
+    }
+    edits
+}
+
+/// `bool` (or whichever type) followed by a `transitional` line indented
+/// like the type line.
+fn with_marker(source: &str, span: Span, kind: TypeKind) -> String {
+    let line_start = source[..span.start].rfind('\n').map_or(0, |p| p + 1);
+    let indent = &source[line_start..span.start];
+    let indent = if indent.trim().is_empty() {
+        indent
+    } else {
+        "\t"
+    };
+    format!("{}\n{}transitional", kind.as_str(), indent)
+}
+
+/// The `help` line at `start` plus its text, using kconfig's rule that help
+/// ends at the first line indented less than the first text line. Trailing
+/// blank lines are left in place.
+fn help_lines(source: &str, start: usize) -> Span {
+    let first = lines_of(source, Span::new(start, start));
+    let mut end = first.end;
+    let mut base: Option<usize> = None;
+    let mut offset = first.end;
+    for line in source[first.end..].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let text = content.trim_start();
+        if !text.is_empty() {
+            let width = indent_width(&content[..content.len() - text.len()]);
+            match base {
+                None if width == 0 => break,
+                None => base = Some(width),
+                Some(b) if width < b => break,
+                Some(_) => {}
+            }
+            end = offset + line.len();
+        }
+        offset += line.len();
+    }
+    Span::new(first.start, end)
+}
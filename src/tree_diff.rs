@@ -0,0 +1,191 @@
+//! `kconfig-lsp diff`: the symbol-level changes between two indexed trees,
+//! e.g. two kernel releases – what a Kconfig changelog would list.
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use serde::Serialize;
+
+use crate::analysis::{Symbol, WorldIndex};
+use crate::include_graph;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Retyped,
+    /// The prompt was removed, added or reworded.
+    Prompt,
+    Defaults,
+    Dependencies,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolChange {
+    pub symbol: String,
+    pub kind: ChangeKind,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// What the comparison looks at in one tree, for one symbol.
+#[derive(PartialEq)]
+struct Summary {
+    type_name: Option<String>,
+    prompt: Option<String>,
+    /// `default` lines in the order kconfig tries them, with their
+    /// conditions, `;`-separated.
+    defaults: Option<String>,
+    /// The dependencies of each definition – `if` blocks, enclosing menus,
+    /// the `source` it sits under, and its own `depends on` – `||`-joined
+    /// across definitions.
+    dependencies: Option<String>,
+}
+
+/// Changes from `old` to `new`, by symbol name.
+pub fn diff(old: &WorldIndex, new: &WorldIndex) -> Vec<SymbolChange> {
+    let (old_order, new_order) = (old.tree_order(), new.tree_order());
+    let names: BTreeSet<&String> = old
+        .definitions
+        .keys()
+        .chain(new.definitions.keys())
+        .collect();
+    let mut changes = Vec::new();
+    for name in names {
+        let before = old.symbol(name, &old_order).map(|s| summarize(old, &s));
+        let after = new.symbol(name, &new_order).map(|s| summarize(new, &s));
+        let mut change = |kind, old: Option<String>, new: Option<String>| {
+            changes.push(SymbolChange {
+                symbol: name.clone(),
+                kind,
+                old,
+                new,
+            })
+        };
+        match (before, after) {
+            (None, None) => {}
+            (None, Some(a)) => change(ChangeKind::Added, None, a.type_name),
+            (Some(b), None) => change(ChangeKind::Removed, b.type_name, None),
+            (Some(b), Some(a)) if b == a => {}
+            (Some(b), Some(a)) => {
+                if b.type_name != a.type_name {
+                    change(ChangeKind::Retyped, b.type_name, a.type_name);
+                }
+                if b.prompt != a.prompt {
+                    change(ChangeKind::Prompt, b.prompt, a.prompt);
+                }
+                if b.defaults != a.defaults {
+                    change(ChangeKind::Defaults, b.defaults, a.defaults);
+                }
+                if b.dependencies != a.dependencies {
+                    change(ChangeKind::Dependencies, b.dependencies, a.dependencies);
+                }
+            }
+        }
+    }
+    changes
+}
+
+fn summarize(index: &WorldIndex, sym: &Symbol) -> Summary {
+    let type_name = sym
+        .definitions
+        .iter()
+        .find_map(|d| d.type_kind)
+        .map(|t| t.as_str().to_string());
+    let prompt = sym.prompts().find_map(|d| d.prompt.clone());
+    let defaults: Vec<String> = sym
+        .defaults()
+        .map(|(_, a)| match &a.condition {
+            Some(cond) => format!("{} if {}", a.value, cond),
+            None => a.value.to_string(),
+        })
+        .collect();
+    let mut dependencies: Vec<String> = Vec::new();
+    for d in &sym.definitions {
+        let sourced = include_graph::inherited_condition(index, &d.file);
+        let terms: Vec<String> = sourced
+            .iter()
+            .chain(&d.inherited_depends)
+            .chain(&d.depends_on)
+            .map(|e| e.to_string())
+            .collect();
+        if terms.is_empty() {
+            // One unconditional definition makes the symbol unconditional.
+            dependencies.clear();
+            break;
+        }
+        let term = terms.join(" && ");
+        if !dependencies.contains(&term) {
+            dependencies.push(term);
+        }
+    }
+    let parenthesize = dependencies.len() > 1;
+    Summary {
+        type_name,
+        prompt,
+        defaults: (!defaults.is_empty()).then(|| defaults.join("; ")),
+        dependencies: (!dependencies.is_empty()).then(|| {
+            dependencies
+                .iter()
+                .map(|d| {
+                    if parenthesize && d.contains("||") {
+                        format!("({})", d)
+                    } else {
+                        d.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" || ")
+        }),
+    }
+}
+
+/// One line per change, e.g. `~ FOO: default y -> n if BAR`.
+pub fn render_text(changes: &[SymbolChange]) -> String {
+    let mut out = String::new();
+    let shown = |v: &Option<String>| v.clone().unwrap_or_else(|| "none".into());
+    for c in changes {
+        let _ = match c.kind {
+            ChangeKind::Added => match &c.new {
+                Some(t) => writeln!(out, "+ {} ({})", c.symbol, t),
+                None => writeln!(out, "+ {}", c.symbol),
+            },
+            ChangeKind::Removed => writeln!(out, "- {}", c.symbol),
+            ChangeKind::Retyped => writeln!(
+                out,
+                "~ {}: type {} -> {}",
+                c.symbol,
+                shown(&c.old),
+                shown(&c.new)
+            ),
+            ChangeKind::Prompt => match (&c.old, &c.new) {
+                (Some(old), None) => writeln!(out, "~ {}: prompt \"{}\" removed", c.symbol, old),
+                (None, Some(new)) => writeln!(out, "~ {}: prompt \"{}\" added", c.symbol, new),
+                _ => writeln!(
+                    out,
+                    "~ {}: prompt \"{}\" -> \"{}\"",
+                    c.symbol,
+                    shown(&c.old),
+                    shown(&c.new)
+                ),
+            },
+            ChangeKind::Defaults => writeln!(
+                out,
+                "~ {}: default {} -> {}",
+                c.symbol,
+                shown(&c.old),
+                shown(&c.new)
+            ),
+            ChangeKind::Dependencies => writeln!(
+                out,
+                "~ {}: depends on {} -> {}",
+                c.symbol,
+                shown(&c.old),
+                shown(&c.new)
+            ),
+        };
+    }
+    out
+}
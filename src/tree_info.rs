@@ -0,0 +1,62 @@
+//! What tree the index holds: its `mainmenu` title, root file, architecture
+//! and profile, so results can say which tree they come from when several
+//! could be open.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::analysis::WorldIndex;
+use crate::ast::Entry;
+use crate::preprocess::Expander;
+use crate::profile::Profile;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeInfo {
+    /// The `mainmenu` title with its macros expanded, e.g. "Linux/x86 6.9.0
+    /// Kernel Configuration".
+    pub title: Option<String>,
+    pub root: PathBuf,
+    /// Files reached from the root through `source`, the root included.
+    pub sourced_files: usize,
+    /// `$(SRCARCH)`, or else `$(ARCH)`, if either expands.
+    pub arch: Option<String>,
+    /// The profile applied to the workspace, when known.
+    pub profile: Option<Profile>,
+}
+
+/// The tree rooted at the index's root Kconfig; `None` until that file is
+/// indexed.
+pub fn tree_info(index: &WorldIndex) -> Option<TreeInfo> {
+    let root = index.root_kconfig.as_deref()?;
+    if !index.files.contains_key(root) {
+        return None;
+    }
+    let expander = Expander::new(index, root, 0);
+    let arch = ["$(SRCARCH)", "$(ARCH)"]
+        .into_iter()
+        .filter_map(|v| expander.expand(v).ok())
+        .find(|v| !v.is_empty());
+    Some(TreeInfo {
+        title: title(index),
+        root: root.to_path_buf(),
+        sourced_files: index.tree_order().files(),
+        arch,
+        profile: (index.profile != Profile::Auto).then_some(index.profile),
+    })
+}
+
+/// The expanded `mainmenu` title of the root Kconfig, if it has one.
+pub fn title(index: &WorldIndex) -> Option<String> {
+    let root = index.root_kconfig.as_deref()?;
+    let fa = index.files.get(root)?;
+    fa.file.entries.iter().find_map(|e| match e {
+        Entry::MainMenu(m) => {
+            let line = fa.line_index.line_col(m.span.start).0 + 1;
+            let expanded = Expander::new(index, root, line).expand(&m.prompt);
+            Some(expanded.unwrap_or_else(|_| m.prompt.clone()))
+        }
+        _ => None,
+    })
+}
@@ -0,0 +1,135 @@
+//! `textDocument/prepareTypeHierarchy`, `typeHierarchy/supertypes` and
+//! `typeHierarchy/subtypes` mapped onto menu containment: the supertype of
+//! an entry is the menu, `menuconfig` or choice it is shown under, and its
+//! subtypes are the entries shown under it. Editors with a type hierarchy
+//! view get a tree to navigate the configuration with.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::analysis::WorldIndex;
+use crate::menu_tree::MenuNode;
+use crate::{symbol_docs, uri};
+
+/// What an item carries between requests: the file whose menu tree it was
+/// found in, and where in that tree: the index of each node on the way
+/// down among its parent's children, none for the root. A file sourced
+/// twice has its entries in two places, told apart by this.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ItemData {
+    tree: PathBuf,
+    #[serde(default)]
+    node: Vec<usize>,
+}
+
+/// The item of the innermost entry around `pos`, placed in the menu tree
+/// of the root Kconfig if that reaches the file, or else of the file.
+pub fn prepare(index: &WorldIndex, path: &Path, pos: Position) -> Option<Vec<TypeHierarchyItem>> {
+    let fa = index.files.get(path)?;
+    let offset = fa.line_index.offset(pos.line, pos.character);
+    let entry = *index.outline_at(path, offset).last()?;
+    let (line, _) = fa.line_index.line_col(entry.span.start);
+    let uri = uri::from_path(path)?;
+    index
+        .root_kconfig
+        .as_deref()
+        .into_iter()
+        .chain([path])
+        .find_map(|tree_path| {
+            let tree = index.menu_tree(tree_path)?;
+            let node = indices_to(&tree, &uri, line)?;
+            Some(vec![item(&tree, tree_path, node)?])
+        })
+}
+
+/// The item of the node `item` is shown under: a menu, `menuconfig` or
+/// choice, or the root of the tree.
+pub fn supertypes(index: &WorldIndex, item: &TypeHierarchyItem) -> Option<Vec<TypeHierarchyItem>> {
+    let (mut data, tree) = resolve(index, item)?;
+    if data.node.pop().is_none() {
+        return Some(Vec::new());
+    }
+    Some(vec![self::item(&tree, &data.tree, data.node)?])
+}
+
+/// The items of the nodes shown under `item`.
+pub fn subtypes(index: &WorldIndex, item: &TypeHierarchyItem) -> Option<Vec<TypeHierarchyItem>> {
+    let (data, tree) = resolve(index, item)?;
+    let node = descendant(&tree, &data.node)?;
+    (0..node.children.len())
+        .map(|i| {
+            let mut child = data.node.clone();
+            child.push(i);
+            self::item(&tree, &data.tree, child)
+        })
+        .collect()
+}
+
+/// The data of `item` and the menu tree it was found in. If the tree has
+/// changed since so that the item's node is elsewhere, it is looked for
+/// by its location.
+fn resolve(index: &WorldIndex, item: &TypeHierarchyItem) -> Option<(ItemData, Arc<MenuNode>)> {
+    let mut data: ItemData = serde_json::from_value(item.data.clone()?).ok()?;
+    let tree = index.menu_tree(&data.tree)?;
+    let line = item.selection_range.start.line;
+    let moved = descendant(&tree, &data.node)
+        .is_none_or(|n| n.location.uri != item.uri || n.location.range.start.line != line);
+    if moved {
+        data.node = indices_to(&tree, &item.uri, line)?;
+    }
+    Some((data, tree))
+}
+
+/// Where [`MenuNode::path_to`] finds the entry at `line` of `uri`, as
+/// [`ItemData::node`] has it.
+fn indices_to(tree: &MenuNode, uri: &Url, line: u32) -> Option<Vec<usize>> {
+    let nodes = tree.path_to(uri, line)?;
+    Some(
+        nodes
+            .windows(2)
+            .map(|pair| {
+                pair[0]
+                    .children
+                    .iter()
+                    .position(|c| std::ptr::eq(c, pair[1]))
+                    .unwrap()
+            })
+            .collect(),
+    )
+}
+
+/// The node at `indices` under `tree`.
+fn descendant<'a>(tree: &'a MenuNode, indices: &[usize]) -> Option<&'a MenuNode> {
+    indices
+        .iter()
+        .try_fold(tree, |node, &i| node.children.get(i))
+}
+
+fn item(tree: &MenuNode, tree_path: &Path, indices: Vec<usize>) -> Option<TypeHierarchyItem> {
+    let node = descendant(tree, &indices)?;
+    let (name, detail) = match &node.symbol {
+        Some(symbol) => (
+            symbol.clone(),
+            (node.label != *symbol).then(|| node.label.clone()),
+        ),
+        None => (node.label.clone(), None),
+    };
+    let data = ItemData {
+        tree: tree_path.to_path_buf(),
+        node: indices,
+    };
+    Some(TypeHierarchyItem {
+        name,
+        kind: symbol_docs::symbol_kind(node.kind),
+        tags: None,
+        detail,
+        uri: node.location.uri.clone(),
+        range: node.location.range,
+        selection_range: node.location.range,
+        data: serde_json::to_value(data).ok(),
+    })
+}
@@ -0,0 +1,63 @@
+//! Conversions between document URIs and the paths the index is keyed by.
+//!
+//! Documents that are not files (`untitled:` buffers, Live Share's `vsls:`,
+//! remote schemes) get a virtual path under [`VIRTUAL_ROOT`] embedding the
+//! whole URI. They are indexed like files, so parsing, hover, completion,
+//! and diagnostics work, and locations in them map back to the same URI.
+//! Cross-file features are best effort: `source` statements in them resolve
+//! only against the workspace root.
+//!
+//! Editors spell the same file differently: percent-encoded or not, with
+//! `.` segments or a trailing slash, and on Windows with either case of
+//! drive letter. [`to_path`] maps every spelling to the path the workspace
+//! scan would have produced, and [`normalize`] to one URI, so handlers find
+//! the file whichever way it was opened.
+
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::Url;
+
+use crate::analysis;
+
+pub const VIRTUAL_ROOT: &str = "/.kconfig-lsp-virtual/";
+
+/// The index key of a document.
+pub fn to_path(uri: &Url) -> Option<PathBuf> {
+    if uri.scheme() == "file" {
+        return uri.to_file_path().ok().map(|p| normalize_path(&p));
+    }
+    Some(PathBuf::from(format!("{}{}", VIRTUAL_ROOT, uri)))
+}
+
+/// The one spelling of `uri` that [`from_path`] gives for its file.
+pub fn normalize(uri: &Url) -> Url {
+    to_path(uri)
+        .and_then(|p| from_path(&p))
+        .unwrap_or_else(|| uri.clone())
+}
+
+/// `path` without `.` and `..` components or a trailing separator, and with
+/// an upper-case drive letter.
+fn normalize_path(path: &Path) -> PathBuf {
+    let path = analysis::normalize(path);
+    if cfg!(windows)
+        && let Some(s) = path.to_str()
+        && s.as_bytes().get(1) == Some(&b':')
+    {
+        return PathBuf::from(s[..1].to_ascii_uppercase() + &s[1..]);
+    }
+    path
+}
+
+/// The URI of an indexed path, inverting [`to_path`].
+pub fn from_path(path: &Path) -> Option<Url> {
+    match path.to_str().and_then(|p| p.strip_prefix(VIRTUAL_ROOT)) {
+        Some(uri) => Url::parse(uri).ok(),
+        None => Url::from_file_path(path).ok(),
+    }
+}
+
+/// Whether `path` stands for a document that is not a file.
+pub fn is_virtual(path: &Path) -> bool {
+    path.to_str().is_some_and(|p| p.starts_with(VIRTUAL_ROOT))
+}
@@ -0,0 +1,82 @@
+//! Helpers the request handlers share: the symbol name under the cursor,
+//! the whole lines of a span, and spans turned into LSP ranges and locations
+//! or `path:line` text.
+
+use std::path::Path;
+
+use tower_lsp::lsp_types::{Location, Position, Range};
+
+use crate::analysis::WorldIndex;
+use crate::ast::{LineIndex, Span};
+use crate::uri;
+
+/// The symbol name around `offset`, if there is one.
+pub fn word_at_offset(source: &str, offset: usize) -> Option<String> {
+    let bytes = source.as_bytes();
+    if offset >= bytes.len() {
+        return None;
+    }
+    let mut start = offset;
+    while start > 0 && is_word_char(bytes[start - 1]) {
+        start -= 1;
+    }
+    let mut end = offset;
+    while end < bytes.len() && is_word_char(bytes[end]) {
+        end += 1;
+    }
+    if start == end {
+        return None;
+    }
+    Some(source[start..end].to_string())
+}
+
+/// Whether `b` can appear in a symbol name.
+pub fn is_word_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// The whole lines `span` touches, including the final newline.
+pub fn lines_of(source: &str, span: Span) -> Span {
+    let start = source[..span.start].rfind('\n').map_or(0, |p| p + 1);
+    let end = source[span.end..]
+        .find('\n')
+        .map_or(source.len(), |p| span.end + p + 1);
+    Span::new(start, end)
+}
+
+pub fn range(line_index: &LineIndex, span: Span) -> Range {
+    let (line, col) = line_index.line_col(span.start);
+    let (end_line, end_col) = line_index.line_col(span.end);
+    Range {
+        start: Position::new(line, col),
+        end: Position::new(end_line, end_col),
+    }
+}
+
+/// The location of `span` in the indexed file `path`.
+pub fn location(index: &WorldIndex, path: &Path, span: Span) -> Option<Location> {
+    let fa = index.files.get(path)?;
+    Some(Location {
+        uri: uri::from_path(path)?,
+        range: range(&fa.line_index, span),
+    })
+}
+
+/// `path:line` of `span`, relative to the source tree.
+pub fn place(index: &WorldIndex, path: &Path, span: Span) -> String {
+    let line = index
+        .files
+        .get(path)
+        .map_or(0, |fa| fa.line_index.line_col(span.start).0);
+    line_place(index, path, line)
+}
+
+/// `path:line` of a 0-based line, relative to the source tree.
+pub fn line_place(index: &WorldIndex, path: &Path, line: u32) -> String {
+    let path = index
+        .root
+        .as_deref()
+        .and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path);
+    format!("{}:{}", path.display(), line + 1)
+}
@@ -0,0 +1,100 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::client_caps::ClientCaps;
+use kconfig_lsp::settings::HoverSettings;
+use kconfig_lsp::{completion, hover};
+use std::path::Path;
+use tower_lsp::lsp_types::*;
+
+fn caps(json: serde_json::Value) -> ClientCaps {
+    ClientCaps::new(&serde_json::from_value(json).unwrap())
+}
+
+#[test]
+fn minimal_clients_get_plain_text_hovers_and_no_optional_features() {
+    let caps = caps(serde_json::json!({}));
+    assert!(!caps.hover_markdown);
+    assert!(!caps.code_actions);
+    assert!(!caps.work_done_progress);
+    assert!(!caps.watched_files);
+
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(
+        path,
+        "config SMP\n\tbool \"Symmetric multi-processing support\"\n\thelp\n\t  Say Y for `SMP`.\n",
+    );
+    let hover = hover::hover(
+        &index,
+        path,
+        Position::new(0, 8),
+        caps.hover_format(),
+        &HoverSettings::default(),
+    )
+    .unwrap();
+    let HoverContents::Markup(content) = hover.contents else {
+        panic!("expected markup");
+    };
+    assert_eq!(content.kind, MarkupKind::PlainText);
+    assert_eq!(
+        content.value,
+        "SMP (config) bool\n\"Symmetric multi-processing support\"\nDefined in /src/Kconfig:1\n\nSay Y for `SMP`."
+    );
+
+    let item = CompletionItem {
+        label: "SMP".into(),
+        kind: Some(CompletionItemKind::CONSTANT),
+        ..Default::default()
+    };
+    let item = completion::resolve(
+        &index,
+        item,
+        caps.completion_format(),
+        &HoverSettings::default(),
+    );
+    let Some(Documentation::MarkupContent(doc)) = item.documentation else {
+        panic!("expected documentation");
+    };
+    assert_eq!(doc.kind, MarkupKind::PlainText);
+    assert_eq!(doc.value, content.value);
+
+    let diag = Diagnostic {
+        related_information: Some(Vec::new()),
+        code_description: Some(CodeDescription {
+            href: Url::parse("https://example.com/").unwrap(),
+        }),
+        ..Default::default()
+    };
+    let diags = caps.diagnostics(vec![diag]);
+    assert!(diags[0].related_information.is_none());
+    assert!(diags[0].code_description.is_none());
+}
+
+#[test]
+fn declared_capabilities_enable_features() {
+    let caps = caps(serde_json::json!({
+        "textDocument": {
+            "hover": { "contentFormat": ["markdown", "plaintext"] },
+            "completion": {
+                "completionItem": { "documentationFormat": ["markdown"], "snippetSupport": true }
+            },
+            "codeAction": {
+                "codeActionLiteralSupport": { "codeActionKind": { "valueSet": ["quickfix"] } }
+            },
+            "publishDiagnostics": { "relatedInformation": true, "codeDescriptionSupport": true },
+            "typeHierarchy": { "dynamicRegistration": true }
+        },
+        "window": { "workDoneProgress": true },
+        "workspace": {
+            "didChangeWatchedFiles": { "dynamicRegistration": true },
+            "workspaceEdit": { "changeAnnotationSupport": { "groupsOnLabel": true } }
+        },
+        "experimental": { "kconfigFileAssociations": true }
+    }));
+    assert_eq!(
+        caps,
+        ClientCaps {
+            code_lens: false,
+            ..ClientCaps::default()
+        }
+    );
+}
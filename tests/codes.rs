@@ -0,0 +1,84 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::codes;
+use kconfig_lsp::diagnostics;
+use kconfig_lsp::settings::Settings;
+use std::collections::HashSet;
+use std::path::Path;
+use tower_lsp::lsp_types::{DiagnosticSeverity, NumberOrString};
+
+#[test]
+fn rules_doc_matches_code_table() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("docs/rules.md");
+    let doc = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        doc == codes::rules_markdown(),
+        "docs/rules.md is out of date; regenerate it from codes::rules_markdown()"
+    );
+    let ids: HashSet<&str> = codes::ALL.iter().map(|c| c.id).collect();
+    assert_eq!(ids.len(), codes::ALL.len());
+}
+
+#[test]
+fn diagnostics_carry_code_and_documentation_link() {
+    let path = Path::new("/src/Kconfig");
+    let src =
+        "config FOO\n\tstring \"Foo\"\n\tdefault foo\n\tdepends on MISSING\n\nmenu \"Open\"\n";
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+
+    let mut settings = Settings::default();
+    let found: Vec<(String, String)> = diagnostics::collect(&index, path, &settings)
+        .into_iter()
+        .map(|d| {
+            let Some(NumberOrString::String(code)) = d.code else {
+                panic!("no code on {:?}", d.message);
+            };
+            (code, d.code_description.expect("link").href.to_string())
+        })
+        .collect();
+    for (id, anchor) in [
+        ("KC0001", "#kc0001-syntax"),
+        ("KC0002", "#kc0002-undefined-symbol"),
+        ("KC0011", "#kc0011-default-quoting"),
+    ] {
+        assert!(
+            found.iter().any(|(c, href)| c == id
+                && href.starts_with(codes::DOCS_URL)
+                && href.ends_with(anchor)),
+            "{} missing from {:?}",
+            id,
+            found
+        );
+    }
+
+    settings.lint.disable = vec!["kc0011".into()];
+    assert!(
+        diagnostics::collect(&index, path, &settings)
+            .iter()
+            .all(|d| d.code != Some(NumberOrString::String("KC0011".into())))
+    );
+}
+
+#[test]
+fn diagnostics_are_capped_with_errors_first() {
+    let path = Path::new("/src/Kconfig");
+    let mut src = String::new();
+    for i in 0..10 {
+        src.push_str(&format!(
+            "config A{}\n\tbool\n\tdepends on MISSING{}\n\n",
+            i, i
+        ));
+    }
+    src.push_str("endmenu\n");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, &src);
+
+    let settings = Settings {
+        max_diagnostics: 3,
+        ..Settings::default()
+    };
+    let diags = diagnostics::collect(&index, path, &settings);
+    assert_eq!(diags.len(), 4);
+    assert_eq!(diags[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(diags[3].message, "8 more problems not shown");
+}
@@ -0,0 +1,132 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::completion;
+use std::path::Path;
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionResponse, CompletionTextEdit, InsertTextFormat, Position,
+};
+
+const NET: &str = "menuconfig NETDEVICES\n\tbool \"Network device support\"\n\nconfig ETHERNET\n\tbool\n\nconfig NET_VENDOR_ACME\n\tbool \"Acme devices\"\n\tdepends on PCI && ETHERNET\n\tdefault y if \n\nconfig PCI\n\tbool\n\nconfig ACPI\n\tbool\n";
+
+fn items(src: &str, pos: Position) -> Vec<CompletionItem> {
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/drivers/net/Kconfig");
+    index.analyze_file(path, src);
+    match completion::complete(&index, path, pos, false) {
+        Some(CompletionResponse::Array(items)) => items,
+        other => panic!("expected completion items, got {:?}", other),
+    }
+}
+
+#[test]
+fn if_condition_ranks_entry_dependencies_first() {
+    let mut items = items(NET, Position::new(9, 14));
+    items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(
+        labels,
+        ["PCI", "ETHERNET", "NETDEVICES", "ACPI", "NET_VENDOR_ACME"]
+    );
+}
+
+#[test]
+fn condition_continued_onto_the_next_line_still_completes_symbols() {
+    let src = NET.replace("\tdefault y if \n", "\tdefault y if \\\n\t\t\n");
+    let mut items = items(&src, Position::new(10, 2));
+    items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(
+        labels,
+        ["PCI", "ETHERNET", "NETDEVICES", "ACPI", "NET_VENDOR_ACME"]
+    );
+}
+
+#[test]
+fn defaults_complete_values_of_the_symbol_type() {
+    let src = "config DEBUG\n\tbool \"Debug\"\n\tdefault \n\nconfig MODE\n\ttristate \"Mode\"\n\tdefault m\n\nconfig NAME\n\tstring \"Name\"\n\tdefault \n\nconfig COUNT\n\tint \"Count\"\n\tdefault \n\nconfig MAX\n\tint\n\nconfig BASE\n\thex\n";
+    let labels = |line, character| {
+        let mut items = items(src, Position::new(line, character));
+        items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+        items.into_iter().map(|i| i.label).collect::<Vec<_>>()
+    };
+    assert_eq!(labels(2, 9), ["y", "n", "MODE"]);
+    assert_eq!(labels(6, 10), ["m"]);
+    assert_eq!(labels(14, 9), ["0", "MAX"]);
+
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(path, src);
+    let Some(CompletionResponse::Array(items)) =
+        completion::complete(&index, path, Position::new(10, 9), true)
+    else {
+        panic!("expected completion items");
+    };
+    let snippets: Vec<(&str, Option<&str>, Option<InsertTextFormat>)> = items
+        .iter()
+        .map(|i| {
+            (
+                i.label.as_str(),
+                i.insert_text.as_deref(),
+                i.insert_text_format,
+            )
+        })
+        .collect();
+    assert_eq!(
+        snippets,
+        [("\"\"", Some("\"$1\""), Some(InsertTextFormat::SNIPPET))]
+    );
+}
+
+#[test]
+fn source_paths_list_unsourced_files_first() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.root_kconfig = Some("/src/Kconfig".into());
+    let root = "source \"drivers/Kconfig\"\n";
+    let drivers = "source \"drivers/net/Kconfig\"\nsource \"drivers/\"\n";
+    index.analyze_file(Path::new("/src/Kconfig"), root);
+    index.analyze_file(Path::new("/src/drivers/Kconfig"), drivers);
+    index.analyze_file(Path::new("/src/drivers/net/Kconfig"), "");
+    index.analyze_file(Path::new("/src/drivers/usb/Kconfig"), "");
+    index.analyze_file(Path::new("/src/fs/Kconfig"), "");
+
+    let Some(CompletionResponse::Array(items)) = completion::complete(
+        &index,
+        Path::new("/src/drivers/Kconfig"),
+        Position::new(1, 16),
+        false,
+    ) else {
+        panic!("expected completion items");
+    };
+    let labels: Vec<(&str, Option<&str>)> = items
+        .iter()
+        .map(|i| (i.label.as_str(), i.detail.as_deref()))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            ("drivers/usb/Kconfig", Some("Not sourced anywhere yet")),
+            ("drivers/net/Kconfig", None),
+        ]
+    );
+    let Some(CompletionTextEdit::Edit(edit)) = &items[0].text_edit else {
+        panic!("expected a text edit");
+    };
+    assert_eq!(edit.range.start, Position::new(1, 8));
+    assert_eq!(edit.range.end, Position::new(1, 16));
+}
+
+#[test]
+fn help_text_and_prompts_are_free_text() {
+    let src = "config PCI\n\tbool \"PCI support\"\n\tdepends on ACPI\n\thelp\n\t  Say Y here if \n\nsource \"drivers/Kconfig\"\n";
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(path, src);
+    let in_text =
+        |line, character| completion::in_text(&index, path, Position::new(line, character));
+
+    assert!(in_text(1, 10));
+    assert!(in_text(4, 16));
+    assert!(!in_text(2, 12));
+    assert!(!in_text(1, 6));
+    assert!(!in_text(6, 10));
+}
@@ -0,0 +1,150 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::config_doc;
+use kconfig_lsp::dotconfig::DotConfig;
+use std::path::Path;
+use tower_lsp::lsp_types::{CodeActionOrCommand, Position, Range, Url};
+
+const KCONFIG: &str = "config NET\n\tbool \"Networking\"\n\nconfig E1000\n\ttristate \"E1000\"\n\nconfig NR_CPUS\n\tint \"CPUs\"\n\trange 2 64\n\nconfig LOCALVERSION\n\tstring \"Local version\"\n";
+
+/// The tokens of `text` as `(text, type, invalid)`, with absolute positions
+/// resolved back to the text they cover.
+fn tokens<'a>(index: &WorldIndex, text: &'a str) -> Vec<(&'a str, &'static str, bool)> {
+    let legend = config_doc::legend();
+    let lines: Vec<&str> = text.lines().collect();
+    let (mut line, mut col) = (0, 0);
+    config_doc::semantic_tokens(index, text, "CONFIG_")
+        .into_iter()
+        .map(|t| {
+            if t.delta_line > 0 {
+                col = 0;
+            }
+            line += t.delta_line as usize;
+            col += t.delta_start as usize;
+            let kind = match legend.token_types[t.token_type as usize].as_str() {
+                "comment" => "comment",
+                "variable" => "variable",
+                "property" => "property",
+                "keyword" => "keyword",
+                "number" => "number",
+                "string" => "string",
+                _ => "operator",
+            };
+            (
+                &lines[line][col..col + t.length as usize],
+                kind,
+                t.token_modifiers_bitset != 0,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn config_files_are_recognized_by_name_or_setting() {
+    let configured = Path::new("/src/build/my-config");
+    for name in [
+        "/src/.config",
+        "/src/arch/x86/configs/x86_64_defconfig",
+        "/src/kernel/configs/kvm_guest.config",
+        "/app/prj.conf",
+        "/app/sdkconfig.defaults",
+        "/src/build/my-config",
+    ] {
+        assert!(
+            config_doc::is_config_file(Path::new(name), Some(configured)),
+            "{}",
+            name
+        );
+    }
+    assert!(!config_doc::is_config_file(Path::new("/src/Kconfig"), None));
+    assert!(!config_doc::is_config_file(
+        Path::new("/src/Kconfig.debug"),
+        None
+    ));
+}
+
+#[test]
+fn assignments_are_colored_by_type_and_validity() {
+    let mut index = WorldIndex::new();
+    index.analyze_file(Path::new("/src/Kconfig"), KCONFIG);
+    let text = "# Networking\nCONFIG_NET=m\n# CONFIG_E1000 is not set\nCONFIG_NR_CPUS=128\nCONFIG_LOCALVERSION=\"-test\"\nCONFIG_GONE=y\n";
+
+    assert_eq!(
+        tokens(&index, text),
+        [
+            ("# Networking", "comment", false),
+            ("CONFIG_NET", "variable", false),
+            ("=", "operator", false),
+            ("m", "keyword", true),
+            ("#", "comment", false),
+            ("CONFIG_E1000", "variable", false),
+            ("is not set", "keyword", false),
+            ("CONFIG_NR_CPUS", "property", false),
+            ("=", "operator", false),
+            ("128", "number", true),
+            ("CONFIG_LOCALVERSION", "property", false),
+            ("=", "operator", false),
+            ("\"-test\"", "string", false),
+            ("CONFIG_GONE", "variable", true),
+            ("=", "operator", false),
+            ("y", "keyword", false),
+        ]
+    );
+    let valid = tokens(&index, "CONFIG_NR_CPUS=64\n");
+    assert_eq!(valid[2], ("64", "number", false));
+}
+
+#[test]
+fn not_set_lines_are_n_and_offer_to_set_the_symbol() {
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "config PCI\n\tbool \"PCI\"\n\nconfig E1000\n\ttristate \"E1000\"\n\tdepends on PCI\n\nconfig CRC32\n\ttristate\n",
+    );
+    let text = "# CONFIG_PCI is not set\n# CONFIG_E1000 is not set\n# CONFIG_CRC32 is not set\n";
+    let config = DotConfig::parse(Path::new("/src/.config"), text, "CONFIG_");
+    assert_eq!(config.get("E1000"), Some("n"));
+
+    let uri = Url::parse("file:///src/.config").unwrap();
+    let titles = |text: &str, line: u32| -> Vec<(String, String)> {
+        let range = Range::new(Position::new(line, 0), Position::new(line, 0));
+        config_doc::code_actions(&index, &uri, text, range, "CONFIG_")
+            .into_iter()
+            .map(|a| match a {
+                CodeActionOrCommand::CodeAction(a) => {
+                    let edit = &a.edit.unwrap().changes.unwrap()[&uri][0];
+                    assert_eq!(edit.range.start.line, line);
+                    (a.title, edit.new_text.clone())
+                }
+                other => panic!("expected an action, got {:?}", other),
+            })
+            .collect()
+    };
+
+    assert_eq!(
+        titles(text, 0),
+        [(
+            "Set CONFIG_PCI to y".to_string(),
+            "CONFIG_PCI=y".to_string()
+        )]
+    );
+    assert_eq!(
+        titles(text, 1),
+        [
+            (
+                "Set CONFIG_E1000 to y (olddefconfig reverts it: it depends on `PCI`)".to_string(),
+                "CONFIG_E1000=y".to_string()
+            ),
+            (
+                "Set CONFIG_E1000 to m (olddefconfig reverts it: it depends on `PCI`)".to_string(),
+                "CONFIG_E1000=m".to_string()
+            ),
+        ]
+    );
+    let with_pci = text.replace("# CONFIG_PCI is not set", "CONFIG_PCI=y");
+    assert_eq!(titles(&with_pci, 1)[1].0, "Set CONFIG_E1000 to m");
+    assert!(
+        titles(text, 2)[0]
+            .0
+            .ends_with("(olddefconfig reverts it: it has no prompt)")
+    );
+}
@@ -0,0 +1,93 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::dependency_path::{self, DependencyPathParams, EdgeKind};
+use std::path::Path;
+
+const KCONFIG: &str = r#"config NET
+	bool "Networking support"
+
+if NET
+
+config INET
+	bool "TCP/IP networking"
+
+endif
+
+config NETFILTER
+	bool "Netfilter"
+	depends on INET
+	select CRC32
+
+config NF_TABLES
+	tristate "nftables"
+	depends on NETFILTER
+	imply CRC32
+
+config CRC32
+	tristate
+	depends on NET
+"#;
+
+fn index() -> WorldIndex {
+    let mut index = WorldIndex::new();
+    index.analyze_file(Path::new("/src/Kconfig"), KCONFIG);
+    index
+}
+
+fn paths(from: &str, to: &str, max_length: Option<usize>) -> Vec<Vec<(String, EdgeKind)>> {
+    let params = DependencyPathParams {
+        from: from.into(),
+        to: to.into(),
+        limit: None,
+        max_length,
+    };
+    dependency_path::dependency_paths(&index(), &params)
+        .into_iter()
+        .map(|p| p.edges.into_iter().map(|e| (e.to, e.kind)).collect())
+        .collect()
+}
+
+#[test]
+fn paths_come_shortest_first_with_their_edge_kinds() {
+    let found = paths("NF_TABLES", "NET", None);
+    let kinds = |p: &[(String, EdgeKind)]| p.iter().map(|(s, k)| (s.clone(), *k)).collect();
+    let expected: Vec<Vec<(String, EdgeKind)>> = vec![
+        kinds(&[
+            ("CRC32".into(), EdgeKind::Imply),
+            ("NET".into(), EdgeKind::DependsOn),
+        ]),
+        kinds(&[
+            ("NETFILTER".into(), EdgeKind::DependsOn),
+            ("CRC32".into(), EdgeKind::Select),
+            ("NET".into(), EdgeKind::DependsOn),
+        ]),
+        kinds(&[
+            ("NETFILTER".into(), EdgeKind::DependsOn),
+            ("INET".into(), EdgeKind::DependsOn),
+            ("NET".into(), EdgeKind::InheritedDependency),
+        ]),
+    ];
+    assert_eq!(found, expected);
+
+    assert_eq!(paths("NF_TABLES", "NET", Some(2)).len(), 1);
+    assert!(paths("NET", "NF_TABLES", None).is_empty());
+}
+
+#[test]
+fn edges_point_at_where_the_target_is_written() {
+    let index = index();
+    let params = DependencyPathParams {
+        from: "NETFILTER".into(),
+        to: "CRC32".into(),
+        limit: Some(1),
+        max_length: None,
+    };
+    let found = dependency_path::dependency_paths(&index, &params);
+    let location = found[0].edges[0].location.as_ref().unwrap();
+    assert_eq!(location.uri.path(), "/src/Kconfig");
+    assert_eq!(location.range.start.line, 13);
+    assert_eq!(location.range.start.character, 8);
+    assert_eq!(
+        dependency_path::render_text(&index, &found),
+        "NETFILTER\n  select CRC32  (/src/Kconfig:14)\n"
+    );
+}
@@ -0,0 +1,110 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::doc_site::{self, DocFormat, Page};
+use std::path::{Path, PathBuf};
+
+const ROOT: &str = r#"mainmenu "Test Configuration"
+
+config MODULES
+	bool "Enable loadable module support"
+
+menu "Networking"
+
+menuconfig NET
+	bool "Networking support"
+	select CRYPTO
+	help
+	  Core networking.
+
+if NET
+source "net/Kconfig"
+endif
+
+endmenu
+
+menu "Crypto API"
+
+config CRYPTO
+	tristate "Cryptographic API"
+
+endmenu
+"#;
+
+const NET: &str = r#"menu "Networking"
+
+config INET
+	bool "TCP/IP networking"
+	depends on MODULES
+	help
+	  The <TCP/IP> stack.
+
+endmenu
+"#;
+
+fn render(format: DocFormat) -> Vec<Page> {
+    let mut index = WorldIndex::new();
+    index.root = Some(PathBuf::from("/src"));
+    index.analyze_file(Path::new("/src/Kconfig"), ROOT);
+    index.analyze_file(Path::new("/src/net/Kconfig"), NET);
+    doc_site::render(&index, Path::new("/src/Kconfig"), format).expect("root is indexed")
+}
+
+fn page<'a>(pages: &'a [Page], name: &str) -> &'a str {
+    &pages
+        .iter()
+        .find(|p| p.path == Path::new(name))
+        .unwrap_or_else(|| panic!("no page {}", name))
+        .content
+}
+
+#[test]
+fn one_page_per_menu_with_symbol_anchors_and_dependency_links() {
+    let pages = render(DocFormat::Markdown);
+    let names: Vec<&str> = pages.iter().map(|p| p.path.to_str().unwrap()).collect();
+    assert_eq!(
+        names,
+        [
+            "index.md",
+            "networking.md",
+            "networking-2.md",
+            "crypto-api.md"
+        ]
+    );
+
+    let index = page(&pages, "index.md");
+    assert!(index.starts_with("# Test Configuration\n"), "{}", index);
+    assert!(
+        index.contains("<a id=\"MODULES\"></a>\n\n## `MODULES`: Enable loadable module support")
+    );
+    assert!(index.contains("**Menu:** [Networking](networking.md)"));
+    assert!(index.contains("**Menu:** [Crypto API](crypto-api.md)"));
+
+    let networking = page(&pages, "networking.md");
+    assert!(networking.starts_with("[Test Configuration](index.md) › Networking\n\n# Networking"));
+    assert!(networking.contains("Selects: [CRYPTO](crypto-api.md#CRYPTO)"));
+    assert!(networking.contains("Core networking."));
+    assert!(networking.contains("Defined in `Kconfig:8`"));
+
+    // Reverse dependencies come from the map built for the whole site.
+    let crypto = page(&pages, "crypto-api.md");
+    assert!(crypto.contains("**Selected by**"), "{}", crypto);
+    assert!(crypto.contains("- `NET` (from Kconfig:10)"), "{}", crypto);
+
+    let nested = page(&pages, "networking-2.md");
+    assert!(nested.starts_with(
+        "[Test Configuration](index.md) › [Networking](networking.md) › Networking\n"
+    ));
+    assert!(nested.contains("Depends on: [NET](networking.md#NET), [MODULES](index.md#MODULES)"));
+    assert!(nested.contains("Defined in `net/Kconfig:3`"));
+}
+
+#[test]
+fn html_pages_render_the_markdown() {
+    let pages = render(DocFormat::Html);
+    let nested = page(&pages, "networking-2.html");
+    assert!(nested.starts_with("<!DOCTYPE html>"));
+    assert!(nested.contains("<title>Networking</title>"));
+    assert!(nested.contains("<a id=\"INET\"></a>\n<h2><code>INET</code>: TCP/IP networking</h2>"));
+    assert!(nested.contains("<a href=\"networking.html#NET\">NET</a>"));
+    assert!(nested.contains("<p>The &lt;TCP/IP&gt; stack.</p>"));
+    assert!(page(&pages, "index.html").contains("<a href=\"networking.html\">Networking</a>"));
+}
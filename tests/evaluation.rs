@@ -0,0 +1,313 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::diagnostics;
+use kconfig_lsp::dotconfig::DotConfig;
+use kconfig_lsp::settings::Settings;
+use std::path::Path;
+use std::sync::Arc;
+
+const CHOICE: &str = r#"
+config EXPERT
+	bool "Expert mode"
+
+choice
+	prompt "Compiler optimization level"
+
+config CC_OPTIMIZE_FOR_PERFORMANCE
+	bool "Optimize for performance (-O2)"
+
+config CC_OPTIMIZE_FOR_SIZE
+	bool "Optimize for size (-Os)"
+	depends on EXPERT
+
+endchoice
+"#;
+
+fn diag_messages(config: &str) -> Vec<String> {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, CHOICE);
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        config,
+        "CONFIG_",
+    ));
+    diagnostics::collect(&index, path, &Settings::default())
+        .into_iter()
+        .map(|d| d.message)
+        .collect()
+}
+
+#[test]
+fn choice_with_single_valid_selection_is_clean() {
+    let msgs = diag_messages("CONFIG_CC_OPTIMIZE_FOR_PERFORMANCE=y\n");
+    assert!(msgs.is_empty(), "{:?}", msgs);
+}
+
+#[test]
+fn choice_with_multiple_selections() {
+    let msgs = diag_messages(
+        "CONFIG_EXPERT=y\nCONFIG_CC_OPTIMIZE_FOR_PERFORMANCE=y\nCONFIG_CC_OPTIMIZE_FOR_SIZE=y\n",
+    );
+    assert_eq!(msgs.len(), 1);
+    assert!(msgs[0].contains("multiple members"), "{}", msgs[0]);
+}
+
+#[test]
+fn choice_selection_with_unmet_dependency() {
+    let msgs = diag_messages("CONFIG_CC_OPTIMIZE_FOR_SIZE=y\n# CONFIG_EXPERT is not set\n");
+    assert_eq!(msgs.len(), 1);
+    assert!(msgs[0].contains("`EXPERT` is not met"), "{}", msgs[0]);
+}
+
+#[test]
+fn choice_without_selection() {
+    let msgs = diag_messages("CONFIG_EXPERT=y\n");
+    assert_eq!(msgs, ["choice has no member selected in .config"]);
+}
+
+const HEX_RANGE: &str = r#"
+config PHYS_START
+	hex "Physical address"
+	range 0x100000 0xFFFFFF
+	default 0x1000
+"#;
+
+#[test]
+fn hex_range_checks_defaults_and_config_values() {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, HEX_RANGE);
+
+    let msgs: Vec<String> = diagnostics::collect(&index, path, &Settings::default())
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(
+        msgs,
+        ["default 0x1000 is outside the range 0x100000..0xffffff of `PHYS_START`"]
+    );
+
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_PHYS_START=0X200000\n",
+        "CONFIG_",
+    ));
+    assert_eq!(
+        diagnostics::collect(&index, path, &Settings::default()).len(),
+        1
+    );
+
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_PHYS_START=0x1000000\n",
+        "CONFIG_",
+    ));
+    let msgs: Vec<String> = diagnostics::collect(&index, path, &Settings::default())
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert!(msgs[1].contains("outside the range"), "{:?}", msgs);
+}
+
+#[test]
+fn menu_attributes_are_inherited_by_contained_symbols() {
+    use kconfig_lsp::eval::{Evaluator, Tristate};
+
+    let src = "menu \"Foo\"\n\tdepends on BAR\n\tvisible if BAZ\n\nconfig FOO\n\tbool \"Foo\"\n\nendmenu\n\nconfig BAR\n\tbool\n\nconfig BAZ\n\tbool\n";
+    let mut index = WorldIndex::new();
+    index.analyze_file(Path::new("/src/Kconfig"), src);
+
+    let foo = &index.get_definitions("FOO")[0];
+    assert_eq!(foo.inherited_depends[0].to_string(), "BAR");
+    assert_eq!(foo.visible_if[0].to_string(), "BAZ");
+
+    let config = DotConfig::parse(Path::new("/src/.config"), "CONFIG_BAR=y\n", "CONFIG_");
+    let eval = Evaluator::new(&index, &config);
+    assert_eq!(eval.dependencies(foo), Tristate::Y);
+    assert_eq!(eval.prompt_visibility(foo), Tristate::N);
+}
+
+#[test]
+fn properties_of_several_definitions_combine_in_parse_order() {
+    use kconfig_lsp::eval::{Evaluator, Tristate};
+    use kconfig_lsp::include_graph::TreeOrder;
+
+    let root = Path::new("/src/Kconfig");
+    let arch = Path::new("/src/arch/Kconfig");
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.root_kconfig = Some(root.into());
+    index.analyze_file(
+        root,
+        "config HIGHMEM\n\tbool\n\nconfig NR_BANKS\n\tint \"Banks\"\n\tdepends on HIGHMEM\n\trange 1 4\n\tdefault 2\n\nsource \"arch/Kconfig\"\n",
+    );
+    index.analyze_file(arch, "config NR_BANKS\n\tint\n\trange 8 32\n\tdefault 16\n");
+    let config = DotConfig::parse(Path::new("/src/.config"), "CONFIG_NR_BANKS=16\n", "CONFIG_");
+
+    let order = TreeOrder::new(&index);
+    let sym = index.symbol("NR_BANKS", &order).expect("symbol");
+    assert_eq!(sym.definitions[0].file, root);
+    let eval = Evaluator::new(&index, &config);
+    // The root definition's dependency fails, but the arch one has none.
+    assert_eq!(eval.symbol_dependencies(&sym), Tristate::Y);
+    assert_eq!(eval.symbol_visibility(&sym), Tristate::N);
+    let (def, default) = eval.active_default(&sym).expect("default");
+    assert_eq!(
+        (def.file.as_path(), default.value.to_string()),
+        (arch, "16".into())
+    );
+
+    // The root definition's `range 1 4` is skipped along with its
+    // dependency, so 16 is within the arch range.
+    index.config = Some(config);
+    let msgs: Vec<String> = diagnostics::collect(&index, arch, &Settings::default())
+        .into_iter()
+        .chain(diagnostics::collect(&index, root, &Settings::default()))
+        .map(|d| d.message)
+        .collect();
+    assert!(msgs.is_empty(), "{:?}", msgs);
+}
+
+#[test]
+fn selecting_a_choice_or_choice_member_is_flagged() {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        path,
+        "choice KERNEL_COMPRESSION\n\tprompt \"Kernel compression\"\n\nconfig KERNEL_GZIP\n\tbool \"Gzip\"\n\nendchoice\n\nconfig BOOT\n\tbool\n\tselect KERNEL_GZIP\n\timply KERNEL_COMPRESSION\n",
+    );
+
+    let diags = diagnostics::collect(&index, path, &Settings::default());
+    let mut messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+    messages.sort_unstable();
+    assert_eq!(
+        messages,
+        [
+            "`KERNEL_COMPRESSION` is a choice and cannot be implied; pick a member with the choice's `default` instead",
+            "`KERNEL_GZIP` is a member of the choice `KERNEL_COMPRESSION`; `select` sets its value but does not make it the choice's selection, set the choice's `default` instead",
+        ]
+    );
+    for d in &diags {
+        let related = d.related_information.as_ref().expect("related");
+        assert_eq!(related[0].location.range.start.line, 0);
+    }
+}
+
+#[test]
+fn repeated_and_cyclic_sources_are_reported_with_their_chain() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.root_kconfig = Some("/src/Kconfig".into());
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "source \"lib/Kconfig\"\nsource \"net/Kconfig\"\n",
+    );
+    index.analyze_file(Path::new("/src/lib/Kconfig"), "config CRC\n\tbool\n");
+    index.analyze_file(
+        Path::new("/src/net/Kconfig"),
+        "source \"lib/Kconfig\"\nsource \"net/ipv4/Kconfig\"\n",
+    );
+    index.analyze_file(
+        Path::new("/src/net/ipv4/Kconfig"),
+        "source \"net/Kconfig\"\n",
+    );
+
+    let net = diagnostics::collect(&index, Path::new("/src/net/Kconfig"), &Settings::default());
+    assert_eq!(net.len(), 1);
+    assert_eq!(
+        net[0].message,
+        "\"lib/Kconfig\" is already sourced from Kconfig:1; its symbols get a second set of definitions"
+    );
+    let related = net[0].related_information.as_ref().expect("related");
+    assert_eq!(
+        related
+            .iter()
+            .map(|r| r.message.as_str())
+            .collect::<Vec<_>>(),
+        ["first sourced through here", "sourced again through here"]
+    );
+
+    let ipv4 = diagnostics::collect(
+        &index,
+        Path::new("/src/net/ipv4/Kconfig"),
+        &Settings::default(),
+    );
+    assert_eq!(ipv4.len(), 1);
+    assert_eq!(
+        ipv4[0].message,
+        "`source \"net/Kconfig\"` forms a cycle: net/Kconfig → net/ipv4/Kconfig → net/Kconfig"
+    );
+    assert_eq!(
+        ipv4[0].severity,
+        Some(tower_lsp::lsp_types::DiagnosticSeverity::ERROR)
+    );
+    assert_eq!(ipv4[0].related_information.as_ref().unwrap().len(), 2);
+}
+
+#[test]
+fn config_reload_counts_changed_values() {
+    let path = Path::new("/src/.config");
+    let old = DotConfig::parse(path, "CONFIG_A=y\nCONFIG_B=m\nCONFIG_C=\"x\"\n", "CONFIG_");
+    let new = DotConfig::parse(path, "CONFIG_A=y\nCONFIG_B=y\nCONFIG_D=1\n", "CONFIG_");
+    // B changed, C was removed, D was added.
+    assert_eq!(old.changed_symbols(&new), 3);
+    assert_eq!(new.changed_symbols(&new), 0);
+}
+
+#[test]
+fn defconfig_list_names_initial_configs_in_order() {
+    use kconfig_lsp::ast::{Attribute, Entry};
+    use kconfig_lsp::dotconfig;
+
+    let path = Path::new("/src/Kconfig");
+    let src = "config UML\n\tbool\n\nconfig DEFCONFIG_LIST\n\tstring\n\toption defconfig_list\n\tdefault \"/lib/modules/$(shell,uname -r)/.config\"\n\tdefault \"arch/um/defconfig\" if UML\n\tdefault \"/etc/kernel-config\"\n\tdefault \"arch/x86/defconfig\" if !UML\n";
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+
+    let fa = &index.files[path];
+    let Entry::Config(c) = &fa.file.entries[1] else {
+        panic!("expected config");
+    };
+    assert!(
+        c.attributes
+            .iter()
+            .any(|a| matches!(a, Attribute::Option(o) if o.name == "defconfig_list"))
+    );
+    assert!(fa.diagnostics.is_empty());
+    assert_eq!(
+        dotconfig::defconfig_list(&index),
+        ["/etc/kernel-config", "arch/x86/defconfig"]
+    );
+}
+
+#[test]
+fn workspace_diagnostics_cover_unopened_files_with_problems() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "source \"net/Kconfig\"\nsource \"lib/Kconfig\"\n",
+    );
+    index.analyze_file(
+        Path::new("/src/net/Kconfig"),
+        "menu \"Networking\"\nconfig NET_CORE\n\tbool \"Core\"\n",
+    );
+    index.analyze_file(Path::new("/src/lib/Kconfig"), "config CRC32\n\tbool\n");
+
+    // The pass shares one parse order and include graph across files.
+    let (order, graph) = (index.tree_order(), index.include_graph());
+    let found = diagnostics::collect_workspace(&index, &Settings::default());
+    assert!(Arc::ptr_eq(&order, &index.tree_order()));
+    assert!(Arc::ptr_eq(&graph, &index.include_graph()));
+    let files: Vec<&Path> = found.iter().map(|(path, _)| *path).collect();
+    assert_eq!(files, [Path::new("/src/net/Kconfig")]);
+    assert!(
+        found[0].1.iter().any(|d| d.message == "expected `endmenu`"),
+        "{:?}",
+        found[0].1
+    );
+
+    index.analyze_file(Path::new("/src/lib/Kconfig"), "config CRC16\n\tbool\n");
+    assert!(!Arc::ptr_eq(&order, &index.tree_order()));
+    assert!(!Arc::ptr_eq(&graph, &index.include_graph()));
+}
@@ -0,0 +1,140 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::extract;
+use std::path::Path;
+use tower_lsp::lsp_types::{CodeActionOrCommand, Url};
+
+const SRC: &str = "\
+menu \"Network drivers\"
+
+config NET_CORE
+\tbool \"Core\"
+
+# Old vendor drivers.
+config NET_VENDOR_ACME
+\tbool \"Acme devices\"
+
+if NET_VENDOR_ACME
+config ACME_ETH
+\ttristate \"Acme Ethernet\"
+endif
+
+config NET_TAIL
+\tbool
+endmenu
+";
+
+#[test]
+fn extract_moves_entries_and_sources_the_new_file() {
+    let path = Path::new("/src/drivers/net/Kconfig");
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.analyze_file(path, SRC);
+    index.analyze_file(Path::new("/src/drivers/net/Kconfig.net-vendor-acme"), "");
+
+    let start = SRC.find("NET_VENDOR_ACME\n").unwrap();
+    let end = SRC.find("Acme Ethernet").unwrap();
+    let ex = extract::extract(&index, path, start, end).expect("extraction");
+
+    assert_eq!(
+        ex.path,
+        Path::new("/src/drivers/net/Kconfig.net-vendor-acme-2")
+    );
+    assert_eq!(ex.entries, 2);
+    assert_eq!(
+        ex.text,
+        "\
+# Old vendor drivers.
+config NET_VENDOR_ACME
+\tbool \"Acme devices\"
+
+if NET_VENDOR_ACME
+config ACME_ETH
+\ttristate \"Acme Ethernet\"
+endif
+"
+    );
+    let mut out = SRC.to_string();
+    out.replace_range(ex.edit.span.start..ex.edit.span.end, &ex.edit.new_text);
+    assert_eq!(
+        out,
+        "\
+menu \"Network drivers\"
+
+config NET_CORE
+\tbool \"Core\"
+
+source \"drivers/net/Kconfig.net-vendor-acme-2\"
+
+config NET_TAIL
+\tbool
+endmenu
+"
+    );
+}
+
+#[test]
+fn choice_members_are_not_extracted() {
+    let src =
+        "choice\n\tprompt \"Mode\"\nconfig A\n\tbool \"A\"\nconfig B\n\tbool \"B\"\nendchoice\n";
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+    let start = src.find("config A").unwrap();
+    assert!(extract::extract(&index, path, start, start + 10).is_none());
+}
+
+#[test]
+fn inline_replaces_source_line_and_deletes_only_unshared_files() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    let root = "menu \"Drivers\"\nsource \"drivers/Kconfig.tiny\"\nendmenu\n";
+    index.analyze_file(Path::new("/src/Kconfig"), root);
+    index.analyze_file(
+        Path::new("/src/drivers/Kconfig.tiny"),
+        "config TINY\n\tbool \"Tiny\"\n",
+    );
+
+    let offset = root.find("source").unwrap();
+    let inlining = extract::inline(&index, Path::new("/src/Kconfig"), offset)
+        .expect("source line")
+        .expect("inlinable");
+    assert_eq!(inlining.target, Path::new("/src/drivers/Kconfig.tiny"));
+    assert!(inlining.sole_source);
+    let mut out = root.to_string();
+    out.replace_range(
+        inlining.edit.span.start..inlining.edit.span.end,
+        &inlining.edit.new_text,
+    );
+    assert_eq!(
+        out,
+        "menu \"Drivers\"\nconfig TINY\n\tbool \"Tiny\"\nendmenu\n"
+    );
+
+    index.analyze_file(
+        Path::new("/src/arch/Kconfig"),
+        "source \"drivers/Kconfig.tiny\"\n",
+    );
+    let shared = extract::inline(&index, Path::new("/src/Kconfig"), offset)
+        .expect("source line")
+        .expect("inlinable");
+    assert!(!shared.sole_source);
+    assert_eq!(shared.edit.new_text, inlining.edit.new_text);
+
+    let uri = Url::parse("file:///src/Kconfig").unwrap();
+    let titles = |index: &WorldIndex| -> Vec<String> {
+        extract::inline_code_actions(index, &uri, Path::new("/src/Kconfig"), offset)
+            .into_iter()
+            .map(|a| match a {
+                CodeActionOrCommand::CodeAction(a) => a.title,
+                CodeActionOrCommand::Command(c) => c.title,
+            })
+            .collect()
+    };
+    assert_eq!(titles(&index), ["Inline sourced file"]);
+    index.remove_file(Path::new("/src/arch/Kconfig"));
+    assert_eq!(
+        titles(&index),
+        ["Inline sourced file", "Inline sourced file and delete it"]
+    );
+    assert!(extract::inline(&index, Path::new("/src/Kconfig"), 0).is_none());
+}
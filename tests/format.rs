@@ -0,0 +1,60 @@
+use kconfig_lsp::format::{self, FormatOptions};
+
+const MESSY: &str = "config FOO  \n    bool \"Foo\"\n  depends on BAR\n\n\n\n\thelp\n\t Line one.\n\t   Indented.\n\n\t Line two.\nconfig BAR\n\tbool\n";
+
+const FORMATTED: &str = "config FOO\n\tbool \"Foo\"\n\tdepends on BAR\n\n\thelp\n\t  Line one.\n\t    Indented.\n\n\t  Line two.\nconfig BAR\n\tbool\n";
+
+#[test]
+fn format_normalizes_indentation_and_blank_lines() {
+    let options = FormatOptions::default();
+    assert_eq!(format::format(MESSY, &options), FORMATTED);
+    assert_eq!(format::format(FORMATTED, &options), FORMATTED);
+}
+
+#[test]
+fn format_with_spaces() {
+    let options = FormatOptions {
+        tab_size: 4,
+        insert_spaces: true,
+    };
+    assert_eq!(
+        format::format(
+            "menu \"Net\"\nconfig A\n\tint\n\thelp\n\t  Text.\nendmenu\n",
+            &options
+        ),
+        "menu \"Net\"\nconfig A\n    int\n    help\n      Text.\nendmenu\n"
+    );
+}
+
+#[test]
+fn format_keeps_crlf_line_breaks() {
+    let options = FormatOptions::default();
+    assert_eq!(
+        format::format("config A\r\n  bool\r\n\r\n\r\nconfig B\r\n\tbool", &options),
+        "config A\r\n\tbool\r\n\r\nconfig B\r\n\tbool\r\n"
+    );
+}
+
+#[test]
+fn edits_touch_only_changed_lines() {
+    let options = FormatOptions::default();
+    assert!(format::edits(FORMATTED, &options).is_empty());
+
+    let apply = |source: &str| {
+        let mut out = source.to_string();
+        for edit in format::edits(source, &options).iter().rev() {
+            out.replace_range(edit.span.start..edit.span.end, &edit.new_text);
+        }
+        out
+    };
+    assert_eq!(apply(MESSY), FORMATTED);
+
+    let source = "config A\n\tbool\n  default y\n\thelp\n\t  Text.\n";
+    let edits = format::edits(source, &options);
+    assert_eq!(edits.len(), 1);
+    assert_eq!(
+        &source[edits[0].span.start..edits[0].span.end],
+        "  default y\n"
+    );
+    assert_eq!(edits[0].new_text, "\tdefault y\n");
+}
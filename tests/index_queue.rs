@@ -0,0 +1,43 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::index_queue::{self, IndexQueue};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn prioritized_files_and_their_neighbours_come_first() {
+    let paths: Vec<PathBuf> = [
+        "/src/Kconfig",
+        "/src/arch/Kconfig",
+        "/src/drivers/Kconfig",
+        "/src/drivers/net/Kconfig",
+        "/src/drivers/net/Kconfig.phy",
+        "/src/lib/Kconfig",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    let mut queue = IndexQueue::new(paths);
+
+    let open = Path::new("/src/drivers/net/Kconfig");
+    queue.prioritize(open);
+    assert_eq!(queue.pop(), Some((open.to_path_buf(), true)));
+
+    let mut index = WorldIndex::new();
+    index.analyze_file(open, "source \"drivers/net/Kconfig.phy\"\n");
+    for p in index_queue::neighbours(Path::new("/src"), open, &index.files[open]) {
+        queue.prioritize(&p);
+    }
+    assert!(queue.has_urgent());
+
+    let order: Vec<(PathBuf, bool)> = std::iter::from_fn(|| queue.pop()).collect();
+    assert_eq!(
+        order,
+        [
+            ("/src/drivers/net/Kconfig.phy", true),
+            ("/src/drivers/Kconfig", true),
+            ("/src/Kconfig", true),
+            ("/src/arch/Kconfig", false),
+            ("/src/lib/Kconfig", false),
+        ]
+        .map(|(p, urgent)| (PathBuf::from(p), urgent))
+    );
+}
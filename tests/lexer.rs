@@ -0,0 +1,165 @@
+//! Token classification checked against kconfig's own lexer
+//! (`scripts/kconfig/lexer.l`).
+
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::diagnostics;
+use kconfig_lsp::lexer::TokenKind::{self, *};
+use kconfig_lsp::lexer::{self, Lexer};
+use kconfig_lsp::settings::Settings;
+use std::path::Path;
+
+fn kinds(src: &str) -> Vec<TokenKind> {
+    Lexer::new(src)
+        .tokenize()
+        .into_iter()
+        .map(|t| t.kind)
+        .filter(|k| *k != Eof)
+        .collect()
+}
+
+fn word(s: &str) -> TokenKind {
+    Ident(s.into())
+}
+
+#[test]
+fn words_take_digits_dashes_slashes_and_dots() {
+    assert_eq!(
+        kinds("range -1 0x1f\ndefault 123abc\nfoo/bar.baz"),
+        [
+            Range,
+            word("-1"),
+            word("0x1f"),
+            Newline,
+            Default,
+            word("123abc"),
+            Newline,
+            word("foo/bar.baz"),
+        ]
+    );
+    assert_eq!(kinds("---help---"), [Help]);
+}
+
+#[test]
+fn dollar_is_a_word_character_unless_it_opens_a_macro() {
+    assert_eq!(
+        kinds("depends on $ARCH && CC_$(cc-name)"),
+        [
+            Depends,
+            On,
+            word("$ARCH"),
+            And,
+            word("CC_"),
+            Macro("cc-name".into()),
+        ]
+    );
+}
+
+#[test]
+fn strings_follow_kconfig_quoting() {
+    assert_eq!(
+        kinds(r#"prompt "it's \"quoted\" \\ here""#),
+        [Prompt, StringLit(r#"it's "quoted" \ here"#.into())]
+    );
+    assert_eq!(kinds("'say \"hi\"'"), [StringLit("say \"hi\"".into())]);
+    assert_eq!(kinds("\"Café ✓\""), [StringLit("Café ✓".into())]);
+    // Unterminated: the string stops at the end of the line, which still
+    // ends the statement.
+    assert_eq!(
+        kinds("\"open\nconfig"),
+        [
+            StringLit("open".into()),
+            Error("unterminated string literal".into()),
+            Newline,
+            Config
+        ]
+    );
+}
+
+#[test]
+fn unterminated_strings_and_stray_characters_are_reported() {
+    assert_eq!(
+        kinds("depends on A @ B"),
+        [
+            Depends,
+            On,
+            word("A"),
+            Error("unexpected character `@`".into()),
+            word("B")
+        ]
+    );
+    assert_eq!(kinds("A\r\n"), [word("A"), Newline]);
+
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, "config FOO\n\tbool \"Foo\n\tdepends on BAR ; BAZ\n");
+    let diags = diagnostics::collect(&index, path, &Settings::default());
+    let found: Vec<(&str, u32, u32)> = diags
+        .iter()
+        .map(|d| {
+            (
+                d.message.as_str(),
+                d.range.start.line,
+                d.range.start.character,
+            )
+        })
+        .collect();
+    assert!(
+        found.contains(&("unterminated string literal", 1, 6)),
+        "{:?}",
+        found
+    );
+    assert!(
+        found.contains(&("unexpected character `;`", 2, 16)),
+        "{:?}",
+        found
+    );
+}
+
+#[test]
+fn numbers_are_not_references_and_dollar_words_are_not_flagged() {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        path,
+        "config FOO\n\tint \"Foo\"\n\trange -1 0x10\n\tdefault 4 if $ARCH\n",
+    );
+    let mut names: Vec<&str> = index.references.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    assert_eq!(names, ["$ARCH"]);
+    let diags = diagnostics::collect(&index, path, &Settings::default());
+    assert!(diags.is_empty(), "{:?}", diags);
+}
+
+#[test]
+fn spans_stay_on_their_lines_across_continuations() {
+    let src =
+        "config FOO\n\tbool \\\n\t\t\"Long prompt\"\n\tdepends on (A && \\\n\t\tB) || \\\n\tC\n";
+    for src in [src.to_string(), src.replace('\n', "\r\n")] {
+        let path = Path::new("/src/Kconfig");
+        let mut index = WorldIndex::new();
+        index.analyze_file(path, &src);
+        let fa = &index.files[path];
+        assert!(fa.diagnostics.is_empty(), "{:?}", fa.diagnostics);
+        let mut found: Vec<(&str, (u32, u32))> = index
+            .references
+            .values()
+            .flatten()
+            .map(|r| {
+                (
+                    &src[r.span.start..r.span.end],
+                    fa.line_index.line_col(r.span.start),
+                )
+            })
+            .collect();
+        found.sort_unstable();
+        assert_eq!(found, [("A", (3, 13)), ("B", (4, 2)), ("C", (5, 1))]);
+        assert_eq!(
+            index.get_definitions("FOO")[0].prompt.as_deref(),
+            Some("Long prompt")
+        );
+    }
+    assert_eq!(
+        lexer::logical_line_before(src, src.find("C\n").unwrap()),
+        "\tdepends on (A &&  \t\tB) ||  \t"
+    );
+}
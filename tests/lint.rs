@@ -0,0 +1,305 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::lint;
+use kconfig_lsp::lint_config::{Baseline, LintConfig};
+use kconfig_lsp::settings::{LintSettings, Settings};
+use std::path::Path;
+
+fn findings(src: &str, settings: &LintSettings) -> Vec<(String, String)> {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+    lint::check_file(&index, path, settings)
+        .into_iter()
+        .map(|f| (f.message, src[f.span.start..f.span.end].to_string()))
+        .collect()
+}
+
+#[test]
+fn whitespace_lint_is_opt_in() {
+    let src = "config FOO\n    bool \"Foo\"  \n";
+    assert!(findings(src, &LintSettings::default()).is_empty());
+}
+
+#[test]
+fn whitespace_lint_flags_spaces_trailing_and_help_indent() {
+    let settings = LintSettings {
+        whitespace: true,
+        ..Default::default()
+    };
+    let src =
+        "config FOO\n    bool \"Foo\"\n\tdefault y \n\thelp\n\t  First line.\n\t Second line.\n";
+    let found = findings(src, &settings);
+    assert_eq!(
+        found,
+        [
+            (
+                "attribute is indented with spaces instead of a tab".to_string(),
+                "    ".to_string()
+            ),
+            ("trailing whitespace".to_string(), " ".to_string()),
+            (
+                "help line is indented less than the first help line, which ends the help text"
+                    .to_string(),
+                "\t ".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn default_quoting_matches_symbol_type() {
+    let src = "config NAME\n\tstring \"Name\"\n\tdefault foo\n\tdefault OTHER\n\nconfig OTHER\n\tstring\n\nconfig COUNT\n\tint \"Count\"\n\tdefault \"10\"\n\tdefault 4\n";
+    let found = findings(src, &LintSettings::default());
+    assert_eq!(
+        found,
+        [
+            (
+                "default of string symbol `NAME` should be a quoted string".to_string(),
+                "foo".to_string()
+            ),
+            (
+                "default of int symbol `COUNT` should not be quoted".to_string(),
+                "\"10\"".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn default_values_match_symbol_type() {
+    let src = "config DEBUG\n\tbool \"Debug\"\n\tdefault \"y\"\n\tdefault 1\n\tdefault COUNT\n\tdefault MODULE\n\nconfig MODULE\n\tdef_tristate \"yes\"\n\nconfig COUNT\n\tint \"Count\"\n\tdefault y\n\tdefault MODULE\n\tdefault 4\n";
+    let found = findings(src, &LintSettings::default());
+    assert_eq!(
+        found,
+        [
+            (
+                "default of bool symbol `DEBUG` is unquoted `y`, not a string".to_string(),
+                "\"y\"".to_string()
+            ),
+            (
+                "default of bool symbol `DEBUG` should be `y`, `n` or a bool or tristate symbol, not the number `1`".to_string(),
+                "1".to_string()
+            ),
+            (
+                "`COUNT` has type int; the default of bool symbol `DEBUG` should be `y`, `n` or a bool or tristate symbol".to_string(),
+                "COUNT".to_string()
+            ),
+            (
+                "default of tristate symbol `MODULE` should be `y`, `m`, `n` or a bool or tristate symbol, not a string".to_string(),
+                "\"yes\"".to_string()
+            ),
+            (
+                "`y` is a tristate value, not a valid default of int symbol `COUNT`".to_string(),
+                "y".to_string()
+            ),
+            (
+                "`MODULE` has type tristate, not a valid default of int symbol `COUNT`".to_string(),
+                "MODULE".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn comparisons_are_checked_against_operand_types() {
+    let src = "config MODE\n\tstring \"Mode\"\n\nconfig DEBUG\n\tbool \"Debug\"\n\nconfig LEVEL\n\tint \"Level\"\n\nconfig EXTRA\n\tbool\n\tdepends on DEBUG = \"y\"\n\tdepends on MODE = fast || MODE = \"slow\"\n\tdepends on LEVEL > 2 && DEBUG < 3\n";
+    let found = findings(src, &LintSettings::default());
+    assert_eq!(
+        found,
+        [
+            (
+                "`DEBUG` is a bool symbol; compare it with unquoted `y`".to_string(),
+                "\"y\"".to_string()
+            ),
+            (
+                "`fast` is not a symbol; string `MODE` is probably meant to be compared with \"fast\""
+                    .to_string(),
+                "fast".to_string()
+            ),
+            (
+                "`DEBUG` is a bool symbol; `<`, `>`, `<=` and `>=` compare int/hex values"
+                    .to_string(),
+                "DEBUG".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn lint_config_applies_path_overrides_in_order() {
+    let config = LintConfig::parse(
+        "whitespace = true\n\n[[paths]]\nprefix = \"vendor/\"\nwhitespace = false\ndisable = [\"default-quoting\"]\n\n[[paths]]\nprefix = \"vendor/strict\"\nwhitespace = true\n",
+    )
+    .unwrap();
+
+    let drivers = config.settings_for(Path::new("drivers/net/Kconfig"));
+    assert!(drivers.whitespace);
+    assert!(drivers.disable.is_empty());
+
+    let vendor = config.settings_for(Path::new("vendor/acme/Kconfig"));
+    assert!(!vendor.whitespace);
+    assert_eq!(vendor.disable, ["default-quoting"]);
+
+    let strict = config.settings_for(Path::new("vendor/strict/Kconfig"));
+    assert!(strict.whitespace);
+    assert_eq!(strict.disable, ["default-quoting"]);
+}
+
+#[test]
+fn baseline_accepts_each_recorded_problem_once() {
+    let key = Baseline::key(
+        Path::new("drivers/Kconfig"),
+        "whitespace",
+        "trailing whitespace",
+    );
+    let mut baseline = Baseline::parse(&Baseline::render(&[key.clone(), key.clone()]));
+    assert!(baseline.take(&key));
+    assert!(baseline.take(&key));
+    assert!(!baseline.take(&key));
+}
+
+#[test]
+fn naming_conventions_apply_per_path() {
+    let config = LintConfig::parse(
+        "[[naming]]\nuppercase = true\n\n[[naming]]\npath = \"soc/\"\npattern = \"[A-Z0-9_]+\"\nprefixes = [\"SOC_\", \"ARCH_\"]\n",
+    )
+    .unwrap();
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    let soc = Path::new("/src/soc/Kconfig");
+    let drivers = Path::new("/src/drivers/Kconfig");
+    index.analyze_file(soc, "config SOC_FAST\n\tbool\n\nconfig gpio_fast\n\tbool\n");
+    index.analyze_file(drivers, "config GPIO\n\tbool\n\nconfig gpio_slow\n\tbool\n");
+    let messages = |path: &Path| -> Vec<String> {
+        let settings = config.settings_for(path.strip_prefix("/src").unwrap());
+        lint::check_file(&index, path, &settings)
+            .into_iter()
+            .map(|f| f.message)
+            .collect()
+    };
+
+    assert_eq!(
+        messages(soc),
+        [
+            "symbol `gpio_fast` has lowercase letters",
+            "symbol `gpio_fast` does not match the naming pattern `[A-Z0-9_]+`",
+            "symbol `gpio_fast` does not start with any of `SOC_`, `ARCH_`",
+        ]
+    );
+    assert_eq!(
+        messages(drivers),
+        ["symbol `gpio_slow` has lowercase letters"]
+    );
+
+    let disabled = LintSettings {
+        disable: vec!["KC0014".into()],
+        ..config.settings_for(Path::new("soc/Kconfig"))
+    };
+    assert!(lint::check_file(&index, soc, &disabled).is_empty());
+
+    let error = LintConfig::parse("[[naming]]\npattern = \"SOC_(\"\n").unwrap_err();
+    assert!(error.contains("regex parse error"), "{}", error);
+}
+
+#[test]
+fn a_bad_naming_pattern_in_settings_drops_only_its_rule() {
+    let (settings, problems) = Settings::from_json(Some(serde_json::json!({
+        "maxDiagnostics": 7,
+        "lint": { "naming": [
+            { "path": "soc/", "pattern": "SOC_(" },
+            { "path": "soc/", "prefixes": ["SOC_"] },
+            { "pattern": "[A-Z0-9_]+" },
+        ] },
+    })));
+    assert_eq!(settings.max_diagnostics, 7);
+    let naming = &settings.lint.naming;
+    assert_eq!(naming.len(), 2);
+    assert_eq!(naming[0].prefixes, ["SOC_"]);
+    assert_eq!(naming[1].pattern.as_ref().unwrap().source, "[A-Z0-9_]+");
+    assert_eq!(problems.len(), 1);
+    assert!(
+        problems[0].starts_with("lint.naming: ignoring the rule with pattern `SOC_(`"),
+        "{}",
+        problems[0]
+    );
+}
+
+#[test]
+fn menu_titles_flag_empty_repeated_and_echoed_titles() {
+    let src = "menu \"\"\nendmenu\n\nmenu \"Drivers\"\ncomment \"Drivers\"\nendmenu\n\nmenu \"drivers\"\nendmenu\n\ncomment \"Net\"\nmenu \"Net\"\nendmenu\n\nif ARM\nmenu \"Drivers\"\nendmenu\nendif\n";
+    assert_eq!(
+        findings(src, &LintSettings::default()),
+        [
+            ("menu has an empty prompt".to_string(), "\"\"".to_string()),
+            (
+                "comment \"Drivers\" repeats the title of the menu it opens".to_string(),
+                "\"Drivers\"".to_string()
+            ),
+            (
+                "menu \"drivers\" has the title of an earlier menu in the same block".to_string(),
+                "\"drivers\"".to_string()
+            ),
+            (
+                "comment \"Net\" repeats the title of the menu next to it".to_string(),
+                "\"Net\"".to_string()
+            ),
+        ]
+    );
+
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+    let fix = lint::check_file(&index, path, &LintSettings::default())
+        .into_iter()
+        .find(|f| f.message.starts_with("comment \"Net\""))
+        .and_then(|f| f.fixes.into_iter().next())
+        .expect("fix");
+    let removed = &fix.edits[0];
+    assert_eq!(
+        &src[removed.span.start..removed.span.end],
+        "comment \"Net\"\n"
+    );
+}
+
+#[test]
+fn end_comments_are_checked_and_written_by_the_annotate_action() {
+    use kconfig_lsp::code_action;
+    use kconfig_lsp::settings::Settings;
+    use tower_lsp::lsp_types::*;
+
+    let src = "menu \"General setup\"\nif NETDEVICES\nconfig DUMMY\n\ttristate \"Dummy\"\nendif # NET_CORE\nchoice\n\tprompt \"Compression\"\nconfig GZIP\n\tbool \"gzip\"\nendchoice #\nendmenu # General setup\nif A && B\nendif # CONFIG_A && CONFIG_B\nmenu \"Net\"\nendmenu\n";
+    assert_eq!(
+        findings(src, &LintSettings::default()),
+        [(
+            "comment after `endif` does not name the block it closes (`NETDEVICES`)".to_string(),
+            "NET_CORE".to_string()
+        )]
+    );
+
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+    let uri = Url::parse("file:///src/Kconfig").unwrap();
+    let at = Position::new(0, 0);
+    let action =
+        code_action::code_actions(&index, &uri, path, Range::new(at, at), &Settings::default())
+            .into_iter()
+            .find_map(|a| match a {
+                CodeActionOrCommand::CodeAction(a) if a.title == "Annotate end keywords" => Some(a),
+                _ => None,
+            })
+            .expect("annotate action");
+    assert_eq!(action.kind, Some(CodeActionKind::SOURCE));
+    let edits: Vec<(Position, String)> = action.edit.unwrap().changes.unwrap()[&uri]
+        .iter()
+        .map(|e| (e.range.start, e.new_text.clone()))
+        .collect();
+    assert_eq!(
+        edits,
+        [
+            (Position::new(4, 8), "NETDEVICES".to_string()),
+            (Position::new(9, 11), " \"Compression\"".to_string()),
+            (Position::new(14, 7), " # \"Net\"".to_string()),
+        ]
+    );
+}
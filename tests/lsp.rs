@@ -0,0 +1,807 @@
+//! End-to-end tests: the server runs on an in-memory transport and is
+//! driven with JSON-RPC messages the way an editor drives it.
+
+use kconfig_lsp::server;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio::io::{ReadHalf, WriteHalf};
+use tower_lsp::Server;
+
+/// How long to wait for the server's next message before failing the test,
+/// rather than hanging it.
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The editor side of a connection to a running server.
+struct TestClient {
+    reader: BufReader<ReadHalf<DuplexStream>>,
+    writer: WriteHalf<DuplexStream>,
+    next_id: i64,
+    /// Notifications and requests from the server received and not yet
+    /// taken, oldest first.
+    notifications: Vec<Value>,
+    /// Whether requests from the server are left unanswered, holding up
+    /// what waits on them, until [`TestClient::answer_held`].
+    hold_requests: bool,
+    /// The ids of the requests from the server left unanswered.
+    held: Vec<Value>,
+}
+
+impl TestClient {
+    /// Start a server and send `initialize` with `capabilities` and
+    /// `options`, then `initialized`.
+    async fn start(capabilities: Value, options: Value) -> (Self, Value) {
+        Self::start_with(json!({
+            "capabilities": capabilities,
+            "initializationOptions": options,
+        }))
+        .await
+    }
+
+    /// Start a server and send `initialize` with `params`, then
+    /// `initialized`.
+    async fn start_with(params: Value) -> (Self, Value) {
+        let (client, server) = tokio::io::duplex(1 << 20);
+        let (server_read, server_write) = tokio::io::split(server);
+        let (service, socket) = server::service();
+        tokio::spawn(Server::new(server_read, server_write, socket).serve(service));
+        let (reader, writer) = tokio::io::split(client);
+        let mut client = Self {
+            reader: BufReader::new(reader),
+            writer,
+            next_id: 0,
+            notifications: Vec::new(),
+            hold_requests: false,
+            held: Vec::new(),
+        };
+        let result = client
+            .request("initialize", params)
+            .await
+            .expect("initialize");
+        client.notify("initialized", json!({})).await;
+        (client, result)
+    }
+
+    async fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        self.writer.write_all(frame.as_bytes()).await.unwrap();
+    }
+
+    async fn receive(&mut self) -> Value {
+        tokio::time::timeout(RECEIVE_TIMEOUT, self.read_message())
+            .await
+            .unwrap_or_else(|_| panic!("no message from the server within {RECEIVE_TIMEOUT:?}"))
+    }
+
+    async fn read_message(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.reader.read_line(&mut header).await.unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(n) = header.strip_prefix("Content-Length: ") {
+                length = n.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.reader.read_exact(&mut body).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Send a request and wait for its response: the result, or the error.
+    /// `null` params are left out, as for requests that take none.
+    /// Requests from the server meanwhile get a `null` result; they and
+    /// notifications are kept for [`TestClient::notification`].
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value, Value> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let mut message = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        if !params.is_null() {
+            message["params"] = params;
+        }
+        self.send(message).await;
+        loop {
+            let message = self.receive().await;
+            if message.get("method").is_none() && message["id"] == id {
+                return match message.get("error") {
+                    Some(error) => Err(error.clone()),
+                    None => Ok(message["result"].clone()),
+                };
+            }
+            self.handle(message).await;
+        }
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await;
+    }
+
+    async fn handle(&mut self, message: Value) {
+        match message.get("id") {
+            Some(id) if message.get("method").is_some() => {
+                let id = id.clone();
+                if self.hold_requests {
+                    self.held.push(id);
+                } else {
+                    self.send(json!({ "jsonrpc": "2.0", "id": id, "result": null }))
+                        .await;
+                }
+                self.notifications.push(message);
+            }
+            _ => self.notifications.push(message),
+        }
+    }
+
+    /// Answer the requests held so far with `null`, and those to come.
+    async fn answer_held(&mut self) {
+        self.hold_requests = false;
+        for id in std::mem::take(&mut self.held) {
+            self.send(json!({ "jsonrpc": "2.0", "id": id, "result": null }))
+                .await;
+        }
+    }
+
+    /// The params of the next `method` notification or request from the
+    /// server matching `filter`, waiting for it if needed.
+    async fn notification(&mut self, method: &str, filter: impl Fn(&Value) -> bool) -> Value {
+        let matches = |n: &Value| n["method"] == method && filter(&n["params"]);
+        if let Some(i) = self.notifications.iter().position(matches) {
+            return self.notifications.remove(i)["params"].clone();
+        }
+        loop {
+            let message = self.receive().await;
+            let found = matches(&message);
+            self.handle(message).await;
+            if found {
+                return self.notifications.pop().unwrap()["params"].clone();
+            }
+        }
+    }
+
+    async fn open(&mut self, uri: &str, text: &str) {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": "kconfig", "version": 1, "text": text }
+            }),
+        )
+        .await;
+    }
+
+    /// The diagnostics published next for `uri`.
+    async fn diagnostics(&mut self, uri: &str) -> Vec<Value> {
+        let params = self
+            .notification("textDocument/publishDiagnostics", |p| p["uri"] == uri)
+            .await;
+        params["diagnostics"].as_array().unwrap().clone()
+    }
+}
+
+fn position(uri: &str, line: u32, character: u32) -> Value {
+    json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": line, "character": character },
+    })
+}
+
+const URI: &str = "file:///lsp-test/Kconfig";
+const DRIVERS: &str = "file:///lsp-test/drivers/Kconfig";
+const KCONFIG: &str = "config NET\n\tbool \"Networking support\"\n\thelp\n\t  Enables networking.\n\nconfig INET\n\tbool \"TCP/IP\"\n\tdepends on NET && MISSING\n";
+
+#[tokio::test]
+async fn open_documents_get_diagnostics_hover_and_definitions() {
+    let (mut client, init) = TestClient::start(json!({}), json!({})).await;
+    assert_eq!(init["capabilities"]["hoverProvider"], true);
+
+    client.open(URI, KCONFIG).await;
+    let diags = client.diagnostics(URI).await;
+    assert_eq!(diags.len(), 1, "{:?}", diags);
+    assert_eq!(
+        diags[0]["message"],
+        "symbol `MISSING` is not defined in any open file"
+    );
+    assert_eq!(
+        diags[0]["range"]["start"],
+        json!({ "line": 7, "character": 19 })
+    );
+
+    let hover = client
+        .request("textDocument/hover", position(URI, 7, 13))
+        .await
+        .unwrap();
+    let text = hover["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("Enables networking."), "{}", text);
+
+    client
+        .open(DRIVERS, "config E1000\n\tbool\n\tdepends on NET\n")
+        .await;
+    client.diagnostics(DRIVERS).await;
+    let definition = client
+        .request("textDocument/definition", position(DRIVERS, 2, 13))
+        .await
+        .unwrap();
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 0, "character": 7 })
+    );
+}
+
+#[tokio::test]
+async fn completion_is_offered_only_to_clients_that_support_it() {
+    let (_, init) = TestClient::start(json!({}), json!({})).await;
+    assert!(init["capabilities"]["completionProvider"].is_null());
+
+    let capabilities = json!({ "textDocument": { "completion": {} } });
+    let (mut client, init) = TestClient::start(capabilities, json!({})).await;
+    assert!(init["capabilities"]["completionProvider"].is_object());
+    client.open(URI, KCONFIG).await;
+    client.diagnostics(URI).await;
+    let items = client
+        .request("textDocument/completion", position(URI, 7, 13))
+        .await
+        .unwrap();
+    let items = items.as_array().expect("completion items");
+    assert!(items.iter().any(|i| i["label"] == "NET"), "{:?}", items);
+}
+
+#[tokio::test]
+async fn unknown_custom_requests_are_errors_and_custom_ones_answer() {
+    let (mut client, _) = TestClient::start(json!({}), json!({})).await;
+    client.open(URI, KCONFIG).await;
+    client.diagnostics(URI).await;
+
+    let error = client
+        .request("kconfig/noSuchRequest", json!({}))
+        .await
+        .unwrap_err();
+    assert_eq!(error["code"], -32601);
+
+    let stats = client
+        .request("kconfig/indexStats", json!(null))
+        .await
+        .unwrap();
+    assert_eq!(stats["files"], 1);
+    assert_eq!(stats["indexing"], false);
+}
+
+#[tokio::test]
+async fn index_stats_report_request_latencies_by_method() {
+    let (mut client, _) = TestClient::start(json!({}), json!({})).await;
+    client.open(URI, KCONFIG).await;
+    client.diagnostics(URI).await;
+    for _ in 0..3 {
+        client
+            .request("textDocument/hover", position(URI, 7, 13))
+            .await
+            .unwrap();
+    }
+
+    let stats = client
+        .request("kconfig/indexStats", json!(null))
+        .await
+        .unwrap();
+    let requests = stats["requests"].as_array().unwrap();
+    let methods: Vec<(&str, u64)> = requests
+        .iter()
+        .map(|r| (r["method"].as_str().unwrap(), r["count"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(
+        methods,
+        [("textDocument/didOpen", 1), ("textDocument/hover", 3)]
+    );
+    let hover = &requests[1];
+    for key in ["meanMs", "p50Ms", "p90Ms", "p99Ms"] {
+        assert!(hover[key].as_f64().unwrap() <= hover["maxMs"].as_f64().unwrap());
+    }
+}
+
+#[tokio::test]
+async fn typed_trigger_characters_complete_only_outside_text() {
+    let capabilities = json!({ "textDocument": { "completion": {} } });
+    let options = json!({ "completion": { "triggerCharacters": ["_"] } });
+    let (mut client, init) = TestClient::start(capabilities, options).await;
+    assert_eq!(
+        init["capabilities"]["completionProvider"]["triggerCharacters"],
+        json!(["_"])
+    );
+    client.open(URI, KCONFIG).await;
+    client.diagnostics(URI).await;
+
+    let typed = |line, character, trigger: &str| {
+        let mut params = position(URI, line, character);
+        params["context"] = json!({ "triggerKind": 2, "triggerCharacter": trigger });
+        params
+    };
+    let result = client
+        .request("textDocument/completion", typed(7, 13, "_"))
+        .await
+        .unwrap();
+    assert!(result.is_array());
+    // In help text, and for a character the settings do not list.
+    for params in [typed(3, 10, "_"), typed(7, 13, " ")] {
+        let result = client
+            .request("textDocument/completion", params)
+            .await
+            .unwrap();
+        assert!(result.is_null(), "{}", result);
+    }
+}
+
+#[tokio::test]
+async fn a_file_opened_under_another_spelling_is_the_indexed_file() {
+    let (mut client, _) = TestClient::start(json!({}), json!({})).await;
+    client.open(URI, KCONFIG).await;
+    client.diagnostics(URI).await;
+
+    let spelled = "file:///lsp-test/drivers/%4Bconfig";
+    client
+        .open(spelled, "config E1000\n\tbool\n\tdepends on NET\n")
+        .await;
+    assert!(client.diagnostics(spelled).await.is_empty());
+    let definition = client
+        .request("textDocument/definition", position(spelled, 2, 13))
+        .await
+        .unwrap();
+    assert_eq!(definition["uri"], URI);
+
+    // The normalized spelling finds the same document.
+    let mut params = position(DRIVERS, 2, 13);
+    params["context"] = json!({ "includeDeclaration": false });
+    let references = client
+        .request("textDocument/references", params)
+        .await
+        .unwrap();
+    let uris: Vec<&Value> = references
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| &l["uri"])
+        .collect();
+    assert_eq!(uris, [URI, DRIVERS]);
+    let formatted = client
+        .request(
+            "textDocument/formatting",
+            json!({
+                "textDocument": { "uri": DRIVERS },
+                "options": { "tabSize": 8, "insertSpaces": false },
+            }),
+        )
+        .await
+        .unwrap();
+    assert!(formatted.is_array());
+}
+
+#[tokio::test]
+async fn debug_commands_dump_the_tokens_and_tree_of_a_document() {
+    let (mut client, init) = TestClient::start(json!({}), json!({})).await;
+    let commands = &init["capabilities"]["executeCommandProvider"]["commands"];
+    assert!(
+        commands
+            .as_array()
+            .unwrap()
+            .contains(&json!("kconfig.debug.dumpAst")),
+        "{}",
+        commands
+    );
+    client.open(URI, KCONFIG).await;
+    client.diagnostics(URI).await;
+
+    let dump = |command: &str| json!({ "command": command, "arguments": [URI] });
+    let tokens = client
+        .request("workspace/executeCommand", dump("kconfig.debug.dumpTokens"))
+        .await
+        .unwrap();
+    let tokens = tokens.as_str().unwrap();
+    assert!(
+        tokens.starts_with("0..6\t1:1\tConfig\n7..10\t1:8\tIdent(\"NET\")\n"),
+        "{}",
+        tokens
+    );
+
+    let ast = client
+        .request("workspace/executeCommand", dump("kconfig.debug.dumpAst"))
+        .await
+        .unwrap();
+    let ast = ast.as_str().unwrap();
+    assert!(ast.contains("name: \"INET\""), "{}", ast);
+    assert!(
+        ast.ends_with("Diagnostics:\n  (none)\n\nAssignments:\n  (none)\n"),
+        "{}",
+        ast
+    );
+
+    let error = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "kconfig.debug.dumpAst", "arguments": ["file:///nowhere/Kconfig"] }),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(error["code"], -32602);
+}
+
+#[tokio::test]
+async fn lazy_indexing_reads_unsourced_files_when_a_lookup_misses() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-lazy-{}", std::process::id()));
+    for (path, text) in [
+        ("Kconfig", "source \"drivers/Kconfig\"\n"),
+        ("drivers/Kconfig", "config DRV\n\tbool\n"),
+        ("staging/Kconfig", "config STRAY\n\tbool\n"),
+        ("unused/Kconfig", "config UNUSED\n\tbool\n"),
+    ] {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": {},
+        "initializationOptions": { "indexing": "lazy" },
+    }))
+    .await;
+    let uri = format!("{}/app.kconfig", root_uri);
+    client
+        .open(&uri, "config APP\n\tbool\n\tdepends on DRV && STRAY\n")
+        .await;
+
+    // Published on open, and again once the startup scan is done.
+    let mut diags = client.diagnostics(&uri).await;
+    while !diags.is_empty() {
+        diags = client.diagnostics(&uri).await;
+    }
+    let stats = client
+        .request("kconfig/indexStats", json!(null))
+        .await
+        .unwrap();
+    // The root, what it sources, the open document, and the file defining
+    // `STRAY`; not `unused/Kconfig`.
+    assert_eq!(stats["files"], 4, "{}", stats);
+
+    // A reindex builds aside; the old index serves meanwhile.
+    client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "kconfig.reindex" }),
+        )
+        .await
+        .unwrap();
+    let stats = client
+        .request("kconfig/indexStats", json!(null))
+        .await
+        .unwrap();
+    assert!(stats["files"].as_u64().unwrap() >= 3, "{}", stats);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn an_edit_adding_a_source_line_indexes_the_sourced_file() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-edit-src-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("sdk")).unwrap();
+    std::fs::write(root.join("sdk/Config.mk"), "config SDK\n\tbool\n").unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": { "experimental": { "kconfigFileAssociations": true } },
+    }))
+    .await;
+    let uri = format!("{}/Kconfig", root_uri);
+    client.open(&uri, "config APP\n\tbool\n").await;
+    client.diagnostics(&uri).await;
+
+    client
+        .notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": "source \"sdk/Config.mk\"\nconfig APP\n\tbool\n" }],
+            }),
+        )
+        .await;
+    let params = client
+        .notification("kconfig/fileAssociations", |_| true)
+        .await;
+    assert_eq!(
+        params["uris"],
+        json!([format!("{}/sdk/Config.mk", root_uri)])
+    );
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn clients_that_ask_are_told_which_sourced_files_are_kconfig() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-assoc-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("sdk")).unwrap();
+    std::fs::write(root.join("Kconfig"), "source \"sdk/Config.mk\"\n").unwrap();
+    std::fs::write(root.join("sdk/Config.mk"), "config SDK\n\tbool\n").unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": { "experimental": { "kconfigFileAssociations": true } },
+    }))
+    .await;
+
+    let params = client
+        .notification("kconfig/fileAssociations", |_| true)
+        .await;
+    assert_eq!(
+        params["uris"],
+        json!([format!("{}/sdk/Config.mk", root_uri)])
+    );
+    let uri = format!("{}/app.kconfig", root_uri);
+    client.open(&uri, "config APP\n\tdepends on SDK\n").await;
+    let diags = client.diagnostics(&uri).await;
+    assert!(
+        diags
+            .iter()
+            .all(|d| !d["message"].as_str().unwrap().contains("SDK")),
+        "{:?}",
+        diags
+    );
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn a_config_written_after_initialize_is_watched_and_loaded() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("Kconfig"), "config NET\n\tbool \"Networking\"\n").unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": {
+            "workspace": { "didChangeWatchedFiles": { "dynamicRegistration": true } },
+        },
+    }))
+    .await;
+
+    let params = client
+        .notification("client/registerCapability", |p| {
+            p["registrations"][0]["id"] == "kconfig-config-watch"
+        })
+        .await;
+    let config = root.join(".config");
+    assert_eq!(
+        params["registrations"][0]["registerOptions"]["watchers"],
+        json!([{ "globPattern": config.display().to_string() }])
+    );
+
+    std::fs::write(&config, "CONFIG_NET=y\n").unwrap();
+    client
+        .notify(
+            "workspace/didChangeWatchedFiles",
+            json!({ "changes": [{ "uri": format!("{}/.config", root_uri), "type": 1 }] }),
+        )
+        .await;
+    let reloaded = client
+        .notification("kconfig/configReloaded", |_| true)
+        .await;
+    assert_eq!(reloaded["changed"], 1, "{}", reloaded);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn reindex_answers_at_once_and_reports_through_progress() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-reindex-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("Kconfig"), "config NET\n\tbool\n").unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": { "window": { "workDoneProgress": true } },
+    }))
+    .await;
+
+    let result = client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "kconfig.reindex", "arguments": [] }),
+        )
+        .await;
+    assert_eq!(result, Ok(Value::Null));
+    let end = client
+        .notification("$/progress", |p| {
+            p["token"] == "kconfig-reindex" && p["value"]["kind"] == "end"
+        })
+        .await;
+    assert_eq!(end["value"]["message"], "Indexed 1 files");
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn reindex_indexes_open_documents_once() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-reopen-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let kconfig = "config NET\n\tbool\n\nconfig INET\n\tbool\n\tdepends on NET\n";
+    std::fs::write(root.join("Kconfig"), kconfig).unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": { "window": { "workDoneProgress": true } },
+    }))
+    .await;
+    let uri = format!("{}/Kconfig", root_uri);
+    client.open(&uri, kconfig).await;
+    client.diagnostics(&uri).await;
+
+    client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "kconfig.reindex" }),
+        )
+        .await
+        .unwrap();
+    client
+        .notification("$/progress", |p| p["value"]["kind"] == "end")
+        .await;
+
+    let stats = client
+        .request("kconfig/indexStats", json!(null))
+        .await
+        .unwrap();
+    assert_eq!(stats["files"], 1, "{}", stats);
+    assert_eq!(stats["definitions"], 2, "{}", stats);
+    assert_eq!(stats["references"], 1, "{}", stats);
+    // One target, answered as a single location.
+    let definition = client
+        .request("textDocument/definition", position(&uri, 5, 13))
+        .await
+        .unwrap();
+    assert_eq!(definition["uri"], uri.as_str(), "{}", definition);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn index_stats_report_a_running_reindex() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-midscan-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("Kconfig"), "config NET\n\tbool\n").unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": { "window": { "workDoneProgress": true } },
+    }))
+    .await;
+
+    // The reindex waits for its progress token to be created.
+    client.hold_requests = true;
+    client
+        .request(
+            "workspace/executeCommand",
+            json!({ "command": "kconfig.reindex" }),
+        )
+        .await
+        .unwrap();
+    client
+        .notification("window/workDoneProgress/create", |_| true)
+        .await;
+    let stats = client
+        .request("kconfig/indexStats", json!(null))
+        .await
+        .unwrap();
+    assert_eq!(stats["indexing"], true);
+
+    client.answer_held().await;
+    client
+        .notification("$/progress", |p| p["value"]["kind"] == "end")
+        .await;
+    let stats = client
+        .request("kconfig/indexStats", json!(null))
+        .await
+        .unwrap();
+    assert_eq!(stats["indexing"], false);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn the_stale_results_notice_is_shown_once_per_scan() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-stale-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("Kconfig"), "config NET\n\tbool \"Net\"\n").unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": { "window": { "workDoneProgress": true } },
+    }))
+    .await;
+    let uri = format!("{}/Kconfig", root_uri);
+    client.open(&uri, "config NET\n\tbool \"Net\"\n").await;
+    client.diagnostics(&uri).await;
+    let stale = |p: &Value| {
+        p["message"]
+            .as_str()
+            .is_some_and(|m| m.contains("still being indexed"))
+    };
+
+    for _ in 0..2 {
+        client.hold_requests = true;
+        client
+            .request(
+                "workspace/executeCommand",
+                json!({ "command": "kconfig.reindex" }),
+            )
+            .await
+            .unwrap();
+        client
+            .notification("window/workDoneProgress/create", |_| true)
+            .await;
+        for _ in 0..3 {
+            client
+                .request("textDocument/hover", position(&uri, 0, 8))
+                .await
+                .unwrap();
+        }
+        client.notification("window/showMessage", stale).await;
+        assert!(
+            !client
+                .notifications
+                .iter()
+                .any(|n| n["method"] == "window/showMessage" && stale(&n["params"])),
+            "{:?}",
+            client.notifications
+        );
+        client.answer_held().await;
+        client
+            .notification("$/progress", |p| p["value"]["kind"] == "end")
+            .await;
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn configuration_changes_keep_the_trust_answer_and_reindex_for_new_file_names() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-settings-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let kconfig = "config CC_VERSION\n\tstring\n\tdefault \"$(shell,echo 13)\"\n";
+    std::fs::write(root.join("Kconfig"), kconfig).unwrap();
+    std::fs::write(root.join("extra.kconf"), "config EXTRA\n\tbool\n").unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let (mut client, _) = TestClient::start_with(json!({
+        "rootUri": root_uri,
+        "capabilities": { "window": { "workDoneProgress": true } },
+        "initializationOptions": { "preprocessor": { "shell": "prompt" } },
+    }))
+    .await;
+    // Dismissed, which is not remembered past the session.
+    client
+        .notification("window/showMessageRequest", |_| true)
+        .await;
+    let uri = format!("{}/Kconfig", root_uri);
+    client.open(&uri, kconfig).await;
+    client.diagnostics(&uri).await;
+
+    client
+        .notify(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": {
+                "preprocessor": { "shell": "prompt" },
+                "fileNames": ["Kconfig", "*.kconf"],
+            } }),
+        )
+        .await;
+    client
+        .notification("$/progress", |p| {
+            p["token"] == "kconfig-reindex" && p["value"]["kind"] == "end"
+        })
+        .await;
+    let stats = client
+        .request("kconfig/indexStats", json!(null))
+        .await
+        .unwrap();
+    assert_eq!(stats["files"], 2, "{}", stats);
+
+    client.diagnostics(&uri).await;
+    let asked_again = tokio::time::timeout(
+        Duration::from_millis(500),
+        client.notification("window/showMessageRequest", |_| true),
+    )
+    .await;
+    assert!(asked_again.is_err(), "{:?}", asked_again);
+    std::fs::remove_dir_all(&root).unwrap();
+}
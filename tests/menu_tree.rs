@@ -0,0 +1,161 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::dotconfig::DotConfig;
+use kconfig_lsp::menu_tree::{self, MenuNode, MenuNodeKind};
+use std::path::{Path, PathBuf};
+
+const ROOT: &str = r#"mainmenu "Test Configuration"
+
+menu "Networking"
+	depends on !UML
+
+menuconfig NET
+	bool "Networking support"
+
+if NET
+source "net/Kconfig"
+endif
+
+endmenu
+"#;
+
+const NET: &str = r#"config INET
+	bool "TCP/IP networking"
+	depends on NET
+"#;
+
+fn index() -> WorldIndex {
+    let mut index = WorldIndex::new();
+    index.root = Some(PathBuf::from("/src"));
+    index.analyze_file(Path::new("/src/Kconfig"), ROOT);
+    index.analyze_file(Path::new("/src/net/Kconfig"), NET);
+    index
+}
+
+fn shape(node: &MenuNode) -> String {
+    if node.children.is_empty() {
+        node.label.clone()
+    } else {
+        let children: Vec<String> = node.children.iter().map(shape).collect();
+        format!("{} [{}]", node.label, children.join(", "))
+    }
+}
+
+#[test]
+fn menu_tree_follows_sources_and_nests_menuconfig_children() {
+    let index = index();
+    let tree = menu_tree::menu_tree(&index, Path::new("/src/Kconfig")).unwrap();
+    assert_eq!(tree.kind, MenuNodeKind::Root);
+    assert_eq!(
+        shape(&tree),
+        "Test Configuration [Networking [Networking support [TCP/IP networking]]]"
+    );
+
+    let inet = &tree.children[0].children[0].children[0];
+    assert_eq!(inet.symbol.as_deref(), Some("INET"));
+    assert_eq!(inet.conditions, ["!UML", "NET", "NET"]);
+    assert_eq!(inet.location.uri.path(), "/src/net/Kconfig");
+    assert_eq!(inet.visible, None);
+}
+
+#[test]
+fn menu_tree_evaluates_visibility_with_config() {
+    let mut index = index();
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "# CONFIG_NET is not set\n",
+        "CONFIG_",
+    ));
+    let tree = menu_tree::menu_tree(&index, Path::new("/src/Kconfig")).unwrap();
+    let net = &tree.children[0].children[0];
+    assert_eq!(net.visible, Some(true));
+    assert_eq!(net.children[0].visible, Some(false));
+}
+
+#[test]
+fn sourced_files_inherit_enclosing_conditions() {
+    use kconfig_lsp::include_graph;
+
+    let index = index();
+    let cond = include_graph::inherited_condition(&index, Path::new("/src/net/Kconfig"));
+    assert_eq!(cond.map(|e| e.to_string()).as_deref(), Some("!UML && NET"));
+    assert!(include_graph::inherited_condition(&index, Path::new("/src/Kconfig")).is_none());
+}
+
+#[test]
+fn files_sourced_under_dead_conditions_do_not_report_undefined_symbols() {
+    use kconfig_lsp::settings::Settings;
+    use kconfig_lsp::{diagnostics, report};
+    use tower_lsp::lsp_types::Url;
+
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "if SOC_VENDOR\nsource \"soc/Kconfig\"\nendif\n",
+    );
+    index.analyze_file(
+        Path::new("/src/soc/Kconfig"),
+        "config SOC_PART\n\tbool\n\tdepends on VENDOR_BUS\n\nif VENDOR_BUS\nconfig SOC_EXTRA\n\tbool\nendif\n",
+    );
+
+    let messages = |path: &str| -> Vec<String> {
+        diagnostics::collect(&index, Path::new(path), &Settings::default())
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    };
+    assert_eq!(
+        messages("/src/Kconfig"),
+        ["symbol `SOC_VENDOR` is not defined in any open file"]
+    );
+    assert!(messages("/src/soc/Kconfig").is_empty());
+
+    let uri = Url::parse("kconfig-report://dead-branches").unwrap();
+    let text = report::report(&index, Some(Path::new("/src/Kconfig")), &uri)
+        .expect("report")
+        .text;
+    assert!(text.contains("`if SOC_VENDOR`"), "{}", text);
+    assert!(!text.contains("VENDOR_BUS"), "{}", text);
+}
+
+#[test]
+fn visible_if_hides_prompts_but_not_values() {
+    use kconfig_lsp::hover;
+    use kconfig_lsp::settings::HoverSettings;
+    use tower_lsp::lsp_types::{HoverContents, MarkupKind, Position};
+
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(
+        path,
+        "config EXPERT\n\tbool \"Expert\"\n\nmenu \"Tuning\"\n\tvisible if EXPERT\n\nconfig TUNE\n\tbool \"Tune\"\n\tdefault y\n\nendmenu\n",
+    );
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_TUNE=y\n",
+        "CONFIG_",
+    ));
+    let tree = menu_tree::menu_tree(&index, path).unwrap();
+    let menu = &tree.children[1];
+    assert_eq!(menu.visible_if, ["EXPERT"]);
+    assert_eq!((menu.visible, menu.selectable), (Some(false), Some(true)));
+    let tune = &menu.children[0];
+    assert_eq!(tune.visible_if, ["EXPERT"]);
+    assert_eq!(tune.conditions, ["EXPERT"]);
+    assert_eq!((tune.visible, tune.selectable), (Some(false), Some(true)));
+
+    let hover = hover::hover(
+        &index,
+        path,
+        Position::new(6, 8),
+        MarkupKind::Markdown,
+        &HoverSettings::default(),
+    )
+    .expect("hover");
+    let HoverContents::Markup(text) = hover.contents else {
+        panic!("expected markup");
+    };
+    assert!(text.value.contains(
+        "Prompt hidden because `visible if EXPERT` is n; the value may still be selected"
+    ));
+}
@@ -0,0 +1,108 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::merge_config::{self, MergeConfigParams, OverrideKind};
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::Position;
+
+const BASE: &str = "CONFIG_NET=y\nCONFIG_E1000=m\n# CONFIG_DEBUG is not set\nCONFIG_HZ=250\n";
+const DEBUG: &str = "CONFIG_DEBUG=y\nCONFIG_HZ=250\n";
+const BUILTIN: &str = "CONFIG_E1000=y\n# CONFIG_NET is not set\n";
+
+fn inputs() -> Vec<(PathBuf, String)> {
+    [
+        ("/src/.config", BASE),
+        ("/src/debug.config", DEBUG),
+        ("/src/builtin.config", BUILTIN),
+    ]
+    .into_iter()
+    .map(|(p, t)| (PathBuf::from(p), t.to_string()))
+    .collect()
+}
+
+fn params() -> MergeConfigParams {
+    serde_json::from_value(serde_json::json!({ "fragments": [] })).unwrap()
+}
+
+#[test]
+fn fragments_override_earlier_assignments_in_order() {
+    let mut params = params();
+    params.warn_redundant = true;
+    let result = merge_config::merge(&inputs(), "CONFIG_", &params);
+
+    assert_eq!(
+        result.text,
+        "\nCONFIG_DEBUG=y\nCONFIG_HZ=250\n\nCONFIG_E1000=y\n# CONFIG_NET is not set\n"
+    );
+    let kinds: Vec<(&str, OverrideKind)> = result
+        .overrides
+        .iter()
+        .map(|o| (o.symbol.as_str(), o.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            ("DEBUG", OverrideKind::Redefined),
+            ("HZ", OverrideKind::Redundant),
+            ("E1000", OverrideKind::Redefined),
+            ("NET", OverrideKind::Redefined),
+        ]
+    );
+    let debug = &result.overrides[0];
+    assert_eq!(debug.winner.text, "CONFIG_DEBUG=y");
+    assert!(
+        debug
+            .winner
+            .location
+            .as_ref()
+            .unwrap()
+            .uri
+            .path()
+            .ends_with("debug.config")
+    );
+    assert_eq!(debug.loser.text, "# CONFIG_DEBUG is not set");
+    assert_eq!(
+        debug.loser.location.as_ref().unwrap().range.start,
+        Position::new(2, 0)
+    );
+    assert_eq!(
+        result.warnings[0],
+        "Value of CONFIG_DEBUG is redefined by fragment /src/debug.config:\n\
+         Previous  value: # CONFIG_DEBUG is not set\n\
+         New value:       CONFIG_DEBUG=y"
+    );
+    assert_eq!(
+        result.warnings[1],
+        "Value of CONFIG_HZ is redundant by fragment /src/debug.config:"
+    );
+}
+
+#[test]
+fn builtin_keeps_y_and_the_tree_check_reports_dropped_values() {
+    let mut params = params();
+    params.builtin = true;
+    let inputs = vec![
+        (PathBuf::from("/src/.config"), BASE.to_string()),
+        (
+            PathBuf::from("/src/mod.config"),
+            "CONFIG_NET=m\n".to_string(),
+        ),
+    ];
+    let mut result = merge_config::merge(&inputs, "CONFIG_", &params);
+    assert!(result.text.starts_with("CONFIG_NET=y\n"));
+    assert!(!result.text.contains("CONFIG_NET=m"));
+    assert_eq!(result.overrides[0].kind, OverrideKind::NotDemoted);
+    assert_eq!(result.overrides[0].winner.text, "CONFIG_NET=y");
+    assert!(result.warnings[0].ends_with("-y passed, will not demote y to m"));
+
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "config NET\n\tbool \"Networking\"\n\nconfig E1000\n\ttristate \"E1000\"\n\tdepends on PCI\n\nconfig PCI\n\tbool \"PCI\"\n\nconfig DEBUG\n\tbool \"Debug\"\n\nconfig HZ\n\tint \"HZ\"\n",
+    );
+    merge_config::check(&index, &mut result, "CONFIG_");
+    assert_eq!(
+        result.warnings[1..],
+        ["Value requested for CONFIG_E1000 not in final .config\n\
+          Requested value:  CONFIG_E1000=m\n\
+          Actual value:     # CONFIG_E1000 is not set"]
+    );
+}
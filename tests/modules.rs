@@ -0,0 +1,362 @@
+//! Trees composed from several roots, Zephyr style: an application sourcing
+//! the Zephyr tree through `$(srctree)`-relative paths, and modules pulled
+//! in through a generated `Kconfig.modules` in the build directory; and the
+//! project profiles, which set such things up on their own.
+
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::ast::Entry;
+use kconfig_lsp::diagnostics;
+use kconfig_lsp::menu_tree::{self, MenuNode};
+use kconfig_lsp::profile::{self, Profile};
+use kconfig_lsp::server;
+use kconfig_lsp::settings::Settings;
+use kconfig_lsp::sourced_files;
+use std::path::{Path, PathBuf};
+
+fn symbols(node: &MenuNode, out: &mut Vec<String>) {
+    out.extend(node.symbol.clone());
+    for child in &node.children {
+        symbols(child, out);
+    }
+}
+
+#[test]
+fn optional_and_relative_source_statements_parse() {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        path,
+        "source \"a\"\nosource \"b\"\nrsource \"c\"\norsource \"d\"\n",
+    );
+    let fa = &index.files[path];
+    assert!(fa.diagnostics.is_empty(), "{:?}", fa.diagnostics);
+    let flags: Vec<(&str, bool, bool)> = fa
+        .file
+        .entries
+        .iter()
+        .map(|e| match e {
+            Entry::Source(s) => (s.path.as_str(), s.optional, s.relative),
+            _ => panic!("not a source entry"),
+        })
+        .collect();
+    assert_eq!(
+        flags,
+        [
+            ("a", false, false),
+            ("b", true, false),
+            ("c", false, true),
+            ("d", true, true)
+        ]
+    );
+}
+
+#[test]
+fn modules_resolve_through_the_binary_dir_and_srctree() {
+    let settings: Settings = serde_json::from_value(serde_json::json!({
+        "moduleRoots": ["../zephyr", "/modules/hal"],
+        "binaryDir": "build/Kconfig",
+        "preprocessor": { "variables": { "srctree": "../zephyr", "ZEPHYR_BASE": "../zephyr" } },
+    }))
+    .unwrap();
+    let app = Path::new("/app");
+    assert_eq!(
+        settings.scan_roots(app),
+        [
+            PathBuf::from("/app"),
+            PathBuf::from("/zephyr"),
+            PathBuf::from("/modules/hal"),
+            PathBuf::from("/app/build/Kconfig"),
+        ]
+    );
+
+    let mut index = WorldIndex::new();
+    index.root = Some(app.to_path_buf());
+    index.root_kconfig = Some(app.join("Kconfig"));
+    index.preprocessor = settings.effective_preprocessor(Some(app));
+    index.analyze_file(
+        &app.join("Kconfig"),
+        "config APP_FEATURE\n\tbool \"App feature\"\n\nsource \"Kconfig.zephyr\"\nsource \"$(ZEPHYR_BASE)/Kconfig.extra\"\n",
+    );
+    index.analyze_file(
+        Path::new("/zephyr/Kconfig.zephyr"),
+        "osource \"$(KCONFIG_BINARY_DIR)/Kconfig.modules\"\norsource \"soc/Kconfig\"\nrsource \"subsys/Kconfig\"\n",
+    );
+    index.analyze_file(
+        Path::new("/zephyr/Kconfig.extra"),
+        "config EXTRA\n\tbool \"Extra\"\n",
+    );
+    index.analyze_file(
+        Path::new("/zephyr/subsys/Kconfig"),
+        "config SUBSYS\n\tbool \"Subsystem\"\n\tdepends on HAL_NORDIC\n",
+    );
+
+    // The build directory does not exist yet: nothing past it resolves, and
+    // nothing is reported for the optional source.
+    let mut found = Vec::new();
+    symbols(
+        &menu_tree::menu_tree(&index, &app.join("Kconfig")).unwrap(),
+        &mut found,
+    );
+    assert_eq!(found, ["APP_FEATURE", "SUBSYS", "EXTRA"]);
+    let path = Path::new("/zephyr/Kconfig.zephyr");
+    assert!(diagnostics::collect(&index, path, &Settings::default()).is_empty());
+
+    index.analyze_file(
+        Path::new("/app/build/Kconfig/Kconfig.modules"),
+        "menu \"hal_nordic (/modules/hal/nordic)\"\nosource \"/modules/hal/nordic/Kconfig\"\nendmenu\n",
+    );
+    index.analyze_file(
+        Path::new("/modules/hal/nordic/Kconfig"),
+        "config HAL_NORDIC\n\tbool \"Nordic HAL\"\n",
+    );
+    let mut found = Vec::new();
+    symbols(
+        &menu_tree::menu_tree(&index, &app.join("Kconfig")).unwrap(),
+        &mut found,
+    );
+    assert_eq!(found, ["APP_FEATURE", "HAL_NORDIC", "SUBSYS", "EXTRA"]);
+}
+
+#[test]
+fn missing_scan_roots_contribute_nothing() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-roots-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("module")).unwrap();
+    std::fs::write(root.join("Kconfig"), "config A\n\tbool\n").unwrap();
+    std::fs::write(root.join("module/Kconfig"), "config B\n\tbool\n").unwrap();
+
+    let files = server::discover_workspace_files(
+        &[
+            root.clone(),
+            root.join("module"),
+            root.join("build/Kconfig"),
+        ],
+        &Settings::default().file_names,
+        None,
+    );
+    assert_eq!(files, [root.join("Kconfig"), root.join("module/Kconfig")]);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn the_scan_stops_at_the_configured_depth() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-depth-{}", std::process::id()));
+    write(
+        &root,
+        &[
+            ("Kconfig", ""),
+            ("drivers/Kconfig", ""),
+            ("drivers/net/Kconfig", ""),
+        ],
+    );
+    let names = Settings::default().file_names;
+    let files = server::discover_workspace_files(std::slice::from_ref(&root), &names, Some(1));
+    assert_eq!(files, [root.join("Kconfig"), root.join("drivers/Kconfig")]);
+    let files = server::discover_workspace_files(std::slice::from_ref(&root), &names, Some(0));
+    assert_eq!(files, [root.join("Kconfig")]);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+fn write(root: &Path, files: &[(&str, &str)]) {
+    for (path, text) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+}
+
+#[test]
+fn u_boot_profile_is_detected_and_sets_its_version() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-uboot-{}", std::process::id()));
+    write(
+        &root,
+        &[
+            (
+                "Kconfig",
+                "mainmenu \"U-Boot $(UBOOTVERSION) Configuration\"\n",
+            ),
+            ("tools/mkimage.c", ""),
+            ("include/configs/sandbox.h", ""),
+            (
+                "Makefile",
+                "VERSION = 2024\nPATCHLEVEL = 01\nSUBLEVEL =\nEXTRAVERSION = -rc1\n",
+            ),
+        ],
+    );
+    let mut settings = Settings::default();
+    assert_eq!(profile::apply(&mut settings, &root), Profile::UBoot);
+    assert_eq!(
+        settings.preprocessor.variables["UBOOTVERSION"],
+        "2024.01-rc1"
+    );
+
+    let mut settings: Settings =
+        serde_json::from_value(serde_json::json!({ "profile": "generic" })).unwrap();
+    assert_eq!(profile::apply(&mut settings, &root), Profile::Generic);
+    assert!(settings.preprocessor.variables.is_empty());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn nuttx_profile_indexes_the_apps_tree_next_to_it() {
+    let base = std::env::temp_dir().join(format!("kconfig-lsp-nuttx-{}", std::process::id()));
+    let root = base.join("nuttx");
+    write(
+        &base,
+        &[
+            (
+                "nuttx/Kconfig",
+                "menu \"Application Configuration\"\nsource \"$APPSDIR/Kconfig\"\nendmenu\n",
+            ),
+            ("nuttx/sched/Kconfig", "config SCHED_WAITPID\n\tbool\n"),
+            ("nuttx/include/nuttx/compiler.h", ""),
+            ("apps/Kconfig", "config EXAMPLES_HELLO\n\tbool \"Hello\"\n"),
+        ],
+    );
+    let mut settings = Settings::default();
+    assert_eq!(profile::apply(&mut settings, &root), Profile::NuttX);
+    assert_eq!(settings.module_roots, ["../apps"]);
+
+    let mut index = WorldIndex::new();
+    index.root = Some(root.clone());
+    index.preprocessor = settings.effective_preprocessor(Some(&root));
+    for path in
+        server::discover_workspace_files(&settings.scan_roots(&root), &settings.file_names, None)
+    {
+        index.analyze_file(&path, &std::fs::read_to_string(&path).unwrap());
+    }
+    assert_eq!(
+        index.resolve_source(&root.join("Kconfig"), "$APPSDIR/Kconfig", false),
+        Some(base.join("apps/Kconfig"))
+    );
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+#[test]
+fn buildroot_profile_indexes_config_in_files() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-buildroot-{}", std::process::id()));
+    write(
+        &root,
+        &[
+            ("Config.in", "source \"package/Config.in\"\n"),
+            (
+                "package/Config.in",
+                "source \"package/busybox/Config.in\"\n",
+            ),
+            (
+                "package/busybox/Config.in",
+                "config BR2_PACKAGE_BUSYBOX\n\tbool \"BusyBox\"\n",
+            ),
+            ("package/busybox/busybox.mk", ""),
+        ],
+    );
+    let mut settings = Settings::default();
+    assert_eq!(profile::apply(&mut settings, &root), Profile::Buildroot);
+    assert_eq!(settings.root_kconfig, "Config.in");
+    assert_eq!(settings.config_prefix, "BR2_");
+    assert_eq!(
+        profile::describe(Profile::Buildroot, &settings),
+        "Detected a Buildroot tree: root Kconfig `Config.in`, `.config` prefix `BR2_`. \
+         Set `profile` to override."
+    );
+    let files =
+        server::discover_workspace_files(&settings.scan_roots(&root), &settings.file_names, None);
+    assert_eq!(
+        files,
+        [
+            root.join("Config.in"),
+            root.join("package/Config.in"),
+            root.join("package/busybox/Config.in"),
+        ]
+    );
+
+    // Explicit settings are left alone.
+    let mut settings: Settings =
+        serde_json::from_value(serde_json::json!({ "configPrefix": "CONFIG_BR2_" })).unwrap();
+    profile::apply(&mut settings, &root);
+    assert_eq!(settings.config_prefix, "CONFIG_BR2_");
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn zephyr_application_builds_against_the_zephyr_tree_next_to_it() {
+    let base = std::env::temp_dir().join(format!("kconfig-lsp-zephyr-app-{}", std::process::id()));
+    let root = base.join("app");
+    write(
+        &base,
+        &[
+            ("zephyr/Kconfig", "source \"Kconfig.zephyr\"\n"),
+            ("zephyr/Kconfig.zephyr", "config KERNEL\n\tbool\n"),
+            ("modules/hal/Kconfig", ""),
+            ("app/prj.conf", "CONFIG_KERNEL=y\n"),
+            (
+                "app/CMakeLists.txt",
+                "find_package(Zephyr REQUIRED HINTS $ENV{ZEPHYR_BASE})\n",
+            ),
+        ],
+    );
+    let mut settings = Settings::default();
+    assert_eq!(profile::apply(&mut settings, &root), Profile::Zephyr);
+    let zephyr = base.join("zephyr").display().to_string();
+    assert_eq!(settings.preprocessor.variables["ZEPHYR_BASE"], zephyr);
+    assert_eq!(settings.preprocessor.variables["srctree"], zephyr);
+    assert_eq!(
+        settings.module_roots,
+        [zephyr.clone(), base.join("modules").display().to_string()]
+    );
+    assert_eq!(settings.root_kconfig, format!("{}/Kconfig", zephyr));
+    assert_eq!(settings.binary_dir.as_deref(), Some("build/Kconfig"));
+
+    let mut settings: Settings =
+        serde_json::from_value(serde_json::json!({ "profile": "generic" })).unwrap();
+    profile::apply(&mut settings, &root);
+    assert_eq!(settings.root_kconfig, "Kconfig");
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+#[test]
+fn generic_trees_are_not_announced_as_a_profile() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-generic-{}", std::process::id()));
+    write(&root, &[("Kconfig", "config FOO\n\tbool\n")]);
+    assert_eq!(profile::detect(&root), Profile::Generic);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn sourced_files_are_indexed_whatever_their_names() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-sourced-{}", std::process::id()));
+    write(
+        &root,
+        &[
+            ("Kconfig", "source \"vendor/Config.mk\"\n"),
+            (
+                "vendor/Config.mk",
+                "config VENDOR\n\tbool\nrsource \"board.opts\"\n",
+            ),
+            ("vendor/board.opts", "config BOARD\n\tbool\n"),
+            ("vendor/unrelated.mk", "config UNRELATED\n\tbool\n"),
+        ],
+    );
+    let settings = Settings::default();
+    let mut index = WorldIndex::new();
+    index.root = Some(root.clone());
+    for path in
+        server::discover_workspace_files(&settings.scan_roots(&root), &settings.file_names, None)
+    {
+        index.analyze_file(&path, &std::fs::read_to_string(&path).unwrap());
+    }
+    assert_eq!(index.files.len(), 1);
+
+    let mut found =
+        sourced_files::index_sourced_files(&mut index, vec![root.join("Kconfig")], 1 << 20);
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            root.join("vendor/Config.mk"),
+            root.join("vendor/board.opts")
+        ]
+    );
+    assert_eq!(index.get_definitions("BOARD").len(), 1);
+    assert!(index.get_definitions("UNRELATED").is_empty());
+    std::fs::remove_dir_all(&root).unwrap();
+}
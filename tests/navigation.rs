@@ -0,0 +1,1288 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::dotconfig::DotConfig;
+use kconfig_lsp::impact::{self, RemovalImpactParams};
+use kconfig_lsp::select_chain::{self, SelectChainParams};
+use kconfig_lsp::settings::{HoverSettings, ReferenceSort, ShellPolicy};
+use kconfig_lsp::{definition, hover, preprocess, references, resolved, shell_trust, uri};
+use std::path::Path;
+use tower_lsp::lsp_types::{
+    GotoDefinitionResponse, HoverContents, Location, MarkupKind, Position, Url,
+};
+
+const ARCH_X86: &str = "config PCI\n\tbool \"PCI support\"\n";
+const ARCH_ARM: &str = "config PCI\n\tbool\n";
+const DRIVERS: &str = "config PCI_QUIRKS\n\tbool\n\tdepends on PCI\n";
+
+fn index() -> WorldIndex {
+    let mut index = WorldIndex::new();
+    index.analyze_file(Path::new("/src/arch/x86/Kconfig"), ARCH_X86);
+    index.analyze_file(Path::new("/src/arch/arm/Kconfig"), ARCH_ARM);
+    index.analyze_file(Path::new("/src/drivers/Kconfig"), DRIVERS);
+    index
+}
+
+#[test]
+fn definition_on_definition_name_returns_other_definitions() {
+    let index = index();
+    let resp = definition::goto_definition(
+        &index,
+        Path::new("/src/arch/x86/Kconfig"),
+        Position::new(0, 8),
+    )
+    .expect("definition");
+    match resp {
+        GotoDefinitionResponse::Scalar(loc) => {
+            assert_eq!(loc.uri.path(), "/src/arch/arm/Kconfig");
+        }
+        other => panic!("expected a single location, got {:?}", other),
+    }
+}
+
+#[test]
+fn definition_from_reference_returns_all_definitions() {
+    let index = index();
+    let resp = definition::goto_definition(
+        &index,
+        Path::new("/src/drivers/Kconfig"),
+        Position::new(2, 13),
+    )
+    .expect("definition");
+    assert!(matches!(resp, GotoDefinitionResponse::Array(ref v) if v.len() == 2));
+}
+
+#[test]
+fn references_respect_include_declaration() {
+    let index = index();
+    let path = Path::new("/src/drivers/Kconfig");
+    let pos = Position::new(2, 13);
+
+    let with = references::find_references(&index, path, pos, true, ReferenceSort::None).unwrap();
+    assert_eq!(with.len(), 3);
+
+    let without =
+        references::find_references(&index, path, pos, false, ReferenceSort::None).unwrap();
+    assert_eq!(without.len(), 1);
+    assert_eq!(without[0].uri.path(), "/src/drivers/Kconfig");
+}
+
+#[test]
+fn references_sorted_by_file_and_kind() {
+    let index = index();
+    let path = Path::new("/src/drivers/Kconfig");
+    let pos = Position::new(2, 13);
+
+    let refs =
+        references::find_references(&index, path, pos, true, ReferenceSort::FileAndKind).unwrap();
+    let files: Vec<&str> = refs.iter().map(|l| l.uri.path()).collect();
+    assert_eq!(
+        files,
+        [
+            "/src/arch/arm/Kconfig",
+            "/src/arch/x86/Kconfig",
+            "/src/drivers/Kconfig"
+        ]
+    );
+}
+
+#[test]
+fn results_follow_parse_order_whatever_the_discovery_order() {
+    let root =
+        "source \"arch/x86/Kconfig\"\nsource \"arch/arm/Kconfig\"\nsource \"drivers/Kconfig\"\n";
+    let files = [
+        ("/src/Kconfig", root),
+        ("/src/arch/x86/Kconfig", ARCH_X86),
+        ("/src/arch/arm/Kconfig", ARCH_ARM),
+        ("/src/drivers/Kconfig", DRIVERS),
+    ];
+    let path = Path::new("/src/drivers/Kconfig");
+    let pos = Position::new(2, 13);
+    let results = |order: &[usize]| {
+        let mut index = WorldIndex::new();
+        index.root = Some("/src".into());
+        index.root_kconfig = Some("/src/Kconfig".into());
+        for &i in order {
+            index.analyze_file(Path::new(files[i].0), files[i].1);
+        }
+        let defs = match definition::goto_definition(&index, path, pos) {
+            Some(GotoDefinitionResponse::Array(locations)) => locations,
+            other => panic!("expected locations, got {:?}", other),
+        };
+        let refs =
+            references::find_references(&index, path, pos, true, ReferenceSort::None).unwrap();
+        let paths = |locations: Vec<Location>| -> Vec<String> {
+            locations.iter().map(|l| l.uri.path().to_string()).collect()
+        };
+        (paths(defs), paths(refs), index.all_symbols.clone())
+    };
+
+    let first = results(&[0, 1, 2, 3]);
+    assert_eq!(first.0, ["/src/arch/x86/Kconfig", "/src/arch/arm/Kconfig"]);
+    assert_eq!(
+        first.1,
+        [
+            "/src/arch/x86/Kconfig",
+            "/src/arch/arm/Kconfig",
+            "/src/drivers/Kconfig"
+        ]
+    );
+    assert_eq!(first.2, ["PCI", "PCI_QUIRKS"]);
+    assert_eq!(results(&[3, 2, 1, 0]), first);
+    assert_eq!(results(&[2, 0, 3, 1]), first);
+}
+
+#[test]
+fn references_tree_groups_by_file_then_kind() {
+    use kconfig_lsp::references::{GroupKind, ReferencesTreeParams};
+
+    let mut index = index();
+    index.remove_file(Path::new("/src/drivers/Kconfig"));
+    index.analyze_file(
+        Path::new("/src/drivers/Kconfig"),
+        "config PCI_QUIRKS\n\tbool\n\tdepends on PCI\n\tselect PCI if PCI\n",
+    );
+    let params = ReferencesTreeParams {
+        symbol: None,
+        text_document: Some(tower_lsp::lsp_types::TextDocumentIdentifier {
+            uri: Url::parse("file:///src/drivers/Kconfig").unwrap(),
+        }),
+        position: Some(Position::new(2, 13)),
+        include_declaration: true,
+    };
+    let tree = references::references_tree(&index, &params).unwrap();
+    assert_eq!(tree.symbol, "PCI");
+    assert_eq!(tree.count, 5);
+    let files: Vec<(&str, usize)> = tree.files.iter().map(|f| (f.uri.path(), f.count)).collect();
+    assert_eq!(
+        files,
+        [
+            ("/src/arch/arm/Kconfig", 1),
+            ("/src/arch/x86/Kconfig", 1),
+            ("/src/drivers/Kconfig", 3)
+        ]
+    );
+    assert_eq!(tree.files[0].groups[0].kind, GroupKind::Definition);
+    let drivers: Vec<(String, usize)> = tree.files[2]
+        .groups
+        .iter()
+        .map(|g| (serde_json::to_string(&g.kind).unwrap(), g.count))
+        .collect();
+    assert_eq!(
+        drivers,
+        [
+            ("\"dependsOn\"".to_string(), 1),
+            ("\"select\"".to_string(), 2)
+        ]
+    );
+}
+
+#[test]
+fn search_text_matches_prompts_before_help() {
+    use kconfig_lsp::search::{self, MatchField, SearchTextParams};
+
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "config NR_CPUS\n\tint \"Maximum number of CPUs\"\n\nconfig SMP\n\tbool \"SMP\"\n\thelp\n\t  Set the maximum number of CPUs with NR_CPUS.\n",
+    );
+    let params = SearchTextParams {
+        query: "maximum CPUS".into(),
+        limit: None,
+    };
+    let hits = search::search_text(&index, &params);
+    let found: Vec<(&str, MatchField)> =
+        hits.iter().map(|h| (h.symbol.as_str(), h.field)).collect();
+    assert_eq!(
+        found,
+        [("NR_CPUS", MatchField::Prompt), ("SMP", MatchField::Help)]
+    );
+}
+
+#[test]
+fn search_text_follows_file_changes() {
+    use kconfig_lsp::search::{self, SearchTextParams};
+
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, "config SMP\n\tbool \"Symmetric multi-processing\"\n");
+    index.analyze_file(
+        Path::new("/src/other/Kconfig"),
+        "config SMP\n\tbool \"Symmetric multi-processing\"\n",
+    );
+    let search = |index: &WorldIndex, query: &str| {
+        let params = SearchTextParams {
+            query: query.into(),
+            limit: None,
+        };
+        search::search_text(index, &params)
+            .into_iter()
+            .map(|h| h.location.uri.path().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        search(&index, "symm multi"),
+        ["/src/Kconfig", "/src/other/Kconfig"]
+    );
+
+    index.reanalyze_file(path, "config SMP\n\tbool \"Multiprocessor support\"\n");
+    assert_eq!(search(&index, "symmetric"), ["/src/other/Kconfig"]);
+    assert_eq!(search(&index, "multiprocessor"), ["/src/Kconfig"]);
+    assert!(search(&index, "metric").is_empty());
+
+    index.remove_file(Path::new("/src/other/Kconfig"));
+    assert!(search(&index, "symmetric").is_empty());
+}
+
+const CHOICE: &str = "choice\n\tprompt \"Compiler optimization level\"\n\nconfig CC_OPTIMIZE_FOR_PERFORMANCE\n\tbool \"-O2\"\n\nconfig CC_OPTIMIZE_FOR_SIZE\n\tbool \"-Os\"\n\nconfig CC_OPTIMIZE_NONE\n\tbool \"-O0\"\n\nendchoice\n";
+
+#[test]
+fn member_navigates_to_containing_choice() {
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/init/Kconfig");
+    index.analyze_file(path, CHOICE);
+
+    let resp =
+        definition::goto_containing_choice(&index, path, Position::new(6, 10)).expect("choice");
+    match resp {
+        GotoDefinitionResponse::Scalar(loc) => {
+            assert_eq!(loc.range.start, Position::new(0, 0));
+            assert_eq!(loc.range.end, Position::new(0, 6));
+        }
+        other => panic!("expected a single location, got {:?}", other),
+    }
+
+    let hover = hover::hover(
+        &index,
+        path,
+        Position::new(6, 10),
+        MarkupKind::Markdown,
+        &HoverSettings::default(),
+    )
+    .expect("hover");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+    assert!(
+        markup
+            .value
+            .contains("Member of choice 'Compiler optimization level' (3 options)")
+    );
+}
+
+#[test]
+fn hover_on_numeric_default_shows_other_base_and_range() {
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/arch/x86/Kconfig");
+    index.analyze_file(
+        path,
+        "config PHYSICAL_ALIGN\n\thex \"Alignment value\"\n\tdefault 0x200000\n\trange 0x2000 0x1000000\n\nconfig LOG_BUF_SHIFT\n\tint\n\tdefault 17\n\trange 12 25 if EXPERT\n",
+    );
+
+    let text = |pos| {
+        let hover = hover::hover(
+            &index,
+            path,
+            pos,
+            MarkupKind::Markdown,
+            &HoverSettings::default(),
+        )
+        .expect("hover");
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markdown hover");
+        };
+        markup.value
+    };
+    assert_eq!(
+        text(Position::new(2, 12)),
+        "`0x200000` = `2097152`\n\nWithin range `0x2000` .. `0x1000000`"
+    );
+    assert_eq!(
+        text(Position::new(7, 10)),
+        "`17` = `0x11`\n\nWithin range `12` .. `25` if `EXPERT`"
+    );
+}
+
+#[test]
+fn removal_impact_lists_dangling_references_defaults_and_choices() {
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/init/Kconfig");
+    index.analyze_file(
+        path,
+        "choice\n\tprompt \"Level\"\n\nconfig LEVEL_LOW\n\tbool \"Low\"\n\ttransitional\n\nconfig LEVEL_HIGH\n\tbool \"High\"\n\nendchoice\n\nconfig USER\n\tbool\n\tdepends on LEVEL_LOW\n\tdefault LEVEL_LOW\n",
+    );
+    let uri = Url::from_file_path(path).unwrap();
+
+    let impact = impact::removal_impact(
+        &index,
+        &RemovalImpactParams {
+            symbol: None,
+            uri: Some(uri.clone()),
+            position: Some(Position::new(3, 8)),
+        },
+    )
+    .expect("impact");
+    assert_eq!(impact.symbol, "LEVEL_LOW");
+    assert!(impact.remaining_definitions.is_empty());
+    assert_eq!(impact.dangling_references.len(), 2);
+    assert_eq!(impact.defaults.len(), 1);
+    assert_eq!(impact.defaults[0].range.start, Position::new(15, 9));
+    assert_eq!(impact.choices.len(), 1);
+    assert_eq!(impact.choices[0].remaining_members, 1);
+
+    let lenses = impact::code_lenses(&index, &uri, path);
+    assert_eq!(lenses.len(), 1);
+    assert_eq!(lenses[0].range.start, Position::new(3, 7));
+    assert_eq!(
+        lenses[0].command.as_ref().unwrap().title,
+        "Removal impact: 2 references"
+    );
+}
+
+#[test]
+fn resolved_symbol_merges_definitions_with_origins() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.root_kconfig = Some("/src/Kconfig".into());
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "source \"arch/x86/Kconfig\"\nsource \"arch/arm/Kconfig\"\n",
+    );
+    index.analyze_file(
+        Path::new("/src/arch/x86/Kconfig"),
+        "if X86_64\nconfig NR_CPUS\n\tint \"Maximum number of CPUs\"\n\tdepends on SMP\n\tdefault 64\n\tselect CPUMASK if NR_CPUS > 8\nendif\n",
+    );
+    index.analyze_file(
+        Path::new("/src/arch/arm/Kconfig"),
+        "config NR_CPUS\n\tint\n\tdefault 8 if ARM_SMALL\n\timply HOTPLUG\n",
+    );
+
+    let merged = resolved::resolve(&index, "NR_CPUS").expect("resolved");
+    assert_eq!(merged.type_kind.as_deref(), Some("int"));
+    assert_eq!(merged.definitions.len(), 2);
+    assert_eq!(
+        merged.text,
+        "config NR_CPUS\n\tint\n\
+         \t# arch/x86/Kconfig:2, depends on SMP && X86_64\n\
+         \tprompt \"Maximum number of CPUs\"\n\
+         \tdefault 64\n\
+         \tselect CPUMASK if NR_CPUS > 8\n\
+         \t# arch/arm/Kconfig:1\n\
+         \tdefault 8 if ARM_SMALL\n\
+         \timply HOTPLUG\n"
+    );
+
+    let Some(hover) = hover::hover(
+        &index,
+        Path::new("/src/arch/arm/Kconfig"),
+        Position::new(0, 8),
+        MarkupKind::Markdown,
+        &HoverSettings::default(),
+    ) else {
+        panic!("no hover");
+    };
+    let HoverContents::Markup(m) = hover.contents else {
+        panic!("expected markup");
+    };
+    assert!(
+        m.value
+            .contains("**Merged view**\n\n```kconfig\nconfig NR_CPUS\n")
+    );
+}
+
+#[test]
+fn hover_summarizes_definitions_past_the_expansion_limit() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.root_kconfig = Some("/src/Kconfig".into());
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "source \"arch/x86/Kconfig\"\nsource \"arch/arm/Kconfig\"\nsource \"arch/mips/Kconfig\"\n",
+    );
+    index.analyze_file(
+        Path::new("/src/arch/mips/Kconfig"),
+        "config PCI\n\tbool \"PCI\"\n",
+    );
+    index.analyze_file(Path::new("/src/arch/x86/Kconfig"), ARCH_X86);
+    index.analyze_file(Path::new("/src/arch/arm/Kconfig"), ARCH_ARM);
+
+    let text = |expand_definitions| {
+        let settings = HoverSettings { expand_definitions };
+        let path = Path::new("/src/arch/arm/Kconfig");
+        let hover = hover::hover(
+            &index,
+            path,
+            Position::new(0, 8),
+            MarkupKind::Markdown,
+            &settings,
+        )
+        .expect("hover");
+        let HoverContents::Markup(m) = hover.contents else {
+            panic!("expected markup");
+        };
+        m.value
+    };
+
+    let summary = text(1);
+    assert!(summary.starts_with("**PCI** (config) `bool`\n\n*\"PCI support\"*"));
+    assert!(summary.contains(
+        "Also defined in [`arch/arm/Kconfig:1`](file:///src/arch/arm/Kconfig#L1)  \n\
+         Also defined in [`arch/mips/Kconfig:1`](file:///src/arch/mips/Kconfig#L1): \"PCI\""
+    ));
+    assert_eq!(summary.matches("**PCI** (config)").count(), 1);
+
+    let full = text(0);
+    assert_eq!(full.matches("**PCI** (config)").count(), 3);
+    assert!(!full.contains("Also defined in"));
+}
+
+#[test]
+fn hover_orders_defaults_by_parse_order_and_marks_the_active_one() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.root_kconfig = Some("/src/Kconfig".into());
+    // `init` is sourced between the two halves of the root file, so its
+    // default comes after the first root definition and before the second.
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "config SWAP\n\tbool \"Swap\"\n\tdefault y if MMU\n\nsource \"init/Kconfig\"\n\nconfig SWAP\n\tdefault y\n\nconfig MMU\n\tbool\n",
+    );
+    index.analyze_file(
+        Path::new("/src/init/Kconfig"),
+        "config SWAP\n\tbool\n\tdefault n\n",
+    );
+    let text = |index: &WorldIndex| {
+        let hover = hover::hover(
+            index,
+            Path::new("/src/Kconfig"),
+            Position::new(0, 8),
+            MarkupKind::Markdown,
+            &HoverSettings::default(),
+        )
+        .expect("hover");
+        match hover.contents {
+            HoverContents::Markup(m) => m.value,
+            other => panic!("expected markup, got {:?}", other),
+        }
+    };
+
+    assert!(text(&index).contains(
+        "Defaults, first match wins:\n\n\
+         1. `y` if `MMU` (from Kconfig:3)\n\
+         2. `n` (from init/Kconfig:3)\n\
+         3. `y` (from Kconfig:8)"
+    ));
+
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_SWAP=y\n",
+        "CONFIG_",
+    ));
+    assert!(text(&index).contains(
+        "Active default: `n` (from init/Kconfig:3)\n\nShadowed defaults: `y` (from Kconfig:8)"
+    ));
+}
+
+#[test]
+fn hover_computes_the_default_of_symbols_the_config_leaves_unset() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(
+        path,
+        "config SWAP\n\ttristate \"Swap\"\n\tdefault m if MMU\n\tdefault y if !MMU\n\nconfig MMU\n\tbool \"MMU\"\n\nconfig HZ\n\tint\n\tdefault 250\n",
+    );
+    let text = |index: &WorldIndex, line: u32| {
+        let hover = hover::hover(
+            index,
+            path,
+            Position::new(line, 8),
+            MarkupKind::Markdown,
+            &HoverSettings::default(),
+        )
+        .expect("hover");
+        match hover.contents {
+            HoverContents::Markup(m) => m.value,
+            other => panic!("expected markup, got {:?}", other),
+        }
+    };
+
+    assert!(text(&index, 0).contains(
+        "Computed default: `y` from Kconfig:4, since its condition `!MMU` holds \
+         (no `.config` is loaded, so every symbol counts as unset)"
+    ));
+    assert!(
+        text(&index, 8)
+            .contains("Computed default: `250` from Kconfig:11, since it has no condition")
+    );
+
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_MMU=y\n",
+        "CONFIG_",
+    ));
+    assert!(text(&index, 0).contains(
+        "Computed default: `m` from Kconfig:3, since its condition `MMU` holds \
+         (the `.config` does not set it)"
+    ));
+    assert!(!text(&index, 5).contains("Computed default"));
+}
+
+#[test]
+fn hover_shows_the_build_value_where_it_differs_from_the_config() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(
+        path,
+        "config PCI\n\tbool \"PCI\"\n\nconfig USB\n\ttristate \"USB\"\n\tdepends on PCI\n\nconfig NR_CPUS\n\tint \"CPUs\"\n",
+    );
+    let text = |index: &WorldIndex, line: u32| {
+        let hover = hover::hover(
+            index,
+            path,
+            Position::new(line, 8),
+            MarkupKind::Markdown,
+            &HoverSettings::default(),
+        )
+        .expect("hover");
+        match hover.contents {
+            HoverContents::Markup(m) => m.value,
+            other => panic!("expected markup, got {:?}", other),
+        }
+    };
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_USB=y\nCONFIG_NR_CPUS=1\n",
+        "CONFIG_",
+    ));
+    index.build_config = Some(DotConfig::parse_header(
+        Path::new("/src/include/generated/autoconf.h"),
+        "#define CONFIG_NR_CPUS 1\n",
+        "CONFIG_",
+    ));
+    assert!(
+        text(&index, 3).contains(
+            "**Build value:** `n` in `include/generated/autoconf.h`, but `y` in `.config`"
+        )
+    );
+    assert!(!text(&index, 0).contains("Build value"));
+    assert!(!text(&index, 7).contains("Build value"));
+
+    index.build_config = Some(DotConfig::parse_header(
+        Path::new("/src/include/generated/autoconf.h"),
+        "#define CONFIG_PCI 1\n#define CONFIG_USB_MODULE 1\n#define CONFIG_NR_CPUS 4\n",
+        "CONFIG_",
+    ));
+    assert!(text(&index, 0).contains("**Build value:** `y` in"));
+    assert!(text(&index, 3).contains("**Build value:** `m` in"));
+    assert!(
+        text(&index, 7).contains("`4` in `include/generated/autoconf.h`, but `1` in `.config`")
+    );
+}
+
+#[test]
+fn select_chains_name_the_fragment_that_started_them() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(
+        path,
+        "config CRC32\n\ttristate\n\nconfig NETFILTER\n\tbool \"Netfilter\"\n\tselect CRC32\n\nconfig NF_TABLES\n\tbool \"nftables\"\n\tselect NETFILTER if NET\n\nconfig NET\n\tbool \"Networking\"\n\nconfig PPP\n\tbool \"PPP\"\n\tselect CRC32\n",
+    );
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_CRC32=y\nCONFIG_NETFILTER=y\nCONFIG_NF_TABLES=y\nCONFIG_NET=y\n# CONFIG_PPP is not set\n",
+        "CONFIG_",
+    ));
+    index.fragments = vec![
+        DotConfig::parse(
+            Path::new("/src/arch/x86/configs/base.config"),
+            "CONFIG_NET=y\nCONFIG_NF_TABLES=n\n",
+            "CONFIG_",
+        ),
+        DotConfig::parse(
+            Path::new("/src/kernel/configs/nf.config"),
+            "# nftables\nCONFIG_NF_TABLES=y\n",
+            "CONFIG_",
+        ),
+    ];
+
+    let params = SelectChainParams {
+        symbol: "CRC32".into(),
+    };
+    let chain = select_chain::select_chain(&index, &params).expect("chain");
+    assert_eq!(chain.value, "y");
+    let [netfilter] = &chain.selected_by[..] else {
+        panic!("expected only NETFILTER, got {:?}", chain.selected_by);
+    };
+    assert_eq!(netfilter.symbol, "NETFILTER");
+    assert!(netfilter.assignment.is_none());
+    let nf_tables = &netfilter.selected_by[0];
+    assert_eq!(nf_tables.condition.as_deref(), Some("NET"));
+    assert_eq!(nf_tables.condition_value.as_deref(), Some("y"));
+    let assignment = nf_tables.assignment.as_ref().expect("assignment");
+    assert_eq!(assignment.uri.path(), "/src/kernel/configs/nf.config");
+    assert_eq!(assignment.line, 1);
+
+    let hover = hover::hover(
+        &index,
+        path,
+        Position::new(0, 8),
+        MarkupKind::Markdown,
+        &HoverSettings::default(),
+    )
+    .expect("hover");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markup");
+    };
+    assert!(
+        markup.value.contains(
+            "**Forced on by** (`CRC32` is `y` in .config):\n\n\
+             - `NETFILTER` [=y] (select at `Kconfig:6`)\n  \
+             - `NF_TABLES` [=y] if `NET` [=y] (select at `Kconfig:10`), \
+             set to `y` in `kernel/configs/nf.config:2`"
+        ),
+        "{}",
+        markup.value
+    );
+
+    index.config = None;
+    assert!(select_chain::select_chain(&index, &params).is_none());
+}
+
+#[test]
+fn hover_on_macro_shows_expansion_and_assignment() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.root_kconfig = Some("/src/Kconfig".into());
+    index
+        .preprocessor
+        .variables
+        .insert("ARCH".into(), "x86".into());
+    let source = "CC := gcc\n\
+                  cc-name = $(CC)-$(1)\n\
+                  version = $(shell,$(CC) --version)\n\
+                  config CC_NAME\n\
+                  \tstring\n\
+                  \tdefault \"$(cc-name,$(ARCH))\"\n\
+                  \tdefault \"$(version)\"\n";
+    index.analyze_file(Path::new("/src/Kconfig"), source);
+    let text = |line: u32, col: u32| {
+        let hover = hover::hover(
+            &index,
+            Path::new("/src/Kconfig"),
+            Position::new(line, col),
+            MarkupKind::Markdown,
+            &HoverSettings::default(),
+        )
+        .expect("hover");
+        match hover.contents {
+            HoverContents::Markup(m) => m.value,
+            other => panic!("expected markup, got {:?}", other),
+        }
+    };
+
+    let value = text(5, 13);
+    assert!(value.starts_with("`$(cc-name,$(ARCH))`\n\nExpands to `gcc-x86`"));
+    assert!(value.contains("Assigned in `Kconfig:2`: `cc-name = $(CC)-$(1)`"));
+
+    assert!(text(5, 23).contains("`ARCH` is set by the `preprocessor.variables` setting"));
+
+    assert!(text(6, 13).contains(
+        "Not expanded: `$(shell,gcc --version)` is not run unless `preprocessor.shell` is enabled"
+    ));
+}
+
+#[test]
+fn macro_at_skips_unterminated_references() {
+    use kconfig_lsp::preprocess::macro_at;
+
+    let source = "broken = $(CC\nflags = $(oops $(CC) \\\n\t$(ARCH)\n";
+    let body = |offset: usize| macro_at(source, offset).map(|(_, body)| body);
+    let at = |needle: &str| source.find(needle).unwrap() + 2;
+    assert_eq!(body(at("$(CC)")), Some("CC"));
+    assert_eq!(body(at("$(ARCH)")), Some("ARCH"));
+    assert_eq!(body(at("$(oops")), None);
+    assert_eq!(body(source.find("broken").unwrap()), None);
+}
+
+#[test]
+fn shell_policy_controls_what_runs() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/".into());
+    let source = "config GREETING\n\tstring\n\tdefault \"$(shell,echo hi)\"\n";
+    index.analyze_file(Path::new("/Kconfig"), source);
+    let text = |index: &WorldIndex| {
+        let hover = hover::hover(
+            index,
+            Path::new("/Kconfig"),
+            Position::new(2, 14),
+            MarkupKind::Markdown,
+            &HoverSettings::default(),
+        )
+        .expect("hover");
+        match hover.contents {
+            HoverContents::Markup(m) => m.value,
+            other => panic!("expected markup, got {:?}", other),
+        }
+    };
+
+    index.preprocessor.shell = ShellPolicy::DryRun;
+    assert_eq!(
+        text(&index),
+        "`$(shell,echo hi)`\n\nExpands to nothing\n\nWould run `echo hi`"
+    );
+
+    index.preprocessor.shell = ShellPolicy::Prompt;
+    assert!(text(&index).contains("is not run until the workspace is trusted"));
+
+    index.preprocessor.shell = ShellPolicy::Enabled;
+    index.preprocessor.shell_allowlist = vec!["uname".into()];
+    assert!(text(&index).contains("Not expanded: `echo` is not in `preprocessor.shellAllowlist`"));
+
+    index.preprocessor.shell_allowlist.push("echo".into());
+    assert!(text(&index).contains("Not expanded: `$(shell,echo hi)` has not run yet"));
+
+    let commands = preprocess::shell_commands(&index);
+    assert_eq!(commands, ["echo hi"]);
+    for command in commands {
+        let output = preprocess::run_shell(&command, Path::new("/"));
+        index.shell_outputs.insert(command, output);
+    }
+    assert!(text(&index).contains("Expands to `hi`"));
+    assert!(preprocess::shell_commands(&index).is_empty());
+}
+
+#[test]
+fn shell_commands_run_without_a_shell_and_time_out() {
+    let run = |command: &str| preprocess::run_shell(command, Path::new("/"));
+    assert_eq!(run("echo a   b"), Ok("a b".to_string()));
+    for command in [
+        "echo hi; rm -rf /tmp/x",
+        "echo hi | cat",
+        "echo $HOME",
+        "echo 'a b'",
+    ] {
+        assert!(
+            run(command).unwrap_err().contains("shell syntax"),
+            "{}",
+            command
+        );
+    }
+    assert!(run("sleep 10").unwrap_err().contains("did not finish"));
+    // The command exits at once, leaving a process holding its output open.
+    if Path::new("/usr/bin/setsid").exists() {
+        let start = std::time::Instant::now();
+        assert!(
+            run("setsid -f sleep 10")
+                .unwrap_err()
+                .contains("did not finish")
+        );
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+}
+
+#[test]
+fn shell_trust_answers_are_kept_per_root() {
+    let dir = std::env::temp_dir().join(format!("kconfig-lsp-trust-{}", std::process::id()));
+    let store = dir.join("state/shell-trust.json");
+    let (linux, zephyr) = (Path::new("/src/linux"), Path::new("/src/zephyr"));
+    assert_eq!(shell_trust::load(&store, linux), None);
+
+    shell_trust::save(&store, linux, true).unwrap();
+    shell_trust::save(&store, zephyr, false).unwrap();
+    assert_eq!(shell_trust::load(&store, linux), Some(true));
+    assert_eq!(shell_trust::load(&store, zephyr), Some(false));
+    assert_eq!(shell_trust::load(&store, Path::new("/src/u-boot")), None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn spellings_of_a_file_uri_map_to_the_indexed_path() {
+    let canonical = Url::parse("file:///src/arch/x86/Kconfig").unwrap();
+    for spelling in [
+        "file:///src/arch/x86/%4Bconfig",
+        "file:///src/arch/x86/Kconfig/",
+        "file://localhost/src/arch/x86/Kconfig",
+        "file:///src//arch/x86/Kconfig",
+    ] {
+        let uri = Url::parse(spelling).unwrap();
+        assert_eq!(
+            uri::to_path(&uri).as_deref(),
+            Some(Path::new("/src/arch/x86/Kconfig")),
+            "{}",
+            spelling
+        );
+        assert_eq!(uri::normalize(&uri), canonical, "{}", spelling);
+    }
+    let spaced = Url::parse("file:///src/my%20tree/Kconfig").unwrap();
+    assert_eq!(
+        uri::to_path(&spaced).as_deref(),
+        Some(Path::new("/src/my tree/Kconfig"))
+    );
+    assert_eq!(uri::normalize(&spaced), spaced);
+}
+
+#[test]
+fn untitled_documents_are_indexed_under_their_uri() {
+    let mut index = index();
+    let untitled = Url::parse("untitled:Untitled-1").unwrap();
+    let path = uri::to_path(&untitled).expect("virtual path");
+    assert!(uri::is_virtual(&path));
+    assert_eq!(uri::from_path(&path).as_ref(), Some(&untitled));
+    index.analyze_file(&path, "config NEW_DRIVER\n\tbool\n\tdepends on PCI\n");
+
+    // From the scratch buffer into the workspace...
+    let resp =
+        definition::goto_definition(&index, &path, Position::new(2, 13)).expect("definition");
+    assert!(matches!(resp, GotoDefinitionResponse::Array(ref v) if v.len() == 2));
+
+    // ...and from the workspace back into it.
+    index.analyze_file(
+        Path::new("/src/drivers/Kconfig"),
+        "config USER\n\tbool\n\tselect NEW_DRIVER\n",
+    );
+    let resp = definition::goto_definition(
+        &index,
+        Path::new("/src/drivers/Kconfig"),
+        Position::new(2, 10),
+    )
+    .expect("definition");
+    match resp {
+        GotoDefinitionResponse::Scalar(loc) => assert_eq!(loc.uri, untitled),
+        other => panic!("expected a single location, got {:?}", other),
+    }
+}
+
+#[test]
+fn hover_tells_select_from_imply_with_current_effect() {
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/Kconfig");
+    index.root = Some("/src".into());
+    let src = "config NET\n\tbool \"Net\"\n\nconfig PTP\n\tbool \"PTP\"\n\tdepends on NET\n\nconfig DRIVER\n\tbool \"Driver\"\n\tselect NET\n\timply PTP\n\nconfig OTHER\n\tbool \"Other\"\n\timply PTP if NET\n";
+    index.analyze_file(path, src);
+    let text = |index: &WorldIndex| {
+        let hover = hover::hover(
+            index,
+            path,
+            Position::new(3, 8),
+            MarkupKind::Markdown,
+            &HoverSettings::default(),
+        )
+        .expect("hover");
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markdown hover");
+        };
+        markup.value
+    };
+    assert!(text(&index).ends_with(
+        "**Implied by** (raises the default within its dependencies; can be set lower):\n\n- `DRIVER` (from Kconfig:11)\n- `OTHER` if `NET` (from Kconfig:15)"
+    ));
+
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_NET=y\nCONFIG_DRIVER=y\n",
+        "CONFIG_",
+    ));
+    assert!(text(&index).ends_with(
+        "\n- `DRIVER` (from Kconfig:11): currently ineffective, overridden to `n` in .config\n- `OTHER` if `NET` (from Kconfig:15): currently ineffective, `OTHER` is n"
+    ));
+    let hover = hover::hover(
+        &index,
+        path,
+        Position::new(0, 8),
+        MarkupKind::Markdown,
+        &HoverSettings::default(),
+    )
+    .expect("hover");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+    assert!(markup.value.ends_with(
+        "**Selected by** (forces the value, even past its dependencies):\n\n- `DRIVER` (from Kconfig:10)\n\n---\n\n\
+         **Forced on by** (`NET` is `y` in .config):\n\n\
+         - `DRIVER` [=y] (select at `Kconfig:10`), set to `y` in `.config:2`"
+    ));
+}
+
+#[test]
+fn detailed_document_symbols_carry_hover_documentation() {
+    use kconfig_lsp::symbol_docs;
+
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/drivers/Kconfig");
+    index.analyze_file(
+        path,
+        "menu \"Drivers\"\n\nconfig ACME\n\ttristate \"Acme device\"\n\thelp\n\t  Say Y here.\n\nchoice\n\tprompt \"Mode\"\nconfig ACME_FAST\n\tbool \"Fast\"\nendchoice\n\ncomment \"Legacy\"\nendmenu\n",
+    );
+
+    let symbols = symbol_docs::document_symbols_detailed(&index, path);
+    let outline: Vec<String> = symbols
+        .iter()
+        .map(|s| {
+            format!(
+                "{:?} {} {:?} in {}",
+                s.kind,
+                s.name.as_deref().unwrap_or("-"),
+                s.prompt.as_deref().unwrap_or(""),
+                s.parents.join(" > ")
+            )
+        })
+        .collect();
+    assert_eq!(
+        outline,
+        [
+            "Menu - \"Drivers\" in ",
+            "Config ACME \"Acme device\" in Drivers",
+            "Choice - \"Mode\" in Drivers",
+            "Config ACME_FAST \"Fast\" in Drivers > Mode",
+            "Comment - \"Legacy\" in Drivers",
+        ]
+    );
+    assert_eq!(
+        symbols[1].documentation,
+        hover::symbol_documentation_as(&index, "ACME", MarkupKind::Markdown, 0)
+    );
+    assert!(
+        symbols[1]
+            .documentation
+            .as_deref()
+            .unwrap()
+            .ends_with("Say Y here.")
+    );
+    assert_eq!(symbols[1].selection_range.start, Position::new(2, 7));
+    assert!(symbols[0].documentation.is_none());
+}
+
+#[test]
+fn file_symbols_and_outline_are_indexed_per_file() {
+    use kconfig_lsp::symbol_docs;
+
+    let mut index = index();
+    let path = Path::new("/src/drivers/Kconfig");
+    index.remove_file(path);
+    index.analyze_file(
+        path,
+        "menu \"Drivers\"\nif PCI\nconfig PCI_QUIRKS\n\tbool \"Quirks\"\nendif\nchoice\nconfig PCI_FAST\n\tbool \"Fast\"\nendchoice\nendmenu\n",
+    );
+
+    let names: Vec<&str> = index
+        .symbols_in_file(path)
+        .iter()
+        .map(|d| d.name.as_str())
+        .collect();
+    assert_eq!(names, ["PCI_QUIRKS", "PCI_FAST"]);
+    assert!(index.symbols_in_file(Path::new("/src/Kconfig")).is_empty());
+
+    let outline = &index.outline(path)[0];
+    assert_eq!(outline.prompt.as_deref(), Some("Drivers"));
+    let children: Vec<Option<&str>> = outline.children.iter().map(|c| c.name.as_deref()).collect();
+    assert_eq!(children, [Some("PCI_QUIRKS"), None]);
+
+    let symbols = symbol_docs::document_symbols(&index, path).unwrap();
+    assert_eq!(symbols[0].name, "Drivers");
+    let children = symbols[0].children.as_ref().unwrap();
+    assert_eq!(children[0].name, "PCI_QUIRKS");
+    assert_eq!(children[0].detail.as_deref(), Some("Quirks"));
+    assert_eq!(children[1].name, "choice");
+    assert_eq!(children[1].children.as_ref().unwrap()[0].name, "PCI_FAST");
+}
+
+#[test]
+fn type_hierarchy_follows_menu_containment_across_sourced_files() {
+    use kconfig_lsp::type_hierarchy;
+    use tower_lsp::lsp_types::SymbolKind;
+
+    let mut index = WorldIndex::new();
+    let root = Path::new("/src/Kconfig");
+    let drivers = Path::new("/src/drivers/Kconfig");
+    index.analyze_file(
+        root,
+        "mainmenu \"Test\"\nmenu \"Drivers\"\nconfig NET\n\tbool \"Networking\"\nmenuconfig USB\n\tbool \"USB support\"\nconfig USB_STORAGE\n\tbool \"USB storage\"\n\tdepends on USB\nsource \"drivers/Kconfig\"\nendmenu\n",
+    );
+    index.analyze_file(
+        drivers,
+        "choice\n\tprompt \"Driver\"\nconfig DRV_A\n\tbool \"A\"\nendchoice\n",
+    );
+    index.root_kconfig = Some(root.to_path_buf());
+
+    // From an attribute line of a config in the sourced file, up to the
+    // root of the tree.
+    let items = type_hierarchy::prepare(&index, drivers, Position::new(3, 2)).unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "DRV_A");
+    assert_eq!(items[0].detail.as_deref(), Some("A"));
+    assert_eq!(items[0].kind, SymbolKind::CONSTANT);
+
+    let mut chain = Vec::new();
+    let mut item = items[0].clone();
+    while let Some(parent) = type_hierarchy::supertypes(&index, &item).unwrap().pop() {
+        chain.push((parent.name.clone(), parent.kind));
+        item = parent;
+    }
+    assert_eq!(
+        chain,
+        [
+            ("Driver".to_string(), SymbolKind::ENUM),
+            ("Drivers".to_string(), SymbolKind::NAMESPACE),
+            ("Test".to_string(), SymbolKind::NAMESPACE),
+        ]
+    );
+
+    // Down from the root; entries depending on a `menuconfig` are under it.
+    let names = |items: Vec<tower_lsp::lsp_types::TypeHierarchyItem>| -> Vec<String> {
+        items.into_iter().map(|i| i.name).collect()
+    };
+    let menus = type_hierarchy::subtypes(&index, &item).unwrap();
+    assert_eq!(names(menus.clone()), ["Drivers"]);
+    let entries = type_hierarchy::subtypes(&index, &menus[0]).unwrap();
+    assert_eq!(names(entries.clone()), ["NET", "USB", "Driver"]);
+    assert_eq!(
+        names(type_hierarchy::subtypes(&index, &entries[1]).unwrap()),
+        ["USB_STORAGE"]
+    );
+    assert_eq!(entries[2].uri.path(), "/src/drivers/Kconfig");
+
+    // Off every entry, there is nothing to show.
+    assert!(type_hierarchy::prepare(&index, root, Position::new(0, 3)).is_none());
+}
+
+#[test]
+fn type_hierarchy_tells_apart_the_places_of_a_file_sourced_twice() {
+    use kconfig_lsp::type_hierarchy;
+
+    let mut index = WorldIndex::new();
+    let root = Path::new("/src/Kconfig");
+    let common = Path::new("/src/common/Kconfig");
+    index.analyze_file(
+        root,
+        "mainmenu \"Test\"\nmenu \"A\"\nsource \"common/Kconfig\"\nendmenu\nmenu \"B\"\nsource \"common/Kconfig\"\nendmenu\n",
+    );
+    index.analyze_file(common, "config SHARED\n\tbool \"Shared\"\n");
+    index.root_kconfig = Some(root.to_path_buf());
+
+    let item = type_hierarchy::prepare(&index, common, Position::new(0, 8)).unwrap()[0].clone();
+    let root_item = type_hierarchy::supertypes(&index, &item).unwrap()[0].clone();
+    let root_item = type_hierarchy::supertypes(&index, &root_item).unwrap()[0].clone();
+    let menus = type_hierarchy::subtypes(&index, &root_item).unwrap();
+    assert_eq!(menus[1].name, "B");
+    let shared = type_hierarchy::subtypes(&index, &menus[1]).unwrap();
+    assert_eq!(shared[0].name, "SHARED");
+    // Up from the second placement goes back to `B`, not to `A`.
+    let parent = type_hierarchy::supertypes(&index, &shared[0]).unwrap();
+    assert_eq!(parent[0].name, "B");
+}
+
+#[test]
+fn references_are_indexed_per_file_and_removed_with_it() {
+    let mut index = index();
+    let drivers = Path::new("/src/drivers/Kconfig");
+    index.analyze_file(
+        Path::new("/src/net/Kconfig"),
+        "config NET_PCI\n\tbool\n\tdepends on PCI && PCI_QUIRKS\n",
+    );
+    let refs: Vec<&str> = index.files[Path::new("/src/net/Kconfig")]
+        .references
+        .iter()
+        .map(|r| r.name.as_str())
+        .collect();
+    assert_eq!(refs, ["PCI", "PCI_QUIRKS"]);
+
+    index.remove_file(drivers);
+    assert!(index.get_definitions("PCI_QUIRKS").is_empty());
+    assert!(!index.all_symbols.iter().any(|s| s == "PCI_QUIRKS"));
+    let files: Vec<&str> = index
+        .get_references("PCI")
+        .iter()
+        .map(|r| r.file.to_str().unwrap())
+        .collect();
+    assert_eq!(files, ["/src/net/Kconfig"]);
+    assert_eq!(index.get_references("PCI_QUIRKS").len(), 1);
+}
+
+#[test]
+fn reanalysis_reports_names_whose_definitions_changed_and_who_references_them() {
+    let mut index = index();
+    let drivers = Path::new("/src/drivers/Kconfig");
+    index.analyze_file(
+        Path::new("/src/net/Kconfig"),
+        "config NET_PCI\n\tbool\n\tdepends on PCI_QUIRKS\n",
+    );
+
+    let changed = index.reanalyze_file(drivers, "config PCI_QUIRKS\n\tbool \"Quirks\"\n");
+    assert!(changed.is_empty());
+
+    let changed = index.reanalyze_file(drivers, "config PCI_FIXUPS\n\tbool\n");
+    let names: Vec<&str> = changed.iter().map(|s| s.as_str()).collect();
+    assert_eq!(names, ["PCI_FIXUPS", "PCI_QUIRKS"]);
+    let files: Vec<_> = index.files_referencing(&changed).into_iter().collect();
+    assert_eq!(files, [Path::new("/src/net/Kconfig")]);
+}
+
+#[test]
+fn diagnostics_from_an_older_epoch_never_replace_newer_ones() {
+    use kconfig_lsp::server::DiagnosticEpochs;
+
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/Kconfig");
+    let uri = uri::from_path(path).unwrap();
+    index.analyze_file(path, "config A\n\tbool\n");
+    let before = index.epoch;
+    index.reanalyze_file(path, "config B\n\tbool\n");
+    let after = index.epoch;
+    assert!(after > before);
+
+    // Diagnostics computed after the edit finish first; those computed
+    // before it, finishing late, are dropped.
+    let mut epochs = DiagnosticEpochs::default();
+    assert!(epochs.admit(&uri, after));
+    assert!(!epochs.admit(&uri, before));
+    assert!(epochs.admit(&uri, after));
+
+    // Removing a file moves the epoch on too; removing it again does not.
+    index.remove_file(path);
+    let removed = index.epoch;
+    assert!(removed > after);
+    index.remove_file(path);
+    assert_eq!(index.epoch, removed);
+    assert!(epochs.admit(&uri, removed));
+
+    // Once forgotten, as when the document is closed, any epoch is shown.
+    epochs.forget(&uri);
+    assert!(epochs.admit(&uri, before));
+}
+
+#[test]
+fn hover_footer_and_index_stats_name_the_tree() {
+    use kconfig_lsp::index_stats;
+    use kconfig_lsp::profile::Profile;
+
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.root_kconfig = Some("/src/Kconfig".into());
+    index.profile = Profile::Linux;
+    for (name, value) in [("SRCARCH", "x86"), ("KERNELVERSION", "6.9.0")] {
+        index
+            .preprocessor
+            .variables
+            .insert(name.into(), value.into());
+    }
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "mainmenu \"Linux/$(SRCARCH) $(KERNELVERSION) Kernel Configuration\"\n\nsource \"arch/$(SRCARCH)/Kconfig\"\n",
+    );
+    index.analyze_file(Path::new("/src/arch/x86/Kconfig"), ARCH_X86);
+    index.analyze_file(Path::new("/src/arch/arm/Kconfig"), ARCH_ARM);
+
+    let hover = hover::hover(
+        &index,
+        Path::new("/src/arch/x86/Kconfig"),
+        Position::new(0, 8),
+        MarkupKind::Markdown,
+        &HoverSettings::default(),
+    )
+    .expect("hover");
+    let HoverContents::Markup(m) = hover.contents else {
+        panic!("expected markup");
+    };
+    assert!(
+        m.value
+            .ends_with("\n\n---\n\n*Linux/x86 6.9.0 Kernel Configuration*"),
+        "{}",
+        m.value
+    );
+
+    let tree = index_stats::index_stats(&index, false, Vec::new())
+        .tree
+        .expect("tree");
+    assert_eq!(
+        serde_json::to_value(&tree).unwrap(),
+        serde_json::json!({
+            "title": "Linux/x86 6.9.0 Kernel Configuration",
+            "root": "/src/Kconfig",
+            "sourcedFiles": 2,
+            "arch": "x86",
+            "profile": "linux",
+        })
+    );
+}
+
+#[test]
+fn hover_off_the_symbols_of_a_dependency_line_breaks_it_down() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(
+        path,
+        "config A\n\tbool \"A\"\n\nconfig B\n\tbool\n\nconfig C\n\tbool\n\nconfig D\n\ttristate\n\nconfig E\n\tbool \"E\"\n\tdepends on A && (B || C) && D != n\n",
+    );
+    let text = |index: &WorldIndex, character: u32, kind: MarkupKind| {
+        let hover = hover::hover(
+            index,
+            path,
+            Position::new(14, character),
+            kind,
+            &HoverSettings::default(),
+        )
+        .expect("hover");
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markup hover");
+        };
+        markup.value
+    };
+
+    // On the keyword and on an operator; a symbol keeps its own hover.
+    for character in [3, 14] {
+        assert_eq!(
+            text(&index, character, MarkupKind::Markdown),
+            "**depends on**, all of:\n\n\
+             - `A` ([`Kconfig:1`](file:///src/Kconfig#L1))\n\
+             - `(B || C)`, any of:\n  \
+               - `B` ([`Kconfig:4`](file:///src/Kconfig#L4))\n  \
+               - `C` ([`Kconfig:7`](file:///src/Kconfig#L7))\n\
+             - `D != n`\n  \
+               - `D` ([`Kconfig:10`](file:///src/Kconfig#L10))"
+        );
+    }
+    assert!(text(&index, 12, MarkupKind::Markdown).starts_with("**A** (config)"));
+
+    index.config = Some(DotConfig::parse(
+        Path::new("/src/.config"),
+        "CONFIG_A=y\nCONFIG_C=y\nCONFIG_D=m\n",
+        "CONFIG_",
+    ));
+    assert_eq!(
+        text(&index, 16, MarkupKind::PlainText),
+        "depends on = y, all of:\n\n\
+         - A = y (Kconfig:1)\n\
+         - (B || C) = y, any of:\n  \
+           - B = n (Kconfig:4)\n  \
+           - C = y (Kconfig:7)\n\
+         - D != n = y\n  \
+           - D = m (Kconfig:10)"
+    );
+}
+
+#[test]
+fn definition_of_a_macro_reference_is_its_assignment() {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    let include = Path::new("/src/scripts/Kconfig.include");
+    index.analyze_file(
+        include,
+        "cc-option = $(success,$(CC) -Werror $(1) -S -x c /dev/null -o /dev/null)\n",
+    );
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(
+        path,
+        "source \"scripts/Kconfig.include\"\n\nconfig CC_HAS_M32\n\tdef_bool $(cc-option,-m32)\n",
+    );
+
+    let Some(GotoDefinitionResponse::Scalar(location)) =
+        definition::goto_definition(&index, path, Position::new(3, 14))
+    else {
+        panic!("expected one location");
+    };
+    assert_eq!(location.uri.path(), "/src/scripts/Kconfig.include");
+    assert_eq!(location.range.start, Position::new(0, 0));
+    assert_eq!(location.range.end, Position::new(0, 9));
+}
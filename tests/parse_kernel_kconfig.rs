@@ -1,6 +1,6 @@
 use kconfig_lsp::analysis::WorldIndex;
 use kconfig_lsp::ast::*;
-use kconfig_lsp::lexer::Lexer;
+use kconfig_lsp::lexer::{Dialect, Lexer};
 use kconfig_lsp::parser;
 use std::path::Path;
 
@@ -166,6 +166,69 @@ fn parser_produces_correct_entries() {
     assert!(errors.is_empty(), "unexpected parse errors: {:?}", errors);
 }
 
+#[test]
+fn choice_attributes_may_follow_members() {
+    let src = "choice\n\tprompt \"Mode\"\n\nif ARM\nconfig MODE_A\n\tbool \"A\"\nendif\n\n\tdefault MODE_A\n\tdepends on ARM || X86\nendchoice\n";
+    let result = parser::parse(src, Lexer::new(src).tokenize());
+    assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+    let Entry::Choice(choice) = &result.file.entries[0] else {
+        panic!("expected a choice");
+    };
+    assert_eq!(choice.entries.len(), 1);
+    let kinds: Vec<(&str, bool)> = choice
+        .attributes
+        .iter()
+        .map(|a| {
+            let kind = match a {
+                Attribute::Prompt { .. } => "prompt",
+                Attribute::Default { .. } => "default",
+                Attribute::DependsOn { .. } => "depends on",
+                _ => "other",
+            };
+            (kind, a.span().start > choice.entries[0].span().end)
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [("prompt", false), ("default", true), ("depends on", true)]
+    );
+}
+
+#[test]
+fn help_and_entry_spans_end_at_their_last_line_of_content() {
+    for src in [
+        SAMPLE_KCONFIG.to_string(),
+        SAMPLE_KCONFIG.replace('\n', "\r\n"),
+    ] {
+        let result = parser::parse(&src, Lexer::new(&src).tokenize());
+        let mut checked = 0;
+        for entry in &result.file.entries {
+            let (Entry::Config(c) | Entry::MenuConfig(c)) = entry else {
+                continue;
+            };
+            for span in c
+                .attributes
+                .iter()
+                .filter(|a| matches!(a, Attribute::Help(_)))
+                .map(Attribute::span)
+                .chain([c.span])
+            {
+                let last = src[..span.end].chars().next_back().unwrap();
+                assert!(!last.is_whitespace(), "{} ends in whitespace", c.name);
+                let rest = src[span.end..].split('\n').next().unwrap();
+                assert!(
+                    rest.trim().is_empty(),
+                    "{} ends mid-line: {:?}",
+                    c.name,
+                    rest
+                );
+                checked += 1;
+            }
+        }
+        assert!(checked > 4, "{}", checked);
+    }
+}
+
 #[test]
 fn analysis_finds_all_symbols() {
     let tokens = Lexer::new(SAMPLE_KCONFIG).tokenize();
@@ -289,3 +352,111 @@ fn debug_help_consumption() {
         names
     );
 }
+
+#[test]
+fn leading_comments_attach_to_config_entries() {
+    let src = "config A\n\tbool # trailing\n\n# Enables the frobnicator.\n# See frob.c.\nconfig FROB\n\tbool\n\n# Unrelated section header\n\nconfig PLAIN\n\tbool\n";
+    let mut index = WorldIndex::new();
+    index.analyze_file(Path::new("test/Kconfig"), src);
+
+    assert_eq!(
+        index.get_definitions("FROB")[0].doc_comment.as_deref(),
+        Some("Enables the frobnicator.\nSee frob.c.")
+    );
+    assert_eq!(index.get_definitions("PLAIN")[0].doc_comment, None);
+    assert_eq!(index.get_definitions("A")[0].doc_comment, None);
+}
+
+#[test]
+fn build_index_reads_workspace_and_honours_cancellation() {
+    use kconfig_lsp::server::build_index;
+    use kconfig_lsp::settings::Settings;
+    use std::sync::atomic::AtomicBool;
+
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-index-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("drivers")).unwrap();
+    std::fs::write(root.join("Kconfig"), "source \"drivers/Kconfig\"\n").unwrap();
+    std::fs::write(root.join("drivers/Kconfig"), "config DRV\n\tbool\n").unwrap();
+    std::fs::write(root.join("drivers/Makefile"), "obj-y += drv.o\n").unwrap();
+
+    let reports = std::cell::Cell::new(0);
+    let (index, files) = build_index(
+        std::slice::from_ref(&root),
+        &Settings::default().file_names,
+        None,
+        0,
+        Dialect::Kconfig,
+        &AtomicBool::new(false),
+        |_, total| {
+            assert_eq!(total, 2);
+            reports.set(reports.get() + 1);
+        },
+    )
+    .expect("not cancelled");
+    assert_eq!(files.len(), 2);
+    assert_eq!(reports.get(), 2);
+    assert_eq!(index.get_definitions("DRV").len(), 1);
+
+    assert!(
+        build_index(
+            std::slice::from_ref(&root),
+            &Settings::default().file_names,
+            None,
+            0,
+            Dialect::Kconfig,
+            &AtomicBool::new(true),
+            |_, _| {}
+        )
+        .is_none()
+    );
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn oversized_and_binary_files_are_skipped_and_reported() {
+    use kconfig_lsp::index_stats;
+    use kconfig_lsp::server::build_index;
+    use kconfig_lsp::settings::Settings;
+    use std::sync::atomic::AtomicBool;
+
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-skip-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("Kconfig"), "config SMALL\n\tbool\n").unwrap();
+    std::fs::write(root.join("Kconfig.big"), "# generated\n".repeat(100)).unwrap();
+    std::fs::write(root.join("Kconfig.bin"), b"\x7fELF\0\0\0").unwrap();
+    std::fs::write(root.join("Kconfig.latin1"), b"# caf\xe9\n").unwrap();
+
+    let (index, _) = build_index(
+        std::slice::from_ref(&root),
+        &Settings::default().file_names,
+        None,
+        1000,
+        Dialect::Kconfig,
+        &AtomicBool::new(false),
+        |_, _| {},
+    )
+    .unwrap();
+    let stats = index_stats::index_stats(&index, false, Vec::new());
+    assert_eq!(stats.files, 1);
+    assert_eq!(stats.definitions, 1);
+    let skipped: Vec<(String, &str)> = stats
+        .skipped
+        .iter()
+        .map(|s| {
+            let name = s.path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, s.reason.as_str())
+        })
+        .collect();
+    assert_eq!(
+        skipped,
+        [
+            (
+                "Kconfig.big".to_string(),
+                "1200 bytes, over the `maxFileSize` limit of 1000"
+            ),
+            ("Kconfig.bin".to_string(), "binary content"),
+            ("Kconfig.latin1".to_string(), "not valid UTF-8 (at byte 5)"),
+        ]
+    );
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,385 @@
+//! Expression parse trees checked against kconfig's own grammar
+//! (`scripts/kconfig/parser.y`).
+
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::ast::*;
+use kconfig_lsp::diagnostics;
+use kconfig_lsp::lexer::{Dialect, Lexer};
+use kconfig_lsp::parser;
+use kconfig_lsp::settings::{DiagnosticRanges, Settings};
+use std::path::Path;
+use tower_lsp::lsp_types::Position;
+
+/// The `depends on` expression of a one-line config, and the parse errors.
+fn parse(expr: &str) -> (Expr, Vec<String>) {
+    let src = format!("config X\n\tbool\n\tdepends on {}\n", expr);
+    let result = parser::parse(&src, Lexer::new(&src).tokenize());
+    let Entry::Config(c) = &result.file.entries[0] else {
+        panic!("expected a config");
+    };
+    let expr = c
+        .attributes
+        .iter()
+        .find_map(|a| match a {
+            Attribute::DependsOn(d) => Some(d.expr.clone()),
+            _ => None,
+        })
+        .expect("depends on");
+    let errors = result.diagnostics.into_iter().map(|d| d.message).collect();
+    (expr, errors)
+}
+
+/// The tree fully parenthesized, e.g. `(!(A = B) && C)`.
+fn tree(e: &Expr) -> String {
+    let binary = |op: &str, a: &Expr, b: &Expr| format!("({} {} {})", tree(a), op, tree(b));
+    match e {
+        Expr::Symbol(s, _) => s.clone(),
+        Expr::StringLit(s, _) => format!("\"{}\"", s),
+        Expr::Not(e) => format!("!{}", tree(e)),
+        Expr::Paren(e) => tree(e),
+        Expr::And(a, b) => binary("&&", a, b),
+        Expr::Or(a, b) => binary("||", a, b),
+        Expr::Eq(a, b) => binary("=", a, b),
+        Expr::NotEq(a, b) => binary("!=", a, b),
+        Expr::Less(a, b) => binary("<", a, b),
+        Expr::LessEq(a, b) => binary("<=", a, b),
+        Expr::Greater(a, b) => binary(">", a, b),
+        Expr::GreaterEq(a, b) => binary(">=", a, b),
+    }
+}
+
+fn parses_as(expr: &str, expected: &str) {
+    let (e, errors) = parse(expr);
+    assert!(errors.is_empty(), "{}: {:?}", expr, errors);
+    assert_eq!(tree(&e), expected, "{}", expr);
+}
+
+#[test]
+fn and_binds_tighter_than_or_and_both_group_left() {
+    parses_as("A || B && C", "(A || (B && C))");
+    parses_as("A && B || C && D", "((A && B) || (C && D))");
+    parses_as("A || B || C", "((A || B) || C)");
+    parses_as("(A || B) && C", "((A || B) && C)");
+}
+
+#[test]
+fn not_negates_the_whole_comparison_after_it() {
+    parses_as("!A = B", "!(A = B)");
+    parses_as("!A != y && B", "(!(A != y) && B)");
+    parses_as("!A || B", "(!A || B)");
+    parses_as("!!A = B", "!!(A = B)");
+    parses_as("!(A) && B", "(!A && B)");
+}
+
+#[test]
+fn comparisons_bind_tighter_than_and() {
+    parses_as("A = y && B < 10", "((A = y) && (B < 10))");
+    parses_as("A >= \"4\" || B", "((A >= \"4\") || B)");
+}
+
+#[test]
+fn chained_comparisons_and_compared_subexpressions_are_errors() {
+    let (_, errors) = parse("A = B = C");
+    assert_eq!(
+        errors,
+        ["comparisons cannot be chained; kconfig rejects `A = B = C`"]
+    );
+    let (_, errors) = parse("(A || B) = y");
+    assert_eq!(errors, ["only a symbol or a string can be compared"]);
+    let (_, errors) = parse("A = !B");
+    assert_eq!(errors, ["only a symbol or a string can be compared"]);
+}
+
+#[test]
+fn errors_spanning_lines_underline_the_first_line_unless_full_ranges_are_asked_for() {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        path,
+        "config X\n\tbool\n\tdepends on (A || \\\n\t\tB)   \\\n\t\t= y\n",
+    );
+    let range = |ranges| {
+        let settings = Settings {
+            diagnostic_ranges: ranges,
+            ..Settings::default()
+        };
+        let diags = diagnostics::collect(&index, path, &settings);
+        let diag = diags
+            .iter()
+            .find(|d| d.message == "only a symbol or a string can be compared")
+            .expect("comparison error");
+        (diag.range.start, diag.range.end)
+    };
+    assert_eq!(
+        range(DiagnosticRanges::FirstLine),
+        (Position::new(2, 13), Position::new(2, 17))
+    );
+    assert_eq!(
+        range(DiagnosticRanges::Full),
+        (Position::new(2, 13), Position::new(3, 3))
+    );
+}
+
+#[test]
+fn deep_nesting_is_cut_off_with_one_error() {
+    let deep = format!("{}A{}", "(".repeat(10_000), ")".repeat(10_000));
+    let (_, errors) = parse(&deep);
+    assert_eq!(
+        errors,
+        ["expression nested more than 128 levels deep; the rest of the line is ignored"]
+    );
+    let (_, errors) = parse(&format!("{}A", "!".repeat(128)));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    // The entries after the line still parse.
+    let src = format!(
+        "config X\n\tbool\n\tdepends on {}\n\nconfig Y\n\tbool\n",
+        deep
+    );
+    let result = parser::parse(&src, Lexer::new(&src).tokenize());
+    assert_eq!(result.file.entries.len(), 2);
+}
+
+#[test]
+fn long_chains_are_cut_off_with_one_error() {
+    let names: Vec<String> = (0..5000).map(|i| format!("A{}", i)).collect();
+    let (expr, errors) = parse(&names.join(" && "));
+    assert_eq!(
+        errors,
+        ["expression has more than 1024 operands; the rest of the line is ignored"]
+    );
+    let mut symbols = Vec::new();
+    expr.collect_symbols(&mut symbols);
+    assert_eq!(symbols.len(), 1025);
+    assert_eq!(symbols[1023].0, "A1023");
+
+    let (expr, errors) = parse(&names[..1024].join(" || "));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(expr.to_string(), names[..1024].join(" || "));
+}
+
+/// Random expressions, some over the limits, go through parsing, analysis,
+/// diagnostics, hover, and evaluation on a stack the size of a tokio
+/// worker's without overflowing it.
+#[test]
+fn generated_expressions_do_not_overflow_the_stack() {
+    use kconfig_lsp::dotconfig::DotConfig;
+    use kconfig_lsp::eval::Evaluator;
+    use kconfig_lsp::hover;
+    use kconfig_lsp::settings::HoverSettings;
+    use tower_lsp::lsp_types::MarkupKind;
+
+    fn next(seed: &mut u64, n: u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*seed >> 33) % n
+    }
+
+    /// An expression up to `depth` levels deep, with at most `budget`
+    /// operands left to spend.
+    fn generate(seed: &mut u64, depth: usize, budget: &mut usize, out: &mut String) {
+        *budget = budget.saturating_sub(1);
+        match next(seed, 8) {
+            _ if depth == 0 || *budget == 0 => out.push_str(&format!("S{}", next(seed, 20))),
+            0 | 1 => {
+                out.push('(');
+                generate(seed, depth - 1, budget, out);
+                out.push(')');
+            }
+            2 => {
+                out.push('!');
+                generate(seed, depth - 1, budget, out);
+            }
+            3 => out.push_str(&format!("S{} != \"x\"", next(seed, 20))),
+            k => {
+                let terms = 1 + next(seed, if k == 7 { 1500 } else { 4 });
+                for i in 0..terms {
+                    if i > 0 {
+                        out.push_str(if next(seed, 2) == 0 { " && " } else { " || " });
+                    }
+                    generate(seed, depth - 1, budget, out);
+                }
+            }
+        }
+    }
+
+    let run = || {
+        let mut seed = 0x5eed;
+        let path = Path::new("/src/Kconfig");
+        let config = DotConfig::parse(Path::new(""), "CONFIG_S1=y\nCONFIG_S2=m\n", "CONFIG_");
+        let mut truncated = 0;
+        for round in 0..40 {
+            let mut expr = String::new();
+            generate(&mut seed, 20 + round * 10, &mut 3000, &mut expr);
+            let src = format!(
+                "config S1\n\tbool\n\nconfig S2\n\ttristate\n\nconfig X\n\tbool \"X\"\n\tdepends on {}\n\tdefault y if {}\n",
+                expr, expr
+            );
+            let mut index = WorldIndex::new();
+            index.analyze_file(path, &src);
+            let diags = diagnostics::collect(&index, path, &Settings::default());
+            if diags
+                .iter()
+                .any(|d| d.message.ends_with("the rest of the line is ignored"))
+            {
+                truncated += 1;
+            }
+            let hover_settings = HoverSettings::default();
+            hover::hover(
+                &index,
+                path,
+                Position::new(6, 8),
+                MarkupKind::Markdown,
+                &hover_settings,
+            );
+            let eval = Evaluator::new(&index, &config);
+            for def in index.get_definitions("X") {
+                eval.dependencies(def);
+                for e in &def.depends_on {
+                    let _ = (e.to_string(), e.span());
+                }
+            }
+        }
+        truncated
+    };
+    let truncated = std::thread::Builder::new()
+        .stack_size(2 << 20)
+        .spawn(run)
+        .unwrap()
+        .join()
+        .expect("no stack overflow");
+    assert!(truncated > 0 && truncated < 40, "{} truncated", truncated);
+}
+
+#[test]
+fn files_of_only_macros_parse_into_their_assignments() {
+    let src = "# Kconfig helper macros\n\ncomma := ,\nquote := \"\nif-success = $(shell,{ $(1); } >/dev/null 2>&1 && echo \"$(2)\" || echo \"$(3)\")\nsuccess = $(if-success,$(1),y,n)\n\n$(error-if,$(failure,command -v $(CC)),C compiler '$(CC)' not found)\ncc-flags += -m32\n\nconfig X\n\tbool\n\thelp\n\t  x := y is not an assignment here.\n";
+    let result = parser::parse(src, Lexer::new(src).tokenize());
+    let errors: Vec<String> = result.diagnostics.into_iter().map(|d| d.message).collect();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let assigned: Vec<(&str, &str)> = result
+        .assignments
+        .iter()
+        .map(|a| (a.name.as_str(), a.value.as_str()))
+        .collect();
+    assert_eq!(
+        assigned,
+        [
+            ("comma", ","),
+            ("quote", "\""),
+            (
+                "if-success",
+                "$(shell,{ $(1); } >/dev/null 2>&1 && echo \"$(2)\" || echo \"$(3)\")"
+            ),
+            ("success", "$(if-success,$(1),y,n)"),
+            ("cc-flags", "-m32"),
+        ]
+    );
+    assert_eq!(result.file.entries.len(), 1);
+}
+
+#[test]
+fn kconfiglib_typed_defaults_are_keywords_only_in_its_dialect() {
+    let src = "config COUNT\n\tdef_int 5\n\nconfig BASE\n\tdef_hex 0x1000\n\nconfig NAME\n\tdef_string \"board\" if COUNT > 1\n";
+    let result = parser::parse(
+        src,
+        Lexer::new(src).with_dialect(Dialect::Kconfiglib).tokenize(),
+    );
+    assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+    let kinds: Vec<(TypeKind, bool)> = result
+        .file
+        .entries
+        .iter()
+        .filter_map(|e| match e {
+            Entry::Config(c) => c.attributes.iter().find_map(|a| match a {
+                Attribute::DefType(d) => Some((d.kind, d.condition.is_some())),
+                _ => None,
+            }),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (TypeKind::Int, false),
+            (TypeKind::Hex, false),
+            (TypeKind::String, true)
+        ]
+    );
+
+    let result = parser::parse(src, Lexer::new(src).tokenize());
+    assert!(!result.diagnostics.is_empty());
+}
+
+#[test]
+fn misplaced_statements_are_explained_and_fixed() {
+    use kconfig_lsp::code_action;
+    use tower_lsp::lsp_types::{CodeActionOrCommand, Range, Url};
+
+    let src = "# SPDX-License-Identifier: GPL-2.0\n\n# Networking\nconfig NET\n\tbool\nendif\n\n\tdepends on NET\n\thelp\n\t  Stray help.\nmainmenu \"Linux\"\n";
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+    let fa = &index.files[path];
+    let found: Vec<(&str, &str)> = fa
+        .diagnostics
+        .iter()
+        .map(|d| (d.message.as_str(), &src[d.span.start..d.span.end]))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                "`endif` without a matching `if`; remove it or add the `if` it closes",
+                "endif"
+            ),
+            (
+                "`depends on` outside any entry; move it under the `config`, `choice` or `menu` it belongs to",
+                "depends"
+            ),
+            (
+                "`help` outside any entry; move it under the `config`, `choice` or `menu` it belongs to",
+                "help"
+            ),
+            (
+                "`mainmenu` must be the first statement; kconfig rejects it after others",
+                "mainmenu"
+            ),
+        ]
+    );
+
+    let uri = Url::parse("file:///src/Kconfig").unwrap();
+    let fix = |line| {
+        let at = Position::new(line, 1);
+        code_action::code_actions(&index, &uri, path, Range::new(at, at), &Settings::default())
+            .into_iter()
+            .find_map(|a| match a {
+                CodeActionOrCommand::CodeAction(a) if a.kind == Some("quickfix".into()) => Some(a),
+                _ => None,
+            })
+            .map(|a| {
+                let edits = &a.edit.unwrap().changes.unwrap()[&uri];
+                let texts: Vec<(u32, String)> = edits
+                    .iter()
+                    .map(|e| (e.range.start.line, e.new_text.clone()))
+                    .collect();
+                (a.title, texts)
+            })
+    };
+    assert_eq!(
+        fix(5),
+        Some((
+            "Remove the stray `endif`".to_string(),
+            vec![(5, String::new())]
+        ))
+    );
+    assert_eq!(
+        fix(10),
+        Some((
+            "Move `mainmenu` to the top of the file".to_string(),
+            vec![(2, "mainmenu \"Linux\"\n".to_string()), (10, String::new())]
+        ))
+    );
+    assert_eq!(fix(7), None);
+}
@@ -0,0 +1,81 @@
+use kconfig_lsp::patch::{Patch, RemovedSymbol};
+use std::path::Path;
+
+const DIFF: &str = "\
+diff --git a/drivers/Kconfig b/drivers/Kconfig
+index 1111111..2222222 100644
+--- a/drivers/Kconfig
++++ b/drivers/Kconfig
+@@ -1,5 +1,4 @@
+-config OLD_DRIVER
+-\tbool
+-
+ config NEW_DRIVER
+ \tbool
++\tdepends on PCI
++--- not a header
+diff --git a/net/Kconfig b/net/Kconfig
+new file mode 100644
+--- /dev/null
++++ b/net/Kconfig
+@@ -0,0 +1 @@
++config NET
+";
+
+#[test]
+fn parse_tracks_added_lines_and_removed_definitions() {
+    let patch = Patch::parse(DIFF);
+    assert_eq!(patch.files.len(), 2);
+
+    let drivers = &patch.files[0];
+    assert_eq!(
+        drivers.old_path.as_deref(),
+        Some(Path::new("drivers/Kconfig"))
+    );
+    assert_eq!(
+        drivers.new_path.as_deref(),
+        Some(Path::new("drivers/Kconfig"))
+    );
+    assert_eq!(drivers.added, [3, 4]);
+    assert!(drivers.adds_line(2));
+    assert!(!drivers.adds_line(0));
+    assert_eq!(
+        drivers.removed_symbols,
+        [RemovedSymbol {
+            name: "OLD_DRIVER".into(),
+            line: 1
+        }]
+    );
+
+    let net = &patch.files[1];
+    assert_eq!(net.old_path, None);
+    assert_eq!(net.new_path.as_deref(), Some(Path::new("net/Kconfig")));
+    assert_eq!(net.added, [1]);
+}
+
+#[test]
+fn parse_ends_a_miscounted_hunk_at_the_next_file() {
+    let patch = Patch::parse(
+        "\
+diff --git a/a/Kconfig b/a/Kconfig
+--- a/a/Kconfig
++++ b/a/Kconfig
+@@ -1,1 +1,10 @@
+ config A
++\tbool
+diff --git a/b/Kconfig b/b/Kconfig
+--- a/b/Kconfig
++++ b/b/Kconfig
+@@ -1 +1,2 @@
+ config B
++\tbool
+",
+    );
+    assert_eq!(patch.files.len(), 2);
+    assert_eq!(patch.files[0].added, [2]);
+    assert_eq!(
+        patch.files[1].new_path.as_deref(),
+        Some(Path::new("b/Kconfig"))
+    );
+    assert_eq!(patch.files[1].added, [2]);
+}
@@ -0,0 +1,79 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::project_stats::{self, ProjectStatsParams};
+use std::path::Path;
+
+fn index() -> WorldIndex {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "config PCI\n\tbool \"PCI\"\n\nif PCI\nconfig PCIEPORTBUS\n\tbool \"PCIe\"\nendif\n",
+    );
+    index.analyze_file(
+        Path::new("/src/drivers/net/Kconfig"),
+        "config E1000\n\ttristate \"E1000\"\n\tdepends on PCIEPORTBUS\n\thelp\n\t  Intel gigabit.\n\n\t  Say M.\n\nconfig E100\n\ttristate\n\tdepends on PCI\n\thelp\n\t  Intel 10/100.\n\nconfig IGB\n\ttristate\n\tdepends on PCI\n",
+    );
+    index.analyze_file(
+        Path::new("/src/drivers/net/Kconfig.acme"),
+        "config ACME\n\tbool \"Acme\n",
+    );
+    index
+}
+
+#[test]
+fn stats_rank_directories_and_offenders() {
+    let index = index();
+    let stats = project_stats::project_stats(&index, &ProjectStatsParams { limit: Some(2) });
+
+    let dirs: Vec<(&str, usize, usize)> = stats
+        .directories
+        .iter()
+        .map(|d| (d.path.to_str().unwrap(), d.files, d.symbols))
+        .collect();
+    assert_eq!(dirs, [("drivers/net", 2, 4), ("", 1, 2)]);
+
+    assert_eq!(stats.parse_diagnostics.len(), 1);
+    assert_eq!(
+        stats.parse_diagnostics[0].path,
+        Path::new("drivers/net/Kconfig.acme")
+    );
+
+    let referenced: Vec<(&str, usize)> = stats
+        .most_referenced
+        .iter()
+        .map(|s| (s.symbol.as_str(), s.count))
+        .collect();
+    assert_eq!(referenced, [("PCI", 3), ("PCIEPORTBUS", 1)]);
+
+    assert_eq!(
+        stats.deepest_chains[0].chain,
+        ["E1000", "PCIEPORTBUS", "PCI"]
+    );
+    assert_eq!(stats.deepest_chains[1].chain.len(), 2);
+
+    let help: Vec<(&str, usize)> = stats
+        .largest_help
+        .iter()
+        .map(|h| (h.symbol.as_str(), h.lines))
+        .collect();
+    assert_eq!(help, [("E1000", 3), ("E100", 1)]);
+
+    let text = project_stats::render_text(&stats);
+    assert!(
+        text.starts_with("Symbols by directory\n  4  drivers/net (2 files)\n  2  . (1 file)\n")
+    );
+    assert!(text.contains("\nDeepest dependency chains\n  3  E1000 -> PCIEPORTBUS -> PCI\n"));
+}
+
+#[test]
+fn dependency_loops_end_the_chain() {
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "config A\n\tbool\n\tdepends on B\n\nconfig B\n\tbool\n\tdepends on A\n",
+    );
+    let stats = project_stats::project_stats(&index, &ProjectStatsParams::default());
+    for chain in &stats.deepest_chains {
+        assert_eq!(chain.chain.len(), 2, "{:?}", chain.chain);
+    }
+}
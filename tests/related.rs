@@ -0,0 +1,82 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::related::{self, RelatedSymbolsParams, Relation};
+use std::path::Path;
+
+const KCONFIG: &str = r#"config NET
+	bool "Networking support"
+
+config ETHERNET
+	bool "Ethernet"
+	select CRC32
+	select PHYLIB
+
+if NET
+
+config INET
+	bool "TCP/IP networking"
+
+config IPV6
+	bool "IPv6"
+	depends on INET
+
+endif
+
+config NETFILTER
+	bool "Netfilter"
+	depends on NET && INET
+	help
+	  Packet filtering for CONFIG_INET and IPv6.
+
+config CRC32
+	tristate
+
+config PHYLIB
+	tristate "PHY Device support"
+
+config UNRELATED
+	bool "Unrelated"
+"#;
+
+fn related(symbol: &str) -> Vec<(String, Vec<Relation>)> {
+    let mut index = WorldIndex::new();
+    index.analyze_file(Path::new("/src/Kconfig"), KCONFIG);
+    let params = RelatedSymbolsParams {
+        symbol: symbol.into(),
+        limit: None,
+    };
+    related::related_symbols(&index, &params)
+        .into_iter()
+        .map(|r| (r.symbol, r.relations))
+        .collect()
+}
+
+#[test]
+fn symbols_under_the_same_conditions_are_related() {
+    let ipv6 = related("IPV6");
+    let names: Vec<&str> = ipv6.iter().map(|(s, _)| s.as_str()).collect();
+    assert_eq!(names, ["NETFILTER", "INET"]);
+    assert_eq!(
+        ipv6[0].1,
+        [Relation::SharedDependency {
+            terms: vec!["INET".into(), "NET".into()]
+        }]
+    );
+}
+
+#[test]
+fn symbols_selected_together_and_help_mentions_are_related() {
+    assert_eq!(
+        related("CRC32"),
+        [(
+            "PHYLIB".to_string(),
+            vec![Relation::SelectedAlongside {
+                by: vec!["ETHERNET".into()]
+            }]
+        )]
+    );
+
+    let inet = related("INET");
+    let netfilter = inet.iter().find(|(s, _)| s == "NETFILTER").unwrap();
+    assert!(netfilter.1.contains(&Relation::MentionedInHelp));
+    assert!(!inet.iter().any(|(s, _)| s == "UNRELATED"));
+}
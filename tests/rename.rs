@@ -0,0 +1,148 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::rename::{self, BuildFiles};
+use std::path::Path;
+use tower_lsp::lsp_types::*;
+
+const ROOT: &str =
+    "config USB\n\ttristate \"USB support\"\n\nconfig HID\n\tbool\n\tdefault y if USB\n";
+const DRIVERS: &str = "config USB_STORAGE\n\ttristate\n\tdepends on USB && y\n";
+
+fn index(root: &Path) -> WorldIndex {
+    let mut index = WorldIndex::new();
+    index.analyze_file(&root.join("Kconfig"), ROOT);
+    index.analyze_file(&root.join("drivers/Kconfig"), DRIVERS);
+    index
+}
+
+#[test]
+fn prepare_rename_refuses_keywords_and_tristate_values() {
+    let root = Path::new("/src");
+    let index = index(root);
+    let drivers = root.join("drivers/Kconfig");
+
+    assert_eq!(
+        rename::prepare_rename(&index, &drivers, Position::new(2, 2)),
+        Err("`depends` is a keyword".to_string())
+    );
+    assert_eq!(
+        rename::prepare_rename(&index, &drivers, Position::new(2, 19)),
+        Err("`y` is a tristate value, not a symbol".to_string())
+    );
+    assert_eq!(
+        rename::prepare_rename(&index, &drivers, Position::new(2, 13)),
+        Ok(Range {
+            start: Position::new(2, 12),
+            end: Position::new(2, 15),
+        })
+    );
+}
+
+#[test]
+fn rename_covers_definitions_references_and_confirmed_build_files() {
+    let root = std::env::temp_dir().join(format!("kconfig-lsp-rename-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("drivers")).unwrap();
+    std::fs::write(
+        root.join("drivers/Makefile"),
+        "obj-$(CONFIG_USB) += usb/\nobj-$(CONFIG_USB_STORAGE) += storage/\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("drivers/usb.c"),
+        "#ifdef CONFIG_USB_MODULE\n#endif\n",
+    )
+    .unwrap();
+    let index = index(&root);
+    let drivers = root.join("drivers/Kconfig");
+
+    assert!(rename::rename(&index, &drivers, Position::new(2, 13), "depends", None).is_err());
+
+    let edit = rename::rename(&index, &drivers, Position::new(2, 13), "USB_HOST", None).unwrap();
+    let changes = edit.changes.unwrap();
+    assert_eq!(changes.len(), 2);
+    let root_edits = &changes[&Url::from_file_path(root.join("Kconfig")).unwrap()];
+    assert_eq!(root_edits.len(), 2);
+    assert_eq!(root_edits[0].new_text, "USB_HOST");
+
+    let build = BuildFiles {
+        files: vec![root.join("drivers/Makefile"), root.join("drivers/usb.c")],
+        prefix: "CONFIG_".into(),
+    };
+    let edit = rename::rename(
+        &index,
+        &drivers,
+        Position::new(2, 13),
+        "USB_HOST",
+        Some(&rename::build_file_edits(&build, "USB", "USB_HOST")),
+    )
+    .unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    let annotation = &edit.change_annotations.unwrap()["buildFiles"];
+    assert_eq!(annotation.needs_confirmation, Some(true));
+    assert_eq!(annotation.label, "Rename CONFIG_USB in build files");
+    let Some(DocumentChanges::Edits(docs)) = edit.document_changes else {
+        panic!("expected document edits");
+    };
+    let build_edits: Vec<(String, Range)> = docs
+        .iter()
+        .filter(|d| !d.text_document.uri.path().ends_with("Kconfig"))
+        .flat_map(|d| {
+            d.edits.iter().map(|e| match e {
+                OneOf::Right(a) => (d.text_document.uri.path().to_string(), a.text_edit.range),
+                OneOf::Left(_) => panic!("build file edits need confirmation"),
+            })
+        })
+        .collect();
+    assert_eq!(build_edits.len(), 2, "{:?}", build_edits);
+    assert!(build_edits[0].0.ends_with("drivers/Makefile"));
+    assert_eq!(build_edits[0].1.start, Position::new(0, 13));
+    assert_eq!(build_edits[0].1.end, Position::new(0, 16));
+    assert!(build_edits[1].0.ends_with("drivers/usb.c"));
+    assert_eq!(build_edits[1].1.start, Position::new(0, 14));
+}
+
+#[test]
+fn rename_refuses_names_in_the_tree_and_flags_other_trees() {
+    let root = Path::new("/src");
+    let mut index = index(root);
+    index.root = Some(root.to_path_buf());
+    index.root_kconfig = Some(root.join("Kconfig"));
+    index.analyze_file(&root.join("arch/arm/Kconfig"), "config USB_OTG\n\tbool\n");
+    let drivers = root.join("drivers/Kconfig");
+
+    assert_eq!(
+        rename::rename(&index, &drivers, Position::new(2, 13), "HID", None).unwrap_err(),
+        "`HID` is already defined at Kconfig:4"
+    );
+
+    assert!(rename::rename(&index, &drivers, Position::new(2, 13), "USB_OTG", None).is_ok());
+    let collision = rename::collision(&index, "USB_OTG").unwrap();
+    assert!(!collision.in_tree);
+    assert_eq!(collision.place, "arch/arm/Kconfig:1");
+}
+
+#[test]
+fn menu_titles_link_with_the_comment_on_their_endmenu() {
+    use kconfig_lsp::linked_editing;
+
+    let mut index = WorldIndex::new();
+    let path = Path::new("/src/Kconfig");
+    index.analyze_file(
+        path,
+        "menu \"General setup\"\nmenu \"Timers\"\nendmenu\t#  Timers \nendmenu # \"General setup\"\nmenu \"Power\"\nendmenu # end of Power\n",
+    );
+
+    let range = |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+    let outer = linked_editing::linked_editing_ranges(&index, path, Position::new(0, 9)).unwrap();
+    assert_eq!(outer.ranges, [range(0, 6, 19), range(3, 11, 24)]);
+    let from_comment =
+        linked_editing::linked_editing_ranges(&index, path, Position::new(3, 15)).unwrap();
+    assert_eq!(from_comment.ranges, outer.ranges);
+
+    // An unquoted comment links too, without its surrounding blanks.
+    let inner = linked_editing::linked_editing_ranges(&index, path, Position::new(1, 7)).unwrap();
+    assert_eq!(inner.ranges, [range(1, 6, 12), range(2, 11, 17)]);
+
+    // A comment that does not repeat the title is left alone.
+    assert!(linked_editing::linked_editing_ranges(&index, path, Position::new(4, 7)).is_none());
+}
@@ -0,0 +1,48 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::report;
+use std::path::Path;
+use tower_lsp::lsp_types::Url;
+
+fn index() -> WorldIndex {
+    let mut index = WorldIndex::new();
+    index.root = Some("/src".into());
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "mainmenu \"Test\"\n\nsource \"net/Kconfig\"\n\nif BROKEN\nconfig OLD\n\tbool\nendif\n",
+    );
+    index.analyze_file(
+        Path::new("/src/net/Kconfig"),
+        "config NET\n\tbool \"Networking\"\n\nconfig INET\n\tbool\n\tdepends on NET\n\tselect CRC32\n\nconfig CRC32\n\tbool\n",
+    );
+    index
+}
+
+fn text(index: &WorldIndex, uri: &str) -> String {
+    let uri = Url::parse(uri).unwrap();
+    report::report(index, Some(Path::new("/src/Kconfig")), &uri)
+        .expect("report")
+        .text
+}
+
+#[test]
+fn resolved_tree_inlines_sourced_files() {
+    let text = text(&index(), "kconfig-report://resolved-tree");
+    assert_eq!(
+        text,
+        "mainmenu \"Test\"\n\n# >>> source \"net/Kconfig\" (/src/net/Kconfig)\nconfig NET\n\tbool \"Networking\"\n\nconfig INET\n\tbool\n\tdepends on NET\n\tselect CRC32\n\nconfig CRC32\n\tbool\n# <<< net/Kconfig\n\nif BROKEN\nconfig OLD\n\tbool\nendif\n"
+    );
+}
+
+#[test]
+fn symbol_report_lists_users_by_kind() {
+    let text = text(&index(), "kconfig-report://symbol/CRC32");
+    assert!(text.starts_with("# CRC32\n\n## Definitions (1)\n\n### /src/net/Kconfig:9\n"));
+    assert!(text.contains("## Selected by\n\n- `INET` (/src/net/Kconfig:7)"));
+}
+
+#[test]
+fn dead_branches_report_undefined_conditions() {
+    let text = text(&index(), "kconfig-report://dead-branches");
+    assert!(text.contains("- `if BROKEN` (/src/Kconfig:5, 1 entry)"));
+    assert!(!text.contains("NET"));
+}
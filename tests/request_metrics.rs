@@ -0,0 +1,44 @@
+use kconfig_lsp::request_metrics::RequestMetrics;
+use std::time::Duration;
+
+#[test]
+fn percentiles_cover_the_recent_requests_and_the_mean_all_of_them() {
+    let metrics = RequestMetrics::default();
+    for ms in 1..=100 {
+        metrics.record("textDocument/completion", Duration::from_millis(ms));
+    }
+    metrics.record("textDocument/hover", Duration::from_millis(4));
+
+    let latencies = metrics.latencies();
+    let methods: Vec<&str> = latencies.iter().map(|l| l.method.as_str()).collect();
+    assert_eq!(methods, ["textDocument/completion", "textDocument/hover"]);
+    let completion = &latencies[0];
+    assert_eq!(completion.count, 100);
+    assert_eq!(
+        (completion.p50_ms, completion.p90_ms, completion.p99_ms),
+        (50.0, 90.0, 99.0)
+    );
+    assert_eq!(completion.max_ms, 100.0);
+    assert!((completion.mean_ms - 50.5).abs() < 1e-9);
+    assert_eq!(latencies[1].p99_ms, 4.0);
+
+    // Only the last 1024 requests count towards the percentiles.
+    for _ in 0..1024 {
+        metrics.record("textDocument/completion", Duration::from_millis(2));
+    }
+    let completion = &metrics.latencies()[0];
+    assert_eq!(completion.count, 1124);
+    assert_eq!(completion.p99_ms, 2.0);
+    assert_eq!(completion.max_ms, 100.0);
+}
+
+#[tokio::test]
+async fn timed_answers_are_recorded_under_their_method() {
+    let metrics = RequestMetrics::default();
+    let answer = metrics.time("kconfig/indexStats", async { 42 }).await;
+    assert_eq!(answer, 42);
+    let latencies = metrics.latencies();
+    assert_eq!(latencies.len(), 1);
+    assert_eq!(latencies[0].method, "kconfig/indexStats");
+    assert_eq!(latencies[0].count, 1);
+}
@@ -0,0 +1,64 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::sarif::SarifLog;
+use kconfig_lsp::settings::Settings;
+use kconfig_lsp::{code_action, diagnostics, lint};
+use serde_json::{Value, json};
+use std::path::Path;
+
+#[test]
+fn problems_carry_their_quick_fixes() {
+    let root = Path::new("/src");
+    let path = Path::new("/src/drivers/Kconfig");
+    let src = "config NAME\n\tstring \"Name\"\n\tdefault cafe\n\nconfig DEBUG\n\tbool \"Debug\"\n\tdepends on MISSING\n";
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+    let fa = &index.files[path];
+    let settings = Settings::default();
+    let fixes = code_action::quick_fixes(fa, &lint::check_file(&index, path, &settings.lint));
+
+    let mut log = SarifLog::new(root);
+    for diag in diagnostics::collect(&index, path, &settings) {
+        log.add(Path::new("drivers/Kconfig"), fa, &diag, &fixes);
+    }
+    let log: Value = serde_json::from_str(&log.render()).unwrap();
+    let run = &log["runs"][0];
+    assert_eq!(log["version"], "2.1.0");
+    assert_eq!(run["originalUriBaseIds"]["SRCROOT"]["uri"], "file:///src/");
+
+    let results = run["results"].as_array().unwrap();
+    let summary: Vec<(&Value, usize)> = results
+        .iter()
+        .map(|r| {
+            let fixes = r.get("fixes").and_then(Value::as_array).map_or(0, Vec::len);
+            (&r["ruleId"], fixes)
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (&json!("KC0002"), 0),
+            (&json!("KC0002"), 0),
+            (&json!("KC0011"), 1)
+        ]
+    );
+    let quoting = &results[2];
+    let rule = &run["tool"]["driver"]["rules"][quoting["ruleIndex"].as_u64().unwrap() as usize];
+    assert_eq!(rule["name"], "default-quoting");
+    assert_eq!(
+        quoting["locations"][0]["physicalLocation"]["region"],
+        json!({ "startLine": 3, "startColumn": 10, "endLine": 3, "endColumn": 14 })
+    );
+    assert_eq!(
+        quoting["fixes"][0],
+        json!({
+            "description": { "text": "Add quotes" },
+            "artifactChanges": [{
+                "artifactLocation": { "uri": "drivers/Kconfig", "uriBaseId": "SRCROOT" },
+                "replacements": [{
+                    "deletedRegion": { "startLine": 3, "startColumn": 10, "endLine": 3, "endColumn": 14 },
+                    "insertedContent": { "text": "\"cafe\"" },
+                }],
+            }],
+        })
+    );
+}
@@ -0,0 +1,148 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::dotconfig::DotConfig;
+use kconfig_lsp::simulate::{self, Preset};
+use std::path::Path;
+
+const KCONFIG: &str = r#"
+config MODULES
+	bool "Enable loadable module support"
+	modules
+
+config ALWAYS_ON
+	bool "Always on"
+	option allnoconfig_y
+
+config NET
+	bool "Networking support"
+
+config E1000
+	tristate "Intel PRO/1000"
+	depends on NET
+	select CRC32
+
+config CRC32
+	tristate
+
+config HZ
+	int
+	default 250 if NET
+	default 100
+
+choice
+	prompt "Kernel compression"
+	default KERNEL_GZIP
+
+config KERNEL_XZ
+	bool "XZ"
+
+config KERNEL_GZIP
+	bool "Gzip"
+
+endchoice
+"#;
+
+fn values(preset: Preset, base: Option<&DotConfig>) -> Vec<(String, String)> {
+    let mut index = WorldIndex::new();
+    index.analyze_file(Path::new("/src/Kconfig"), KCONFIG);
+    let result = simulate::simulate(&index, preset, base, None);
+    assert!(result.converged);
+    result
+        .values
+        .into_iter()
+        .map(|v| (v.symbol, v.value.unwrap_or_default()))
+        .collect()
+}
+
+fn expected(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(s, v)| (s.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn allnoconfig_keeps_allnoconfig_y_and_choice_default() {
+    assert_eq!(
+        values(Preset::AllNo, None),
+        expected(&[
+            ("ALWAYS_ON", "y"),
+            ("CRC32", "n"),
+            ("E1000", "n"),
+            ("HZ", "100"),
+            ("KERNEL_GZIP", "y"),
+            ("KERNEL_XZ", "n"),
+            ("MODULES", "n"),
+            ("NET", "n"),
+        ])
+    );
+}
+
+#[test]
+fn allmodconfig_builds_tristates_as_modules_and_propagates_select() {
+    assert_eq!(
+        values(Preset::AllMod, None),
+        expected(&[
+            ("ALWAYS_ON", "y"),
+            ("CRC32", "m"),
+            ("E1000", "m"),
+            ("HZ", "250"),
+            ("KERNEL_GZIP", "y"),
+            ("KERNEL_XZ", "n"),
+            ("MODULES", "y"),
+            ("NET", "y"),
+        ])
+    );
+}
+
+#[test]
+fn defconfig_fills_unset_symbols_with_defaults() {
+    let base = DotConfig::parse(
+        Path::new("/src/arch/x86/defconfig"),
+        "CONFIG_NET=y\nCONFIG_E1000=y\nCONFIG_KERNEL_XZ=y\n",
+        "CONFIG_",
+    );
+    assert_eq!(
+        values(Preset::Defconfig, Some(&base)),
+        expected(&[
+            ("ALWAYS_ON", "n"),
+            ("CRC32", "y"),
+            ("E1000", "y"),
+            ("HZ", "250"),
+            ("KERNEL_GZIP", "n"),
+            ("KERNEL_XZ", "y"),
+            ("MODULES", "n"),
+            ("NET", "y"),
+        ])
+    );
+}
+
+#[test]
+fn imply_raises_defaults_within_dependencies_and_yields_to_user_values() {
+    let mut index = WorldIndex::new();
+    index.analyze_file(
+        Path::new("/src/Kconfig"),
+        "config NET\n\tbool \"Net\"\n\nconfig USB\n\tbool \"USB\"\n\nconfig PTP\n\tbool\n\tdepends on NET\n\nconfig PHY\n\tbool \"PHY\"\n\nconfig USB_HELPER\n\tbool\n\tdepends on USB\n\nconfig DRIVER\n\tbool \"Driver\"\n\timply PTP\n\timply PHY\n\timply USB_HELPER\n",
+    );
+    let base = DotConfig::parse(
+        Path::new("/src/defconfig"),
+        "CONFIG_NET=y\nCONFIG_DRIVER=y\nCONFIG_PHY=n\n",
+        "CONFIG_",
+    );
+    let result = simulate::simulate(&index, Preset::Defconfig, Some(&base), None);
+    let values: Vec<(String, String)> = result
+        .values
+        .into_iter()
+        .map(|v| (v.symbol, v.value.unwrap_or_default()))
+        .collect();
+    assert_eq!(
+        values,
+        expected(&[
+            ("DRIVER", "y"),
+            ("NET", "y"),
+            ("PHY", "n"),
+            ("PTP", "y"),
+            ("USB", "n"),
+            ("USB_HELPER", "n"),
+        ])
+    );
+}
@@ -0,0 +1,95 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::sort_entries;
+use std::path::Path;
+
+const SRC: &str = "\
+menu \"Drivers\"
+
+# Needs the vendor firmware.
+config ZETA
+\ttristate \"Zeta\"
+\thelp
+\t  Zeta chips.
+
+\t  Second paragraph.
+
+config ALPHA
+\ttristate \"Alpha\"
+
+comment \"Legacy\"
+
+config OLD_B
+\tbool \"B\"
+
+config OLD_A
+\tbool \"A\"
+endmenu
+
+config TOP
+\tbool
+";
+
+fn apply(src: &str, mut edits: Vec<kconfig_lsp::lint::Edit>) -> String {
+    edits.sort_by_key(|e| std::cmp::Reverse(e.span.start));
+    let mut out = src.to_string();
+    for e in edits {
+        out.replace_range(e.span.start..e.span.end, &e.new_text);
+    }
+    out
+}
+
+#[test]
+fn sorts_runs_within_the_menu_keeping_comments_attached() {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, SRC);
+    let fa = &index.files[path];
+
+    let offset = SRC.find("config ALPHA").unwrap();
+    let (menu, edits) = sort_entries::sort_at(SRC, &fa.file, offset).expect("unsorted");
+    assert_eq!(menu, Some("Drivers"));
+    assert_eq!(
+        apply(SRC, edits),
+        "\
+menu \"Drivers\"
+
+config ALPHA
+\ttristate \"Alpha\"
+
+# Needs the vendor firmware.
+config ZETA
+\ttristate \"Zeta\"
+\thelp
+\t  Zeta chips.
+
+\t  Second paragraph.
+
+comment \"Legacy\"
+
+config OLD_A
+\tbool \"A\"
+
+config OLD_B
+\tbool \"B\"
+endmenu
+
+config TOP
+\tbool
+"
+    );
+}
+
+#[test]
+fn sorted_menu_offers_nothing() {
+    let src = "config A\n\tbool\n\nconfig B\n\tbool";
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, src);
+    assert!(sort_entries::sort_at(src, &index.files[path].file, 0).is_none());
+
+    let src = "config B\n\tbool\n\nconfig A\n\tbool";
+    index.analyze_file(path, src);
+    let (menu, edits) = sort_entries::sort_at(src, &index.files[path].file, 0).unwrap();
+    assert_eq!(menu, None);
+    assert_eq!(apply(src, edits), "config A\n\tbool\n\nconfig B\n\tbool");
+}
@@ -0,0 +1,65 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::symbol_metrics::{self, SymbolMetricsParams};
+use std::path::Path;
+
+const KCONFIG: &str = r#"config NET
+	bool "Networking support"
+	help
+	  Networking.
+
+	  Say Y.
+
+if NET
+
+config INET
+	bool "TCP/IP networking"
+	select CRC32
+
+config IPV6
+	tristate "IPv6"
+	depends on INET
+	imply CRC32
+	default m if INET
+
+endif
+
+config CRC32
+	tristate
+"#;
+
+#[test]
+fn metrics_count_references_by_kind_and_fan_in_and_out() {
+    let mut index = WorldIndex::new();
+    index.analyze_file(Path::new("/src/Kconfig"), KCONFIG);
+    index.analyze_file(Path::new("/src/lib/Kconfig"), "config CRC32\n\ttristate\n");
+
+    let all = symbol_metrics::symbol_metrics(&index, &SymbolMetricsParams::default());
+    let names: Vec<&str> = all.iter().map(|m| m.symbol.as_str()).collect();
+    assert_eq!(names, ["CRC32", "INET", "IPV6", "NET"]);
+
+    let crc32 = &all[0];
+    assert_eq!(crc32.type_name, Some("tristate"));
+    assert_eq!(crc32.definitions, 2);
+    assert_eq!(crc32.references.total, 2);
+    assert_eq!(crc32.references.select, 1);
+    assert_eq!(crc32.references.imply, 1);
+    assert_eq!((crc32.fan_in, crc32.fan_out), (2, 0));
+
+    let inet = &all[1];
+    assert_eq!(inet.references.depends_on, 1);
+    assert_eq!(inet.references.default, 1);
+    // NET, inherited from the `if`, and CRC32.
+    assert_eq!((inet.fan_in, inet.fan_out), (1, 2));
+
+    let net = &all[3];
+    assert_eq!(net.references.if_condition, 1);
+    assert_eq!((net.help_lines, net.help_bytes), (3, 19));
+
+    let params = SymbolMetricsParams {
+        symbols: vec!["IPV6".into(), "MISSING".into()],
+    };
+    let some = symbol_metrics::symbol_metrics(&index, &params);
+    assert_eq!(some.len(), 2);
+    assert_eq!((some[0].symbol.as_str(), some[0].fan_out), ("IPV6", 3));
+    assert_eq!((some[1].definitions, some[1].type_name), (0, None));
+}
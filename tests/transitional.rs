@@ -0,0 +1,58 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::settings::Settings;
+use kconfig_lsp::{diagnostics, transitional};
+use std::path::Path;
+
+const SRC: &str = "\
+config OLD_NAME
+\tbool \"Old option\"
+\tdefault y
+\tselect CRC32
+\thelp
+\t  Enables the old thing.
+
+\t  Second paragraph.
+
+config NEW_NAME
+\tbool \"New option\"
+\tdefault OLD_NAME
+
+config USER
+\tbool
+\tdepends on OLD_NAME
+";
+
+fn apply(src: &str, mut edits: Vec<kconfig_lsp::lint::Edit>) -> String {
+    edits.sort_by_key(|e| std::cmp::Reverse(e.span.start));
+    let mut out = src.to_string();
+    for e in edits {
+        out.replace_range(e.span.start..e.span.end, &e.new_text);
+    }
+    out
+}
+
+#[test]
+fn convert_strips_prompt_defaults_selects_and_help() {
+    let path = Path::new("/src/Kconfig");
+    let mut index = WorldIndex::new();
+    index.analyze_file(path, SRC);
+    let fa = &index.files[path];
+
+    let entry = fa.file.config_at(20).expect("entry");
+    assert_eq!(entry.name, "OLD_NAME");
+    let converted = apply(SRC, transitional::convert(SRC, entry));
+    assert!(converted.starts_with("config OLD_NAME\n\tbool\n\ttransitional\n\nconfig NEW_NAME\n"));
+
+    // Only the `depends on` reference needs migrating; `default OLD_NAME`
+    // is how the replacement picks up the old value.
+    index.reanalyze_file(path, &converted);
+    let notes: Vec<String> = diagnostics::collect(&index, path, &Settings::default())
+        .into_iter()
+        .map(|d| format!("{}: {}", d.range.start.line, d.message))
+        .filter(|m| m.contains("transitional"))
+        .collect();
+    assert_eq!(
+        notes,
+        ["10: `OLD_NAME` is transitional; migrate this reference to its replacement"]
+    );
+}
@@ -0,0 +1,52 @@
+use kconfig_lsp::analysis::WorldIndex;
+use kconfig_lsp::tree_diff::{self, ChangeKind};
+use std::path::{Path, PathBuf};
+
+fn index(root: &str, sub: &str) -> WorldIndex {
+    let mut index = WorldIndex::new();
+    index.root = Some(PathBuf::from("/src"));
+    index.root_kconfig = Some(PathBuf::from("/src/Kconfig"));
+    index.analyze_file(Path::new("/src/Kconfig"), root);
+    index.analyze_file(Path::new("/src/sub/Kconfig"), sub);
+    index
+}
+
+#[test]
+fn reports_symbol_level_changes() {
+    let old = index(
+        "config A\n\tbool \"A\"\n\tdefault y\n\nconfig B\n\tbool \"B\"\n\nconfig GONE\n\tbool\n\nsource \"sub/Kconfig\"\n",
+        "config C\n\tint \"C\"\n",
+    );
+    let new = index(
+        "config A\n\ttristate \"A\"\n\tdefault m if C\n\nconfig B\n\tbool\n\tdepends on A\n\nconfig D\n\tbool\n\nif A\nsource \"sub/Kconfig\"\nendif\n",
+        "config C\n\tint \"C\"\n",
+    );
+    let changes = tree_diff::diff(&old, &new);
+    let found: Vec<(&str, ChangeKind)> = changes
+        .iter()
+        .map(|c| (c.symbol.as_str(), c.kind))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("A", ChangeKind::Retyped),
+            ("A", ChangeKind::Defaults),
+            ("B", ChangeKind::Prompt),
+            ("B", ChangeKind::Dependencies),
+            ("C", ChangeKind::Dependencies),
+            ("D", ChangeKind::Added),
+            ("GONE", ChangeKind::Removed),
+        ]
+    );
+    assert_eq!(
+        tree_diff::render_text(&changes),
+        "~ A: type bool -> tristate\n\
+         ~ A: default y -> m if C\n\
+         ~ B: prompt \"B\" removed\n\
+         ~ B: depends on none -> A\n\
+         ~ C: depends on none -> A\n\
+         + D (bool)\n\
+         - GONE\n"
+    );
+    assert!(tree_diff::diff(&old, &old).is_empty());
+}